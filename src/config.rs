@@ -11,6 +11,65 @@ pub struct Env {
     #[envconfig(from = "REDIS_URL")]
     pub redis_url: String,
 
+    // segundos que se espera a que redis acepte la conexión (al arrancar o al reconectar)
+    #[envconfig(from = "REDIS_CONNECTION_TIMEOUT_SECS", default = "5")]
+    pub redis_connection_timeout_secs: u64,
+
+    // segundos que se espera la respuesta de un comando antes de dar error
+    #[envconfig(from = "REDIS_RESPONSE_TIMEOUT_SECS", default = "10")]
+    pub redis_response_timeout_secs: u64,
+
+    // límites de los documentos graphql, se revisan antes de ejecutar
+    #[envconfig(from = "GRAPHQL_MAX_DEPTH", default = "10")]
+    pub graphql_max_depth: usize,
+
+    // cantidad máxima de campos por documento (los fragments cuentan cada vez que se usan)
+    #[envconfig(from = "GRAPHQL_MAX_COMPLEXITY", default = "200")]
+    pub graphql_max_complexity: usize,
+
+    // JSON { "<sha256 de la query>": "<query>" } con las persisted queries de los clientes
+    #[envconfig(from = "GRAPHQL_PERSISTED_QUERIES_PATH", default = "")]
+    pub graphql_persisted_queries_path: String,
+
+    // en producción: solo se ejecutan las queries del archivo de persisted queries
+    #[envconfig(from = "GRAPHQL_REQUIRE_PERSISTED_QUERIES", default = "false")]
+    pub graphql_require_persisted_queries: bool,
+
+    // dónde se guardan los comprobantes: local (carpeta BLOB_LOCAL_PATH) o s3
+    #[envconfig(from = "BLOB_STORE", default = "local")]
+    pub blob_store: String,
+
+    #[envconfig(from = "BLOB_LOCAL_PATH", default = "uploads")]
+    pub blob_local_path: String,
+
+    // vacío es AWS, para MinIO u otro S3 compatible la URL del server (http://localhost:9000)
+    #[envconfig(from = "S3_ENDPOINT", default = "")]
+    pub s3_endpoint: String,
+
+    // llave de las URLs firmadas de comprobantes, tiene que ser la misma en todos los servers
+    // (vacía: una random por proceso)
+    #[envconfig(from = "RECEIPT_URL_SECRET", default = "")]
+    pub receipt_url_secret: String,
+
+    // segundos que dura una URL firmada de comprobante
+    #[envconfig(from = "RECEIPT_URL_TTL_SECS", default = "300")]
+    pub receipt_url_ttl_secs: u64,
+
+    // OCR de comprobantes: none (apagado) o tesseract
+    #[envconfig(from = "OCR_ENGINE", default = "none")]
+    pub ocr_engine: String,
+
+    #[envconfig(from = "TESSERACT_PATH", default = "tesseract")]
+    pub tesseract_path: String,
+
+    // idiomas de tesseract (tienen que estar instalados sus traineddata)
+    #[envconfig(from = "OCR_LANGUAGES", default = "spa+eng")]
+    pub ocr_languages: String,
+
+    // horas que puede quedar un comprobante subido sin usarse en un pago antes de borrarlo
+    #[envconfig(from = "RECEIPT_ORPHAN_TTL_HOURS", default = "24")]
+    pub receipt_orphan_ttl_hours: u64,
+
     // S3 configuration (optional)
     #[envconfig(from = "BUCKET_NAME", default = "")]
     pub bucket_name: String,
@@ -23,6 +82,14 @@ pub struct Env {
 
     #[envconfig(from = "AWS_REGION", default = "us-east-1")]
     pub aws_region: String,
+
+    // zona horaria IANA de la cooperativa, define qué día es "hoy" y cómo se muestran las horas
+    #[envconfig(from = "COOPERATIVE_TIMEZONE", default = "America/Guatemala")]
+    pub cooperative_timezone: String,
+
+    // moneda en la que se llevan los reportes (GTQ o USD)
+    #[envconfig(from = "BASE_CURRENCY", default = "GTQ")]
+    pub base_currency: String,
 }
 
 impl Env {
@@ -1,14 +1,21 @@
-use actix_web::web::{get, post, resource, Data, ServiceConfig};
-use aws_sdk_s3::Client as S3Client;
+use actix_web::web::{ServiceConfig, get, post, resource};
 
-use crate::endpoints::handlers::rest::file::{get_ticket_from_payment, upload_ticket_for_payment};
+use crate::endpoints::handlers::rest::file::{
+    get_attachment, get_export, get_statement, get_ticket_from_payment, get_ticket_payment_url,
+    submit_payment, upload_attachment_file, upload_ticket_for_payment,
+};
 
-pub fn file_endpoints(config: &mut ServiceConfig, s3_client: S3Client, bucket_name: String) {
+// el BlobStore (Data<dyn BlobStore>) y el ReceiptUrlSigner los registra main igual que el pool
+pub fn file_endpoints(config: &mut ServiceConfig) {
     config
-        .app_data(Data::new(s3_client))
-        .app_data(Data::new(bucket_name))
         .service(
             resource("/general/upload_ticket_payment").route(post().to(upload_ticket_for_payment)),
         )
-        .service(resource("/general/get_ticket_payment").route(get().to(get_ticket_from_payment)));
+        .service(resource("/general/submit_payment").route(post().to(submit_payment)))
+        .service(resource("/general/get_ticket_payment").route(get().to(get_ticket_from_payment)))
+        .service(resource("/general/ticket_payment_url").route(get().to(get_ticket_payment_url)))
+        .service(resource("/general/upload_attachment").route(post().to(upload_attachment_file)))
+        .service(resource("/general/get_attachment").route(get().to(get_attachment)))
+        .service(resource("/general/statement").route(get().to(get_statement)))
+        .service(resource("/general/export/{entity}").route(get().to(get_export)));
 }
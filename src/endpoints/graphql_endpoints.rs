@@ -1,38 +1,63 @@
-use actix_web::web::{post, resource, ServiceConfig};
+use actix_web::web::{get, post, resource, ServiceConfig};
 
 use crate::endpoints::handlers::graphql::{
     fine::FineMutation, loan::LoanMutation, payment::PaymentMutation, quota::QuotaMutation,
 };
 
 use super::handlers::{
-    configs::{connection_pool::get_pool_connection, schema::create_schema},
+    configs::{
+        schema::{create_schema, create_schema_with_subscriptions, NoSubscription},
+    },
     graphql::{
-        fine::FineQuery, graphql, loan::LoanQuery, payment::PaymentQuery, quota::QuotaQuery,
+        fine::FineQuery,
+        graphql, graphql_subscriptions,
+        loan::LoanQuery,
+        payment::PaymentQuery,
+        quota::QuotaQuery,
+        root::{Mutation, Query, Subscription},
     },
 };
 
 //this is pretty much boilerplate for any Graphql api
+// el pool de redis no se crea aquí, lo registra main con .app_data para toda la app
 
 pub fn graphql_config(config: &mut ServiceConfig) {
-    // redis pool
-    let pool = get_pool_connection();
-
     //instance of Schemas with generic function
+    let schema = create_schema_with_subscriptions(Query, Mutation, Subscription);
+
+    // TODO: quitar los schemas por dominio cuando el frontend migre a /graphql
     let payment_schema = create_schema(PaymentQuery {}, PaymentMutation {});
     let loan_schema = create_schema(LoanQuery {}, LoanMutation {});
     let fine_schema = create_schema(FineQuery {}, FineMutation {});
     let quota_schema = create_schema(QuotaQuery {}, QuotaMutation {});
 
     config
-        .app_data(pool)
+        .app_data(schema)
         .app_data(payment_schema)
         .app_data(loan_schema)
         .app_data(fine_schema)
         .app_data(quota_schema)
         .service(
-            resource("/graphql/payment").route(post().to(graphql::<PaymentQuery, PaymentMutation>)),
+            resource("/graphql")
+                .route(post().to(graphql::<Query, Mutation, Subscription>))
+                // websocket para las subscriptions
+                .route(get().to(graphql_subscriptions)),
+        )
+        // aliases por dominio, se mantienen durante la transición
+        .service(
+            resource("/graphql/payment")
+                .route(post().to(graphql::<PaymentQuery, PaymentMutation, NoSubscription>)),
         )
-        .service(resource("/graphql/loan").route(post().to(graphql::<LoanQuery, LoanMutation>)))
-        .service(resource("/graphql/fine").route(post().to(graphql::<FineQuery, FineMutation>)))
-        .service(resource("/graphql/quota").route(post().to(graphql::<QuotaQuery, QuotaMutation>)));
+        .service(
+            resource("/graphql/loan")
+                .route(post().to(graphql::<LoanQuery, LoanMutation, NoSubscription>)),
+        )
+        .service(
+            resource("/graphql/fine")
+                .route(post().to(graphql::<FineQuery, FineMutation, NoSubscription>)),
+        )
+        .service(
+            resource("/graphql/quota")
+                .route(post().to(graphql::<QuotaQuery, QuotaMutation, NoSubscription>)),
+        );
 }
@@ -1,24 +1,167 @@
+use std::sync::{Arc, PoisonError, RwLock};
 use std::time::Duration;
 
+use envconfig::Envconfig;
+use futures::lock::Mutex;
+use redis::aio::{ConnectionLike, MultiplexedConnection};
+use redis::{AsyncConnectionConfig, Client, Cmd, Pipeline, RedisFuture, Value};
+
 use crate::config::Env;
-use actix_web::web::Data;
-use r2d2::Pool;
-use redis::Client;
+use crate::errors::AppError;
 
-pub fn get_pool_connection() -> Data<Pool<Client>> {
-    let config: Env = Env::env_init();
+/// conexión async compartida de toda la app: una sola conexión multiplexada a redis, cada
+/// request saca un clon barato con get() y los comandos de varios requests viajan por el mismo
+/// socket sin bloquear los workers de actix
+/// se conecta la primera vez que se usa (si redis está caído al arrancar el server igual
+/// levanta) y si la conexión se cae el siguiente get() vuelve a conectar
+/// el RwLock solo se toma para clonar la conexión (nunca a través de un await), el Mutex
+/// async es para que al reconectar no abran conexión todos los requests a la vez
+#[derive(Clone)]
+pub struct RedisPool {
+    client: Client,
+    config: AsyncConnectionConfig,
+    shared: Arc<RwLock<SharedConnection>>,
+    reconnecting: Arc<Mutex<()>>,
+}
 
-    //TODO: Change the url for being concat friendly
-    let client = Client::open(config.redis_url).expect("Couldn't stablished client");
-
-    match Pool::builder()
-        .connection_timeout(Duration::from_secs(5))
-        .build(client)
-    {
-        Ok(val) => Data::<Pool<Client>>::new(val),
-        Err(e) => {
-            println!("Couldn't Connect Because: {:}", e);
-            panic!("Couldn't Connect Because: {:}", e);
+struct SharedConnection {
+    // cuántas veces se ha conectado, evita tirar una conexión que ya se reemplazó
+    generation: u64,
+    connection: Option<MultiplexedConnection>,
+}
+
+impl RedisPool {
+    pub fn new(client: Client, connection_timeout: Duration, response_timeout: Duration) -> Self {
+        RedisPool {
+            client,
+            config: AsyncConnectionConfig::new()
+                .set_connection_timeout(connection_timeout)
+                .set_response_timeout(response_timeout),
+            shared: Arc::new(RwLock::new(SharedConnection {
+                generation: 0,
+                connection: None,
+            })),
+            reconnecting: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// el cliente de redis, para conexiones que no pueden compartirse (pub/sub)
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// conexión lista para usar con redis::AsyncCommands / JsonAsyncCommands
+    pub async fn get(&self) -> Result<RedisConnection, AppError> {
+        if let Some(connection) = self.current() {
+            return Ok(connection);
         }
+
+        let _reconnecting = self.reconnecting.lock().await;
+        // otro request pudo reconectar mientras esperábamos
+        if let Some(connection) = self.current() {
+            return Ok(connection);
+        }
+
+        let connection = self
+            .client
+            .get_multiplexed_async_connection_with_config(&self.config)
+            .await?;
+        let mut shared = self.shared.write().unwrap_or_else(PoisonError::into_inner);
+        shared.generation += 1;
+        shared.connection = Some(connection.clone());
+
+        Ok(RedisConnection {
+            inner: connection,
+            generation: shared.generation,
+            shared: self.shared.clone(),
+        })
+    }
+
+    fn current(&self) -> Option<RedisConnection> {
+        let shared = self.shared.read().unwrap_or_else(PoisonError::into_inner);
+
+        shared.connection.as_ref().map(|connection| RedisConnection {
+            inner: connection.clone(),
+            generation: shared.generation,
+            shared: self.shared.clone(),
+        })
     }
 }
+
+/// lo que regresa RedisPool::get, si un comando falla porque se cayó la conexión la saca del
+/// pool para que el siguiente get() reconecte
+pub struct RedisConnection {
+    inner: MultiplexedConnection,
+    generation: u64,
+    shared: Arc<RwLock<SharedConnection>>,
+}
+
+impl RedisConnection {
+    fn discard_if_dropped<T>(&self, result: &redis::RedisResult<T>) {
+        let Err(err) = result else {
+            return;
+        };
+        if !(err.is_connection_dropped() || err.is_unrecoverable_error()) {
+            return;
+        }
+
+        let mut shared = self.shared.write().unwrap_or_else(PoisonError::into_inner);
+        if shared.generation == self.generation {
+            shared.connection = None;
+        }
+    }
+}
+
+impl ConnectionLike for RedisConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        Box::pin(async move {
+            let result = self.inner.req_packed_command(cmd).await;
+            self.discard_if_dropped(&result);
+            result
+        })
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        Box::pin(async move {
+            let result = self.inner.req_packed_commands(cmd, offset, count).await;
+            self.discard_if_dropped(&result);
+            result
+        })
+    }
+
+    fn get_db(&self) -> i64 {
+        self.inner.get_db()
+    }
+}
+
+/// crea la conexión compartida de la app, main la crea una sola vez y la inyecta como Data
+/// solo falla si el REDIS_URL está mal formado, la conexión real se abre en el primer get()
+pub fn create_pool(config: &Env) -> Result<RedisPool, AppError> {
+    //TODO: Change the url for being concat friendly
+    let client = Client::open(config.redis_url.as_str()).map_err(|err| {
+        println!("Couldn't stablished client: {:?}", err);
+        AppError::storage("Couldn't stablished client")
+    })?;
+
+    Ok(RedisPool::new(
+        client,
+        Duration::from_secs(config.redis_connection_timeout_secs),
+        Duration::from_secs(config.redis_response_timeout_secs),
+    ))
+}
+
+/// lee el env y crea una conexión nueva, en la app usar la de main (Data<RedisPool>)
+/// esto queda para scripts y tests
+pub fn get_pool_connection() -> Result<RedisPool, AppError> {
+    let config = Env::init_from_env().map_err(|err| {
+        println!("Couldn't read env: {:?}", err);
+        AppError::storage("Couldn't read redis config")
+    })?;
+
+    create_pool(&config)
+}
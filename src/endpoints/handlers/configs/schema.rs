@@ -1,46 +1,167 @@
-use actix_web::web::Data;
-use juniper::{EmptySubscription, GraphQLType, GraphQLTypeAsync, RootNode};
-use r2d2::Pool;
-use redis::Client as RedisClient;
+use std::sync::Arc;
 
+use actix_web::web::Data;
+use juniper::{
+    EmptySubscription, GraphQLSubscriptionType, GraphQLType, GraphQLTypeAsync, RootNode,
+};
+use crate::endpoints::handlers::configs::connection_pool::RedisPool;
+use crate::errors::AppError;
+use crate::models::currency::ExchangeRates;
+use crate::repos::graphql::currency::load_exchange_rates;
+use crate::repos::graphql::memory::MemoryStore;
 use crate::repos::graphql::quota::QuotaRepo;
-use crate::repos::graphql::{fine::FineRepo, loan::LoanRepo, payment::PaymentRepo};
+use crate::repos::graphql::store::{
+    AttachmentStore, CurrencyStore, FineStore, LoanStore, PaymentStore, QuotaStore, UserStore,
+};
+use crate::repos::graphql::{
+    attachment::AttachmentRepo, currency::CurrencyRepo, fine::FineRepo, loader::RequestLoader,
+    loan::LoanRepo, payment::PaymentRepo, report::ReportRepo, statement::StatementRepo,
+    user::UserRepo,
+};
 
 //Context Related
 #[derive(Clone)]
 pub struct GeneralContext {
-    pub pool: Data<Pool<RedisClient>>,
+    storage: Storage,
+}
+
+/// de dónde sacan los datos los repos del request
+// el contexto se arma una vez por request, no vale la pena meter el pool en un Box
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone)]
+enum Storage {
+    Redis {
+        pool: RedisPool,
+        // se crea uno nuevo por request, así la cache no se comparte entre requests
+        loader: Arc<RequestLoader>,
+    },
+    // sin redis, pensado para tests
+    Memory(Arc<MemoryStore>),
 }
 
 impl GeneralContext {
-    pub fn payment_repo(&self) -> PaymentRepo {
-        PaymentRepo {
-            pool: self.pool.clone(),
+    pub fn new(pool: RedisPool) -> Self {
+        GeneralContext {
+            storage: Storage::Redis {
+                loader: Arc::new(RequestLoader::new(pool.clone())),
+                pool,
+            },
+        }
+    }
+
+    /// contexto que lee y escribe en un MemoryStore, sin tocar redis
+    pub fn in_memory(store: Arc<MemoryStore>) -> Self {
+        GeneralContext {
+            storage: Storage::Memory(store),
+        }
+    }
+
+    /// el pool de redis, solo lo que todavía no tiene store (subscriptions) lo usa
+    pub fn pool(&self) -> Result<&RedisPool, AppError> {
+        match &self.storage {
+            Storage::Redis { pool, .. } => Ok(pool),
+            Storage::Memory(_) => Err(redis_required()),
+        }
+    }
+
+    pub fn loader(&self) -> Result<&Arc<RequestLoader>, AppError> {
+        match &self.storage {
+            Storage::Redis { loader, .. } => Ok(loader),
+            Storage::Memory(_) => Err(redis_required()),
+        }
+    }
+
+    pub fn payment_repo(&self) -> Arc<dyn PaymentStore> {
+        match &self.storage {
+            Storage::Redis { pool, loader } => Arc::new(PaymentRepo {
+                pool: pool.clone(),
+                loader: loader.clone(),
+            }),
+            Storage::Memory(store) => store.clone(),
+        }
+    }
+    pub fn loan_repo(&self) -> Arc<dyn LoanStore> {
+        match &self.storage {
+            Storage::Redis { pool, loader } => Arc::new(LoanRepo {
+                pool: pool.clone(),
+                loader: loader.clone(),
+            }),
+            Storage::Memory(store) => store.clone(),
         }
     }
-    pub fn loan_repo(&self) -> LoanRepo {
-        LoanRepo {
-            pool: self.pool.clone(),
+    pub fn fine_repo(&self) -> Arc<dyn FineStore> {
+        match &self.storage {
+            Storage::Redis { pool, loader } => Arc::new(FineRepo {
+                pool: pool.clone(),
+                loader: loader.clone(),
+            }),
+            Storage::Memory(store) => store.clone(),
         }
     }
-    pub fn fine_repo(&self) -> FineRepo {
-        FineRepo {
-            pool: self.pool.clone(),
+    pub fn quota_repo(&self) -> Arc<dyn QuotaStore> {
+        match &self.storage {
+            Storage::Redis { pool, loader } => Arc::new(QuotaRepo {
+                pool: pool.clone(),
+                loader: loader.clone(),
+            }),
+            Storage::Memory(store) => store.clone(),
         }
     }
-    pub fn quota_repo(&self) -> QuotaRepo {
-        QuotaRepo {
-            pool: self.pool.clone(),
+    pub fn currency_repo(&self) -> Arc<dyn CurrencyStore> {
+        match &self.storage {
+            Storage::Redis { pool, .. } => Arc::new(CurrencyRepo { pool: pool.clone() }),
+            Storage::Memory(store) => store.clone(),
+        }
+    }
+    pub fn attachment_repo(&self) -> Arc<dyn AttachmentStore> {
+        match &self.storage {
+            Storage::Redis { pool, .. } => Arc::new(AttachmentRepo { pool: pool.clone() }),
+            Storage::Memory(store) => store.clone(),
+        }
+    }
+    /// estados de cuenta, arma todo con los otros repos del contexto
+    pub fn statement_repo(&self) -> StatementRepo {
+        StatementRepo {
+            context: self.clone(),
+        }
+    }
+    /// reportes para directivos, igual que el estado de cuenta
+    pub fn report_repo(&self) -> ReportRepo {
+        ReportRepo {
+            context: self.clone(),
+        }
+    }
+    /// tipos de cambio vigentes del backend del contexto
+    pub async fn exchange_rates(&self) -> Result<ExchangeRates, AppError> {
+        match &self.storage {
+            Storage::Redis { pool, .. } => load_exchange_rates(&mut pool.get().await?).await,
+            Storage::Memory(store) => store.exchange_rates(),
+        }
+    }
+    pub fn user_repo(&self) -> Arc<dyn UserStore> {
+        match &self.storage {
+            Storage::Redis { pool, loader } => Arc::new(UserRepo {
+                pool: pool.clone(),
+                loader: loader.clone(),
+            }),
+            Storage::Memory(store) => store.clone(),
         }
     }
 }
 
+fn redis_required() -> AppError {
+    AppError::storage("Esta operación necesita redis, no está disponible con el store en memoria")
+}
+
 //I don't like this rust boilerplate, but meh, Ig rust doesn't adapt that good to abstractions
 impl juniper::Context for GeneralContext {}
 
 //Schema Related
-pub type GeneralSchema<Query, Mutation> =
-    RootNode<'static, Query, Mutation, EmptySubscription<GeneralContext>>;
+pub type GeneralSchema<Query, Mutation, Subscription = EmptySubscription<GeneralContext>> =
+    RootNode<'static, Query, Mutation, Subscription>;
+
+// los schemas por dominio no tienen subscriptions
+pub type NoSubscription = EmptySubscription<GeneralContext>;
 
 pub fn create_schema<GenericQuery, GenericMutation>(
     query: GenericQuery,
@@ -67,3 +188,18 @@ where
     // I always need for passing the squema to actix
     Data::new(schema)
 }
+
+/// igual que create_schema pero con subscriptions (solo el schema unificado de /graphql)
+pub fn create_schema_with_subscriptions<GenericQuery, GenericMutation, GenericSubscription>(
+    query: GenericQuery,
+    mutation: GenericMutation,
+    subscription: GenericSubscription,
+) -> Data<GeneralSchema<GenericQuery, GenericMutation, GenericSubscription>>
+where
+    GenericQuery: GraphQLTypeAsync<Context = GeneralContext, TypeInfo = ()> + Send + Sync,
+    GenericMutation: GraphQLTypeAsync<Context = GeneralContext, TypeInfo = ()> + Send + Sync,
+    GenericSubscription:
+        GraphQLSubscriptionType<Context = GeneralContext, TypeInfo = ()> + Send + Sync,
+{
+    Data::new(RootNode::new(query, mutation, subscription))
+}
@@ -0,0 +1,73 @@
+use crate::{
+    endpoints::handlers::configs::schema::GeneralContext,
+    models::graphql::{Attachment, AttachmentOwnerType, AttachmentStatus},
+    repos::graphql::attachment::check_attachment_viewer,
+};
+use crate::errors::AppError;
+
+// los documentos se suben y se descargan por REST (/general/upload_attachment y
+// /general/get_attachment), acá solo se listan y se revisan
+
+pub struct AttachmentQuery {}
+
+#[juniper::graphql_object(
+    Context = GeneralContext,
+)]
+impl AttachmentQuery {
+    /// documentos de un socio (affiliate_key), préstamo o multa, solo para el socio dueño o
+    /// un directivo
+    pub async fn get_attachments(
+        context: &GeneralContext,
+        access_token: String,
+        owner_type: AttachmentOwnerType,
+        owner_id: String,
+    ) -> Result<Vec<Attachment>, AppError> {
+        let member_key = context
+            .attachment_repo()
+            .get_member_key(owner_type, &owner_id)
+            .await?;
+        let member = context
+            .user_repo()
+            .get_member_by_affiliate_key(member_key)
+            .await?;
+        check_attachment_viewer(context.user_repo().as_ref(), &access_token, &member.owner_key)
+            .await?;
+
+        context
+            .attachment_repo()
+            .get_attachments(owner_type, &owner_id)
+            .await
+    }
+
+    /// documentos que falta revisar, solo para directivos
+    pub async fn get_pending_attachments(
+        context: &GeneralContext,
+        access_token: String,
+    ) -> Result<Vec<Attachment>, AppError> {
+        context
+            .attachment_repo()
+            .get_pending_attachments(access_token)
+            .await
+    }
+}
+
+pub struct AttachmentMutation;
+
+#[juniper::graphql_object(
+    Context = GeneralContext,
+)]
+impl AttachmentMutation {
+    /// un directivo aprueba o rechaza un documento, el rechazo lleva comentario
+    pub async fn review_attachment(
+        context: &GeneralContext,
+        access_token: String,
+        id: String,
+        status: AttachmentStatus,
+        commentary: Option<String>,
+    ) -> Result<Attachment, AppError> {
+        context
+            .attachment_repo()
+            .review_attachment(access_token, id, status, commentary)
+            .await
+    }
+}
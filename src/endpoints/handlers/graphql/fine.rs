@@ -1,7 +1,11 @@
 use crate::{
     endpoints::handlers::configs::schema::GeneralContext,
-    models::graphql::{Fine, FineStatus, UsersWithFines},
+    models::{
+        graphql::{Fine, FineStatus, UsersWithFines},
+        money::Money,
+    },
 };
+use crate::errors::AppError;
 
 pub struct FineQuery {}
 
@@ -15,12 +19,12 @@ impl FineQuery {
     pub async fn get_fines_by_id(
         context: &GeneralContext,
         access_token: String,
-    ) -> Result<Vec<Fine>, String> {
-        context.fine_repo().get_user_fines(access_token)
+    ) -> Result<Vec<Fine>, AppError> {
+        context.fine_repo().get_user_fines(access_token).await
     }
 
-    pub async fn get_fines(context: &GeneralContext) -> Result<Vec<UsersWithFines>, String> {
-        context.fine_repo().get_users_with_there_fines()
+    pub async fn get_fines(context: &GeneralContext) -> Result<Vec<UsersWithFines>, AppError> {
+        context.fine_repo().get_users_with_there_fines().await
     }
 }
 
@@ -34,23 +38,25 @@ impl FineMutation {
     pub async fn create_fine(
         context: &GeneralContext,
         affiliate_key: String,
-        amount: f64,
+        amount: Money,
         motive: String,
-    ) -> Result<String, String> {
+    ) -> Result<String, AppError> {
         context
             .fine_repo()
-            .create_fine(affiliate_key, amount as f32, motive)
+            .create_fine(affiliate_key, amount, motive)
+            .await
     }
 
     pub async fn edit_fine(
         context: &GeneralContext,
         fine_key: String,
-        new_amount: Option<f64>,
+        new_amount: Option<Money>,
         new_motive: Option<String>,
         new_status: Option<FineStatus>,
-    ) -> Result<String, String> {
+    ) -> Result<String, AppError> {
         context
             .fine_repo()
             .edit_fine(fine_key, new_amount, new_motive, new_status)
+            .await
     }
 }
@@ -0,0 +1,336 @@
+use std::collections::{HashMap, HashSet};
+
+use juniper::http::GraphQLRequest;
+use juniper::parser::parse_document_source;
+use juniper::{Definition, InputValue, OperationType, ScalarValue, SchemaType, Selection};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::config::Env;
+use crate::errors::AppError;
+
+// con las relaciones (member -> loans -> quotas, payments -> target, ...) un documento
+// anidado o con muchos campos se vuelve una ráfaga de lecturas a redis, así que antes de
+// ejecutar se mide el documento y en producción solo se aceptan las queries conocidas
+
+/// límites que el handler de graphql revisa antes de ejecutar, main los registra como Data
+#[derive(Clone, Debug)]
+pub struct QueryLimits {
+    pub max_depth: usize,
+    pub max_complexity: usize,
+    /// sha256 (hex en minúsculas) -> query
+    persisted_queries: HashMap<String, String>,
+    /// true: solo se ejecutan las queries del allow-list
+    require_persisted: bool,
+}
+
+/// body del POST: lo mismo que GraphQLRequest pero la query es opcional si viene el hash
+/// (extensions.persistedQuery.sha256Hash, el formato de Apollo)
+#[derive(Deserialize, Debug)]
+pub struct PersistedQueryRequest<S: ScalarValue = juniper::DefaultScalarValue> {
+    pub query: Option<String>,
+    #[serde(rename = "operationName")]
+    pub operation_name: Option<String>,
+    #[serde(bound(deserialize = "InputValue<S>: Deserialize<'de>"))]
+    pub variables: Option<InputValue<S>>,
+    pub extensions: Option<RequestExtensions>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RequestExtensions {
+    #[serde(rename = "persistedQuery")]
+    pub persisted_query: Option<PersistedQueryHash>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PersistedQueryHash {
+    #[serde(rename = "sha256Hash")]
+    pub sha256_hash: String,
+}
+
+impl QueryLimits {
+    /// sin allow-list, cualquier query que pase los límites se ejecuta
+    pub fn new(max_depth: usize, max_complexity: usize) -> Self {
+        QueryLimits {
+            max_depth,
+            max_complexity,
+            persisted_queries: HashMap::new(),
+            require_persisted: false,
+        }
+    }
+
+    /// agrega las queries al allow-list, si require es true son las únicas que se ejecutan
+    pub fn with_persisted_queries(
+        mut self,
+        queries: impl IntoIterator<Item = String>,
+        require: bool,
+    ) -> Self {
+        for query in queries {
+            self.persisted_queries.insert(query_hash(&query), query);
+        }
+        self.require_persisted = require;
+        self
+    }
+
+    /// límites del Env, el archivo de persisted queries se lee una sola vez al arrancar
+    pub fn from_env(config: &Env) -> Result<Self, AppError> {
+        let limits = QueryLimits::new(config.graphql_max_depth, config.graphql_max_complexity);
+        let path = config.graphql_persisted_queries_path.trim();
+
+        if path.is_empty() {
+            if config.graphql_require_persisted_queries {
+                return Err(AppError::validation(
+                    "GRAPHQL_REQUIRE_PERSISTED_QUERIES necesita GRAPHQL_PERSISTED_QUERIES_PATH",
+                    &["GRAPHQL_PERSISTED_QUERIES_PATH"],
+                ));
+            }
+            return Ok(limits);
+        }
+
+        let raw = std::fs::read_to_string(path).map_err(|err| {
+            AppError::validation(
+                format!("No se pudo leer {}: {}", path, err),
+                &["GRAPHQL_PERSISTED_QUERIES_PATH"],
+            )
+        })?;
+        let manifest: HashMap<String, String> = serde_json::from_str(&raw).map_err(|err| {
+            AppError::validation(
+                format!("{} no es un JSON {{ hash: query }}: {}", path, err),
+                &["GRAPHQL_PERSISTED_QUERIES_PATH"],
+            )
+        })?;
+
+        // un hash que no corresponde a su query es un error del build del cliente, mejor
+        // no levantar que rechazar esa query en producción
+        for (hash, query) in &manifest {
+            if !hash.eq_ignore_ascii_case(&query_hash(query)) {
+                return Err(AppError::validation(
+                    format!("El hash {} no corresponde a su query", hash),
+                    &["GRAPHQL_PERSISTED_QUERIES_PATH"],
+                ));
+            }
+        }
+
+        Ok(limits.with_persisted_queries(
+            manifest.into_values(),
+            config.graphql_require_persisted_queries,
+        ))
+    }
+
+    /// resuelve la persisted query (si viene el hash) y mide el documento
+    /// devuelve el GraphQLRequest listo para ejecutar
+    pub fn prepare<S: ScalarValue>(
+        &self,
+        request: PersistedQueryRequest<S>,
+        schema: &SchemaType<S>,
+    ) -> Result<GraphQLRequest<S>, AppError> {
+        let hash = request
+            .extensions
+            .and_then(|extensions| extensions.persisted_query)
+            .map(|persisted| persisted.sha256_hash.to_lowercase());
+
+        let query = match (hash, request.query) {
+            (Some(hash), query) => {
+                if let Some(query) = &query
+                    && query_hash(query) != hash
+                {
+                    return Err(AppError::validation(
+                        "El sha256Hash no corresponde a la query",
+                        &["extensions.persistedQuery.sha256Hash"],
+                    ));
+                }
+                match (self.persisted_queries.get(&hash), query) {
+                    (Some(persisted), _) => persisted.clone(),
+                    // hash + query de un cliente nuevo, solo pasa si el allow-list es opcional
+                    (None, Some(query)) if !self.require_persisted => query,
+                    (None, _) => {
+                        return Err(AppError::persisted_query_not_found(
+                            "PersistedQueryNotFound",
+                        ));
+                    }
+                }
+            }
+            (None, Some(query)) => {
+                if self.require_persisted
+                    && !self.persisted_queries.contains_key(&query_hash(&query))
+                {
+                    return Err(AppError::persisted_query_not_found(
+                        "Solo se aceptan persisted queries",
+                    ));
+                }
+                query
+            }
+            (None, None) => {
+                return Err(AppError::validation("Falta la query", &["query"]));
+            }
+        };
+
+        self.check(&query, schema)?;
+
+        Ok(GraphQLRequest::new(
+            query,
+            request.operation_name,
+            request.variables,
+        ))
+    }
+
+    /// prepare para el websocket, donde solo se ejecutan subscriptions: las queries y
+    /// mutations van por POST, con su propio loader por request
+    pub fn prepare_subscription<S: ScalarValue>(
+        &self,
+        request: PersistedQueryRequest<S>,
+        schema: &SchemaType<S>,
+    ) -> Result<GraphQLRequest<S>, AppError> {
+        let request = self.prepare(request, schema)?;
+
+        let only_subscriptions = match parse_document_source(&request.query, schema) {
+            Ok(document) => document.iter().all(|definition| match definition {
+                Definition::Operation(operation) => {
+                    let operation = &operation.item;
+                    // con operationName solo importa la que se va a ejecutar
+                    let selected = match &request.operation_name {
+                        Some(name) => operation.name.as_ref().is_some_and(|op| op.item == name),
+                        None => true,
+                    };
+                    !selected || operation.operation_type == OperationType::Subscription
+                }
+                Definition::Fragment(_) => true,
+            }),
+            // juniper devuelve el error de sintaxis
+            Err(_) => true,
+        };
+        if !only_subscriptions {
+            return Err(AppError::validation(
+                "Por websocket solo se aceptan subscriptions, las queries y mutations van por POST",
+                &["query"],
+            ));
+        }
+
+        Ok(request)
+    }
+
+    /// profundidad y complejidad de todas las operaciones del documento
+    /// si no se puede parsear se deja pasar, juniper devuelve el error de sintaxis
+    pub fn check<S: ScalarValue>(
+        &self,
+        query: &str,
+        schema: &SchemaType<S>,
+    ) -> Result<(), AppError> {
+        let Ok(document) = parse_document_source(query, schema) else {
+            return Ok(());
+        };
+
+        let mut analyzer = Analyzer::new(&document);
+        for definition in &document {
+            let Definition::Operation(operation) = definition else {
+                continue;
+            };
+            let cost = analyzer.measure(&operation.item.selection_set);
+
+            if cost.depth > self.max_depth {
+                return Err(AppError::query_too_complex(format!(
+                    "La query tiene profundidad {}, el máximo es {}",
+                    cost.depth, self.max_depth
+                )));
+            }
+            if cost.complexity > self.max_complexity {
+                return Err(AppError::query_too_complex(format!(
+                    "La query pide {} campos, el máximo es {}",
+                    cost.complexity, self.max_complexity
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// sha256 en hex minúsculas, igual que lo calcula Apollo en el cliente
+pub fn query_hash(query: &str) -> String {
+    format!("{:x}", Sha256::digest(query.as_bytes()))
+}
+
+#[derive(Clone, Copy, Default)]
+struct Cost {
+    depth: usize,
+    complexity: usize,
+}
+
+/// mide selecciones expandiendo los fragments, cada fragment se mide una sola vez así un
+/// documento con fragments anidados no explota al analizarlo
+struct Analyzer<'d, 'a, S> {
+    fragments: HashMap<&'a str, &'d [Selection<'a, S>]>,
+    measured: HashMap<&'a str, Cost>,
+    // fragments que se están midiendo, un ciclo lo rechaza la validación de juniper
+    visiting: HashSet<&'a str>,
+}
+
+impl<'d, 'a, S> Analyzer<'d, 'a, S> {
+    fn new(document: &'d [Definition<'a, S>]) -> Self {
+        let fragments = document
+            .iter()
+            .filter_map(|definition| match definition {
+                Definition::Fragment(fragment) => Some((
+                    fragment.item.name.item,
+                    fragment.item.selection_set.as_slice(),
+                )),
+                Definition::Operation(_) => None,
+            })
+            .collect();
+
+        Analyzer {
+            fragments,
+            measured: HashMap::new(),
+            visiting: HashSet::new(),
+        }
+    }
+
+    fn measure(&mut self, selections: &'d [Selection<'a, S>]) -> Cost {
+        let mut total = Cost::default();
+
+        for selection in selections {
+            let cost = match selection {
+                Selection::Field(field) => {
+                    let field = &field.item;
+                    // introspección (__schema, __type, __typename) no toca redis
+                    if field.name.item.starts_with("__") {
+                        continue;
+                    }
+                    let inner = field
+                        .selection_set
+                        .as_deref()
+                        .map(|selections| self.measure(selections))
+                        .unwrap_or_default();
+                    Cost {
+                        depth: inner.depth + 1,
+                        complexity: inner.complexity.saturating_add(1),
+                    }
+                }
+                Selection::InlineFragment(fragment) => self.measure(&fragment.item.selection_set),
+                Selection::FragmentSpread(spread) => self.measure_fragment(spread.item.name.item),
+            };
+
+            total.depth = total.depth.max(cost.depth);
+            total.complexity = total.complexity.saturating_add(cost.complexity);
+        }
+
+        total
+    }
+
+    fn measure_fragment(&mut self, name: &'a str) -> Cost {
+        if let Some(cost) = self.measured.get(name) {
+            return *cost;
+        }
+        let Some(selections) = self.fragments.get(name).copied() else {
+            return Cost::default();
+        };
+        if !self.visiting.insert(name) {
+            return Cost::default();
+        }
+
+        let cost = self.measure(selections);
+        self.visiting.remove(name);
+        self.measured.insert(name, cost);
+        cost
+    }
+}
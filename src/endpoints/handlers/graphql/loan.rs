@@ -1,4 +1,12 @@
-use crate::{endpoints::handlers::configs::schema::GeneralContext, models::graphql::Loan};
+use crate::{
+    endpoints::handlers::configs::schema::GeneralContext,
+    models::{
+        currency::{base_currency, Currency},
+        graphql::Loan,
+        money::Money,
+    },
+};
+use crate::errors::AppError;
 
 //* Queries
 
@@ -14,13 +22,13 @@ impl LoanQuery {
     pub async fn get_user_loans(
         context: &GeneralContext,
         access_token: String,
-    ) -> Result<Vec<Loan>, String> {
-        context.loan_repo().get_user_loans(access_token)
+    ) -> Result<Vec<Loan>, AppError> {
+        context.loan_repo().get_user_loans(access_token).await
     }
 
     /// obtiene todos los préstamos de todos los socios
-    pub async fn get_all_loans(context: &GeneralContext) -> Result<Vec<Loan>, String> {
-        context.loan_repo().get_all_loans()
+    pub async fn get_all_loans(context: &GeneralContext) -> Result<Vec<Loan>, AppError> {
+        context.loan_repo().get_all_loans().await
     }
 }
 
@@ -30,16 +38,23 @@ pub struct LoanMutation;
     Context = GeneralContext,
 )]
 impl LoanMutation {
+    /// currency es opcional, si no se manda el préstamo queda en la moneda base
     pub async fn create_user_loan(
         context: &GeneralContext,
         affiliate_key: String,
         total_quota: i32,
-        base_needed_payment: f64,
+        base_needed_payment: Money,
+        currency: Option<Currency>,
         interest_rate: f64,
         reason: String,
-    ) -> Result<String, String> {
-        context
-            .loan_repo()
-            .create_loan(affiliate_key, total_quota, base_needed_payment, interest_rate, reason)
+    ) -> Result<String, AppError> {
+        context.loan_repo().create_loan(
+            affiliate_key,
+            total_quota,
+            base_needed_payment,
+            currency.unwrap_or_else(base_currency),
+            interest_rate,
+            reason,
+        ).await
     }
 }
@@ -1,25 +1,33 @@
+pub mod attachment;
 pub mod fine;
+pub mod limits;
 pub mod loan;
 pub mod payment;
 pub mod quota;
+pub mod root;
+pub mod ws;
+
+pub use self::ws::graphql_subscriptions;
 
 use actix_web::{
     web::{Data, Json},
-    HttpResponse,
+    HttpResponse, ResponseError,
 };
-use juniper::{http::GraphQLRequest, GraphQLType, GraphQLTypeAsync};
-use r2d2::Pool;
-use redis::Client;
+use juniper::{GraphQLType, GraphQLTypeAsync};
 
 // use aws_sdk_s3::Client as S3Client; // COMENTADO POR AHORA PARA ENFOCARSE EN RECOVER-PASSWORD
 
+use self::limits::{PersistedQueryRequest, QueryLimits};
+use super::configs::connection_pool::RedisPool;
 use super::configs::schema::{GeneralContext, GeneralSchema};
+use crate::errors::AppError;
 
 // Graphql creator schema generic
-pub async fn graphql<GenericQuery, GenericMutation>(
-    pool: Data<Pool<Client>>,
-    data: Json<GraphQLRequest>,
-    schema: Data<GeneralSchema<GenericQuery, GenericMutation>>,
+pub async fn graphql<GenericQuery, GenericMutation, GenericSubscription>(
+    pool: Data<RedisPool>,
+    limits: Data<QueryLimits>,
+    data: Json<PersistedQueryRequest>,
+    schema: Data<GeneralSchema<GenericQuery, GenericMutation, GenericSubscription>>,
 ) -> HttpResponse
 where
     //Okay, first time using the where key word so time to explain
@@ -50,10 +58,30 @@ where
         + Send
         + Sync,
     GenericMutation::TypeInfo: Send + Sync,
+
+    // por POST las subscriptions no se ejecutan, solo tienen que ser parte del schema
+    GenericSubscription: GraphQLType<Context = GeneralContext, TypeInfo = ()> + Send + Sync,
 {
-    let context = GeneralContext { pool };
+    // persisted query + profundidad/complejidad, si no pasa no se toca redis
+    let request = match limits.prepare(data.into_inner(), &schema.schema) {
+        Ok(request) => request,
+        Err(err) => return request_error(err),
+    };
+
+    let context = GeneralContext::new(pool.get_ref().clone());
 
-    let res = data.execute(&schema, &context).await;
+    let res = request.execute(&schema, &context).await;
 
     HttpResponse::Ok().json(res)
 }
+
+/// error antes de ejecutar, con la misma forma que los errores de graphql
+/// { errors: [{ message, extensions: { code } }] }
+fn request_error(err: AppError) -> HttpResponse {
+    HttpResponse::build(err.status_code()).json(serde_json::json!({
+        "errors": [{
+            "message": err.message(),
+            "extensions": { "code": err.code() },
+        }]
+    }))
+}
@@ -1,10 +1,17 @@
 use crate::{
     endpoints::handlers::configs::schema::GeneralContext,
     models::{
-        graphql::{Affiliate, Payment, PaymentHistory, PaymentType},
+        currency::{base_currency, Currency},
+        graphql::{
+            Affiliate, BaseCurrencyReport, DuplicatePaymentGroup, ExchangeRate, Payment,
+            PaymentHistory, PaymentType,
+        },
+        money::Money,
         PayedTo,
     },
 };
+use crate::errors::AppError;
+use crate::repos::file::payment_link::receipt_link_warning;
 
 pub struct PaymentQuery {}
 
@@ -19,25 +26,53 @@ impl PaymentQuery {
     pub async fn get_history(
         context: &GeneralContext,
         access_token: String,
-    ) -> Result<PaymentHistory, String> {
-        context.payment_repo().get_user_history(access_token)
+    ) -> Result<PaymentHistory, AppError> {
+        context.payment_repo().get_user_history(access_token).await
     }
 
     /// Get's all user's payments
     pub async fn get_users_payments(
         context: &GeneralContext,
         access_token: String,
-    ) -> Result<Vec<Payment>, String> {
-        context.payment_repo().get_user_payments(access_token)
+    ) -> Result<Vec<Payment>, AppError> {
+        context.payment_repo().get_user_payments(access_token).await
     }
     /// Obtiene todos los pagos de todos los socios
-    pub async fn get_all_payments(context: &GeneralContext) -> Result<Vec<Payment>, String> {
-        context.payment_repo().get_all_payments()
+    pub async fn get_all_payments(context: &GeneralContext) -> Result<Vec<Payment>, AppError> {
+        context.payment_repo().get_all_payments().await
+    }
+
+    /// Pagos que comparten cuenta + número de boleta con otro pago no rechazado
+    /// solo para directivos, trae pagos y nombres de todos los socios
+    pub async fn get_possible_duplicate_payments(
+        context: &GeneralContext,
+        access_token: String,
+    ) -> Result<Vec<DuplicatePaymentGroup>, AppError> {
+        if !context.user_repo().is_directive(&access_token).await {
+            return Err(AppError::unauthorized(
+                "Solo los directivos pueden ver los pagos duplicados",
+            ));
+        }
+        context.payment_repo().get_possible_duplicate_payments().await
     }
 
     /// Get's all the members names with there affiliate_keys
-    pub async fn get_all_members(context: &GeneralContext) -> Result<Vec<Affiliate>, String> {
-        context.payment_repo().get_all_users_for_affiliates()
+    pub async fn get_all_members(context: &GeneralContext) -> Result<Vec<Affiliate>, AppError> {
+        context.user_repo().get_all_users_for_affiliates().await
+    }
+
+    /// Tipos de cambio vigentes respecto a la moneda base
+    pub async fn get_exchange_rates(
+        context: &GeneralContext,
+    ) -> Result<Vec<ExchangeRate>, AppError> {
+        context.currency_repo().get_exchange_rates().await
+    }
+
+    /// Totales de pagos y préstamos convertidos a la moneda base
+    pub async fn get_base_currency_report(
+        context: &GeneralContext,
+    ) -> Result<BaseCurrencyReport, AppError> {
+        context.currency_repo().get_base_currency_report().await
     }
 }
 
@@ -48,38 +83,83 @@ pub struct PaymentMutation;
 )]
 impl PaymentMutation {
     /// mutation for adding payments in general
-    /// Take in mind that u have to inject the path for the ticket manually
+    /// comprobante_path es el ticket_id que regresó /general/upload_ticket_payment, tiene que
+    /// ser del mismo socio y no estar en otro pago (o usar /general/submit_payment)
+    /// currency es opcional, si no se manda el pago queda en la moneda base
+    #[allow(clippy::too_many_arguments)]
     pub async fn create_user_payment(
         context: &GeneralContext,
         access_token: String,
         comprobante_path: String,
         name: String,
-        total_amount: f64,
+        total_amount: Money,
+        currency: Option<Currency>,
         ticket_number: String,
         account_number: String,
         being_payed: Vec<crate::models::PayedToInput>,
-    ) -> Result<String, String> {
-        context.payment_repo().create_payment(
+    ) -> Result<String, AppError> {
+        let payment_repo = context.payment_repo();
+        let receipt = payment_repo
+            .claim_receipt(&access_token, &comprobante_path)
+            .await?;
+
+        let created = payment_repo.create_payment(
             access_token,
             name,
-            comprobante_path,
+            receipt.key,
             total_amount,
+            currency.unwrap_or_else(base_currency),
             ticket_number,
             account_number,
             being_payed,
-        )
+        ).await;
+
+        // sin pago el comprobante se suelta para poder usarlo otra vez
+        let created = match created {
+            Ok(created) => created,
+            Err(err) => {
+                if let Err(release) = payment_repo.release_receipt(&receipt.ticket_id).await {
+                    println!("Couldn't release receipt {}: {release:?}", receipt.ticket_id);
+                }
+                return Err(err);
+            }
+        };
+
+        // el pago ya existe, si falla marcar el comprobante se avisa en vez de regresar error
+        // (el cliente reintentaría y quedaría el pago repetido), el GC lo marca después
+        if let Err(err) = payment_repo.link_receipt(&receipt.ticket_id).await {
+            println!("Couldn't link receipt {}: {err:?}", receipt.ticket_id);
+            return Ok(receipt_link_warning(created));
+        }
+        Ok(created)
     }
 
     /// Mutation para aprobar o rechazar un pago
+    /// el access_token es el del directivo que revisa, queda registrado en el historial del pago
     pub async fn approve_or_reject_payment(
         context: &GeneralContext,
+        access_token: String,
         id: String,
         new_state: String,
         commentary: String,
-    ) -> Result<Payment, String> {
+    ) -> Result<Payment, AppError> {
         context
             .payment_repo()
-            .approve_or_reject_payment(id, new_state, commentary)
+            .approve_or_reject_payment(access_token, id, new_state, commentary)
+            .await
+    }
+
+    /// Mutation para que un directivo actualice el tipo de cambio de una moneda
+    /// rate es cuánto vale 1 unidad de la moneda en la moneda base (ej: "7.75")
+    pub async fn set_exchange_rate(
+        context: &GeneralContext,
+        access_token: String,
+        currency: Currency,
+        rate: String,
+    ) -> Result<ExchangeRate, AppError> {
+        context
+            .currency_repo()
+            .set_exchange_rate(access_token, currency, rate)
             .await
     }
 }
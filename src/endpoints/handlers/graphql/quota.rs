@@ -1,21 +1,8 @@
 use crate::endpoints::handlers::configs::schema::GeneralContext;
 use crate::models::graphql::Quota;
+use crate::errors::AppError;
 
 pub struct QuotaQuery {}
-const MESES_ES: [&str; 12] = [
-    "Enero",
-    "Febrero",
-    "Marzo",
-    "Abril",
-    "Mayo",
-    "Junio",
-    "Julio",
-    "Agosto",
-    "Septiembre",
-    "Octubre",
-    "Noviembre",
-    "Diciembre",
-];
 
 #[juniper::graphql_object(
     Context = GeneralContext,
@@ -25,37 +12,39 @@ impl QuotaQuery {
     pub async fn get_pending_quotas(
         context: &GeneralContext,
         access_token: String,
-    ) -> Result<Vec<Quota>, String> {
-        context.quota_repo().get_pending_quotas(access_token)
+    ) -> Result<Vec<Quota>, AppError> {
+        context.quota_repo().get_pending_quotas(access_token).await
     }
 
     /// Retorna las cuotas mensuales de afiliado pendientes con campos adicionales para frontend
     pub async fn get_monthly_affiliate_quota(
         context: &GeneralContext,
         access_token: String,
-    ) -> Result<Vec<Quota>, String> {
-        let afiliados = context.payment_repo().get_all_users_for_affiliates()?;
+    ) -> Result<Vec<Quota>, AppError> {
+        let afiliados = context.user_repo().get_all_users_for_affiliates().await?;
         context
             .quota_repo()
             .get_monthly_affiliate_quota(afiliados, access_token)
+            .await
     }
 
     /// Retorna solo las cuotas de préstamo pendientes filtradas por lógica de negocio
     pub async fn get_quotas_prestamo_pendientes(
         context: &GeneralContext,
         access_token: String,
-    ) -> Result<Vec<Quota>, String> {
+    ) -> Result<Vec<Quota>, AppError> {
         context
             .quota_repo()
             .get_quotas_prestamo_pendientes(access_token)
+            .await
     }
 
     /// Retorna las cuotas de préstamo pendientes con campos adicionales para frontend
     pub async fn get_pending_loans_quotas(
         context: &GeneralContext,
         access_token: String,
-    ) -> Result<Vec<Quota>, String> {
-        context.quota_repo().get_pending_loans_quotas(access_token)
+    ) -> Result<Vec<Quota>, AppError> {
+        context.quota_repo().get_pending_loans_quotas(access_token).await
     }
 }
 
@@ -66,7 +55,8 @@ pub struct QuotaMutation;
 )]
 impl QuotaMutation {
     /// Crea una nueva cuota en el sistema (implementación pendiente)
-    pub async fn create_quota() -> Result<String, String> {
-        todo!()
+    /// mientras no exista regresa error en vez de tumbar el worker
+    pub async fn create_quota() -> Result<String, AppError> {
+        Err(AppError::conflict("Todavía no se pueden crear cuotas desde la api"))
     }
 }
@@ -0,0 +1,217 @@
+use std::sync::Arc;
+
+use futures::{future, StreamExt};
+
+use crate::endpoints::handlers::configs::schema::GeneralContext;
+use crate::models::dates::Date;
+use crate::models::graphql::{
+    FinancialReport, Fine, Member, Payment, PaymentStatusChangedEvent, Statement,
+};
+use crate::repos::auth::utils::hashing_composite_key;
+use crate::repos::graphql::events::{
+    subscribe_events, EventStream, FINE_ISSUED_CHANNEL, PAYMENT_STATUS_CHANGED_CHANNEL,
+    PAYMENT_SUBMITTED_CHANNEL,
+};
+use crate::repos::graphql::loader::RequestLoader;
+
+use super::{
+    attachment::{AttachmentMutation, AttachmentQuery},
+    fine::{FineMutation, FineQuery},
+    loan::{LoanMutation, LoanQuery},
+    payment::{PaymentMutation, PaymentQuery},
+    quota::{QuotaMutation, QuotaQuery},
+};
+use crate::errors::AppError;
+
+// Schema unificado que se sirve en /graphql
+// cada dominio queda como un campo del root, así el cliente puede pedir préstamos, multas y
+// cuotas de un socio en un solo request:
+//   { loan { getUserLoans(accessToken: "...") { id } } fine { getFinesById(accessToken: "...") { id } } }
+// y navegar el grafo desde el socio:
+//   { member(accessToken: "...", affiliateKey: "...") { loans { quotas { expDate } } payments { beingPayed { target { ... on Fine { id } } } } } }
+// las subscriptions van por websocket en el mismo /graphql (GET)
+// los endpoints viejos (/graphql/payment, /graphql/loan, ...) usan los mismos objetos
+
+pub struct Query;
+
+#[juniper::graphql_object(
+    Context = GeneralContext,
+)]
+impl Query {
+    /// queries de pagos, tipos de cambio y reportes
+    pub fn payment() -> PaymentQuery {
+        PaymentQuery {}
+    }
+
+    /// queries de préstamos
+    pub fn loan() -> LoanQuery {
+        LoanQuery {}
+    }
+
+    /// queries de multas
+    pub fn fine() -> FineQuery {
+        FineQuery {}
+    }
+
+    /// queries de cuotas
+    pub fn quota() -> QuotaQuery {
+        QuotaQuery {}
+    }
+
+    /// documentos de socios, préstamos y multas
+    pub fn attachment() -> AttachmentQuery {
+        AttachmentQuery {}
+    }
+
+    /// socio con sus préstamos, multas, pagos y cuotas pendientes
+    /// solo lo ve el mismo socio (dueño del access_token) o un directivo
+    pub async fn member(
+        context: &GeneralContext,
+        access_token: String,
+        affiliate_key: String,
+    ) -> Result<Member, AppError> {
+        let users = context.user_repo();
+        let member = users.get_member_by_affiliate_key(affiliate_key).await;
+        if users.is_directive(&access_token).await {
+            return member;
+        }
+        // a los demás no les decimos si el socio existe o no
+        match member {
+            Ok(member) if member.owner_key == hashing_composite_key(&[&access_token]) => Ok(member),
+            _ => Err(AppError::unauthorized("No puedes ver los datos de otro socio")),
+        }
+    }
+
+    /// el socio dueño del access_token
+    pub async fn me(context: &GeneralContext, access_token: String) -> Result<Member, AppError> {
+        context.user_repo().get_member_by_access_token(access_token).await
+    }
+
+    /// estado de cuenta del socio dueño del access_token (o de affiliate_key si lo pide un
+    /// directivo) entre from y to, en PDF o CSV está en /general/statement
+    pub async fn statement(
+        context: &GeneralContext,
+        access_token: String,
+        affiliate_key: Option<String>,
+        from: Option<Date>,
+        to: Option<Date>,
+    ) -> Result<Statement, AppError> {
+        context
+            .statement_repo()
+            .get_statement(access_token, affiliate_key, from, to)
+            .await
+    }
+
+    /// cobros por mes, cartera de préstamos, morosidad, multas y número de socios entre from
+    /// y to, solo para directivos
+    pub async fn financial_report(
+        context: &GeneralContext,
+        access_token: String,
+        from: Option<Date>,
+        to: Option<Date>,
+    ) -> Result<FinancialReport, AppError> {
+        context
+            .report_repo()
+            .get_financial_report(access_token, from, to)
+            .await
+    }
+}
+
+pub struct Mutation;
+
+#[juniper::graphql_object(
+    Context = GeneralContext,
+)]
+impl Mutation {
+    /// mutations de pagos y tipos de cambio
+    pub fn payment() -> PaymentMutation {
+        PaymentMutation
+    }
+
+    /// mutations de préstamos
+    pub fn loan() -> LoanMutation {
+        LoanMutation
+    }
+
+    /// mutations de multas
+    pub fn fine() -> FineMutation {
+        FineMutation
+    }
+
+    /// mutations de cuotas
+    pub fn quota() -> QuotaMutation {
+        QuotaMutation
+    }
+
+    /// revisión de documentos
+    pub fn attachment() -> AttachmentMutation {
+        AttachmentMutation
+    }
+}
+
+pub struct Subscription;
+
+// cada subscription abre su propia conexión pub/sub de redis, los repos publican en
+// repos::graphql::events después de escribir
+#[juniper::graphql_subscription(
+    Context = GeneralContext,
+)]
+impl Subscription {
+    /// avisa cuando un directivo acepta o rechaza un pago del socio dueño del access_token
+    pub async fn payment_status_changed(
+        context: &GeneralContext,
+        access_token: String,
+    ) -> Result<EventStream<PaymentStatusChangedEvent>, AppError> {
+        let owner_key = hashing_composite_key(&[&access_token]);
+        let events = subscribe_events::<PaymentStatusChangedEvent>(
+            context.pool()?.client(),
+            PAYMENT_STATUS_CHANGED_CHANNEL,
+        )
+        .await?;
+
+        Ok(fresh_per_event(
+            events.filter(move |event| future::ready(event.owner_key == owner_key)),
+            context.loader()?.clone(),
+        ))
+    }
+
+    /// cola en vivo de pagos nuevos (ON_REVISION), solo para directivos
+    pub async fn new_payment_submitted(
+        context: &GeneralContext,
+        access_token: String,
+    ) -> Result<EventStream<Payment>, AppError> {
+        if !context.user_repo().is_directive(&access_token).await {
+            return Err(AppError::unauthorized(
+                "Solo los directivos pueden ver la cola de pagos",
+            ));
+        }
+
+        let events =
+            subscribe_events::<Payment>(context.pool()?.client(), PAYMENT_SUBMITTED_CHANNEL).await?;
+
+        Ok(fresh_per_event(events, context.loader()?.clone()))
+    }
+
+    /// multas nuevas del socio dueño del access_token
+    pub async fn fine_issued(
+        context: &GeneralContext,
+        access_token: String,
+    ) -> Result<EventStream<Fine>, AppError> {
+        let owner_key = hashing_composite_key(&[&access_token]);
+        let events = subscribe_events::<Fine>(context.pool()?.client(), FINE_ISSUED_CHANNEL).await?;
+
+        Ok(fresh_per_event(
+            events.filter(move |fine| future::ready(fine.owner_key == owner_key)),
+            context.loader()?.clone(),
+        ))
+    }
+}
+
+/// el contexto de un websocket vive lo que dure la conexión, así que vaciamos la cache del
+/// loader antes de resolver cada evento (como si cada uno fuera un request nuevo)
+fn fresh_per_event<T>(
+    events: impl futures::Stream<Item = T> + Send + 'static,
+    loader: Arc<RequestLoader>,
+) -> EventStream<T> {
+    Box::pin(events.inspect(move |_| loader.clear()))
+}
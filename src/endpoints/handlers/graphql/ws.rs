@@ -0,0 +1,295 @@
+use std::convert::Infallible;
+use std::fmt;
+use std::pin::pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::{
+    http::header::{HeaderName, HeaderValue},
+    web::{Data, Payload},
+    HttpRequest, HttpResponse,
+};
+use actix_ws::{CloseCode, CloseReason, Message, MessageStream, ProtocolError, Session};
+use futures::{future, Sink, SinkExt, Stream, StreamExt};
+use juniper::ScalarValue;
+use juniper_graphql_ws::{graphql_transport_ws, graphql_ws, ArcSchema, ConnectionConfig};
+use serde_json::{json, Value as JsonValue};
+
+use super::limits::{PersistedQueryRequest, QueryLimits};
+use super::root::{Mutation, Query, Subscription};
+use crate::endpoints::handlers::configs::connection_pool::RedisPool;
+use crate::endpoints::handlers::configs::schema::{GeneralContext, GeneralSchema};
+use crate::errors::AppError;
+
+// websocket de /graphql (graphql-transport-ws o el viejo graphql-ws, según lo que pida el
+// cliente en Sec-WebSocket-Protocol). es el mismo loop de juniper_actix pero cada operación
+// pasa antes por QueryLimits, y solo se aceptan subscriptions: el contexto dura lo que dure la
+// conexión y el loader solo se vacía por evento, las queries y mutations van por POST
+
+type Schema = GeneralSchema<Query, Mutation, Subscription>;
+
+pub async fn graphql_subscriptions(
+    req: HttpRequest,
+    stream: Payload,
+    pool: Data<RedisPool>,
+    limits: Data<QueryLimits>,
+    schema: Data<Schema>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let protocol = Protocol::from_request(&req);
+    let config = ConnectionConfig::new(GeneralContext::new(pool.get_ref().clone()))
+        // sin esto algunos proxies cierran el websocket por inactividad
+        .with_keep_alive_interval(Duration::from_secs(15));
+    let schema = schema.into_inner();
+
+    let (mut resp, session, ws_rx) = actix_ws::handle(&req, stream)?;
+    let input = checked_input(ws_rx, session.clone(), protocol, limits, schema.clone());
+
+    match protocol {
+        Protocol::TransportWs => {
+            let (s_tx, s_rx) =
+                graphql_transport_ws::Connection::new(ArcSchema(schema), config).split();
+            let replies = s_rx.map(|output| match output {
+                graphql_transport_ws::Output::Message(message) => Reply::from_message(&message),
+                graphql_transport_ws::Output::Close { code, message } => Reply::Close(code, message),
+            });
+            actix_web::rt::spawn(serve(input, s_tx, replies, session));
+        }
+        Protocol::LegacyWs => {
+            let (s_tx, s_rx) = graphql_ws::Connection::new(ArcSchema(schema), config).split();
+            let replies = s_rx.map(|message| Reply::from_message(&message));
+            actix_web::rt::spawn(serve(input, s_tx, replies, session));
+        }
+    }
+
+    resp.headers_mut().insert(
+        HeaderName::from_static("sec-websocket-protocol"),
+        HeaderValue::from_static(protocol.name()),
+    );
+    Ok(resp)
+}
+
+#[derive(Clone, Copy)]
+enum Protocol {
+    TransportWs,
+    LegacyWs,
+}
+
+impl Protocol {
+    fn from_request(req: &HttpRequest) -> Self {
+        match req.headers().get("sec-websocket-protocol") {
+            Some(value) if value.as_bytes() == b"graphql-ws" => Protocol::LegacyWs,
+            _ => Protocol::TransportWs,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Protocol::TransportWs => "graphql-transport-ws",
+            Protocol::LegacyWs => "graphql-ws",
+        }
+    }
+
+    /// tipo del mensaje con el que el cliente empieza una operación
+    fn start_type(&self) -> &'static str {
+        match self {
+            Protocol::TransportWs => "subscribe",
+            Protocol::LegacyWs => "start",
+        }
+    }
+
+    /// respuesta a una operación que no se ejecutó, con la misma forma que los errores de graphql
+    fn error_message(&self, id: &str, err: &AppError) -> String {
+        let error = json!({
+            "message": err.message(),
+            "extensions": { "code": err.code() },
+        });
+        let payload = match self {
+            Protocol::TransportWs => json!([error]),
+            Protocol::LegacyWs => error,
+        };
+        json!({ "type": "error", "id": id, "payload": payload }).to_string()
+    }
+}
+
+/// lo que se hace con un mensaje del cliente
+enum Checked {
+    /// pasa a juniper_graphql_ws, con la persisted query ya resuelta
+    Forward(Message),
+    /// la operación no pasó los límites, se contesta el error sin ejecutarla
+    Reject(String),
+}
+
+fn check_message(
+    message: Message,
+    protocol: Protocol,
+    limits: &QueryLimits,
+    schema: &Schema,
+) -> Checked {
+    let raw = match &message {
+        Message::Text(text) => text.as_bytes(),
+        Message::Binary(bytes) => bytes.as_ref(),
+        _ => return Checked::Forward(message),
+    };
+    // si no es JSON lo rechaza juniper_graphql_ws como cualquier mensaje mal formado
+    let Ok(mut parsed) = serde_json::from_slice::<JsonValue>(raw) else {
+        return Checked::Forward(message);
+    };
+    if parsed["type"] != protocol.start_type() {
+        return Checked::Forward(message);
+    }
+
+    let id = parsed["id"].as_str().unwrap_or_default().to_owned();
+    let request = serde_json::from_value::<PersistedQueryRequest>(parsed["payload"].take())
+        .map_err(|_| AppError::validation("Payload inválido", &["payload"]))
+        .and_then(|request| limits.prepare_subscription(request, &schema.schema));
+
+    match request {
+        Ok(request) => {
+            parsed["payload"] = json!(request);
+            Checked::Forward(Message::Text(parsed.to_string().into()))
+        }
+        Err(err) => Checked::Reject(protocol.error_message(&id, &err)),
+    }
+}
+
+/// mensajes del cliente ya revisados, las operaciones rechazadas se contestan acá mismo
+fn checked_input(
+    ws_rx: MessageStream,
+    session: Session,
+    protocol: Protocol,
+    limits: Data<QueryLimits>,
+    schema: Arc<Schema>,
+) -> impl Stream<Item = Result<ClientMessage, ProtocolError>> {
+    ws_rx.filter_map(move |message| {
+        let mut session = session.clone();
+        let limits = limits.clone();
+        let schema = schema.clone();
+        async move {
+            let message = match message {
+                Ok(message) => message,
+                Err(err) => return Some(Err(err)),
+            };
+            match check_message(message, protocol, &limits, &schema) {
+                Checked::Forward(message) => Some(Ok(ClientMessage(message))),
+                Checked::Reject(error) => {
+                    // si ya se cerró, el loop de abajo termina solo
+                    let _ = session.text(error).await;
+                    None
+                }
+            }
+        }
+    })
+}
+
+/// lo que se le manda al cliente
+enum Reply {
+    Text(String),
+    Close(u16, String),
+}
+
+impl Reply {
+    fn from_message(message: &impl serde::Serialize) -> Self {
+        match serde_json::to_string(message) {
+            Ok(text) => Reply::Text(text),
+            Err(err) => Reply::Close(
+                CloseCode::Error.into(),
+                format!("error serializing response: {err}"),
+            ),
+        }
+    }
+}
+
+/// pasa los mensajes del cliente a la conexión de juniper y sus respuestas al cliente, hasta
+/// que alguno de los dos lados termine
+async fn serve(
+    input: impl Stream<Item = Result<ClientMessage, ProtocolError>>,
+    connection: impl Sink<ClientMessage, Error = Infallible>,
+    replies: impl Stream<Item = Reply>,
+    mut session: Session,
+) {
+    let input = input.forward(connection.sink_map_err(|err| match err {}));
+    let output = pin!(async move {
+        let mut replies = pin!(replies);
+        while let Some(reply) = replies.next().await {
+            match reply {
+                Reply::Text(text) => {
+                    if session.text(text).await.is_err() {
+                        return;
+                    }
+                }
+                Reply::Close(code, message) => {
+                    let _ = session
+                        .close(Some(CloseReason {
+                            code: code.into(),
+                            description: Some(message),
+                        }))
+                        .await;
+                    return;
+                }
+            }
+        }
+        let _ = session
+            .close(Some((CloseCode::Normal, "Normal Closure").into()))
+            .await;
+    });
+
+    // acá no hay a quién devolverle errores
+    let _ = future::select(pin!(input), output).await;
+}
+
+/// mensaje del websocket tal como lo espera juniper_graphql_ws
+#[derive(Debug)]
+struct ClientMessage(Message);
+
+impl<S: ScalarValue> TryFrom<ClientMessage> for graphql_transport_ws::Input<S> {
+    type Error = ClientMessageError;
+
+    fn try_from(message: ClientMessage) -> Result<Self, Self::Error> {
+        match message.0 {
+            Message::Text(text) => serde_json::from_slice(text.as_bytes())
+                .map(Self::Message)
+                .map_err(ClientMessageError::Serde),
+            Message::Binary(bytes) => serde_json::from_slice(bytes.as_ref())
+                .map(Self::Message)
+                .map_err(ClientMessageError::Serde),
+            Message::Close(_) => Ok(Self::Close),
+            other => Err(ClientMessageError::Unexpected(other)),
+        }
+    }
+}
+
+impl<S: ScalarValue> TryFrom<ClientMessage> for graphql_ws::ClientMessage<S> {
+    type Error = ClientMessageError;
+
+    fn try_from(message: ClientMessage) -> Result<Self, Self::Error> {
+        match message.0 {
+            Message::Text(text) => {
+                serde_json::from_slice(text.as_bytes()).map_err(ClientMessageError::Serde)
+            }
+            Message::Binary(bytes) => {
+                serde_json::from_slice(bytes.as_ref()).map_err(ClientMessageError::Serde)
+            }
+            Message::Close(_) => Ok(Self::ConnectionTerminate),
+            other => Err(ClientMessageError::Unexpected(other)),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum ClientMessageError {
+    Serde(serde_json::Error),
+    Unexpected(Message),
+}
+
+impl fmt::Display for ClientMessageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientMessageError::Serde(err) => write!(f, "`serde` error: {err}"),
+            ClientMessageError::Unexpected(message) => {
+                write!(f, "unexpected message received from client: {message:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ClientMessageError {}
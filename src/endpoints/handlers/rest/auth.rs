@@ -1,6 +1,8 @@
 use actix_web::{web, HttpResponse};
 
 use crate::{
+    endpoints::handlers::configs::connection_pool::RedisPool,
+    errors::AppError,
     models::auth::{LoginInfo, SignUpInfo, SecurityQuestionsResponse, ValidateSecurityAnswerRequest, 
                    ValidateSecurityAnswerResponse, ResetPasswordRequest, SECURITY_QUESTIONS, ConfigureAllSecurityAnswersRequest},
     repos::auth::{create_user_with_access_token, get_user_access_token, validate_security_answer,
@@ -11,41 +13,51 @@ use crate::{
 /// 
 /// POST /general/configure-security-answers
 pub async fn configure_all_security_answers_handler(
+    pool: web::Data<RedisPool>,
     body: web::Json<ConfigureAllSecurityAnswersRequest>,
-) -> HttpResponse {
+) -> Result<HttpResponse, AppError> {
     let data = body.into_inner();
-    match configure_all_security_answers(data.access_token, data.answers) {
-        Ok(_) => HttpResponse::Ok().json(crate::models::StatusMessage {
-            message: "Respuestas de seguridad guardadas correctamente".to_string(),
-        }),
-        Err(err) => HttpResponse::BadRequest().json(crate::models::StatusMessage {
-            message: err.message,
-        }),
-    }
+    configure_all_security_answers(&pool, data.access_token, data.answers).await?;
+
+    Ok(HttpResponse::Ok().json(crate::models::StatusMessage {
+        message: "Respuestas de seguridad guardadas correctamente".to_string(),
+    }))
 }
 
 
 //Just for returning the access token for the user
 //Won't be use on mobile prod
-pub async fn user_sign_up(user_data: web::Json<SignUpInfo>) -> HttpResponse {
+// los errores salen con su status http (409 si el usuario ya existe)
+pub async fn user_sign_up(
+    pool: web::Data<RedisPool>,
+    user_data: web::Json<SignUpInfo>,
+) -> Result<HttpResponse, AppError> {
     let data = user_data.into_inner();
 
-    HttpResponse::Ok().json(create_user_with_access_token(
+    let token_info = create_user_with_access_token(
+        &pool,
         data.user_name.to_string().clone(),
         data.pass_code.to_string().clone(),
-        data.real_name.to_string().clone(), //Let's assume it goes correctly
-    ))
+        data.real_name.to_string().clone(),
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(token_info))
 }
 
 //This will be used on mobile prod
-pub async fn user_login(user_data: web::Query<LoginInfo>) -> HttpResponse {
+// 401 si el usuario/contraseña no coinciden
+pub async fn user_login(
+    pool: web::Data<RedisPool>,
+    user_data: web::Query<LoginInfo>,
+) -> Result<HttpResponse, AppError> {
     let data = user_data.into_inner();
-    println!("{data:?}");
 
-    HttpResponse::Ok().json(get_user_access_token(
-        data.user_name.to_string(),
-        data.pass_code.to_string(),
-    ))
+    let token_info =
+        get_user_access_token(&pool, data.user_name.to_string(), data.pass_code.to_string())
+            .await?;
+
+    Ok(HttpResponse::Ok().json(token_info))
 }
 
 
@@ -64,38 +76,36 @@ pub async fn get_security_questions_handler(query: web::Query<crate::models::aut
 /// 
 /// POST /general/validate-security-answer
 pub async fn validate_security_answer_handler(
+    pool: web::Data<RedisPool>,
     body: web::Json<ValidateSecurityAnswerRequest>,
-) -> HttpResponse {
+) -> Result<HttpResponse, AppError> {
     let data = body.into_inner();
     
-    match validate_security_answer(data.user_name, data.question_index, data.security_answer) {
-        Ok(_) => {
-            HttpResponse::Ok().json(ValidateSecurityAnswerResponse {
-                message: "Respuesta válida".to_string(),
-            })
-        }
-        Err(err) => {
-            HttpResponse::BadRequest().json(ValidateSecurityAnswerResponse {
-                message: err.message,
-            })
-        }
-    }
+    validate_security_answer(&pool, data.user_name, data.question_index, data.security_answer)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(ValidateSecurityAnswerResponse {
+        message: "Respuesta válida".to_string(),
+    }))
 }
 
 /// resetea la contraseña validando respuesta de seguridad
 /// 
 /// POST /general/reset-password
 pub async fn reset_password_handler(
+    pool: web::Data<RedisPool>,
     body: web::Json<ResetPasswordRequest>,
-) -> HttpResponse {
+) -> Result<HttpResponse, AppError> {
     let data = body.into_inner();
     
-    match reset_password(data.user_name, data.question_index, data.security_answer, data.new_pass_code) {
-        Ok(token_info) => HttpResponse::Ok().json(token_info),
-        Err(err) => {
-            HttpResponse::BadRequest().json(crate::models::StatusMessage {
-                message: err.message,
-            })
-        }
-    }
+    let token_info = reset_password(
+        &pool,
+        data.user_name,
+        data.question_index,
+        data.security_answer,
+        data.new_pass_code,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(token_info))
 }
@@ -1,58 +1,316 @@
-use actix_files::NamedFile;
 use actix_multipart::form::MultipartForm;
 use actix_web::{
-    mime::Mime,
-    web::{Data, Query},
-    HttpRequest, HttpResponse, Responder,
+    HttpRequest, HttpResponse,
+    http::{
+        StatusCode,
+        header::{
+            ACCEPT_RANGES, CacheControl, CacheDirective, ContentDisposition, ContentRange,
+            ContentRangeSpec, DispositionParam, DispositionType, ETag, EntityTag, Header,
+            IfNoneMatch, IfRange, Range,
+        },
+    },
+    web::{Data, Path, Query},
 };
-use aws_sdk_s3::Client as S3Client;
 
 use crate::{
-    endpoints::handlers::rest::file,
-    models::{
-        file::{FilePayloadRetrival, FilePayloadUpload, UploadForm},
-        StatusMessage,
+    endpoints::handlers::configs::{connection_pool::RedisPool, schema::GeneralContext},
+    errors::AppError,
+    models::file::{
+        AttachmentRetrival, AttachmentUpload, ExportDownload, ExportEntity, FilePayloadRetrival,
+        FilePayloadSignUrl, FilePayloadUpload, PaymentSubmissionForm, StatementDownload,
+        UploadForm,
+    },
+    repos::file::{
+        TicketFile,
+        attachment::{get_attachment_file, upload_attachment},
+        export::{export, export_file_name},
+        get_signed_ticket_payment, get_ticket_payment,
+        ocr::ReceiptOcr,
+        payment_link::submit_payment_with_receipt,
+        sign_ticket_url,
+        signed_url::ReceiptUrlSigner,
+        statement::{render_statement, statement_file_name},
+        store::{BlobStore, ByteRange},
+        upload_ticket_payment,
     },
-    repos::file::{get_ticket_payment, upload_ticket_payment},
 };
 
 pub async fn upload_ticket_for_payment(
     MultipartForm(form): MultipartForm<UploadForm>,
     file_upload_credentials: Query<FilePayloadUpload>,
-    s3_client: Data<S3Client>,
-    bucket_name: Data<String>,
-) -> HttpResponse {
+    pool: Data<RedisPool>,
+    blob_store: Data<dyn BlobStore>,
+    // solo está si OCR_ENGINE lo prende (ver main)
+    ocr: Option<Data<dyn ReceiptOcr>>,
+) -> Result<HttpResponse, AppError> {
     // safe check for just letting users upload payments
 
     let file_upload_credentials = file_upload_credentials.into_inner();
 
-    HttpResponse::Ok().json(
-        upload_ticket_payment(
-            form,
-            file_upload_credentials.access_token.clone(),
-            s3_client.into_inner(),
-            bucket_name.into_inner(),
-        )
-        .await,
+    let upload_info = upload_ticket_payment(
+        form,
+        file_upload_credentials.access_token,
+        &pool,
+        blob_store.get_ref(),
+        ocr.as_ref().map(|ocr| ocr.get_ref()),
     )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(upload_info))
 }
 
-pub async fn get_ticket_from_payment(
-    file_getter_credentials: Query<FilePayloadRetrival>,
-    s3_client: Data<S3Client>,
-    bucket_name: Data<String>,
-) -> HttpResponse {
+// comprobante + pago en un solo request, el pago queda apuntando al comprobante que se subió
+pub async fn submit_payment(
+    MultipartForm(form): MultipartForm<PaymentSubmissionForm>,
+    credentials: Query<FilePayloadUpload>,
+    pool: Data<RedisPool>,
+    blob_store: Data<dyn BlobStore>,
+    ocr: Option<Data<dyn ReceiptOcr>>,
+) -> Result<HttpResponse, AppError> {
+    let submitted = submit_payment_with_receipt(
+        form,
+        credentials.into_inner().access_token,
+        &pool,
+        blob_store.get_ref(),
+        ocr.as_ref().map(|ocr| ocr.get_ref()),
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(submitted))
+}
+
+// URL firmada de corta duración para el comprobante, así el frontend lo puede poner en un
+// <img> o abrirlo en otra pestaña sin meter el access_token en la URL
+pub async fn get_ticket_payment_url(
+    file_getter_credentials: Query<FilePayloadSignUrl>,
+    pool: Data<RedisPool>,
+    blob_store: Data<dyn BlobStore>,
+    signer: Data<ReceiptUrlSigner>,
+) -> Result<HttpResponse, AppError> {
     let file_getter_credentials = file_getter_credentials.into_inner();
 
-    match get_ticket_payment(
+    let signed_url = sign_ticket_url(
         file_getter_credentials.access_token,
         file_getter_credentials.ticket_id,
-        s3_client.into_inner(),
-        bucket_name.into_inner(),
+        &pool,
+        blob_store.get_ref(),
+        &signer,
     )
-    .await
-    {
-        Ok(res) => res,
-        Err(msg) => HttpResponse::Ok().json(msg),
+    .await?;
+
+    Ok(HttpResponse::Ok().json(signed_url))
+}
+
+// el comprobante (y los documentos en get_attachment) sale directo del BlobStore a la
+// respuesta, con soporte de Range (un solo rango) para que el frontend pueda mostrar PDFs
+// grandes por partes
+pub async fn get_ticket_from_payment(
+    req: HttpRequest,
+    file_getter_credentials: Query<FilePayloadRetrival>,
+    pool: Data<RedisPool>,
+    blob_store: Data<dyn BlobStore>,
+    signer: Data<ReceiptUrlSigner>,
+) -> Result<HttpResponse, AppError> {
+    let file_getter_credentials = file_getter_credentials.into_inner();
+
+    // con firma no hace falta el access_token, la firma ya se dio con los permisos revisados
+    let file = match file_getter_credentials {
+        FilePayloadRetrival {
+            ticket_id,
+            expires: Some(expires),
+            signature: Some(signature),
+            ..
+        } => {
+            get_signed_ticket_payment(
+                ticket_id,
+                expires,
+                &signature,
+                &pool,
+                blob_store.get_ref(),
+                &signer,
+            )
+            .await?
+        }
+        FilePayloadRetrival {
+            ticket_id,
+            access_token: Some(access_token),
+            ..
+        } => get_ticket_payment(access_token, ticket_id, &pool, blob_store.get_ref()).await?,
+        _ => return Err(AppError::unauthorized("Couldn't verify user")),
+    };
+
+    send_stored_file(&req, file, blob_store.get_ref()).await
+}
+
+pub async fn upload_attachment_file(
+    MultipartForm(form): MultipartForm<UploadForm>,
+    upload: Query<AttachmentUpload>,
+    pool: Data<RedisPool>,
+    blob_store: Data<dyn BlobStore>,
+) -> Result<HttpResponse, AppError> {
+    let attachment =
+        upload_attachment(form, upload.into_inner(), &pool, blob_store.get_ref()).await?;
+
+    Ok(HttpResponse::Ok().json(attachment))
+}
+
+pub async fn get_attachment(
+    req: HttpRequest,
+    credentials: Query<AttachmentRetrival>,
+    pool: Data<RedisPool>,
+    blob_store: Data<dyn BlobStore>,
+) -> Result<HttpResponse, AppError> {
+    let credentials = credentials.into_inner();
+    let file = get_attachment_file(credentials.access_token, credentials.id, &pool).await?;
+
+    send_stored_file(&req, file, blob_store.get_ref()).await
+}
+
+// estado de cuenta en PDF o CSV, lo mismo que Query.statement
+pub async fn get_statement(
+    download: Query<StatementDownload>,
+    pool: Data<RedisPool>,
+) -> Result<HttpResponse, AppError> {
+    let download = download.into_inner();
+    let statement = GeneralContext::new(pool.get_ref().clone())
+        .statement_repo()
+        .get_statement(
+            download.access_token,
+            download.affiliate_key,
+            download.from,
+            download.to,
+        )
+        .await?;
+
+    let body = render_statement(&statement, download.format)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(download.format.content_type())
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(statement_file_name(
+                &statement,
+                download.format,
+            ))],
+        })
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .body(body))
+}
+
+/// pagos, préstamos, multas o cuotas en CSV o XLSX, se manda en streaming
+pub async fn get_export(
+    entity: Path<ExportEntity>,
+    download: Query<ExportDownload>,
+    pool: Data<RedisPool>,
+) -> Result<HttpResponse, AppError> {
+    let entity = entity.into_inner();
+    let download = download.into_inner();
+    let file_name = export_file_name(entity, &download);
+    let format = download.format;
+
+    let body = export(
+        GeneralContext::new(pool.get_ref().clone()),
+        entity,
+        download,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(file_name)],
+        })
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .streaming(body))
+}
+
+/// manda el archivo del BlobStore con ETag, If-None-Match y Range
+async fn send_stored_file(
+    req: &HttpRequest,
+    file: TicketFile,
+    blob_store: &dyn BlobStore,
+) -> Result<HttpResponse, AppError> {
+    // un ticket_id nunca se reutiliza ni se sobreescribe, sirve de ETag
+    let etag = EntityTag::new_strong(file.ticket_id.clone());
+
+    if let Ok(if_none_match) = IfNoneMatch::parse(req) {
+        let matches = match if_none_match {
+            IfNoneMatch::Any => true,
+            IfNoneMatch::Items(tags) => tags.iter().any(|tag| tag.weak_eq(&etag)),
+        };
+        if matches {
+            return Ok(HttpResponse::NotModified()
+                .insert_header(ETag(etag))
+                .finish());
+        }
+    }
+
+    let range = match requested_range(req, &etag, &file) {
+        Ok(range) => range,
+        Err(()) => {
+            return Ok(HttpResponse::RangeNotSatisfiable()
+                .insert_header(ContentRange(ContentRangeSpec::Bytes {
+                    range: None,
+                    instance_length: Some(file.size),
+                }))
+                .finish());
+        }
+    };
+
+    let Some(body) = blob_store.stream(&file.key, range).await? else {
+        return Err(AppError::not_found("Ticket not found"));
+    };
+
+    let mut response = HttpResponse::build(match range {
+        Some(_) => StatusCode::PARTIAL_CONTENT,
+        None => StatusCode::OK,
+    });
+    response
+        .content_type(file.content_type.as_str())
+        .insert_header(ETag(etag))
+        .insert_header((ACCEPT_RANGES, "bytes"))
+        // lleva datos del socio, ningún proxy lo debe guardar
+        .insert_header(CacheControl(vec![CacheDirective::Private]));
+
+    if let Some(range) = range {
+        response.insert_header(ContentRange(ContentRangeSpec::Bytes {
+            range: Some((range.start, range.end)),
+            instance_length: Some(file.size),
+        }));
     }
+
+    Ok(response
+        .no_chunking(range.map_or(file.size, |range| range.len()))
+        .streaming(body))
+}
+
+/// el rango del header Range, None para mandar el archivo completo
+/// varios rangos o un If-Range que no coincide también mandan el archivo completo,
+/// Err si el rango queda fuera del archivo (416)
+fn requested_range(
+    req: &HttpRequest,
+    etag: &EntityTag,
+    file: &TicketFile,
+) -> Result<Option<ByteRange>, ()> {
+    let Ok(Range::Bytes(specs)) = Range::parse(req) else {
+        return Ok(None);
+    };
+    let [spec] = specs.as_slice() else {
+        return Ok(None);
+    };
+
+    if let Ok(if_range) = IfRange::parse(req) {
+        let still_valid = match if_range {
+            IfRange::EntityTag(tag) => tag.strong_eq(etag),
+            IfRange::Date(_) => false,
+        };
+        if !still_valid {
+            return Ok(None);
+        }
+    }
+
+    spec.to_satisfiable_range(file.size)
+        .map(|(start, end)| Some(ByteRange { start, end }))
+        .ok_or(())
 }
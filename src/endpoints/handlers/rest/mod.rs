@@ -1,2 +1,2 @@
 pub(crate) mod auth;
-// pub mod file; // COMENTADO POR AHORA PARA ENFOCARSE EN RECOVER-PASSWORD
+pub mod file;
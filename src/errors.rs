@@ -0,0 +1,161 @@
+use std::fmt::Display;
+
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use juniper::{FieldError, IntoFieldError, Object, ScalarValue, Value};
+use serde::Serialize;
+
+/// error de toda la api, lo usan los repos, los resolvers de graphql y los handlers REST
+/// el mensaje es para humanos, el código (extensions.code en graphql) es lo que debería
+/// revisar el frontend
+#[derive(Debug, Clone, PartialEq)]
+pub enum AppError {
+    /// el modelo (pago, multa, socio, ...) no existe
+    NotFound(String),
+    /// token inválido o sin permisos para la operación
+    Unauthorized(String),
+    /// datos de entrada inválidos, `fields` son los argumentos que hay que corregir
+    Validation { message: String, fields: Vec<String> },
+    /// la operación choca con el estado actual (pago ya finalizado, usuario repetido, ...)
+    Conflict(String),
+    /// redis (o el almacenamiento de comprobantes) no respondió o regresó algo que no se pudo leer
+    Storage(String),
+    /// el documento de graphql pasa el límite de profundidad o complejidad, no se ejecuta
+    QueryTooComplex(String),
+    /// el hash no está en el allow-list de persisted queries (o se mandó una query suelta
+    /// cuando solo se aceptan persisted queries)
+    PersistedQueryNotFound(String),
+}
+
+impl AppError {
+    pub fn not_found(message: impl Into<String>) -> Self {
+        AppError::NotFound(message.into())
+    }
+
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        AppError::Unauthorized(message.into())
+    }
+
+    pub fn validation(message: impl Into<String>, fields: &[&str]) -> Self {
+        AppError::Validation {
+            message: message.into(),
+            fields: fields.iter().map(|field| field.to_string()).collect(),
+        }
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        AppError::Conflict(message.into())
+    }
+
+    pub fn storage(message: impl Into<String>) -> Self {
+        AppError::Storage(message.into())
+    }
+
+    pub fn query_too_complex(message: impl Into<String>) -> Self {
+        AppError::QueryTooComplex(message.into())
+    }
+
+    pub fn persisted_query_not_found(message: impl Into<String>) -> Self {
+        AppError::PersistedQueryNotFound(message.into())
+    }
+
+    /// código estable para el cliente, no cambia aunque cambie el mensaje
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::NotFound(_) => "NOT_FOUND",
+            AppError::Unauthorized(_) => "UNAUTHORIZED",
+            AppError::Validation { .. } => "VALIDATION",
+            AppError::Conflict(_) => "CONFLICT",
+            AppError::Storage(_) => "STORAGE",
+            AppError::QueryTooComplex(_) => "QUERY_TOO_COMPLEX",
+            AppError::PersistedQueryNotFound(_) => "PERSISTED_QUERY_NOT_FOUND",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            AppError::NotFound(message)
+            | AppError::Unauthorized(message)
+            | AppError::Validation { message, .. }
+            | AppError::Conflict(message)
+            | AppError::Storage(message)
+            | AppError::QueryTooComplex(message)
+            | AppError::PersistedQueryNotFound(message) => message,
+        }
+    }
+
+    /// campos con error, solo las validaciones traen
+    pub fn fields(&self) -> &[String] {
+        match self {
+            AppError::Validation { fields, .. } => fields,
+            _ => &[],
+        }
+    }
+}
+
+impl Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<redis::RedisError> for AppError {
+    fn from(_: redis::RedisError) -> Self {
+        AppError::storage("Couldn't reach the database")
+    }
+}
+
+// graphql: { message, extensions: { code, fields? } }
+impl<S: ScalarValue> IntoFieldError<S> for AppError {
+    fn into_field_error(self) -> FieldError<S> {
+        let mut extensions = Object::with_capacity(2);
+        extensions.add_field("code", Value::scalar(self.code().to_string()));
+        if !self.fields().is_empty() {
+            extensions.add_field(
+                "fields",
+                Value::list(
+                    self.fields()
+                        .iter()
+                        .map(|field| Value::scalar(field.clone()))
+                        .collect(),
+                ),
+            );
+        }
+
+        FieldError::new(self.message(), Value::Object(extensions))
+    }
+}
+
+/// cuerpo de los errores REST, `message` se mantiene igual que en StatusMessage
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    message: &'a str,
+    code: &'static str,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    fields: &'a [String],
+}
+
+// REST: el mismo error con su status http
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Validation { .. } => StatusCode::BAD_REQUEST,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::QueryTooComplex(_) | AppError::PersistedQueryNotFound(_) => {
+                StatusCode::BAD_REQUEST
+            }
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(ErrorBody {
+            message: self.message(),
+            code: self.code(),
+            fields: self.fields(),
+        })
+    }
+}
@@ -1,5 +1,6 @@
 pub mod config;
 pub mod endpoints;
+pub mod errors;
 pub mod models;
 pub mod repos;
 pub mod test_sync;
@@ -1,12 +1,21 @@
 use actix_cors::Cors;
-use actix_web::{App, HttpServer};
-// use aws_config::{BehaviorVersion, Region, SdkConfig};
-// use aws_sdk_s3::Client as S3Client;
-// use aws_smithy_http_client::{Builder, tls};
+use actix_web::{web::Data, App, HttpServer};
 use general_api::config::Env;
-// use general_api::endpoints::file_endpoints::{self, file_endpoints};
+use general_api::endpoints::handlers::configs::connection_pool::create_pool;
+use general_api::endpoints::handlers::graphql::limits::QueryLimits;
+use general_api::models::currency::init_base_currency;
+use general_api::models::dates::init_cooperative_timezone;
+use general_api::repos::migrations::run_migrations;
 use general_api::endpoints::{
-    auth_endpoints::auth_config, graphql_endpoints::graphql_config, health_config,
+    auth_endpoints::auth_config, file_endpoints::file_endpoints, graphql_endpoints::graphql_config,
+    health_config,
+};
+use general_api::repos::file::{
+    create_blob_store,
+    ocr::{create_receipt_ocr, ReceiptOcr},
+    payment_link::run_orphan_receipt_gc,
+    signed_url::ReceiptUrlSigner,
+    store::BlobStore,
 };
 use std::fs;
 
@@ -18,15 +27,50 @@ async fn main() -> std::io::Result<()> {
     let config = Env::env_init();
 
     let port = config.port;
-    let host = config.host;
-    // let bucket_name = config.bucket_name;
+    let host = config.host.clone();
 
     println!("{}", config.redis_url);
     env_logger::init();
 
-    // S3 client pool (ready for S3 endpoints when path issue is resolved)
-    // let s3_config: SdkConfig = aws_config::load_from_env().await;
-    // let s3_client = S3Client::new(&s3_config);
+    init_cooperative_timezone(&config.cooperative_timezone)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    init_base_currency(&config.base_currency)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+
+    // una sola conexión multiplexada para toda la app (todos los workers la comparten)
+    // si el REDIS_URL está mal mejor no levantar el server
+    let pool = create_pool(&config)
+        .map(Data::new)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+
+    // límites de graphql y allow-list de persisted queries, un archivo mal armado no levanta
+    let query_limits = QueryLimits::from_env(&config)
+        .map(Data::new)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+
+    // migraciones de datos (se saltan solas si ya corrieron)
+    if let Err(e) = run_migrations(&pool).await {
+        println!("Couldn't run migrations: {}", e);
+    }
+
+    // comprobantes en disco o en S3/MinIO, compartido por todos los workers
+    let blob_store = create_blob_store(&config)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+    // las URLs firmadas de los comprobantes tienen que validar en cualquier worker
+    let receipt_url_signer = Data::new(ReceiptUrlSigner::from_env(&config));
+
+    // comprobantes que se subieron y nunca llegaron a un pago
+    actix_web::rt::spawn(run_orphan_receipt_gc(
+        pool.get_ref().clone(),
+        blob_store.clone(),
+        chrono::Duration::hours(config.receipt_orphan_ttl_hours as i64),
+    ));
+    let blob_store: Data<dyn BlobStore> = Data::from(blob_store);
+
+    // OCR de los comprobantes, solo si OCR_ENGINE lo prende
+    let receipt_ocr: Option<Data<dyn ReceiptOcr>> = create_receipt_ocr(&config)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?
+        .map(Data::from);
 
     HttpServer::new(move || {
         let cors = Cors::default()
@@ -35,11 +79,17 @@ async fn main() -> std::io::Result<()> {
             .allow_any_header()
             .max_age(3600);
 
-        App::new()
-            .configure(graphql_config)
-            // .configure(|config| {
-            //     file_endpoints(config, s3_client.to_owned(), bucket_name.to_owned())
-            // })
+        let mut app = App::new()
+            .app_data(pool.clone())
+            .app_data(query_limits.clone())
+            .app_data(blob_store.clone())
+            .app_data(receipt_url_signer.clone());
+        if let Some(receipt_ocr) = &receipt_ocr {
+            app = app.app_data(receipt_ocr.clone());
+        }
+
+        app.configure(graphql_config)
+            .configure(file_endpoints)
             .configure(health_config)
             .configure(auth_config)
             .wrap(cors)
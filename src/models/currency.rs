@@ -0,0 +1,103 @@
+use std::{collections::HashMap, fmt, str::FromStr, sync::OnceLock};
+
+use juniper::GraphQLEnum;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::AppError;
+use crate::models::money::Money;
+
+// moneda en la que la cooperativa lleva sus reportes, se inicializa en main desde el Env
+static BASE_CURRENCY: OnceLock<Currency> = OnceLock::new();
+
+/// fija la moneda base (ej: "GTQ"), solo la primera llamada cuenta
+pub fn init_base_currency(code: &str) -> Result<(), String> {
+    let currency = code.parse::<Currency>()?;
+
+    let _ = BASE_CURRENCY.set(currency);
+    Ok(())
+}
+
+/// moneda base configurada, si nadie la inicializó (ej: en tests) usamos la misma default del Env
+/// también sirve de default de serde para los montos viejos que no guardaban moneda
+pub fn base_currency() -> Currency {
+    *BASE_CURRENCY.get_or_init(|| Currency::Gtq)
+}
+
+/// Monedas en las que depositan los socios
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, GraphQLEnum)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Currency {
+    /// quetzales
+    Gtq,
+    /// dólares
+    Usd,
+}
+
+impl Currency {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Currency::Gtq => "GTQ",
+            Currency::Usd => "USD",
+        }
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Currency {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw.trim().to_uppercase().as_str() {
+            "GTQ" => Ok(Currency::Gtq),
+            "USD" => Ok(Currency::Usd),
+            _ => Err(format!("Moneda inválida: {}", raw)),
+        }
+    }
+}
+
+/// tabla de tipos de cambio: cuántas unidades de la moneda base vale 1 unidad de cada moneda
+/// (ej: USD -> 7.75 con base GTQ). la moneda base siempre vale 1
+#[derive(Clone, Debug, Default)]
+pub struct ExchangeRates {
+    rates: HashMap<Currency, Decimal>,
+}
+
+impl ExchangeRates {
+    pub fn new(rates: HashMap<Currency, Decimal>) -> Self {
+        ExchangeRates { rates }
+    }
+
+    pub fn rate_to_base(&self, currency: Currency) -> Result<Decimal, AppError> {
+        if currency == base_currency() {
+            return Ok(Decimal::ONE);
+        }
+
+        self.rates
+            .get(&currency)
+            .copied()
+            .ok_or_else(|| {
+                AppError::conflict(format!("No hay tipo de cambio configurado para {}", currency))
+            })
+    }
+
+    /// convierte un monto entre dos monedas pasando por la moneda base
+    /// el cálculo se hace exacto y se redondea a centavos una sola vez al final
+    pub fn convert(&self, amount: Money, from: Currency, to: Currency) -> Result<Money, AppError> {
+        if from == to {
+            return Ok(amount);
+        }
+
+        let in_base = amount.amount() * self.rate_to_base(from)?;
+        Ok(Money::new(in_base / self.rate_to_base(to)?))
+    }
+
+    pub fn to_base(&self, amount: Money, from: Currency) -> Result<Money, AppError> {
+        self.convert(amount, from, base_currency())
+    }
+}
@@ -0,0 +1,232 @@
+use std::{fmt, str::FromStr, sync::OnceLock};
+
+use chrono::{NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use juniper::{GraphQLScalar, InputValue, ScalarValue, Value};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+// zona horaria de la cooperativa, se usa para saber qué día es "hoy" y para devolver las
+// fechas/horas al frontend en hora local. se inicializa en main desde el Env
+static COOPERATIVE_TIMEZONE: OnceLock<Tz> = OnceLock::new();
+
+/// fija la zona horaria de la cooperativa (ej: "America/Guatemala"), solo la primera llamada cuenta
+pub fn init_cooperative_timezone(name: &str) -> Result<(), String> {
+    let timezone = name
+        .parse::<Tz>()
+        .map_err(|_| format!("Zona horaria inválida: {}", name))?;
+
+    let _ = COOPERATIVE_TIMEZONE.set(timezone);
+    Ok(())
+}
+
+/// zona horaria configurada, si nadie la inicializó (ej: en tests) usamos la misma default del Env
+pub fn cooperative_timezone() -> Tz {
+    *COOPERATIVE_TIMEZONE.get_or_init(|| chrono_tz::America::Guatemala)
+}
+
+/// el día de hoy en la zona horaria de la cooperativa (no la del servidor)
+pub fn today() -> Date {
+    Date(Utc::now().with_timezone(&cooperative_timezone()).date_naive())
+}
+
+/// Fecha sin hora (vencimientos de cuotas, etc)
+/// En redis y en graphql viaja como "YYYY-MM-DD"
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, GraphQLScalar)]
+#[graphql(
+    name = "Date",
+    description = "Fecha en formato YYYY-MM-DD",
+    to_output_with = Self::as_graphql_value,
+    from_input_with = Self::from_graphql_input,
+    parse_token(String)
+)]
+pub struct Date(pub NaiveDate);
+
+impl Date {
+    pub fn naive(&self) -> NaiveDate {
+        self.0
+    }
+
+    fn as_graphql_value<S: ScalarValue>(&self) -> Value<S> {
+        Value::scalar(self.to_string())
+    }
+
+    fn from_graphql_input<S: ScalarValue>(input: &InputValue<S>) -> Result<Self, String> {
+        input
+            .as_string_value()
+            .ok_or_else(|| format!("Expected `String`, found: {input}"))
+            .and_then(str::parse)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.format("%Y-%m-%d"))
+    }
+}
+
+impl FromStr for Date {
+    type Err = String;
+
+    // además del formato oficial aceptamos los formatos viejos que quedaron guardados
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let raw = raw.trim();
+
+        for format in ["%Y-%m-%d", "%d/%m/%Y", "%d-%m-%Y"] {
+            if let Ok(date) = NaiveDate::parse_from_str(raw, format) {
+                return Ok(Date(date));
+            }
+        }
+
+        // si viene con hora nos quedamos con el día local de la cooperativa
+        raw.parse::<DateTime>()
+            .map(|date_time| date_time.local_date())
+            .map_err(|_| format!("Fecha inválida: {}", raw))
+    }
+}
+
+impl From<NaiveDate> for Date {
+    fn from(date: NaiveDate) -> Self {
+        Date(date)
+    }
+}
+
+impl Serialize for Date {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Date {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        raw.parse().map_err(de::Error::custom)
+    }
+}
+
+/// para campos Option<Date> guardados en redis: si el valor viejo no se puede interpretar lo
+/// tomamos como None en vez de romper la deserialización de todo el objeto
+pub fn deserialize_optional_date<'de, D>(deserializer: D) -> Result<Option<Date>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw = Option::<String>::deserialize(deserializer)?;
+
+    Ok(raw.and_then(|raw| match raw.parse::<Date>() {
+        Ok(date) => Some(date),
+        Err(e) => {
+            println!("WARNING: deserialize_optional_date - {}", e);
+            None
+        }
+    }))
+}
+
+/// Fecha y hora con zona horaria (creación de pagos, revisiones, etc)
+/// Se guarda en redis como RFC 3339 en UTC y en graphql se devuelve en hora de la cooperativa
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, GraphQLScalar)]
+#[graphql(
+    name = "DateTime",
+    description = "Fecha y hora en formato RFC 3339 (ej: 2025-10-13T09:30:00-06:00)",
+    to_output_with = Self::as_graphql_value,
+    from_input_with = Self::from_graphql_input,
+    parse_token(String)
+)]
+pub struct DateTime(pub chrono::DateTime<Utc>);
+
+impl DateTime {
+    pub fn now() -> Self {
+        DateTime(Utc::now())
+    }
+
+    pub fn utc(&self) -> chrono::DateTime<Utc> {
+        self.0
+    }
+
+    /// la misma hora expresada en la zona horaria de la cooperativa
+    pub fn local(&self) -> chrono::DateTime<Tz> {
+        self.0.with_timezone(&cooperative_timezone())
+    }
+
+    /// el día (local de la cooperativa) en que cae esta hora
+    pub fn local_date(&self) -> Date {
+        Date(self.local().date_naive())
+    }
+
+    fn as_graphql_value<S: ScalarValue>(&self) -> Value<S> {
+        Value::scalar(self.local().to_rfc3339())
+    }
+
+    fn from_graphql_input<S: ScalarValue>(input: &InputValue<S>) -> Result<Self, String> {
+        input
+            .as_string_value()
+            .ok_or_else(|| format!("Expected `String`, found: {input}"))
+            .and_then(str::parse)
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.to_rfc3339())
+    }
+}
+
+impl FromStr for DateTime {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let raw = raw.trim();
+
+        if let Ok(date_time) = chrono::DateTime::parse_from_rfc3339(raw) {
+            return Ok(DateTime(date_time.with_timezone(&Utc)));
+        }
+
+        // sin offset asumimos hora local de la cooperativa
+        let naive = NaiveDateTime::parse_from_str(raw, "%Y-%m-%d %H:%M:%S")
+            .or_else(|_| NaiveDateTime::parse_from_str(raw, "%Y-%m-%dT%H:%M:%S"))
+            .or_else(|_| {
+                // los pagos viejos solo guardaban el día (Utc::now().date_naive())
+                NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+                    .map(|date| date.and_hms_opt(0, 0, 0).unwrap_or_default())
+            })
+            .map_err(|_| format!("Fecha y hora inválida: {}", raw))?;
+
+        cooperative_timezone()
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|local| DateTime(local.with_timezone(&Utc)))
+            .ok_or_else(|| format!("Fecha y hora inválida: {}", raw))
+    }
+}
+
+impl From<chrono::DateTime<Utc>> for DateTime {
+    fn from(date_time: chrono::DateTime<Utc>) -> Self {
+        DateTime(date_time)
+    }
+}
+
+impl Serialize for DateTime {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for DateTime {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        raw.parse().map_err(de::Error::custom)
+    }
+}
+
+/// para el date_created de los pagos guardados en redis: un valor viejo que no se puede
+/// interpretar (ej: el default "0-00-0000") queda como el epoch en vez de sacar el pago de
+/// todas las listas, migrate_stored_dates lo sigue reportando hasta que se corrija
+pub fn deserialize_lenient_date_time<'de, D>(deserializer: D) -> Result<DateTime, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw = String::deserialize(deserializer)?;
+
+    Ok(raw.parse().unwrap_or_else(|e| {
+        println!("WARNING: deserialize_lenient_date_time - {}", e);
+        DateTime(chrono::DateTime::UNIX_EPOCH)
+    }))
+}
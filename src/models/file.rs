@@ -1,16 +1,59 @@
-use std::fs::File;
-
-use actix_multipart::form::{tempfile::TempFile, MultipartForm};
+use actix_multipart::form::{MultipartForm, tempfile::TempFile, text::Text};
 use serde::{Deserialize, Serialize};
 
+use crate::models::PayedToInput;
+use crate::models::currency::Currency;
+use crate::models::dates::{Date, DateTime};
+use crate::models::graphql::{AttachmentOwnerType, DocumentType, QuotaType, ReceiptCheck};
+use crate::models::money::Money;
+
+// mismo límite que repos::file::receipt::MAX_RECEIPT_BYTES, lo más grande ni se termina de leer
 #[derive(Debug, MultipartForm)]
 pub struct UploadForm {
-    #[multipart(limit = "100MB")]
+    #[multipart(limit = "10MiB")]
     pub file: TempFile,
 }
 
+/// /general/submit_payment: el comprobante y el pago en un solo request, así el pago siempre
+/// apunta a un comprobante que sí se subió
+#[derive(Debug, MultipartForm)]
+pub struct PaymentSubmissionForm {
+    #[multipart(limit = "10MiB")]
+    pub file: TempFile,
+    /// PaymentSubmission en JSON, como texto para no depender del Content-Type de la parte
+    pub payment: Text<String>,
+}
+
+/// los mismos campos de create_user_payment menos comprobante_path, ese lo pone el server
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PaymentSubmission {
+    pub name: String,
+    pub total_amount: Money,
+    pub currency: Option<Currency>,
+    pub ticket_number: String,
+    pub account_number: String,
+    pub being_payed: Vec<PayedToInput>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PaymentSubmissionInfo {
+    pub ticket_id: String,
+    pub message: String,
+    /// lo que leyó el OCR contra lo que se escribió, null sin OCR
+    pub receipt_check: Option<ReceiptCheck>,
+}
+
+/// con access_token, o con expires + signature de una URL firmada (/general/ticket_payment_url)
 #[derive(Clone, Serialize, Deserialize)]
 pub struct FilePayloadRetrival {
+    pub access_token: Option<String>,
+    pub ticket_id: String,
+    pub expires: Option<i64>,
+    pub signature: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FilePayloadSignUrl {
     pub access_token: String,
     pub ticket_id: String,
 }
@@ -20,7 +63,156 @@ pub struct FilePayloadUpload {
     pub access_token: String,
 }
 
+/// /general/upload_attachment, los enums van como en graphql (LOAN, PAGARE, ...)
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AttachmentUpload {
+    pub access_token: String,
+    pub owner_type: AttachmentOwnerType,
+    /// affiliate_key del socio, o id del préstamo o de la multa
+    pub owner_id: String,
+    pub document_type: DocumentType,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AttachmentRetrival {
+    pub access_token: String,
+    pub id: String,
+}
+
+/// /general/statement: sin affiliate_key es el estado de cuenta del dueño del access_token,
+/// las fechas van YYYY-MM-DD y cualquiera puede faltar
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StatementDownload {
+    pub access_token: String,
+    pub affiliate_key: Option<String>,
+    pub from: Option<Date>,
+    pub to: Option<Date>,
+    #[serde(default)]
+    pub format: StatementFormat,
+}
+
+/// formato de descarga del estado de cuenta
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatementFormat {
+    #[default]
+    Pdf,
+    Csv,
+}
+
+impl StatementFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            StatementFormat::Pdf => "application/pdf",
+            StatementFormat::Csv => "text/csv; charset=utf-8",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            StatementFormat::Pdf => "pdf",
+            StatementFormat::Csv => "csv",
+        }
+    }
+}
+
+/// /general/export/{entity}: sin affiliate_key son todos los socios (solo directivos), con
+/// affiliate_key solo ese socio (él mismo o un directivo)
+/// pending y quota_type solo aplican a las cuotas, igual que getPendingQuotas y
+/// getQuotasPrestamoPendientes en graphql
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExportDownload {
+    pub access_token: String,
+    pub affiliate_key: Option<String>,
+    #[serde(default)]
+    pub format: ExportFormat,
+    #[serde(default)]
+    pub pending: bool,
+    pub quota_type: Option<QuotaType>,
+}
+
+/// lo que se exporta, va en el path
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportEntity {
+    Payments,
+    Loans,
+    Fines,
+    Quotas,
+}
+
+impl ExportEntity {
+    /// nombre del archivo y de la hoja
+    pub fn title(&self) -> &'static str {
+        match self {
+            ExportEntity::Payments => "Pagos",
+            ExportEntity::Loans => "Prestamos",
+            ExportEntity::Fines => "Multas",
+            ExportEntity::Quotas => "Cuotas",
+        }
+    }
+}
+
+/// formato de las exportaciones, CSV por default
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Xlsx,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Xlsx => {
+                "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+            }
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Xlsx => "xlsx",
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct FileUploadInfo {
     pub ticket_id: String,
+    /// lo que leyó el OCR, para prellenar el formulario del pago (null sin OCR)
+    pub reading: Option<ReceiptReading>,
+}
+
+/// lo que el OCR encontró en el comprobante, cualquiera puede venir vacío
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReceiptReading {
+    pub amount: Option<Money>,
+    pub date: Option<Date>,
+    /// número de boleta, referencia o autorización
+    pub reference: Option<String>,
+}
+
+/// lo que se guarda en redis (receipts:{ticket_id}) de cada comprobante subido
+/// el content type es el que se detectó al subirlo, no el que mandó el cliente
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StoredReceipt {
+    /// llave en el BlobStore
+    pub key: String,
+    pub content_type: String,
+    pub size: u64,
+    pub uploaded_at: DateTime,
+    /// affiliate_key de quien lo subió (no el access_token, ese cambia al resetear la
+    /// contraseña), None en los que se subieron antes de guardarlo
+    #[serde(default)]
+    pub owner: Option<String>,
+    /// cuándo se usó en un pago, los que se quedan en None los borra el GC de huérfanos
+    #[serde(default)]
+    pub linked_at: Option<DateTime>,
+    /// lo que leyó el OCR al subirlo, se compara con el pago al pegarlo
+    #[serde(default)]
+    pub reading: Option<ReceiptReading>,
 }
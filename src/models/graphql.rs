@@ -1,8 +1,15 @@
 use std::fs::File;
 
-use juniper::{GraphQLEnum, GraphQLObject};
+use juniper::{GraphQLEnum, GraphQLObject, GraphQLUnion};
 use serde::{Deserialize, Serialize};
 
+use crate::endpoints::handlers::configs::schema::GeneralContext;
+use crate::models::currency::Currency;
+use crate::models::dates::{Date, DateTime};
+use crate::models::money::Money;
+use crate::errors::AppError;
+use crate::repos::graphql::attachment::check_attachment_viewer;
+
 #[derive(Clone, Serialize, Deserialize, Debug, GraphQLEnum, PartialEq)]
 pub enum QuotaType {
     Prestamo,
@@ -116,44 +123,187 @@ impl ToString for FineStatus {
     }
 }
 
-#[derive(Clone, Serialize, Deserialize, GraphQLObject, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Loan {
     pub id: String,
-    pub quotas: i32, // total couta needed
-    pub payed: f64,
-    pub debt: f64,
-    pub total: f64,
+    pub total_quotas: i32, // total couta needed
+    pub payed: Money,
+    pub debt: Money,
+    pub total: Money,
+    /// moneda en la que se otorgó el préstamo
+    pub currency: Currency,
     pub status: LoanStatus, //TODO: ASk bryan how to do this
     pub reason: String,
     /// tasa de interés del préstamo
     pub interest_rate: f64,
+    /// cuándo se registró el préstamo (null para préstamos viejos)
+    pub created_at: Option<DateTime>,
     /// nombre completo del socio que solicitó el préstamo
     pub presented_by_name: String,
+    // hash del dueño (users:{owner_key}:loans:{id}), no se expone, sirve para ir a traer
+    // las cuotas y el socio
+    pub owner_key: String,
 }
 
-#[derive(Clone, Serialize, Deserialize, GraphQLObject, Debug)]
+// Loan ya no es derive(GraphQLObject) porque quotas y member necesitan el contexto
+#[juniper::graphql_object(Context = GeneralContext)]
+impl Loan {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// total de cuotas en las que se pactó el préstamo
+    fn total_quotas(&self) -> i32 {
+        self.total_quotas
+    }
+
+    fn payed(&self) -> Money {
+        self.payed
+    }
+
+    fn debt(&self) -> Money {
+        self.debt
+    }
+
+    fn total(&self) -> Money {
+        self.total
+    }
+
+    /// moneda en la que se otorgó el préstamo
+    fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    fn status(&self) -> &LoanStatus {
+        &self.status
+    }
+
+    fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    /// tasa de interés del préstamo
+    fn interest_rate(&self) -> f64 {
+        self.interest_rate
+    }
+
+    /// cuándo se registró el préstamo (null para préstamos viejos)
+    fn created_at(&self) -> Option<DateTime> {
+        self.created_at
+    }
+
+    /// nombre completo del socio que solicitó el préstamo
+    fn presented_by_name(&self) -> &str {
+        &self.presented_by_name
+    }
+
+    /// cuotas del préstamo (pagadas y pendientes)
+    async fn quotas(&self, context: &GeneralContext) -> Result<Vec<Quota>, AppError> {
+        context
+            .quota_repo()
+            .get_loan_quotas_by_owner(&self.owner_key, &self.id)
+            .await
+    }
+
+    /// socio que solicitó el préstamo
+    async fn member(&self, context: &GeneralContext) -> Result<Member, AppError> {
+        context.user_repo().get_member_by_owner_key(&self.owner_key).await
+    }
+
+    /// pagaré firmado, solicitud y demás documentos del préstamo
+    /// solo para el socio dueño o un directivo
+    async fn attachments(
+        &self,
+        context: &GeneralContext,
+        access_token: String,
+    ) -> Result<Vec<Attachment>, AppError> {
+        check_attachment_viewer(context.user_repo().as_ref(), &access_token, &self.owner_key)
+            .await?;
+        context
+            .attachment_repo()
+            .get_attachments(AttachmentOwnerType::Loan, &self.id)
+            .await
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Fine {
     pub id: String,
-    pub amount: f64,
+    pub amount: Money,
     pub status: FineStatus,
     pub reason: String,
+    /// cuándo se puso la multa (null para multas viejas)
+    pub created_at: Option<DateTime>,
     // nombre de quien presentó la multa (viene del complete_name del usuario)
     pub presented_by_name: String,
+    // hash del socio multado (users:{owner_key}:fines:{id}), no se expone
+    pub owner_key: String,
+}
+
+#[juniper::graphql_object(Context = GeneralContext)]
+impl Fine {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn amount(&self) -> Money {
+        self.amount
+    }
+
+    fn status(&self) -> &FineStatus {
+        &self.status
+    }
+
+    fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    /// cuándo se puso la multa (null para multas viejas)
+    fn created_at(&self) -> Option<DateTime> {
+        self.created_at
+    }
+
+    fn presented_by_name(&self) -> &str {
+        &self.presented_by_name
+    }
+
+    /// socio al que se le puso la multa
+    async fn member(&self, context: &GeneralContext) -> Result<Member, AppError> {
+        context.user_repo().get_member_by_owner_key(&self.owner_key).await
+    }
+
+    /// pruebas que subió el socio para disputar la multa
+    /// solo para el socio dueño o un directivo
+    async fn attachments(
+        &self,
+        context: &GeneralContext,
+        access_token: String,
+    ) -> Result<Vec<Attachment>, AppError> {
+        check_attachment_viewer(context.user_repo().as_ref(), &access_token, &self.owner_key)
+            .await?;
+        context
+            .attachment_repo()
+            .get_attachments(AttachmentOwnerType::Fine, &self.id)
+            .await
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, GraphQLObject, Debug)]
+#[graphql(context = GeneralContext)]
 pub struct UsersWithFines {
     pub complete_name: String,
     pub user_id: String,
     pub fines: Vec<Fine>,
 }
 
-#[derive(Clone, Serialize, Deserialize, GraphQLObject, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Payment {
     pub id: String,
     pub name: String,
-    pub total_amount: f64,
-    pub payment_date: String, // I'll pass it as a string, for not having parsing difficulties
+    pub total_amount: Money,
+    /// moneda en la que se hizo el depósito
+    pub currency: Currency,
+    pub payment_date: DateTime,
     pub ticket_num: String,
     pub account_num: String,
     pub commentary: Option<String>,
@@ -164,6 +314,383 @@ pub struct Payment {
     pub being_payed: Vec<crate::models::PayedTo>,
     // nombre de quien presentó/creó el pago (viene del complete_name del usuario con el access_token)
     pub presented_by_name: String,
+    /// true si otro pago (no rechazado) usa la misma cuenta y número de boleta
+    pub possible_duplicates: bool,
+    /// lo que leyó el OCR del comprobante comparado con lo que escribió el socio
+    pub receipt_check: Option<ReceiptCheck>,
+}
+
+/// datos que el OCR sacó del comprobante y si coinciden con los del pago, para el directivo
+/// que lo revisa. los *_matches son null si el OCR no encontró ese dato
+#[derive(Clone, Serialize, Deserialize, GraphQLObject, Debug, PartialEq)]
+pub struct ReceiptCheck {
+    pub amount: Option<Money>,
+    pub date: Option<Date>,
+    pub reference: Option<String>,
+    /// el monto leído contra total_amount
+    pub amount_matches: Option<bool>,
+    /// la referencia leída contra ticket_num
+    pub reference_matches: Option<bool>,
+    /// true si algo de lo que se leyó no coincide
+    pub has_mismatch: bool,
+}
+
+// Payment ya no es derive(GraphQLObject) porque el campo history necesita el contexto
+// para ir a traer el historial a redis (solo se fetchea si el cliente lo pide)
+#[juniper::graphql_object(Context = GeneralContext)]
+impl Payment {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn total_amount(&self) -> Money {
+        self.total_amount
+    }
+
+    /// moneda en la que se hizo el depósito
+    fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    fn payment_date(&self) -> DateTime {
+        self.payment_date
+    }
+
+    fn ticket_num(&self) -> &str {
+        &self.ticket_num
+    }
+
+    fn account_num(&self) -> &str {
+        &self.account_num
+    }
+
+    fn commentary(&self) -> Option<&str> {
+        self.commentary.as_deref()
+    }
+
+    fn photo_path(&self) -> &str {
+        &self.photo_path
+    }
+
+    fn state(&self) -> &PaymentStatus {
+        &self.state
+    }
+
+    fn being_payed(&self) -> &[crate::models::PayedTo] {
+        &self.being_payed
+    }
+
+    fn presented_by_name(&self) -> &str {
+        &self.presented_by_name
+    }
+
+    /// true si otro pago (no rechazado) usa la misma cuenta y número de boleta
+    fn possible_duplicates(&self) -> bool {
+        self.possible_duplicates
+    }
+
+    /// lo que leyó el OCR del comprobante comparado con lo que escribió el socio
+    /// null si no hay OCR o no se pudo leer nada
+    fn receipt_check(&self) -> Option<&ReceiptCheck> {
+        self.receipt_check.as_ref()
+    }
+
+    /// cambios de estado del pago (quién lo revisó, cuándo y con qué comentario)
+    /// ordenados del más viejo al más nuevo
+    async fn history(
+        &self,
+        context: &GeneralContext,
+    ) -> Result<Vec<PaymentStatusChange>, AppError> {
+        context.payment_repo().get_payment_history(self.id.clone()).await
+    }
+}
+
+/// un cambio de estado de un pago hecho por un directivo
+#[derive(Clone, Serialize, Deserialize, GraphQLObject, Debug)]
+pub struct PaymentStatusChange {
+    pub previous_state: PaymentStatus,
+    pub new_state: PaymentStatus,
+    /// affiliate_key del directivo que hizo el cambio
+    pub reviewer_id: String,
+    pub reviewer_name: String,
+    pub changed_at: DateTime,
+    pub comment: Option<String>,
+}
+
+/// evento de la subscription paymentStatusChanged
+#[derive(Clone, Serialize, Deserialize, GraphQLObject, Debug)]
+pub struct PaymentStatusChangedEvent {
+    pub payment_id: String,
+    pub change: PaymentStatusChange,
+    // hash del dueño del pago, cada socio solo recibe los cambios de sus pagos
+    #[graphql(skip)]
+    pub owner_key: String,
+}
+
+/// pagos que comparten la misma boleta (cuenta + número de ticket), para revisión de directivos
+#[derive(Clone, Serialize, Deserialize, GraphQLObject, Debug)]
+#[graphql(context = GeneralContext)]
+pub struct DuplicatePaymentGroup {
+    pub account_num: String,
+    pub ticket_num: String,
+    pub payments: Vec<Payment>,
+}
+
+/// tipo de cambio vigente de una moneda respecto a la moneda base
+#[derive(Clone, Serialize, Deserialize, GraphQLObject, Debug)]
+pub struct ExchangeRate {
+    pub currency: Currency,
+    pub base_currency: Currency,
+    /// cuánto vale 1 unidad de `currency` en la moneda base, como string decimal (ej: "7.75")
+    pub rate: String,
+    /// affiliate_key del directivo que lo actualizó
+    pub updated_by: String,
+    pub updated_by_name: String,
+    pub updated_at: DateTime,
+}
+
+/// totales de la cooperativa convertidos a la moneda base
+#[derive(Clone, Serialize, Deserialize, GraphQLObject, Debug)]
+pub struct BaseCurrencyReport {
+    pub base_currency: Currency,
+    pub accepted_payments_total: Money,
+    pub pending_payments_total: Money,
+    pub loans_total: Money,
+    pub loans_debt: Money,
+}
+
+/// estado de cuenta de un socio en un período (Query.statement)
+/// es lo mismo que sale en PDF o CSV por /general/statement
+#[derive(Clone, Serialize, Deserialize, GraphQLObject, Debug)]
+#[graphql(context = GeneralContext)]
+pub struct Statement {
+    pub affiliate_key: String,
+    pub member_name: String,
+    /// null es desde el principio
+    pub from: Option<Date>,
+    /// null es hasta hoy
+    pub to: Option<Date>,
+    pub generated_at: DateTime,
+    /// moneda del resumen, los pagos y préstamos de la lista van en su propia moneda
+    pub currency: Currency,
+    pub summary: StatementSummary,
+    /// pagos presentados en el período, del más viejo al más nuevo
+    pub payments: Vec<Payment>,
+    /// préstamos otorgados hasta el final del período, con su saldo actual
+    pub loans: Vec<Loan>,
+    /// cuotas de afiliado y de préstamo que vencen en el período
+    pub quotas: Vec<Quota>,
+    /// multas del socio, no guardan fecha así que van todas
+    pub fines: Vec<Fine>,
+}
+
+/// totales del estado de cuenta en la moneda base
+#[derive(Clone, Serialize, Deserialize, GraphQLObject, Debug, PartialEq)]
+pub struct StatementSummary {
+    /// pagos aceptados del período
+    pub total_paid: Money,
+    /// de lo pagado, lo que se abonó a cuotas (aportes y cuotas de préstamo)
+    pub paid_to_quotas: Money,
+    pub paid_to_loans: Money,
+    pub paid_to_fines: Money,
+    /// pagos del período que ningún directivo ha revisado
+    pub pending_review: Money,
+    /// lo que falta de las cuotas del período que no se han pagado
+    pub quotas_pending: Money,
+    /// saldo actual de los préstamos
+    pub loan_debt: Money,
+    pub unpaid_fines: Money,
+}
+
+/// reporte financiero de toda la cooperativa para directivos (Query.financialReport)
+/// todos los montos van en la moneda base
+#[derive(Clone, Serialize, Deserialize, GraphQLObject, Debug)]
+pub struct FinancialReport {
+    pub from: Option<Date>,
+    pub to: Option<Date>,
+    pub generated_at: DateTime,
+    pub currency: Currency,
+    pub member_count: i32,
+    /// lo cobrado por mes (pagos aceptados), de más viejo a más nuevo
+    pub collected: Vec<MonthlyCollection>,
+    pub loan_portfolio: LoanPortfolio,
+    /// cuotas vencidas sin pagar a la fecha de corte, en rangos de días de atraso
+    pub delinquency: Vec<DelinquencyBucket>,
+    pub fines: FinesReport,
+}
+
+/// lo cobrado en un mes según a qué se abonó cada pago
+#[derive(Clone, Serialize, Deserialize, GraphQLObject, Debug, PartialEq)]
+pub struct MonthlyCollection {
+    /// "YYYY-MM"
+    pub month: String,
+    pub loans: Money,
+    pub quotas: Money,
+    pub fines: Money,
+    /// total de los pagos, incluye lo que no se pudo clasificar
+    pub total: Money,
+    pub payments: i32,
+}
+
+/// cartera de préstamos con saldo pendiente
+#[derive(Clone, Serialize, Deserialize, GraphQLObject, Debug, PartialEq)]
+pub struct LoanPortfolio {
+    pub loans_with_debt: i32,
+    /// lo prestado en esos préstamos
+    pub total_lent: Money,
+    pub total_payed: Money,
+    pub outstanding: Money,
+}
+
+/// cuotas vencidas con entre min_days y max_days días de atraso (max_days null es "o más")
+#[derive(Clone, Serialize, Deserialize, GraphQLObject, Debug, PartialEq)]
+pub struct DelinquencyBucket {
+    pub min_days: i32,
+    pub max_days: Option<i32>,
+    pub quotas: i32,
+    /// lo que falta pagar de esas cuotas
+    pub amount: Money,
+}
+
+/// multas puestas contra lo cobrado
+/// issued son las multas puestas en el período, unpaid lo que se debe de multas al corte y
+/// collected los pagos a multas del período (las multas viejas no tienen fecha, cuentan siempre)
+#[derive(Clone, Serialize, Deserialize, GraphQLObject, Debug, PartialEq)]
+pub struct FinesReport {
+    pub issued_count: i32,
+    pub issued_amount: Money,
+    pub unpaid_amount: Money,
+    pub collected: Money,
+}
+
+/// socio de la cooperativa con todo lo que tiene asociado
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Member {
+    pub affiliate_key: String,
+    pub name: String,
+    // hash con el que se guardan sus datos en redis (users:{owner_key}:*), no se expone
+    pub owner_key: String,
+}
+
+#[juniper::graphql_object(Context = GeneralContext)]
+impl Member {
+    fn affiliate_key(&self) -> &str {
+        &self.affiliate_key
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn loans(&self, context: &GeneralContext) -> Result<Vec<Loan>, AppError> {
+        context.loan_repo().get_loans_by_owner(&self.owner_key).await
+    }
+
+    async fn fines(&self, context: &GeneralContext) -> Result<Vec<Fine>, AppError> {
+        context.fine_repo().get_fines_by_owner(&self.owner_key).await
+    }
+
+    async fn payments(&self, context: &GeneralContext) -> Result<Vec<Payment>, AppError> {
+        context.payment_repo().get_payments_by_owner(&self.owner_key).await
+    }
+
+    /// cuotas de afiliado y de préstamo que todavía no se han pagado
+    async fn pending_quotas(&self, context: &GeneralContext) -> Result<Vec<Quota>, AppError> {
+        context.quota_repo().get_pending_quotas_by_owner(&self.owner_key).await
+    }
+
+    /// documentos del socio (DPI, etc), los de sus préstamos y multas van en cada uno
+    /// solo para el socio dueño o un directivo
+    async fn attachments(
+        &self,
+        context: &GeneralContext,
+        access_token: String,
+    ) -> Result<Vec<Attachment>, AppError> {
+        check_attachment_viewer(context.user_repo().as_ref(), &access_token, &self.owner_key)
+            .await?;
+        context
+            .attachment_repo()
+            .get_attachments(AttachmentOwnerType::Member, &self.affiliate_key)
+            .await
+    }
+}
+
+/// a qué está pegado un documento
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, GraphQLEnum, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AttachmentOwnerType {
+    /// el id es el affiliate_key del socio
+    Member,
+    Loan,
+    /// disputa de una multa
+    Fine,
+}
+
+impl AttachmentOwnerType {
+    /// como va en las keys de redis y del BlobStore
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AttachmentOwnerType::Member => "member",
+            AttachmentOwnerType::Loan => "loan",
+            AttachmentOwnerType::Fine => "fine",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, GraphQLEnum, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DocumentType {
+    /// pagaré firmado
+    Pagare,
+    /// DPI o pasaporte
+    IdDocument,
+    /// solicitud de préstamo
+    LoanApplication,
+    /// pruebas para disputar una multa
+    FineDispute,
+    Other,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, GraphQLEnum, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AttachmentStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+/// documento subido por /general/upload_attachment, se descarga con /general/get_attachment
+#[derive(Clone, Serialize, Deserialize, GraphQLObject, Debug)]
+pub struct Attachment {
+    pub id: String,
+    pub owner_type: AttachmentOwnerType,
+    pub owner_id: String,
+    pub document_type: DocumentType,
+    pub content_type: String,
+    /// bytes
+    pub size: i32,
+    /// affiliate_key de quien lo subió
+    pub uploaded_by: String,
+    pub uploaded_by_name: String,
+    pub uploaded_at: DateTime,
+    pub status: AttachmentStatus,
+    /// nombre del directivo que lo revisó
+    pub reviewed_by_name: Option<String>,
+    pub reviewed_at: Option<DateTime>,
+    pub review_comment: Option<String>,
+}
+
+/// a qué se está abonando con una parte de un pago (PayedTo.target)
+#[derive(Clone, Debug, GraphQLUnion)]
+#[graphql(context = GeneralContext)]
+pub enum PaymentTarget {
+    Loan(Loan),
+    Quota(Quota),
+    Fine(Fine),
 }
 
 #[derive(Clone, Serialize, Deserialize, GraphQLObject, Debug)]
@@ -175,9 +702,9 @@ pub struct Affiliate {
 #[derive(Clone, Serialize, Deserialize, GraphQLObject, Debug)]
 pub struct PaymentHistory {
     /// the value that brings
-    pub payed_to_capital: f64,
+    pub payed_to_capital: Money,
     /// The capital that the user owes in total
-    pub owed_capital: f64,
+    pub owed_capital: Money,
 }
 
 #[derive(Clone, Serialize, Deserialize, GraphQLObject, Debug)]
@@ -198,16 +725,16 @@ pub struct Codeudor {
 #[derive(Clone, Serialize, Deserialize, GraphQLObject, Debug)]
 pub struct PrestamoDetalles {
     pub numero_quota: i32,
-    pub monto_quota: f64,
-    pub fecha_vencimiento: String, // I'll pass it as a string, for not having parsing difficulties
-    pub monto_pagado: f64,
-    pub multa: f64,
+    pub monto_quota: Money,
+    pub fecha_vencimiento: Date,
+    pub monto_pagado: Money,
+    pub multa: Money,
 }
 
 // ! As bryan sent me the model, it left for room for tons of overfetching, restructuring
 #[derive(Clone, Serialize, Deserialize, GraphQLObject, Debug)]
 pub struct Aporte {
-    pub monto: f64,
+    pub monto: Money,
 }
 
 /// Modelo unificado de Quota para manejar tanto cuotas de afiliado como de préstamo
@@ -217,13 +744,14 @@ pub struct Quota {
     /// ID del usuario (debe coincidir con access_token para dummy data)
     pub user_id: String,
     /// Monto de la cuota
-    pub amount: f64,
-    /// Fecha de vencimiento en formato YYYY-MM-DD
-    pub exp_date: Option<String>,
+    pub amount: Money,
+    /// Fecha de vencimiento
+    #[serde(default, deserialize_with = "crate::models::dates::deserialize_optional_date")]
+    pub exp_date: Option<Date>,
     /// Monto ya pagado de la cuota (0.0 para nuevas cuotas)
-    pub monto_pagado: Option<f64>,
+    pub monto_pagado: Option<Money>,
     /// Multa aplicada a la cuota (0.0 para nuevas cuotas)
-    pub multa: Option<f64>,
+    pub multa: Option<Money>,
     /// Usuario que pagó la cuota (para pagos por terceros)
     pub pay_by: Option<String>,
     /// Tipo de cuota: Prestamo o Afiliado
@@ -259,3 +787,9 @@ impl WithPresenterName for Fine {
         self.presented_by_name = name;
     }
 }
+
+impl WithPresenterName for Loan {
+    fn set_presenter_name(&mut self, name: String) {
+        self.presented_by_name = name;
+    }
+}
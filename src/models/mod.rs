@@ -1,11 +1,22 @@
 use std::fmt::Display;
 
-use juniper::{GraphQLInputObject, GraphQLObject};
+use juniper::GraphQLInputObject;
 use serde::{Deserialize, Serialize};
 
+use crate::endpoints::handlers::configs::schema::GeneralContext;
+use crate::models::{
+    currency::{base_currency, Currency},
+    graphql::PaymentTarget,
+    money::Money,
+};
+use crate::errors::AppError;
+
 pub mod auth;
-// pub mod file; // COMENTADO POR AHORA PARA ENFOCARSE EN RECOVER-PASSWORD
+pub mod currency;
+pub mod dates;
+pub mod file;
 pub mod graphql;
+pub mod money;
 pub mod redis;
 
 // valor por default cuando no se puede fetchear el nombre del usuario que presentó algo
@@ -24,22 +35,62 @@ pub struct GeneralInfo {
 }
 
 // PayedTo: necesitamos dos versiones separadas debido a restricciones de Juniper
-// - PayedTo (GraphQLObject): para queries/output - retornar datos al frontend
+// - PayedTo (graphql_object): para queries/output - retornar datos al frontend
 // - PayedToInput (GraphQLInputObject): para mutations/input - recibir datos del frontend
 // Redis usa PayedTo directamente. La conversión PayedToInput -> PayedTo es automática via From trait
-#[derive(Debug, Clone, Serialize, Deserialize, GraphQLObject)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[derive(PartialEq)]
 pub struct PayedTo {
     pub model_type: String,
-    pub amount: f64,
+    /// monto en la moneda del pago
+    pub amount: Money,
     pub model_key: String,
+    /// moneda del préstamo/cuota/multa al que se abona (los pagos viejos no la traen)
+    #[serde(default = "base_currency")]
+    pub allocated_currency: Currency,
+    /// el mismo monto convertido a allocated_currency con el tipo de cambio del día del pago
+    #[serde(default)]
+    pub allocated_amount: Option<Money>,
+}
+
+// PayedTo ya no es derive(GraphQLObject) porque target necesita el contexto para ir a traer
+// el préstamo/cuota/multa
+#[juniper::graphql_object(Context = GeneralContext)]
+impl PayedTo {
+    fn model_type(&self) -> &str {
+        &self.model_type
+    }
+
+    /// monto en la moneda del pago
+    fn amount(&self) -> Money {
+        self.amount
+    }
+
+    fn model_key(&self) -> &str {
+        &self.model_key
+    }
+
+    /// moneda del préstamo/cuota/multa al que se abona (los pagos viejos no la traen)
+    fn allocated_currency(&self) -> Currency {
+        self.allocated_currency
+    }
+
+    /// el mismo monto convertido a allocated_currency con el tipo de cambio del día del pago
+    fn allocated_amount(&self) -> Option<Money> {
+        self.allocated_amount
+    }
+
+    /// préstamo, cuota o multa a la que se abonó, null si ya no existe
+    async fn target(&self, context: &GeneralContext) -> Result<Option<PaymentTarget>, AppError> {
+        context.payment_repo().get_payed_to_target(self).await
+    }
 }
 
 // versión input para usar en mutations (create_user_payment, etc)
 #[derive(Debug, Clone, Serialize, Deserialize, GraphQLInputObject)]
 pub struct PayedToInput {
     pub model_type: String,
-    pub amount: f64,
+    pub amount: Money,
     pub model_key: String,
 }
 
@@ -47,7 +98,7 @@ impl Default for PayedToInput {
     fn default() -> Self {
         PayedToInput {
             model_type: "LOAN".to_owned(),
-            amount: 0.00,
+            amount: Money::ZERO,
             model_key: "000000000000".to_owned(),
         }
     }
@@ -60,6 +111,9 @@ impl From<PayedToInput> for PayedTo {
             model_type: input.model_type,
             amount: input.amount,
             model_key: input.model_key,
+            // se llenan en create_payment cuando ya sabemos la moneda del destino
+            allocated_currency: base_currency(),
+            allocated_amount: None,
         }
     }
 }
@@ -68,8 +122,10 @@ impl Default for PayedTo {
     fn default() -> Self {
         PayedTo {
             model_type: "LOAN".to_owned(),
-            amount: 0.00,
+            amount: Money::ZERO,
             model_key: "000000000000".to_owned(),
+            allocated_currency: base_currency(),
+            allocated_amount: None,
         }
     }
 }
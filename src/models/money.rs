@@ -0,0 +1,241 @@
+use std::{
+    fmt,
+    iter::Sum,
+    ops::{Add, AddAssign, Neg, Sub, SubAssign},
+    str::FromStr,
+};
+
+use juniper::{GraphQLScalar, InputValue, ScalarValue, Value};
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// Monto de dinero exacto con 2 decimales (centavos), en vez de f64/f32
+///
+/// Reglas de redondeo:
+/// - todo monto se guarda redondeado a centavos, mitad hacia arriba (0.005 -> 0.01)
+/// - los intereses se calculan sobre el monto exacto y se redondean una sola vez al final
+/// - al dividir un monto en partes (ej: cuotas) los centavos que sobran se reparten entre las
+///   primeras partes, así la suma de las partes siempre da el total
+///
+/// En redis se guarda como string ("123.45") y en graphql viaja con el scalar Money
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, GraphQLScalar)]
+#[graphql(
+    name = "Money",
+    description = "Monto exacto con 2 decimales, se devuelve como string (ej: \"123.45\")",
+    to_output_with = Self::as_graphql_value,
+    from_input_with = Self::from_graphql_input,
+    parse_token(String, f64, i32)
+)]
+pub struct Money(Decimal);
+
+impl Money {
+    /// centavos: tanto quetzales como dólares usan 2 decimales
+    pub const SCALE: u32 = 2;
+
+    pub const ZERO: Money = Money(Decimal::ZERO);
+
+    pub fn new(amount: Decimal) -> Self {
+        Money(amount.round_dp_with_strategy(Self::SCALE, RoundingStrategy::MidpointAwayFromZero))
+    }
+
+    pub fn amount(&self) -> Decimal {
+        self.0
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.0.is_sign_negative() && !self.0.is_zero()
+    }
+
+    /// interés (o cualquier porcentaje) sobre el monto, ej: rate_percent = 2.5 -> 2.5%
+    pub fn percentage(&self, rate_percent: Decimal) -> Money {
+        Money::new(self.0 * rate_percent / Decimal::ONE_HUNDRED)
+    }
+
+    /// multiplica por un factor (ej: tipo de cambio) redondeando a centavos al final
+    pub fn multiply(&self, factor: Decimal) -> Money {
+        Money::new(self.0 * factor)
+    }
+
+    /// divide el monto en `parts` partes que suman exactamente el total
+    pub fn split_evenly(&self, parts: u32) -> Vec<Money> {
+        if parts == 0 {
+            return Vec::new();
+        }
+
+        let cent = Decimal::new(1, Self::SCALE);
+        let total_cents = (self.0 / cent).trunc();
+        let parts_decimal = Decimal::from(parts);
+        let base_cents = (total_cents / parts_decimal).trunc();
+        let remainder = total_cents - base_cents * parts_decimal;
+        let extra_cent = if remainder.is_sign_negative() {
+            -Decimal::ONE
+        } else {
+            Decimal::ONE
+        };
+
+        (0..parts)
+            .map(|index| {
+                let extra = if Decimal::from(index) < remainder.abs() {
+                    extra_cent
+                } else {
+                    Decimal::ZERO
+                };
+                Money::new((base_cents + extra) * cent)
+            })
+            .collect()
+    }
+
+    fn as_graphql_value<S: ScalarValue>(&self) -> Value<S> {
+        Value::scalar(self.to_string())
+    }
+
+    fn from_graphql_input<S: ScalarValue>(input: &InputValue<S>) -> Result<Self, String> {
+        // aceptamos strings y números para no romper a los clientes que mandan floats
+        if let Some(raw) = input.as_string_value() {
+            return raw.parse();
+        }
+        if let Some(raw) = input.as_int_value() {
+            return Ok(Money::from(raw));
+        }
+        if let Some(raw) = input.as_float_value() {
+            return Money::try_from(raw);
+        }
+
+        Err(format!("Expected `String` or number, found: {input}"))
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // siempre con los 2 decimales, 5 -> "5.00"
+        let mut amount = self.0;
+        amount.rescale(Self::SCALE);
+        write!(f, "{}", amount)
+    }
+}
+
+impl FromStr for Money {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        Decimal::from_str(raw.trim())
+            .map(Money::new)
+            .map_err(|_| format!("Monto inválido: {}", raw))
+    }
+}
+
+impl From<Decimal> for Money {
+    fn from(amount: Decimal) -> Self {
+        Money::new(amount)
+    }
+}
+
+impl From<i32> for Money {
+    fn from(amount: i32) -> Self {
+        Money(Decimal::from(amount))
+    }
+}
+
+impl TryFrom<f64> for Money {
+    type Error = String;
+
+    // pasamos por el texto más corto del float (100.1 -> "100.1") para no arrastrar
+    // basura binaria como 100.09999999999999
+    fn try_from(amount: f64) -> Result<Self, Self::Error> {
+        if !amount.is_finite() {
+            return Err(format!("Monto inválido: {}", amount));
+        }
+        amount.to_string().parse()
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, other: Money) -> Money {
+        Money(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, other: Money) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, other: Money) -> Money {
+        Money(self.0 - other.0)
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, other: Money) {
+        self.0 -= other.0;
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money(-self.0)
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::ZERO, Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Money> for Money {
+    fn sum<I: Iterator<Item = &'a Money>>(iter: I) -> Money {
+        iter.copied().sum()
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(MoneyVisitor)
+    }
+}
+
+// los montos viejos en redis son números (f64/f32), los nuevos son strings
+struct MoneyVisitor;
+
+impl de::Visitor<'_> for MoneyVisitor {
+    type Value = Money;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("un monto como string o número")
+    }
+
+    fn visit_str<E: de::Error>(self, raw: &str) -> Result<Money, E> {
+        raw.parse().map_err(E::custom)
+    }
+
+    fn visit_i64<E: de::Error>(self, raw: i64) -> Result<Money, E> {
+        Ok(Money(Decimal::from(raw)))
+    }
+
+    fn visit_u64<E: de::Error>(self, raw: u64) -> Result<Money, E> {
+        Ok(Money(Decimal::from(raw)))
+    }
+
+    fn visit_f64<E: de::Error>(self, raw: f64) -> Result<Money, E> {
+        Money::try_from(raw).map_err(E::custom)
+    }
+}
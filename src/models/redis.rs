@@ -1,53 +1,82 @@
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     models::{
         graphql::{
+            Attachment as GraphQLAttachment, AttachmentOwnerType, AttachmentStatus, DocumentType,
             Fine as GraphQLFine, FineStatus, Loan as GraphQLLoan, LoanStatus,
-            Payment as GraphQLPayment, PaymentStatus,
+            ExchangeRate as GraphQLExchangeRate, Payment as GraphQLPayment, PaymentStatus,
+            PaymentStatusChange as GraphQLPaymentStatusChange, ReceiptCheck,
         },
+        currency::{base_currency, Currency},
+        dates::DateTime,
+        money::Money,
         GraphQLMappable, PayedTo,
     },
-    repos::graphql::utils::get_key,
+    repos::graphql::utils::{extract_user_hash_from_key, get_key},
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Payment {
-    pub date_created: String,
+    #[serde(deserialize_with = "crate::models::dates::deserialize_lenient_date_time")]
+    pub date_created: DateTime,
     pub account_number: String,
-    pub total_amount: f64,
+    pub total_amount: Money,
+    // los pagos viejos no guardaban moneda, todos eran en la moneda base
+    #[serde(default = "base_currency")]
+    pub currency: Currency,
     pub name: String,
     pub comments: Option<String>, // it will be added if the directive sends it
     pub comprobante_bucket: String,
     pub ticket_number: String,
     pub status: String,
     pub being_payed: Vec<PayedTo>,
+    // se marca al crear el pago si ya existe otro con la misma cuenta + número de boleta
+    // (los pagos viejos no traen el campo, por eso el default)
+    #[serde(default)]
+    pub possible_duplicates: bool,
+    // lo llena el OCR al pegar el comprobante al pago (repos::file::payment_link)
+    #[serde(default)]
+    pub receipt_check: Option<ReceiptCheck>,
 }
 
 impl Default for Payment {
     fn default() -> Self {
         Payment {
             name: "none".to_owned(),
-            date_created: "0-00-0000".to_owned(),
+            date_created: DateTime::now(),
             comprobante_bucket: "/".to_owned(),
             account_number: "000000000".to_owned(),
             ticket_number: "000000000".to_owned(),
             status: "NOT_PROCESS".to_owned(),
-            total_amount: 0.00,
+            total_amount: Money::ZERO,
+            currency: base_currency(),
             comments: Some("".to_owned()),
             being_payed: vec![PayedTo::default()],
+            possible_duplicates: false,
+            receipt_check: None,
         }
     }
 }
 
+/// id corto del modelo, si la key viene mal formada se usa la key completa en vez de hacer panic
+fn model_id(key: &str, key_type: &str) -> String {
+    get_key(key.to_owned(), key_type.to_owned()).unwrap_or_else(|err| {
+        println!("{err}");
+        key.to_owned()
+    })
+}
+
 impl GraphQLMappable<GraphQLPayment> for Payment {
     fn to_graphql_type(&self, key: String) -> GraphQLPayment {
         GraphQLPayment {
-            id: get_key(key, "payments".to_owned()),
+            id: model_id(&key, "payments"),
             name: (*self.name).to_owned(),
             total_amount: self.total_amount,
+            currency: self.currency,
             account_num: (*self.account_number).to_string(),
-            payment_date: (*self.date_created).to_string(),
+            payment_date: self.date_created,
             ticket_num: (*self.ticket_number).to_string(),
             commentary: self.comments.clone(), // f*** options, can't do low level stuff some times
             photo_path: (*self.comprobante_bucket).to_string(),
@@ -57,6 +86,34 @@ impl GraphQLMappable<GraphQLPayment> for Payment {
             // el nombre real se fetchea después en el repo con el helper genérico enrich_with_presenter_names
             // acá ponemos el default porque este trait no tiene acceso al pool de redis
             presented_by_name: crate::models::DEFAULT_PRESENTER_NAME.to_string(),
+            possible_duplicates: self.possible_duplicates,
+            receipt_check: self.receipt_check.clone(),
+        }
+    }
+}
+
+/// entrada del historial de un pago, se guarda en la lista payment_history:{payment_id}
+/// cada vez que un directivo cambia el estado del pago
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentStatusChange {
+    pub previous_status: String,
+    pub new_status: String,
+    pub reviewer_id: String, // affiliate_key del directivo
+    pub reviewer_name: String,
+    pub changed_at: DateTime,
+    pub comment: Option<String>,
+}
+
+impl GraphQLMappable<GraphQLPaymentStatusChange> for PaymentStatusChange {
+    // el historial no vive bajo una key users:*, así que la key no se usa
+    fn to_graphql_type(&self, _key: String) -> GraphQLPaymentStatusChange {
+        GraphQLPaymentStatusChange {
+            previous_state: PaymentStatus::from_string(self.previous_status.clone()),
+            new_state: PaymentStatus::from_string(self.new_status.clone()),
+            reviewer_id: self.reviewer_id.clone(),
+            reviewer_name: self.reviewer_name.clone(),
+            changed_at: self.changed_at,
+            comment: self.comment.clone(),
         }
     }
 }
@@ -64,26 +121,33 @@ impl GraphQLMappable<GraphQLPayment> for Payment {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Loan {
     pub total_quota: i32,         // total couta needed
-    pub base_needed_payment: f64, //initial value of the lone without fines
-    pub payed: f64,
-    pub debt: f64,
-    pub total: f64,
+    pub base_needed_payment: Money, //initial value of the lone without fines
+    pub payed: Money,
+    pub debt: Money,
+    pub total: Money,
+    #[serde(default = "base_currency")]
+    pub currency: Currency,
     pub status: String, //TODO: ASk bryan how to do this
     pub reason: String,
     pub interest_rate: Option<f64>, // tasa de interés del préstamo
+    // los préstamos creados antes de guardar la fecha no la tienen
+    #[serde(default)]
+    pub created_at: Option<DateTime>,
 }
 
 impl Default for Loan {
     fn default() -> Self {
         Loan {
             total_quota: 0,
-            base_needed_payment: 0.,
-            payed: 0.,
-            debt: 0.,
-            total: 0.,
+            base_needed_payment: Money::ZERO,
+            payed: Money::ZERO,
+            debt: Money::ZERO,
+            total: Money::ZERO,
+            currency: base_currency(),
             status: "Not Done".to_owned(),
             reason: "None".to_owned(),
             interest_rate: Some(0.),
+            created_at: None,
         }
     }
 }
@@ -91,34 +155,41 @@ impl Default for Loan {
 impl GraphQLMappable<GraphQLLoan> for Loan {
     fn to_graphql_type(&self, key: String) -> GraphQLLoan {
         GraphQLLoan {
-            id: get_key(key, "loans".to_owned()),
-            quotas: self.total_quota,
+            id: model_id(&key, "loans"),
+            total_quotas: self.total_quota,
             payed: self.payed,
             debt: self.debt,
             total: self.total,
+            currency: self.currency,
             status: LoanStatus::from_string((*self.status).to_string()),
             reason: (*self.reason).to_string(),
             interest_rate: self.interest_rate.unwrap_or(0.0),
+            created_at: self.created_at,
             // campo que requiere contexto adicional se llena con default aquí
             // solo get_all_loans lo llena correctamente con datos de redis
             presented_by_name: "N/A".to_string(),
+            owner_key: extract_user_hash_from_key(&key).unwrap_or_default(),
         }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Fine {
-    pub amount: f32,
+    pub amount: Money,
     pub motive: String,
     pub status: String,
+    // las multas puestas antes de guardar la fecha no la tienen
+    #[serde(default)]
+    pub created_at: Option<DateTime>,
 }
 
 impl Default for Fine {
     fn default() -> Self {
         Fine {
-            amount: 0.,
+            amount: Money::ZERO,
             status: "UNPAID".to_owned(),
             motive: "nu uh".to_owned(),
+            created_at: None,
         }
     }
 }
@@ -126,12 +197,84 @@ impl Default for Fine {
 impl GraphQLMappable<GraphQLFine> for Fine {
     fn to_graphql_type(&self, key: String) -> GraphQLFine {
         GraphQLFine {
-            id: get_key(key, "fines".to_owned()),
+            id: model_id(&key, "fines"),
             status: FineStatus::from_string((*self.status).to_string()),
-            amount: self.amount as f64,
+            amount: self.amount,
             reason: (*self.motive).to_string(),
+            created_at: self.created_at,
             // el nombre real se fetchea después en el repo con el helper genérico
             presented_by_name: crate::models::DEFAULT_PRESENTER_NAME.to_string(),
+            owner_key: extract_user_hash_from_key(&key).unwrap_or_default(),
+        }
+    }
+}
+
+/// tipo de cambio vigente, se guarda en exchange_rates:{moneda}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExchangeRate {
+    pub rate: Decimal, // cuánto vale 1 unidad de la moneda en la moneda base
+    pub updated_by: String, // affiliate_key del directivo
+    pub updated_by_name: String,
+    pub updated_at: DateTime,
+}
+
+impl GraphQLMappable<GraphQLExchangeRate> for ExchangeRate {
+    fn to_graphql_type(&self, key: String) -> GraphQLExchangeRate {
+        GraphQLExchangeRate {
+            // la key es exchange_rates:{moneda}
+            currency: key
+                .rsplit(':')
+                .next()
+                .and_then(|code| code.parse().ok())
+                .unwrap_or_else(base_currency),
+            base_currency: base_currency(),
+            rate: self.rate.normalize().to_string(),
+            updated_by: self.updated_by.clone(),
+            updated_by_name: self.updated_by_name.clone(),
+            updated_at: self.updated_at,
+        }
+    }
+}
+
+/// attachments:{owner_type}:{owner_id}:{id}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub owner_type: AttachmentOwnerType,
+    pub owner_id: String,
+    /// affiliate_key del socio al que pertenece el dueño (el mismo socio, el que pidió el
+    /// préstamo o el multado), él y los directivos lo pueden ver
+    pub member_key: String,
+    pub document_type: DocumentType,
+    /// llave en el BlobStore
+    pub blob_key: String,
+    pub content_type: String,
+    pub size: u64,
+    pub uploaded_by: String,
+    pub uploaded_by_name: String,
+    pub uploaded_at: DateTime,
+    pub status: AttachmentStatus,
+    pub reviewed_by: Option<String>,
+    pub reviewed_by_name: Option<String>,
+    pub reviewed_at: Option<DateTime>,
+    pub review_comment: Option<String>,
+}
+
+impl GraphQLMappable<GraphQLAttachment> for Attachment {
+    fn to_graphql_type(&self, key: String) -> GraphQLAttachment {
+        GraphQLAttachment {
+            id: key.rsplit(':').next().unwrap_or(&key).to_owned(),
+            owner_type: self.owner_type,
+            owner_id: self.owner_id.clone(),
+            document_type: self.document_type,
+            content_type: self.content_type.clone(),
+            size: self.size.min(i32::MAX as u64) as i32,
+            uploaded_by: self.uploaded_by.clone(),
+            uploaded_by_name: self.uploaded_by_name.clone(),
+            uploaded_at: self.uploaded_at,
+            status: self.status,
+            reviewed_by_name: self.reviewed_by_name.clone(),
+            reviewed_at: self.reviewed_at,
+            review_comment: self.review_comment.clone(),
         }
     }
 }
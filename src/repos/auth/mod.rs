@@ -1,28 +1,26 @@
-use redis::{cmd, Commands, JsonCommands};
+use redis::{cmd, AsyncCommands};
 use utils::{
     hashing_composite_key, get_db_key_from_username, copy_redis_field, 
     copy_security_answers, delete_keys_by_pattern
 };
+use crate::repos::graphql::utils::scan_keys;
 
 use crate::{
-    endpoints::handlers::configs::connection_pool::get_pool_connection,
-    models::{
-        auth::{TokenInfo, UserType},
-        StatusMessage,
-    },
+    endpoints::handlers::configs::connection_pool::RedisPool,
+    errors::AppError,
+    models::auth::{TokenInfo, UserType},
 };
 
 pub mod utils;
 
 //TODO: ~Set for ALC (ALC is out of scoope)~
-pub fn create_user_with_access_token(
+pub async fn create_user_with_access_token(
+    pool: &RedisPool,
     user_name: String,
     pass: String,
     real_name: String,
-) -> Result<TokenInfo, StatusMessage> {
-    let mut con = get_pool_connection()
-        .get()
-        .expect("Couldn't connect to pool"); //Can't abstracted to a struct, :C
+) -> Result<TokenInfo, AppError> {
+    let mut con = pool.get().await?;
 
     // This will be the token that the user will use for loging
     let access_token = hashing_composite_key(&[&user_name, &pass]);
@@ -36,7 +34,8 @@ pub fn create_user_with_access_token(
     //For checking the existance of the field
     match cmd("GET")
         .arg(format!("users_on_used:{}", &user_name))
-        .query::<String>(&mut con)
+        .query_async::<String>(&mut con)
+        .await
     {
         //This will look weird, but we are looking here in the case it fails
         Err(_) => {
@@ -46,57 +45,68 @@ pub fn create_user_with_access_token(
             //Want to have the resource the closest to key level, cause is just for checking if it exists
             let _: () = con
                 .set(format!("users_on_used:{}", &user_name), "")
-                .expect("USERNAME CREATION : Couldn't filled username");
+                .await
+                .map_err(|_| AppError::storage("USERNAME CREATION : Couldn't filled username"))?;
 
             let _: () = con
                 .set(
                     format!("users:{}:complete_name", &db_composite_key),
                     &real_name,
                 )
-                .expect("ACCESS TOKEN CREATION: Couldn't create field");
+                .await
+                .map_err(|_| AppError::storage("ACCESS TOKEN CREATION: Couldn't create field"))?;
 
             let _: () = con
                 .set(
                     format!("users:{}:affiliate_key", &db_composite_key),
                     &affiliate_key,
                 )
-                .expect("ACCESS TOKEN CREATION: Couldn't create field");
+                .await
+                .map_err(|_| AppError::storage("ACCESS TOKEN CREATION: Couldn't create field"))?;
 
             let _: () = con
                 .set(format!("affiliate_keys:{}", &affiliate_key), &user_name)
-                .expect("USERNAME CREATION : Couldn't filled username");
+                .await
+                .map_err(|_| AppError::storage("USERNAME CREATION : Couldn't filled username"))?;
 
             let _: () = con
                 .set(
                     format!("affiliate_key_to_db_access:{}", &affiliate_key),
                     &db_composite_key,
                 )
-                .expect("ACCESS TOKEN CREATION: Couldn't create field");
+                .await
+                .map_err(|_| AppError::storage("ACCESS TOKEN CREATION: Couldn't create field"))?;
 
             let _: () = con
                 .set(format!("users:{}:payed_to_capital", &db_composite_key), 0.0)
-                .expect("ACCESS TOKEN CREATION: Couldn't create field");
+                .await
+                .map_err(|_| AppError::storage("ACCESS TOKEN CREATION: Couldn't create field"))?;
 
             let _: () = con
                 .set(format!("users:{}:owed_capital", &db_composite_key), 0.0)
-                .expect("ACCESS TOKEN CREATION: Couldn't create field");
+                .await
+                .map_err(|_| AppError::storage("ACCESS TOKEN CREATION: Couldn't create field"))?;
 
             // For default any new user won't be
             let _: () = con
                 .set(format!("users:{}:is_directive", &db_composite_key), false)
-                .expect("ACCESS TOKEN CREATION: Couldn't create field");
+                .await
+                .map_err(|_| AppError::storage("ACCESS TOKEN CREATION: Couldn't create field"))?;
 
             let _: () = con
                 .set(format!("users:{}:payments", &db_composite_key), false)
-                .expect("BASE PAYMENTS CREATION: Couldn't create field");
+                .await
+                .map_err(|_| AppError::storage("BASE PAYMENTS CREATION: Couldn't create field"))?;
 
             let _: () = con
                 .set(format!("users:{}:loans", &db_composite_key), false)
-                .expect("BASE LOANS CREATION: Couldn't create field");
+                .await
+                .map_err(|_| AppError::storage("BASE LOANS CREATION: Couldn't create field"))?;
 
             let _: () = con
                 .set(format!("users:{}:fines", &db_composite_key), false)
-                .expect("BASE FINES CREATION: Couldn't create field");
+                .await
+                .map_err(|_| AppError::storage("BASE FINES CREATION: Couldn't create field"))?;
 
             Ok(TokenInfo {
                 user_name,
@@ -105,17 +115,17 @@ pub fn create_user_with_access_token(
             })
         }
 
-        Ok(_) => Err(StatusMessage {
-            message: "Couldn't Create User".to_string(),
-        }),
+        Ok(_) => Err(AppError::conflict("Couldn't Create User")),
     }
 }
 
 //TODO: Refactor this for recieving the access token
-pub fn get_user_access_token(user_name: String, pass: String) -> Result<TokenInfo, StatusMessage> {
-    let mut con = get_pool_connection()
-        .get()
-        .expect("Couldn't connect to pool"); //Can't abstracted to a struct, :C
+pub async fn get_user_access_token(
+    pool: &RedisPool,
+    user_name: String,
+    pass: String,
+) -> Result<TokenInfo, AppError> {
+    let mut con = pool.get().await?;
 
     // THe token derived from the user and pass
     let access_token = hashing_composite_key(&[&user_name, &pass]);
@@ -126,18 +136,16 @@ pub fn get_user_access_token(user_name: String, pass: String) -> Result<TokenInf
     //Passing an String for recieving an nil
     match cmd("EXISTS")
         .arg(format!("users:{db_access_token}:complete_name")) //Closests key-value we have at hand
-        .query::<bool>(&mut con)
+        .query_async::<bool>(&mut con)
+        .await
     {
         Ok(it_exists) => {
             if it_exists {
-                let mut con = get_pool_connection()
-                    .get()
-                    .expect("Couldn't connect to pool");
-
                 // get the the user type
 
                 let user_type = match con
                     .get::<String, bool>(format!("users:{db_access_token}:is_directive"))
+                    .await
                     .unwrap_or_default()
                 {
                     true => UserType::Directive.to_string(),
@@ -152,33 +160,29 @@ pub fn get_user_access_token(user_name: String, pass: String) -> Result<TokenInf
                 });
             }
 
-            Err(StatusMessage {
-                message: "User Might Not Exist or User/Password is wrong".to_string(),
-            })
+            Err(AppError::unauthorized("User Might Not Exist or User/Password is wrong"))
         }
-        Err(e) => Err(StatusMessage {
-            message: format!("Error: {e}"),
-        }),
+        Err(e) => Err(AppError::storage(format!("Error: {e}"))),
     }
 }
 
 /// Configure security answer for a user
 /// 
 /// # Arguments
+/// * `pool` - Shared redis pool
 /// * `user_name` - Username to configure
 /// * `security_question_index` - Index (0, 1, or 2) of the question
 /// * `security_answer` - User's answer to the security question (will be hashed)
-pub fn configure_security_answer(
+pub async fn configure_security_answer(
+    pool: &RedisPool,
     user_name: String,
     security_question_index: u8,
     security_answer: String,
-) -> Result<(), StatusMessage> {
-    let mut con = get_pool_connection()
-        .get()
-        .expect("Couldn't connect to pool");
+) -> Result<(), AppError> {
+    let mut con = pool.get().await?;
 
     // Get db_composite_key from username using helper
-    let db_composite_key = get_db_key_from_username(&user_name, &mut con)?;
+    let db_composite_key = get_db_key_from_username(&user_name, &mut con).await?;
 
     // Normalize answer: lowercase + trim
     let normalized_answer = security_answer.trim().to_lowercase();
@@ -190,9 +194,8 @@ pub fn configure_security_answer(
             format!("users:{}:security_question_index", &db_composite_key),
             security_question_index.to_string(),
         )
-        .map_err(|_| StatusMessage {
-            message: "No se pudo guardar la pregunta de seguridad".to_string(),
-        })?;
+        .await
+        .map_err(|_| AppError::storage("No se pudo guardar la pregunta de seguridad"))?;
 
     // Save answer hash
     let _: () = con
@@ -200,21 +203,19 @@ pub fn configure_security_answer(
             format!("users:{}:security_answer", &db_composite_key),
             answer_hash,
         )
-        .map_err(|_| StatusMessage {
-            message: "No se pudo guardar la respuesta de seguridad".to_string(),
-        })?;
+        .await
+        .map_err(|_| AppError::storage("No se pudo guardar la respuesta de seguridad"))?;
 
     Ok(())
 }
 
 /// guarda las 3 respuestas de seguridad para un usuario usando access_token
-pub fn configure_all_security_answers(
+pub async fn configure_all_security_answers(
+    pool: &RedisPool,
     access_token: String,
     answers: [String; 3],
-) -> Result<(), StatusMessage> {
-    let mut con = get_pool_connection()
-        .get()
-        .expect("Couldn't connect to pool");
+) -> Result<(), AppError> {
+    let mut con = pool.get().await?;
 
     // obtiene db_composite_key del access_token
     let db_composite_key = hashing_composite_key(&[&access_token]);
@@ -222,11 +223,10 @@ pub fn configure_all_security_answers(
     // verifica que el usuario exista
     let exists: bool = con
         .exists(format!("users:{}:complete_name", &db_composite_key))
+        .await
         .unwrap_or(false);
     if !exists {
-        return Err(StatusMessage {
-            message: "Usuario no encontrado o token inválido".to_string(),
-        });
+        return Err(AppError::unauthorized("Usuario no encontrado o token inválido"));
     }
 
     // guarda las 3 respuestas hasheadas con su índice
@@ -239,8 +239,9 @@ pub fn configure_all_security_answers(
                 format!("users:{}:security_answer_{}", &db_composite_key, index),
                 answer_hash,
             )
-            .map_err(|_| StatusMessage {
-                message: format!("No se pudo guardar la respuesta {} de seguridad", index),
+            .await
+            .map_err(|_| {
+                AppError::storage(format!("No se pudo guardar la respuesta {} de seguridad", index))
             })?;
     }
 
@@ -248,24 +249,22 @@ pub fn configure_all_security_answers(
 }
 
 /// valida la respuesta de seguridad para recuperación de contraseña
-pub fn validate_security_answer(
+pub async fn validate_security_answer(
+    pool: &RedisPool,
     user_name: String,
     question_index: u8,
     security_answer: String,
-) -> Result<String, StatusMessage> {
-    let mut con = get_pool_connection()
-        .get()
-        .expect("Couldn't connect to pool");
+) -> Result<String, AppError> {
+    let mut con = pool.get().await?;
 
     // obtiene db_composite_key del username usando helper
-    let db_composite_key = get_db_key_from_username(&user_name, &mut con)?;
+    let db_composite_key = get_db_key_from_username(&user_name, &mut con).await?;
 
     // obtiene la respuesta hasheada guardada en ese índice
     let stored_answer_hash: String = con
         .get(format!("users:{}:security_answer_{}", &db_composite_key, question_index))
-        .map_err(|_| StatusMessage {
-            message: "Usuario sin pregunta de seguridad configurada".to_string(),
-        })?;
+        .await
+        .map_err(|_| AppError::not_found("Usuario sin pregunta de seguridad configurada"))?;
 
     // normaliza y hashea la respuesta que el usuario ingresó
     let normalized_answer = security_answer.trim().to_lowercase();
@@ -275,25 +274,23 @@ pub fn validate_security_answer(
     if provided_answer_hash == stored_answer_hash {
         Ok(db_composite_key)
     } else {
-        Err(StatusMessage {
-            message: "Respuesta incorrecta".to_string(),
-        })
+        Err(AppError::unauthorized("Respuesta incorrecta"))
     }
 }
 
 /// resetea la contraseña validando respuesta de seguridad
-pub fn reset_password(
+pub async fn reset_password(
+    pool: &RedisPool,
     user_name: String,
     question_index: u8,
     security_answer: String,
     new_pass: String,
-) -> Result<TokenInfo, StatusMessage> {
-    let mut con = get_pool_connection()
-        .get()
-        .expect("Couldn't connect to pool");
+) -> Result<TokenInfo, AppError> {
+    let mut con = pool.get().await?;
 
     // valida la respuesta y obtiene el db_composite_key anterior
-    let old_db_composite_key = validate_security_answer(user_name.clone(), question_index, security_answer)?;
+    let old_db_composite_key =
+        validate_security_answer(pool, user_name.clone(), question_index, security_answer).await?;
 
     // genera los nuevos hashes con la nueva contraseña
     let new_access_token = hashing_composite_key(&[&user_name, &new_pass]);
@@ -303,9 +300,8 @@ pub fn reset_password(
     // obtiene el nombre del usuario (lo necesita para la nueva entrada)
     let real_name: String = con
         .get(format!("users:{}:complete_name", &old_db_composite_key))
-        .map_err(|_| StatusMessage {
-            message: "No se pudo obtener datos del usuario".to_string(),
-        })?;
+        .await
+        .map_err(|_| AppError::storage("No se pudo obtener datos del usuario"))?;
 
     // copia todos los datos del usuario a las nuevas claves (con el nuevo db_composite_key)
     
@@ -315,36 +311,32 @@ pub fn reset_password(
             format!("users:{}:complete_name", &new_db_composite_key),
             &real_name,
         )
-        .map_err(|_| StatusMessage {
-            message: "No se pudo crear nuevo usuario".to_string(),
-        })?;
+        .await
+        .map_err(|_| AppError::storage("No se pudo crear nuevo usuario"))?;
 
     let _: () = con
         .set(
             format!("users:{}:affiliate_key", &new_db_composite_key),
             &affiliate_key,
         )
-        .map_err(|_| StatusMessage {
-            message: "No se pudo crear nuevo usuario".to_string(),
-        })?;
+        .await
+        .map_err(|_| AppError::storage("No se pudo crear nuevo usuario"))?;
 
     // copia las 3 respuestas de seguridad usando helper
-    copy_security_answers(&mut con, &old_db_composite_key, &new_db_composite_key)?;
+    copy_security_answers(&mut con, &old_db_composite_key, &new_db_composite_key).await?;
 
     // copia campos financieros usando helper
-    copy_redis_field(&mut con, "payed_to_capital", &old_db_composite_key, &new_db_composite_key, 0.0)?;
-    copy_redis_field(&mut con, "owed_capital", &old_db_composite_key, &new_db_composite_key, 0.0)?;
+    copy_redis_field(&mut con, "payed_to_capital", &old_db_composite_key, &new_db_composite_key, 0.0).await?;
+    copy_redis_field(&mut con, "owed_capital", &old_db_composite_key, &new_db_composite_key, 0.0).await?;
     
     // copia tipo de usuario usando helper
-    copy_redis_field(&mut con, "is_directive", &old_db_composite_key, &new_db_composite_key, false)?;
+    copy_redis_field(&mut con, "is_directive", &old_db_composite_key, &new_db_composite_key, false).await?;
 
     // Copiar datos de payments, loans y fines del usuario (todas las claves individuales)
     // Estos datos se almacenan como JSON, los copiamos directamente preservando su contenido
     for data_type in &["payments", "loans", "fines"] {
         let pattern = format!("users:{}:{}:*", &old_db_composite_key, data_type);
-        if let Ok(keys_iter) = con.scan_match::<String, String>(pattern) {
-            let old_keys: Vec<String> = keys_iter.collect();
-            
+        if let Ok(old_keys) = scan_keys(&mut con, &pattern).await {
             for old_key in old_keys {
                 // Extract the hash/id part after the data_type (último componente después del último :)
                 if let Some(hash_id) = old_key.split(':').last() {
@@ -354,13 +346,15 @@ pub fn reset_password(
                     // Usar cmd("JSON.GET") directamente para obtener el JSON raw
                     if let Ok(json_str) = cmd("JSON.GET")
                         .arg(&old_key)
-                        .query::<String>(&mut con)
+                        .query_async::<String>(&mut con)
+                        .await
                     {
                         let _: Result<(), _> = cmd("JSON.SET")
                             .arg(&new_key)
                             .arg("$")
                             .arg(&json_str)
-                            .query(&mut con)
+                            .query_async(&mut con)
+                            .await
                             .map_err(|e| println!("Warning: couldn't copy {} data: {:?}", data_type, e));
                     }
                 }
@@ -374,21 +368,18 @@ pub fn reset_password(
             format!("users:{}:payments", &new_db_composite_key),
             false,
         )
-        .map_err(|_| StatusMessage {
-            message: "No se pudo inicializar contadores".to_string(),
-        })?;
+        .await
+        .map_err(|_| AppError::storage("No se pudo inicializar contadores"))?;
 
     let _: () = con
         .set(format!("users:{}:loans", &new_db_composite_key), false)
-        .map_err(|_| StatusMessage {
-            message: "No se pudo inicializar contadores".to_string(),
-        })?;
+        .await
+        .map_err(|_| AppError::storage("No se pudo inicializar contadores"))?;
 
     let _: () = con
         .set(format!("users:{}:fines", &new_db_composite_key), false)
-        .map_err(|_| StatusMessage {
-            message: "No se pudo inicializar contadores".to_string(),
-        })?;
+        .await
+        .map_err(|_| AppError::storage("No se pudo inicializar contadores"))?;
 
     // crea el nuevo mapping con las nuevas claves
     let _: () = con
@@ -396,9 +387,8 @@ pub fn reset_password(
             format!("affiliate_key_to_db_access:{}", &affiliate_key),
             &new_db_composite_key,
         )
-        .map_err(|_| StatusMessage {
-            message: "No se pudo actualizar mapeo de usuario".to_string(),
-        })?;
+        .await
+        .map_err(|_| AppError::storage("No se pudo actualizar mapeo de usuario"))?;
 
     // elimina todas las claves viejas del usuario anterior (seguridad: invalida el token anterior)
     let old_keys_to_delete = vec![
@@ -416,18 +406,19 @@ pub fn reset_password(
     ];
     
     for key in old_keys_to_delete {
-        let _: Result<(), _> = con.del(&key);
+        let _: Result<(), _> = con.del(&key).await;
     }
 
     // Eliminar también todas las claves individuales de payments/loans/fines del db_key anterior usando helper
     for data_type in &["payments", "loans", "fines"] {
         let pattern = format!("users:{}:{}:*", &old_db_composite_key, data_type);
-        let _ = delete_keys_by_pattern(&mut con, pattern);
+        let _ = delete_keys_by_pattern(&mut con, pattern).await;
     }
 
     // retorna el nuevo token con datos actualizados
     let is_directive: bool = con
         .get(format!("users:{}:is_directive", &new_db_composite_key))
+        .await
         .unwrap_or(false);
     
     Ok(TokenInfo {
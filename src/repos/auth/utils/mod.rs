@@ -1,7 +1,9 @@
 use sha2::{Digest, Sha256};
-use redis::{Commands, RedisResult};
+use redis::{AsyncCommands, RedisResult};
 
-use crate::models::StatusMessage;
+use crate::endpoints::handlers::configs::connection_pool::RedisConnection;
+use crate::errors::AppError;
+use crate::repos::graphql::utils::scan_keys;
 
 /// function that giving n reference to arguments, returns the hasked key in string format
 pub fn hashing_composite_key(args: &[&String]) -> String {
@@ -27,16 +29,15 @@ pub fn hashing_composite_key(args: &[&String]) -> String {
 /// 
 /// # Returns
 /// * `Ok(String)` - El db_composite_key del usuario
-/// * `Err(StatusMessage)` - Si el usuario no existe
-pub fn get_db_key_from_username(
+/// * `Err(AppError::NotFound)` - Si el usuario no existe
+pub async fn get_db_key_from_username(
     user_name: &str,
-    con: &mut redis::Connection,
-) -> Result<String, StatusMessage> {
+    con: &mut RedisConnection,
+) -> Result<String, AppError> {
     let affiliate_key = hashing_composite_key(&[&user_name.to_string()]);
     con.get(format!("affiliate_key_to_db_access:{}", &affiliate_key))
-        .map_err(|_| StatusMessage {
-            message: "Usuario no encontrado".to_string(),
-        })
+        .await
+        .map_err(|_| AppError::not_found("Usuario no encontrado"))
 }
 
 /// Copia un campo scalar de Redis desde una key antigua a una nueva
@@ -50,24 +51,24 @@ pub fn get_db_key_from_username(
 /// 
 /// # Type Parameters
 /// * `T` - Tipo del valor (debe implementar ToRedisArgs + FromRedisValue + Clone)
-pub fn copy_redis_field<T>(
-    con: &mut redis::Connection,
+pub async fn copy_redis_field<T>(
+    con: &mut RedisConnection,
     field_name: &str,
     old_key: &str,
     new_key: &str,
     default: T,
-) -> Result<(), StatusMessage>
+) -> Result<(), AppError>
 where
-    T: redis::ToRedisArgs + redis::FromRedisValue + Clone,
+    T: redis::ToRedisArgs + redis::FromRedisValue + Clone + Send + Sync,
 {
     let value: T = con
         .get(format!("users:{}:{}", old_key, field_name))
+        .await
         .unwrap_or(default);
     
     con.set(format!("users:{}:{}", new_key, field_name), value)
-        .map_err(|_| StatusMessage {
-            message: format!("No se pudo copiar campo: {}", field_name),
-        })
+        .await
+        .map_err(|_| AppError::storage(format!("No se pudo copiar campo: {}", field_name)))
 }
 
 /// Copia las 3 respuestas de seguridad desde un db_key antiguo a uno nuevo
@@ -76,24 +77,23 @@ where
 /// * `con` - Conexión a Redis
 /// * `old_key` - db_composite_key antiguo
 /// * `new_key` - db_composite_key nuevo
-pub fn copy_security_answers(
-    con: &mut redis::Connection,
+pub async fn copy_security_answers(
+    con: &mut RedisConnection,
     old_key: &str,
     new_key: &str,
-) -> Result<(), StatusMessage> {
+) -> Result<(), AppError> {
     for i in 0..3 {
         if let Ok(answer) = con.get::<String, String>(
             format!("users:{}:security_answer_{}", old_key, i)
-        ) {
+        ).await {
             if !answer.is_empty() {
                 let _: () = con
                     .set(
                         format!("users:{}:security_answer_{}", new_key, i),
                         answer
                     )
-                    .map_err(|_| StatusMessage {
-                        message: format!("No se pudo copiar respuesta de seguridad {}", i),
-                    })?;
+                    .await
+                    .map_err(|_| AppError::storage(format!("No se pudo copiar respuesta de seguridad {}", i)))?;
             }
         }
     }
@@ -105,14 +105,13 @@ pub fn copy_security_answers(
 /// # Arguments
 /// * `con` - Conexión a Redis
 /// * `pattern` - Patrón de búsqueda (ej: "users:ABC123:payments:*")
-pub fn delete_keys_by_pattern(
-    con: &mut redis::Connection,
+pub async fn delete_keys_by_pattern(
+    con: &mut RedisConnection,
     pattern: String,
 ) -> RedisResult<()> {
-    if let Ok(keys_iter) = con.scan_match::<String, String>(pattern) {
-        let keys: Vec<String> = keys_iter.collect();
+    if let Ok(keys) = scan_keys(con, &pattern).await {
         for key in keys {
-            let _: RedisResult<()> = con.del(&key);
+            let _: RedisResult<()> = con.del(&key).await;
         }
     }
     Ok(())
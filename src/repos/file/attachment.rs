@@ -0,0 +1,130 @@
+use chrono::Utc;
+use redis::AsyncCommands;
+
+use crate::{
+    endpoints::handlers::configs::connection_pool::RedisPool,
+    errors::AppError,
+    models::{
+        dates::DateTime,
+        file::{AttachmentUpload, UploadForm},
+        graphql::{Attachment, AttachmentStatus},
+        redis::Attachment as RedisAttachment,
+    },
+    repos::{
+        auth::utils::hashing_composite_key,
+        graphql::{attachment::AttachmentRepo, store::AttachmentStore},
+    },
+};
+
+use super::{TicketFile, get_viewer, read_normalized, store::BlobStore, validate_ticket_id};
+
+// bytes de los documentos (repos::graphql::attachment tiene el registro y la revisión)
+// mismas reglas que los comprobantes: JPEG, PNG o PDF, las fotos sin metadatos
+// los sube y los ve el socio dueño (el del préstamo o la multa) y cualquier directivo
+
+/// guarda el documento y lo registra como PENDING
+pub async fn upload_attachment(
+    form: UploadForm,
+    upload: AttachmentUpload,
+    pool: &RedisPool,
+    blob_store: &dyn BlobStore,
+) -> Result<Attachment, AppError> {
+    // los ids de socios, préstamos y multas son hashes igual que los ticket_id
+    validate_ticket_id(&upload.owner_id)
+        .map_err(|_| AppError::validation("owner_id inválido", &["owner_id"]))?;
+
+    let repo = AttachmentRepo { pool: pool.clone() };
+    let member_key = repo
+        .get_member_key(upload.owner_type, &upload.owner_id)
+        .await?;
+
+    let mut con = pool.get().await?;
+    let viewer = get_viewer(&mut con, &upload.access_token).await?;
+    if !viewer.is_directive && viewer.affiliate_key != member_key {
+        return Err(AppError::unauthorized(
+            "No puedes subir documentos de otro socio",
+        ));
+    }
+    let uploaded_by_name = con
+        .get::<String, Option<String>>(format!(
+            "users:{}:complete_name",
+            hashing_composite_key(&[&upload.access_token])
+        ))
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+
+    let document = read_normalized(form.file).await?;
+
+    let id = hashing_composite_key(&[
+        &upload.owner_id,
+        &Utc::now().timestamp_micros().to_string(),
+        &rand::random::<u64>().to_string(),
+    ]);
+    let attachment = RedisAttachment {
+        owner_type: upload.owner_type,
+        blob_key: format!(
+            "attachments/{}/{}/{id}.{}",
+            upload.owner_type.as_str(),
+            upload.owner_id,
+            document.kind.extension()
+        ),
+        owner_id: upload.owner_id,
+        member_key,
+        document_type: upload.document_type,
+        content_type: document.kind.content_type().to_string(),
+        size: document.bytes.len() as u64,
+        uploaded_by: viewer.affiliate_key,
+        uploaded_by_name,
+        uploaded_at: DateTime::now(),
+        status: AttachmentStatus::Pending,
+        reviewed_by: None,
+        reviewed_by_name: None,
+        reviewed_at: None,
+        review_comment: None,
+    };
+
+    blob_store.put(&attachment.blob_key, document.bytes).await?;
+
+    repo.save_attachment(&id, &attachment).await
+}
+
+/// el documento si el dueño del access_token lo puede ver, los bytes los manda el handler
+/// directo del BlobStore
+pub async fn get_attachment_file(
+    access_token: String,
+    id: String,
+    pool: &RedisPool,
+) -> Result<TicketFile, AppError> {
+    validate_ticket_id(&id).map_err(|_| AppError::validation("id inválido", &["id"]))?;
+
+    let viewer = get_viewer(&mut pool.get().await?, &access_token).await?;
+    let attachment = AttachmentRepo { pool: pool.clone() }
+        .get_redis_attachment(&id)
+        .await?;
+
+    // a un socio no se le dice si el documento existe, solo que no es suyo
+    let attachment = match attachment {
+        Some(attachment)
+            if viewer.is_directive
+                || viewer.affiliate_key == attachment.member_key
+                || viewer.affiliate_key == attachment.uploaded_by =>
+        {
+            attachment
+        }
+        None if viewer.is_directive => {
+            return Err(AppError::not_found("Documento no encontrado"));
+        }
+        _ => {
+            return Err(AppError::unauthorized("No tienes acceso a este documento"));
+        }
+    };
+
+    Ok(TicketFile {
+        ticket_id: id,
+        key: attachment.blob_key,
+        content_type: attachment.content_type,
+        size: attachment.size,
+    })
+}
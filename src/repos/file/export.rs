@@ -0,0 +1,516 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use actix_web::web::Bytes;
+use chrono::Datelike;
+use futures::channel::mpsc;
+use futures::{SinkExt, stream};
+use rust_decimal::prelude::ToPrimitive;
+use rust_xlsxwriter::{ExcelDateTime, Format, Workbook, Worksheet, XlsxError};
+
+use crate::{
+    endpoints::handlers::configs::schema::GeneralContext,
+    errors::AppError,
+    models::{
+        currency::{Currency, base_currency},
+        dates::{Date, today},
+        file::{ExportDownload, ExportEntity, ExportFormat},
+        graphql::{Member, QuotaType},
+        money::Money,
+    },
+};
+
+use super::statement::{fine_status_label, loan_status_label, payment_status_label};
+use super::store::BlobStream;
+
+// exportaciones de pagos, préstamos, multas y cuotas para /general/export/{entity}
+// se va socio por socio con los repos del contexto, así nunca está toda la cooperativa en
+// memoria: el CSV se manda por socio y el XLSX se escribe en una hoja constant_memory (las
+// filas se van a un temporal) y el zip se manda mientras rust_xlsxwriter lo arma
+
+// de a cuánto se manda el XLSX
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// una celda, así el XLSX lleva números y fechas de verdad y el CSV los mismos textos
+enum Cell {
+    Text(String),
+    Money(Money),
+    Number(f64),
+    Date(Option<Date>),
+}
+
+impl Cell {
+    fn text(text: impl Into<String>) -> Cell {
+        Cell::Text(text.into())
+    }
+
+    fn csv(&self) -> String {
+        match self {
+            Cell::Text(text) => escape_formula(text).into_owned(),
+            Cell::Money(money) => money.to_string(),
+            Cell::Number(number) => number.to_string(),
+            Cell::Date(date) => date.map(|date| date.to_string()).unwrap_or_default(),
+        }
+    }
+}
+
+/// Excel y compañía abren como fórmula lo que empieza con = + - @, a esos textos (que vienen
+/// de lo que escriben los socios) se les antepone ' para que se queden como texto
+fn escape_formula(text: &str) -> Cow<'_, str> {
+    if text.starts_with(['=', '+', '-', '@']) {
+        Cow::Owned(format!("'{text}"))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// los socios que entran en la exportación: affiliate_key si es él mismo o lo pide un
+/// directivo, todos si no viene (solo directivos)
+pub async fn export_members(
+    context: &GeneralContext,
+    access_token: &str,
+    affiliate_key: Option<String>,
+) -> Result<Vec<Member>, AppError> {
+    let users = context.user_repo();
+    let me = users
+        .get_member_by_access_token(access_token.to_string())
+        .await
+        .map_err(|_| AppError::unauthorized("Credenciales inválidas"))?;
+    if affiliate_key.as_deref() == Some(me.affiliate_key.as_str()) {
+        return Ok(vec![me]);
+    }
+    if !users.is_directive(access_token).await {
+        return Err(AppError::unauthorized(
+            "Solo los directivos pueden exportar datos de otros socios",
+        ));
+    }
+
+    if let Some(affiliate_key) = affiliate_key {
+        return Ok(vec![
+            users.get_member_by_affiliate_key(affiliate_key).await?,
+        ]);
+    }
+
+    let mut members = Vec::new();
+    for affiliate in users.get_all_users_for_affiliates().await? {
+        // si un socio tiene los datos a medias se salta, no se cae toda la exportación
+        if let Ok(member) = users.get_member_by_affiliate_key(affiliate.user_id).await {
+            members.push(member);
+        }
+    }
+    members.sort_by(|a, b| a.affiliate_key.cmp(&b.affiliate_key));
+
+    Ok(members)
+}
+
+/// {entidad}[-{affiliate_key}]-{hoy}.{ext}, solo con caracteres seguros
+pub fn export_file_name(entity: ExportEntity, download: &ExportDownload) -> String {
+    let mut name = entity.title().to_lowercase();
+    if let Some(affiliate_key) = &download.affiliate_key {
+        name.push_str(&format!("-{affiliate_key}"));
+    }
+    name.push_str(&format!("-{}", today()));
+    name.retain(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    format!("{name}.{}", download.format.extension())
+}
+
+/// el archivo en streaming, los errores de antes de empezar a mandar salen como AppError
+pub async fn export(
+    context: GeneralContext,
+    entity: ExportEntity,
+    download: ExportDownload,
+) -> Result<BlobStream, AppError> {
+    let members = export_members(
+        &context,
+        &download.access_token,
+        download.affiliate_key.clone(),
+    )
+    .await?;
+
+    match download.format {
+        ExportFormat::Csv => Ok(export_csv(context, entity, download, members)),
+        ExportFormat::Xlsx => export_xlsx(context, entity, download, members).await,
+    }
+}
+
+fn header(entity: ExportEntity) -> &'static [&'static str] {
+    match entity {
+        ExportEntity::Payments => &[
+            "socio",
+            "nombre",
+            "id",
+            "fecha",
+            "concepto",
+            "boleta",
+            "cuenta",
+            "monto",
+            "moneda",
+            "estado",
+            "abonado_a",
+            "comentario",
+        ],
+        ExportEntity::Loans => &[
+            "socio", "nombre", "id", "fecha", "motivo", "total", "pagado", "saldo", "moneda",
+            "interes", "cuotas", "estado",
+        ],
+        ExportEntity::Fines => &["socio", "nombre", "id", "motivo", "monto", "estado"],
+        ExportEntity::Quotas => &[
+            "socio",
+            "nombre",
+            "tipo",
+            "prestamo",
+            "numero",
+            "vencimiento",
+            "monto",
+            "pagado",
+            "multa",
+            "estado",
+            "extraordinaria",
+            "moneda",
+        ],
+    }
+}
+
+/// las filas de un socio, con el mismo orden de columnas que header
+async fn member_rows(
+    context: &GeneralContext,
+    entity: ExportEntity,
+    download: &ExportDownload,
+    member: &Member,
+) -> Result<Vec<Vec<Cell>>, AppError> {
+    let socio = || [Cell::text(&member.affiliate_key), Cell::text(&member.name)];
+
+    let rows = match entity {
+        ExportEntity::Payments => {
+            let mut payments = context
+                .payment_repo()
+                .get_payments_by_owner(&member.owner_key)
+                .await?;
+            payments.sort_by_key(|payment| payment.payment_date);
+
+            payments
+                .into_iter()
+                .map(|payment| {
+                    let being_payed = payment
+                        .being_payed
+                        .iter()
+                        .map(|payed_to| format!("{} {}", payed_to.model_type, payed_to.amount))
+                        .collect::<Vec<_>>()
+                        .join("; ");
+
+                    socio()
+                        .into_iter()
+                        .chain([
+                            Cell::text(payment.id),
+                            Cell::Date(Some(payment.payment_date.local_date())),
+                            Cell::text(payment.name),
+                            Cell::text(payment.ticket_num),
+                            Cell::text(payment.account_num),
+                            Cell::Money(payment.total_amount),
+                            Cell::text(payment.currency.as_str()),
+                            Cell::text(payment_status_label(&payment.state)),
+                            Cell::text(being_payed),
+                            Cell::text(payment.commentary.unwrap_or_default()),
+                        ])
+                        .collect()
+                })
+                .collect()
+        }
+        ExportEntity::Loans => {
+            let mut loans = context
+                .loan_repo()
+                .get_loans_by_owner(&member.owner_key)
+                .await?;
+            loans.sort_by_key(|loan| loan.created_at);
+
+            loans
+                .into_iter()
+                .map(|loan| {
+                    socio()
+                        .into_iter()
+                        .chain([
+                            Cell::text(loan.id),
+                            Cell::Date(loan.created_at.map(|created_at| created_at.local_date())),
+                            Cell::text(loan.reason),
+                            Cell::Money(loan.total),
+                            Cell::Money(loan.payed),
+                            Cell::Money(loan.debt),
+                            Cell::text(loan.currency.as_str()),
+                            Cell::Number(loan.interest_rate),
+                            Cell::Number(loan.total_quotas as f64),
+                            Cell::text(loan_status_label(&loan.status)),
+                        ])
+                        .collect()
+                })
+                .collect()
+        }
+        ExportEntity::Fines => context
+            .fine_repo()
+            .get_fines_by_owner(&member.owner_key)
+            .await?
+            .into_iter()
+            .map(|fine| {
+                socio()
+                    .into_iter()
+                    .chain([
+                        Cell::text(fine.id),
+                        Cell::text(fine.reason),
+                        Cell::Money(fine.amount),
+                        Cell::text(fine_status_label(&fine.status)),
+                    ])
+                    .collect()
+            })
+            .collect(),
+        ExportEntity::Quotas => {
+            let quota_repo = context.quota_repo();
+            let mut quotas = if download.pending {
+                quota_repo
+                    .get_pending_quotas_by_owner(&member.owner_key)
+                    .await?
+            } else {
+                quota_repo.get_quotas_by_owner(&member.owner_key).await?
+            };
+            if let Some(quota_type) = &download.quota_type {
+                quotas.retain(|quota| &quota.quota_type == quota_type);
+            }
+            quotas.sort_by_key(|quota| quota.exp_date);
+
+            // las cuotas de préstamo van en la moneda del préstamo
+            let loan_currencies: HashMap<String, Currency> =
+                if quotas.iter().any(|quota| quota.loan_id.is_some()) {
+                    context
+                        .loan_repo()
+                        .get_loans_by_owner(&member.owner_key)
+                        .await?
+                        .into_iter()
+                        .map(|loan| (loan.id, loan.currency))
+                        .collect()
+                } else {
+                    HashMap::new()
+                };
+
+            quotas
+                .into_iter()
+                .map(|quota| {
+                    let currency = quota
+                        .loan_id
+                        .as_ref()
+                        .and_then(|loan_id| loan_currencies.get(loan_id).copied())
+                        .unwrap_or_else(base_currency);
+
+                    socio()
+                        .into_iter()
+                        .chain([
+                            Cell::text(match quota.quota_type {
+                                QuotaType::Prestamo => "PRESTAMO",
+                                QuotaType::Afiliado => "AFILIADO",
+                            }),
+                            Cell::text(quota.nombre_prestamo.or(quota.loan_id).unwrap_or_default()),
+                            match quota.quota_number {
+                                Some(number) => Cell::Number(number as f64),
+                                None => Cell::text(""),
+                            },
+                            Cell::Date(quota.exp_date),
+                            Cell::Money(quota.amount),
+                            Cell::Money(quota.monto_pagado.unwrap_or(Money::ZERO)),
+                            Cell::Money(quota.multa.unwrap_or(Money::ZERO)),
+                            Cell::text(if quota.payed == Some(true) {
+                                "PAGADA"
+                            } else {
+                                "PENDIENTE"
+                            }),
+                            Cell::text(if quota.is_extraordinary == Some(true) {
+                                "SI"
+                            } else {
+                                "NO"
+                            }),
+                            Cell::text(currency.as_str()),
+                        ])
+                        .collect()
+                })
+                .collect()
+        }
+    };
+
+    Ok(rows)
+}
+
+fn export_error(err: &dyn std::fmt::Debug) -> AppError {
+    println!("Couldn't write export: {err:?}");
+    AppError::storage("couldn't generate export")
+}
+
+fn csv_chunk<I, S>(records: impl IntoIterator<Item = I>) -> Result<Bytes, AppError>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<[u8]>,
+{
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for record in records {
+        writer
+            .write_record(record)
+            .map_err(|err| export_error(&err))?;
+    }
+
+    writer
+        .into_inner()
+        .map(Bytes::from)
+        .map_err(|err| export_error(&err))
+}
+
+/// primero el encabezado y después un pedazo por socio (los que no tienen filas se saltan)
+/// si un socio falla a medio camino el stream termina con error y se corta la descarga
+fn export_csv(
+    context: GeneralContext,
+    entity: ExportEntity,
+    download: ExportDownload,
+    members: Vec<Member>,
+) -> BlobStream {
+    let header_chunk = stream::once(async move { csv_chunk([header(entity)]) });
+    let rows = stream::try_unfold(
+        (context, download, members.into_iter()),
+        move |(context, download, mut members)| async move {
+            loop {
+                let Some(member) = members.next() else {
+                    return Ok(None);
+                };
+                let rows = member_rows(&context, entity, &download, &member).await?;
+                if rows.is_empty() {
+                    continue;
+                }
+                let chunk = csv_chunk(rows.iter().map(|row| row.iter().map(Cell::csv)))?;
+                return Ok(Some((chunk, (context, download, members))));
+            }
+        },
+    );
+
+    Box::pin(futures::StreamExt::chain(header_chunk, rows))
+}
+
+/// escribe todas las filas en la hoja (constant_memory, van a un temporal) y después manda
+/// el zip desde un hilo aparte mientras se arma
+async fn export_xlsx(
+    context: GeneralContext,
+    entity: ExportEntity,
+    download: ExportDownload,
+    members: Vec<Member>,
+) -> Result<BlobStream, AppError> {
+    let bold = Format::new().set_bold();
+    let money = Format::new().set_num_format("#,##0.00");
+    let date = Format::new().set_num_format("yyyy-mm-dd");
+
+    let mut workbook = Workbook::new();
+    let sheet = workbook.add_worksheet_with_constant_memory();
+    sheet
+        .set_name(entity.title())
+        .map_err(|err| export_error(&err))?;
+    for (col, title) in header(entity).iter().enumerate() {
+        sheet
+            .write_string_with_format(0, col as u16, *title, &bold)
+            .map_err(|err| export_error(&err))?;
+    }
+    sheet
+        .set_freeze_panes(1, 0)
+        .map_err(|err| export_error(&err))?;
+
+    let mut row = 1;
+    for member in &members {
+        for cells in member_rows(&context, entity, &download, member).await? {
+            write_xlsx_row(sheet, row, &cells, &money, &date).map_err(|err| export_error(&err))?;
+            row += 1;
+        }
+    }
+
+    // el sender se cierra cuando termina el hilo, con eso termina el stream
+    let (sender, receiver) = mpsc::channel::<Result<Bytes, AppError>>(4);
+    tokio::task::spawn_blocking(move || {
+        let mut writer = ChannelWriter {
+            sender,
+            buffer: Vec::with_capacity(CHUNK_SIZE),
+        };
+        let saved = workbook
+            .save_to_writer(&mut writer)
+            .map_err(|err| export_error(&err))
+            .and_then(|()| writer.flush().map_err(|err| export_error(&err)));
+        if let Err(err) = saved {
+            // si ya no hay quien reciba (se cortó la descarga) no importa
+            let _ = futures::executor::block_on(writer.sender.send(Err(err)));
+        }
+    });
+
+    Ok(Box::pin(receiver))
+}
+
+fn write_xlsx_row(
+    sheet: &mut Worksheet,
+    row: u32,
+    cells: &[Cell],
+    money: &Format,
+    date: &Format,
+) -> Result<(), XlsxError> {
+    for (col, cell) in cells.iter().enumerate() {
+        let col = col as u16;
+        match cell {
+            Cell::Text(text) if text.is_empty() => {}
+            Cell::Text(text) => {
+                sheet.write_string(row, col, escape_formula(text))?;
+            }
+            Cell::Money(amount) => {
+                let amount = amount.amount().to_f64().unwrap_or_default();
+                sheet.write_number_with_format(row, col, amount, money)?;
+            }
+            Cell::Number(number) => {
+                sheet.write_number(row, col, *number)?;
+            }
+            Cell::Date(None) => {}
+            Cell::Date(Some(value)) => {
+                let naive = value.naive();
+                let value = ExcelDateTime::from_ymd(
+                    naive.year() as u16,
+                    naive.month() as u8,
+                    naive.day() as u8,
+                )?;
+                sheet.write_datetime_with_format(row, col, value, date)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Write que manda el XLSX por el canal de a CHUNK_SIZE, el send bloquea si el cliente va
+/// más lento (por eso corre en spawn_blocking)
+struct ChannelWriter {
+    sender: mpsc::Sender<Result<Bytes, AppError>>,
+    buffer: Vec<u8>,
+}
+
+impl ChannelWriter {
+    fn send_buffer(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let chunk = Bytes::from(std::mem::replace(
+            &mut self.buffer,
+            Vec::with_capacity(CHUNK_SIZE),
+        ));
+        futures::executor::block_on(self.sender.send(Ok(chunk)))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "export download closed"))
+    }
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        if self.buffer.len() >= CHUNK_SIZE {
+            self.send_buffer()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send_buffer()
+    }
+}
@@ -0,0 +1,130 @@
+use std::io::{ErrorKind, SeekFrom};
+use std::path::PathBuf;
+
+use actix_web::web::Bytes;
+use async_trait::async_trait;
+use futures::stream;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use super::store::{BlobStore, BlobStream, ByteRange, validate_key};
+use crate::errors::AppError;
+
+// de a cuánto se lee el archivo cuando se manda en streaming
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// comprobantes en una carpeta del server (BLOB_LOCAL_PATH), para desarrollo o para una
+/// instalación con un solo server
+#[derive(Clone, Debug)]
+pub struct LocalBlobStore {
+    root: PathBuf,
+}
+
+impl LocalBlobStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        LocalBlobStore { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, AppError> {
+        validate_key(key)?;
+        Ok(self.root.join(key))
+    }
+}
+
+fn storage_error(action: &str, key: &str, err: std::io::Error) -> AppError {
+    println!("Couldn't {} {}: {:?}", action, key, err);
+    AppError::storage(format!("Couldn't {} file", action))
+}
+
+#[async_trait]
+impl BlobStore for LocalBlobStore {
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<(), AppError> {
+        let path = self.path(key)?;
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|err| storage_error("create folder for", key, err))?;
+        }
+
+        // se escribe a un temporal y se renombra, así nadie lee un archivo a medias
+        let tmp = path.with_extension("upload");
+        tokio::fs::write(&tmp, bytes)
+            .await
+            .map_err(|err| storage_error("write", key, err))?;
+        tokio::fs::rename(&tmp, &path)
+            .await
+            .map_err(|err| storage_error("write", key, err))
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, AppError> {
+        match tokio::fs::read(self.path(key)?).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(storage_error("read", key, err)),
+        }
+    }
+
+    async fn size(&self, key: &str) -> Result<Option<u64>, AppError> {
+        match tokio::fs::metadata(self.path(key)?).await {
+            Ok(metadata) => Ok(Some(metadata.len())),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(storage_error("check", key, err)),
+        }
+    }
+
+    async fn stream(
+        &self,
+        key: &str,
+        range: Option<ByteRange>,
+    ) -> Result<Option<BlobStream>, AppError> {
+        let mut file = match tokio::fs::File::open(self.path(key)?).await {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(storage_error("read", key, err)),
+        };
+
+        let remaining = match range {
+            Some(range) => {
+                file.seek(SeekFrom::Start(range.start))
+                    .await
+                    .map_err(|err| storage_error("read", key, err))?;
+                range.len()
+            }
+            None => u64::MAX,
+        };
+
+        // se lee de a CHUNK_SIZE hasta completar el rango (o el final del archivo)
+        let key = key.to_string();
+        let chunks = stream::try_unfold(
+            (file, remaining, key),
+            |(mut file, remaining, key)| async move {
+                if remaining == 0 {
+                    return Ok(None);
+                }
+                let mut chunk = vec![0; CHUNK_SIZE.min(remaining) as usize];
+                let read = file
+                    .read(&mut chunk)
+                    .await
+                    .map_err(|err| storage_error("read", &key, err))?;
+                if read == 0 {
+                    return Ok(None);
+                }
+                chunk.truncate(read);
+                Ok(Some((
+                    Bytes::from(chunk),
+                    (file, remaining - read as u64, key),
+                )))
+            },
+        );
+
+        Ok(Some(Box::pin(chunks)))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), AppError> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(storage_error("delete", key, err)),
+        }
+    }
+}
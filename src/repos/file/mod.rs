@@ -1,133 +1,314 @@
+pub mod attachment;
+pub mod export;
+pub mod local;
+pub mod ocr;
+pub mod payment_link;
+pub mod receipt;
+pub mod s3;
+pub mod signed_url;
+pub mod statement;
+pub mod store;
 pub mod utils;
 
-use std::{fs::File, io::Write, sync::Arc};
+use std::sync::Arc;
 
-use actix_files::NamedFile;
-use actix_web::{
-    HttpResponse,
-    http::header::{ContentDisposition, DispositionType},
-    mime::Mime,
-};
-use aws_sdk_s3::{Client as S3Client, primitives::ByteStream};
-use tokio::fs::read;
+use actix_multipart::form::tempfile::TempFile;
+use actix_web::web;
+use chrono::Utc;
+use redis::{AsyncCommands, JsonAsyncCommands, from_redis_value};
 
 use crate::{
+    config::Env,
+    endpoints::handlers::configs::connection_pool::{RedisConnection, RedisPool},
+    errors::AppError,
     models::{
-        StatusMessage,
-        file::{FileUploadInfo, UploadForm},
+        dates::DateTime,
+        file::{FileUploadInfo, StoredReceipt, UploadForm},
+    },
+    repos::{
+        auth::utils::hashing_composite_key,
+        graphql::{payment::PaymentRepo, store::PaymentStore},
     },
-    repos::{auth::utils::hashing_composite_key, file::utils::check_file_upload_credentials},
 };
 
-//TODO: refactor this for multiple documents
+use self::local::LocalBlobStore;
+use self::ocr::{ReceiptOcr, read_receipt};
+use self::receipt::{NormalizedReceipt, normalize_receipt};
+use self::s3::S3BlobStore;
+use self::signed_url::{ReceiptUrlSigner, SignedTicketUrl};
+use self::store::BlobStore;
 
-pub async fn upload_ticket_payment(
-    form: UploadForm,
-    access_token: String,
-    s3_client: Arc<S3Client>,
-    bucket_name: Arc<String>,
-) -> Result<FileUploadInfo, StatusMessage> {
-    // in case it doesn't have good credentials, a bit of deffensive programming
-    if !check_file_upload_credentials(&access_token) {
-        return Err(StatusMessage {
-            message: "Couldn't verify user".to_owned(),
-        });
+/// backend de los comprobantes según BLOB_STORE (local o s3)
+pub fn create_blob_store(config: &Env) -> Result<Arc<dyn BlobStore>, AppError> {
+    match config.blob_store.trim().to_ascii_lowercase().as_str() {
+        "local" => Ok(Arc::new(LocalBlobStore::new(&config.blob_local_path))),
+        "s3" => Ok(Arc::new(S3BlobStore::new(
+            &config.s3_endpoint,
+            &config.bucket_name,
+            &config.aws_region,
+            &config.aws_access_key_id,
+            &config.aws_secret_access_key,
+        )?)),
+        other => Err(AppError::validation(
+            format!("BLOB_STORE debe ser local o s3, no {}", other),
+            &["BLOB_STORE"],
+        )),
     }
+}
 
-    // check how many files there are
+fn receipt_record_key(ticket_id: &str) -> String {
+    format!("receipts:{ticket_id}")
+}
 
-    let files_len = s3_client
-        .list_objects()
-        .bucket(bucket_name.as_str())
-        .send()
+async fn get_stored_receipt(
+    con: &mut RedisConnection,
+    ticket_id: &str,
+) -> Result<Option<StoredReceipt>, AppError> {
+    let raw = con
+        .json_get::<String, &str, redis::Value>(receipt_record_key(ticket_id), "$")
         .await
-        .unwrap()
-        .contents
-        .unwrap()
-        .len();
+        .map_err(|_| AppError::storage("Error fetching receipt"))?;
+    let Some(nested) = from_redis_value::<Option<String>>(&raw)
+        .map_err(|_| AppError::storage("Error decoding redis value"))?
+    else {
+        return Ok(None);
+    };
+
+    let mut parsed: Vec<StoredReceipt> = serde_json::from_str(&nested)
+        .map_err(|_| AppError::storage("Error deserializing receipt"))?;
+    Ok(parsed.pop())
+}
 
-    let file_name = hashing_composite_key(&[&(files_len.to_string()), &access_token.clone()]);
+/// quién está pidiendo o subiendo un comprobante
+struct Viewer {
+    affiliate_key: String,
+    is_directive: bool,
+}
 
-    // casting in to std file type
-    let file = form.file.file.into_file();
+async fn get_viewer(con: &mut RedisConnection, access_token: &str) -> Result<Viewer, AppError> {
+    // How is registered on the db
+    let db_access_token = hashing_composite_key(&[&access_token.to_string()]);
 
-    let body = ByteStream::read_from()
-        .file(file.into()) // here the casting to the tokio one
-        .build()
+    let affiliate_key = con
+        .get::<String, Option<String>>(format!("users:{db_access_token}:affiliate_key"))
         .await
-        .unwrap();
-
-    match s3_client
-        .put_object()
-        .bucket(bucket_name.as_str())
-        .set_body(Some(body))
-        .key(format!("payment-tickets/{file_name}.jpeg"))
-        .send()
+        .map_err(|_| AppError::storage("Couldn't verify user"))?
+        .ok_or_else(|| AppError::unauthorized("Couldn't verify user"))?;
+    let is_directive = con
+        .get::<String, Option<bool>>(format!("users:{db_access_token}:is_directive"))
         .await
+        .map_err(|_| AppError::storage("Couldn't verify user"))?
+        .unwrap_or(false);
+
+    Ok(Viewer {
+        affiliate_key,
+        is_directive,
+    })
+}
+
+/// los directivos ven todos los comprobantes, un socio solo los que subió él o los de sus pagos
+/// (los comprobantes de antes no tienen dueño registrado, para esos solo cuentan los pagos)
+async fn authorize_ticket(
+    con: &mut RedisConnection,
+    pool: &RedisPool,
+    access_token: &str,
+    ticket_id: &str,
+    record: Option<&StoredReceipt>,
+) -> Result<(), AppError> {
+    let viewer = get_viewer(con, access_token).await?;
+
+    if viewer.is_directive
+        || record.is_some_and(|record| record.owner.as_ref() == Some(&viewer.affiliate_key))
+    {
+        return Ok(());
+    }
+
+    let payments = PaymentRepo::new(pool.clone())
+        .get_user_payments(access_token.to_string())
+        .await?;
+    if payments
+        .iter()
+        .any(|payment| payment.photo_path.contains(ticket_id))
     {
-        Ok(_) => Ok(FileUploadInfo {
-            ticket_id: file_name,
-        }),
-        Err(e) => {
-            println!("{e:?}");
-            Err(StatusMessage {
-                message: "couldn't upload file".to_owned(),
-            })
-        }
+        return Ok(());
     }
+
+    Err(AppError::unauthorized(
+        "No tienes acceso a este comprobante",
+    ))
 }
 
-pub async fn get_ticket_payment(
+//TODO: refactor this for multiple documents
+
+pub async fn upload_ticket_payment(
+    form: UploadForm,
     access_token: String,
-    ticket_name: String,
-    s3_client: Arc<S3Client>,
-    bucket_name: Arc<String>,
-) -> Result<HttpResponse, StatusMessage> {
-    // in case it doesn't have good credentials, a bit of deffensive programming
-    if !check_file_upload_credentials(&access_token) {
-        return Err(StatusMessage {
-            message: "Couldn't verify user".to_owned(),
-        });
-    }
+    pool: &RedisPool,
+    blob_store: &dyn BlobStore,
+    ocr: Option<&dyn ReceiptOcr>,
+) -> Result<FileUploadInfo, AppError> {
+    let (ticket_id, record) =
+        store_receipt(form.file, &access_token, pool, blob_store, ocr).await?;
+
+    Ok(FileUploadInfo {
+        ticket_id,
+        reading: record.reading,
+    })
+}
 
-    // we need to write it somewhere, then when can delete it
-    let mut tmp_file = File::create(&ticket_name).map_err(|err| {
-        println!("{err:?}");
-        StatusMessage {
-            message: "couldn't create temp file".to_owned(),
-        }
+/// el archivo subido ya validado y normalizado (ver receipt::normalize_receipt)
+async fn read_normalized(file: TempFile) -> Result<NormalizedReceipt, AppError> {
+    let bytes = tokio::fs::read(file.file.path()).await.map_err(|err| {
+        println!("Couldn't read uploaded file: {err:?}");
+        AppError::storage("couldn't upload file")
     })?;
 
-    // byte stream from s3 bucket
-    let mut raw_file = match s3_client
-        .get_object()
-        .bucket(bucket_name.as_str())
-        .key(format!("payment-tickets/{ticket_name}.jpeg"))
-        .send()
+    // decodificar y re-encodear una foto es CPU, fuera de los workers de actix
+    web::block(move || normalize_receipt(bytes))
         .await
-    {
-        Ok(file) => file,
-        Err(err) => {
-            eprintln!("{err:?}");
-            return Err(StatusMessage {
-                message: "couldn't get file".to_owned(),
-            });
-        }
+        .map_err(|_| AppError::storage("couldn't process receipt"))?
+}
+
+/// normaliza y guarda el comprobante con su registro (receipts:{ticket_id}), todavía sin pago
+/// si hay OCR el registro lleva lo que se pudo leer
+async fn store_receipt(
+    file: TempFile,
+    access_token: &str,
+    pool: &RedisPool,
+    blob_store: &dyn BlobStore,
+    ocr: Option<&dyn ReceiptOcr>,
+) -> Result<(String, StoredReceipt), AppError> {
+    // in case it doesn't have good credentials, a bit of deffensive programming
+    let owner = get_viewer(&mut pool.get().await?, access_token)
+        .await?
+        .affiliate_key;
+
+    let receipt = read_normalized(file).await?;
+    let reading = read_receipt(ocr, receipt.kind, &receipt.bytes).await;
+
+    // hora + random, dos subidas del mismo socio nunca chocan
+    let ticket_id = hashing_composite_key(&[
+        &access_token.to_string(),
+        &Utc::now().timestamp_micros().to_string(),
+        &rand::random::<u64>().to_string(),
+    ]);
+    let record = StoredReceipt {
+        key: format!("payment-tickets/{ticket_id}.{}", receipt.kind.extension()),
+        content_type: receipt.kind.content_type().to_string(),
+        size: receipt.bytes.len() as u64,
+        uploaded_at: DateTime::now(),
+        owner: Some(owner),
+        linked_at: None,
+        reading,
     };
 
-    // we write bytes to the tmp_file (if they exist)
-    while let Some(bytes) = raw_file.body.try_next().await.map_err(|_| StatusMessage {
-        message: "couldn't open raw file".to_owned(),
-    })? {
-        tmp_file.write_all(&bytes).map_err(|_| StatusMessage {
-            message: "couldn't write to temp file".to_owned(),
-        })?;
+    blob_store.put(&record.key, receipt.bytes).await?;
+
+    let mut con = pool.get().await?;
+    con.json_set::<String, &str, StoredReceipt, ()>(receipt_record_key(&ticket_id), "$", &record)
+        .await
+        .map_err(|_| AppError::storage("couldn't save receipt"))?;
+
+    Ok((ticket_id, record))
+}
+
+/// lo necesario para mandar un comprobante (o un documento, ver attachment.rs): dónde está,
+/// qué es y cuánto pesa
+#[derive(Clone, Debug, PartialEq)]
+pub struct TicketFile {
+    /// el ticket_id o el id del documento, nunca se reutilizan
+    pub ticket_id: String,
+    pub key: String,
+    pub content_type: String,
+    pub size: u64,
+}
+
+/// los ticket_id son el sha256 que arma upload_ticket_payment con hashing_composite_key (hex
+/// en mayúsculas), cualquier otra cosa ni se busca
+pub fn validate_ticket_id(ticket_id: &str) -> Result<(), AppError> {
+    let valid = ticket_id.len() == 64
+        && ticket_id
+            .chars()
+            .all(|c| c.is_ascii_digit() || ('A'..='F').contains(&c));
+
+    if valid {
+        Ok(())
+    } else {
+        Err(AppError::validation("ticket_id inválido", &["ticket_id"]))
     }
+}
+
+/// el comprobante si el dueño del access_token lo puede ver, los bytes los manda el handler
+/// directo del BlobStore
+pub async fn get_ticket_payment(
+    access_token: String,
+    ticket_id: String,
+    pool: &RedisPool,
+    blob_store: &dyn BlobStore,
+) -> Result<TicketFile, AppError> {
+    validate_ticket_id(&ticket_id)?;
+
+    let mut con = pool.get().await?;
+    let record = get_stored_receipt(&mut con, &ticket_id).await?;
+    authorize_ticket(&mut con, pool, &access_token, &ticket_id, record.as_ref()).await?;
+
+    ticket_file(ticket_id, record, blob_store).await
+}
+
+/// URL firmada para descargar el comprobante sin access_token, con los mismos permisos que
+/// get_ticket_payment
+pub async fn sign_ticket_url(
+    access_token: String,
+    ticket_id: String,
+    pool: &RedisPool,
+    blob_store: &dyn BlobStore,
+    signer: &ReceiptUrlSigner,
+) -> Result<SignedTicketUrl, AppError> {
+    let file = get_ticket_payment(access_token, ticket_id, pool, blob_store).await?;
+
+    Ok(signer.sign(&file.ticket_id))
+}
+
+/// el comprobante de una URL firmada, la firma ya dice quién lo puede ver
+pub async fn get_signed_ticket_payment(
+    ticket_id: String,
+    expires: i64,
+    signature: &str,
+    pool: &RedisPool,
+    blob_store: &dyn BlobStore,
+    signer: &ReceiptUrlSigner,
+) -> Result<TicketFile, AppError> {
+    validate_ticket_id(&ticket_id)?;
+    signer.verify(&ticket_id, expires, signature)?;
+
+    let record = get_stored_receipt(&mut pool.get().await?, &ticket_id).await?;
+    ticket_file(ticket_id, record, blob_store).await
+}
 
-    match read(ticket_name).await {
-        Ok(image) => Ok(HttpResponse::Ok().content_type("image/jpeg").body(image)),
-        Err(_) => Err(StatusMessage {
-            message: "Couldn't send Named file".to_owned(),
-        }),
+async fn ticket_file(
+    ticket_id: String,
+    record: Option<StoredReceipt>,
+    blob_store: &dyn BlobStore,
+) -> Result<TicketFile, AppError> {
+    if let Some(record) = record {
+        return Ok(TicketFile {
+            ticket_id,
+            key: record.key,
+            content_type: record.content_type,
+            size: record.size,
+        });
     }
+
+    // los comprobantes de antes no tienen registro, todos se guardaban como .jpeg
+    let key = format!("payment-tickets/{ticket_id}.jpeg");
+    let Some(size) = blob_store.size(&key).await? else {
+        return Err(AppError::not_found("Ticket not found"));
+    };
+
+    Ok(TicketFile {
+        ticket_id,
+        content_type: store::content_type_for(&key).to_string(),
+        key,
+        size,
+    })
 }
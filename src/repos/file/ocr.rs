@@ -0,0 +1,218 @@
+use std::process::Stdio;
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use regex::Regex;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::config::Env;
+use crate::errors::AppError;
+use crate::models::dates::Date;
+use crate::models::file::ReceiptReading;
+use crate::models::graphql::ReceiptCheck;
+use crate::models::money::Money;
+
+use super::receipt::ReceiptKind;
+
+// OCR opcional de los comprobantes: al subirlo se lee monto, fecha y referencia para prellenar
+// el pago, y al pegarlo al pago se compara con lo que escribió el socio (ReceiptCheck) así el
+// directivo ve si algo no cuadra. el motor se elige con OCR_ENGINE, main lo registra como
+// Data<dyn ReceiptOcr> y los handlers lo reciben como Option (sin OCR no se lee nada)
+
+// lo que puede tardar el motor con una foto, después se sigue sin lectura
+const OCR_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[async_trait]
+pub trait ReceiptOcr: Send + Sync {
+    /// texto del comprobante, None si el motor no lee ese tipo de archivo
+    async fn read_text(&self, kind: ReceiptKind, bytes: &[u8]) -> Result<Option<String>, AppError>;
+}
+
+/// tesseract instalado en el server (apt install tesseract-ocr tesseract-ocr-spa)
+pub struct TesseractOcr {
+    command: String,
+    languages: String,
+}
+
+impl TesseractOcr {
+    pub fn new(command: &str, languages: &str) -> Self {
+        TesseractOcr {
+            command: command.to_string(),
+            languages: languages.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl ReceiptOcr for TesseractOcr {
+    async fn read_text(&self, kind: ReceiptKind, bytes: &[u8]) -> Result<Option<String>, AppError> {
+        // tesseract no abre PDFs
+        if kind == ReceiptKind::Pdf {
+            return Ok(None);
+        }
+
+        let failed = |err: &dyn std::fmt::Debug| {
+            println!("Tesseract failed: {err:?}");
+            AppError::storage("couldn't read receipt")
+        };
+
+        let mut child = Command::new(&self.command)
+            .args(["stdin", "stdout", "-l", &self.languages])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|err| failed(&err))?;
+
+        let mut stdin = child.stdin.take().ok_or_else(|| failed(&"no stdin"))?;
+        stdin.write_all(bytes).await.map_err(|err| failed(&err))?;
+        drop(stdin);
+
+        let output = tokio::time::timeout(OCR_TIMEOUT, child.wait_with_output())
+            .await
+            .map_err(|err| failed(&err))?
+            .map_err(|err| failed(&err))?;
+        if !output.status.success() {
+            return Err(failed(&String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()))
+    }
+}
+
+/// motor de OCR según OCR_ENGINE (none o tesseract), None si está apagado
+pub fn create_receipt_ocr(config: &Env) -> Result<Option<Arc<dyn ReceiptOcr>>, AppError> {
+    match config.ocr_engine.trim().to_ascii_lowercase().as_str() {
+        "" | "none" => Ok(None),
+        "tesseract" => Ok(Some(Arc::new(TesseractOcr::new(
+            &config.tesseract_path,
+            &config.ocr_languages,
+        )))),
+        other => Err(AppError::validation(
+            format!("OCR_ENGINE debe ser none o tesseract, no {}", other),
+            &["OCR_ENGINE"],
+        )),
+    }
+}
+
+/// lee el comprobante ya normalizado, el OCR es una ayuda: si falla o no encuentra nada
+/// la subida sigue igual
+pub async fn read_receipt(
+    ocr: Option<&dyn ReceiptOcr>,
+    kind: ReceiptKind,
+    bytes: &[u8],
+) -> Option<ReceiptReading> {
+    let text = ocr?.read_text(kind, bytes).await.ok()??;
+    let reading = parse_receipt_text(&text);
+
+    (reading != ReceiptReading::default()).then_some(reading)
+}
+
+// montos tipo 1,250.00 o 1250.5, con o sin moneda adelante
+static AMOUNT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)(?:(GTQ|USD|US\$|Q|\$)\s*)?\b(\d{1,3}(?:,\d{3})+(?:\.\d{1,2})?|\d+\.\d{1,2}|\d+)\b",
+    )
+    .unwrap()
+});
+static AMOUNT_LABEL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)monto|total|importe|valor|cantidad").unwrap());
+// 25/03/2025, 25-03-25 o 2025-03-25
+static DATE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(?:(\d{1,2})[/-](\d{1,2})[/-](\d{4}|\d{2})|(\d{4})-(\d{1,2})-(\d{1,2}))\b")
+        .unwrap()
+});
+static REFERENCE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\b(?:referencia|ref|boleta|documento|transacci[oó]n|autorizaci[oó]n|comprobante|operaci[oó]n)\b\.?\s*(?:no\.?|n[uú]mero|#)?\s*[:#]?\s*([A-Z0-9][A-Z0-9-]{3,})",
+    )
+    .unwrap()
+});
+
+/// monto, fecha y referencia del texto del OCR
+/// las fechas van como en Guatemala (día/mes/año), el monto preferido es el de una línea con
+/// "monto", "total", etc y si no el primero que traiga moneda
+pub fn parse_receipt_text(text: &str) -> ReceiptReading {
+    ReceiptReading {
+        amount: find_amount(text),
+        date: DATE.captures_iter(text).find_map(|captures| {
+            let part = |i: usize| captures.get(i).and_then(|m| m.as_str().parse::<u32>().ok());
+            let (year, month, day) = match (part(1), part(2), part(3)) {
+                (Some(day), Some(month), Some(year)) if year < 100 => (year + 2000, month, day),
+                (Some(day), Some(month), Some(year)) => (year, month, day),
+                _ => (part(4)?, part(5)?, part(6)?),
+            };
+            NaiveDate::from_ymd_opt(year as i32, month, day).map(Date::from)
+        }),
+        reference: REFERENCE
+            .captures_iter(text)
+            .map(|captures| captures[1].to_string())
+            .find(|reference| reference.chars().any(|c| c.is_ascii_digit())),
+    }
+}
+
+fn find_amount(text: &str) -> Option<Money> {
+    let amounts = |line: &str| {
+        AMOUNT
+            .captures_iter(line)
+            .filter_map(|captures| {
+                // sin moneda solo cuentan los que tienen decimales, un entero suelto es
+                // cualquier cosa (cuenta, número de boleta, ...)
+                let digits = &captures[2];
+                if captures.get(1).is_none() && !digits.contains('.') {
+                    return None;
+                }
+                digits.replace(',', "").parse::<Money>().ok()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let labeled = text
+        .lines()
+        .filter(|line| AMOUNT_LABEL.is_match(line))
+        .find_map(|line| amounts(line).into_iter().next());
+
+    labeled.or_else(|| {
+        AMOUNT
+            .captures_iter(text)
+            .filter(|captures| captures.get(1).is_some())
+            .find_map(|captures| captures[2].replace(',', "").parse::<Money>().ok())
+    })
+}
+
+/// compara la lectura con lo que escribió el socio en el pago
+pub fn check_receipt(
+    reading: &ReceiptReading,
+    total_amount: Money,
+    ticket_number: &str,
+) -> ReceiptCheck {
+    let amount_matches = reading.amount.map(|amount| amount == total_amount);
+    let reference_matches = reading
+        .reference
+        .as_deref()
+        .map(|reference| normalize_reference(reference) == normalize_reference(ticket_number));
+
+    ReceiptCheck {
+        amount: reading.amount,
+        date: reading.date,
+        reference: reading.reference.clone(),
+        amount_matches,
+        reference_matches,
+        has_mismatch: amount_matches == Some(false) || reference_matches == Some(false),
+    }
+}
+
+// el socio escribe la boleta con o sin guiones, espacios o ceros a la izquierda
+fn normalize_reference(reference: &str) -> String {
+    let alphanumeric: String = reference
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+
+    alphanumeric.trim_start_matches('0').to_string()
+}
@@ -0,0 +1,387 @@
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+
+use chrono::{Duration, Utc};
+use redis::{AsyncCommands, ExistenceCheck, JsonAsyncCommands, SetExpiry, SetOptions};
+
+use crate::{
+    endpoints::handlers::configs::connection_pool::RedisPool,
+    errors::AppError,
+    models::{
+        currency::base_currency,
+        dates::DateTime,
+        file::{PaymentSubmission, PaymentSubmissionForm, PaymentSubmissionInfo, StoredReceipt},
+        graphql::ReceiptCheck,
+    },
+    repos::graphql::{
+        payment::{PaymentRepo, find_payment_by_receipt},
+        store::PaymentStore,
+        utils::{get_db_access_token_with_affiliate_key, scan_keys},
+    },
+};
+
+use super::ocr::{ReceiptOcr, check_receipt};
+use super::{
+    get_stored_receipt, get_viewer, receipt_record_key, store::BlobStore, store_receipt,
+    validate_ticket_id,
+};
+
+// el pago guarda la llave del comprobante en comprobante_bucket (photo_path en graphql)
+// acá se revisa que esa llave sea de un comprobante que el socio sí subió y que no esté ya en
+// otro pago, y se borran los comprobantes que nunca llegaron a un pago
+
+// cada cuánto corre el GC de huérfanos
+const ORPHAN_GC_INTERVAL: StdDuration = StdDuration::from_secs(60 * 60);
+
+// cuánto dura apartado un comprobante si el proceso muere entre claim_receipt y
+// mark_receipt_linked / release_receipt
+const RECEIPT_CLAIM_TTL_SECS: u64 = 5 * 60;
+
+/// comprobante revisado por claim_receipt, listo para ponerlo en un pago
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClaimedReceipt {
+    pub ticket_id: String,
+    /// llave en el BlobStore, es lo que queda en comprobante_bucket
+    pub key: String,
+}
+
+/// el ticket_id dentro de comprobante_path: acepta el ticket_id solo o la llave completa
+/// (payment-tickets/<ticket_id>.<ext>)
+pub fn ticket_id_from_path(comprobante_path: &str) -> &str {
+    let file_name = comprobante_path
+        .rsplit('/')
+        .next()
+        .unwrap_or(comprobante_path);
+
+    file_name
+        .split_once('.')
+        .map_or(file_name, |(ticket_id, _)| ticket_id)
+}
+
+/// revisa que comprobante_path sea un comprobante que subió el dueño del access_token y que
+/// todavía no esté en ningún pago. no lo marca (eso lo hace mark_receipt_linked ya con el pago
+/// creado) pero lo aparta con SET NX en receipt_claims:{ticket_id}, así dos pagos al mismo
+/// tiempo no pueden usar el mismo comprobante. si la revisión falla se suelta de una vez, si
+/// falla la creación del pago lo suelta release_receipt
+pub async fn claim_receipt(
+    pool: &RedisPool,
+    access_token: &str,
+    comprobante_path: &str,
+) -> Result<ClaimedReceipt, AppError> {
+    let ticket_id = claimed_ticket_id(comprobante_path)?;
+
+    let mut con = pool.get().await?;
+    let options = SetOptions::default()
+        .conditional_set(ExistenceCheck::NX)
+        .with_expiration(SetExpiry::EX(RECEIPT_CLAIM_TTL_SECS));
+    let claimed: Option<String> = con
+        .set_options(receipt_claim_key(ticket_id), access_token, options)
+        .await
+        .map_err(|_| AppError::storage("couldn't claim receipt"))?;
+    if claimed.is_none() {
+        return Err(AppError::conflict("El comprobante ya se está usando en otro pago"));
+    }
+
+    // linked_at se lee ya con el comprobante apartado, si otro pago lo marcó antes acá se ve
+    let checked = match get_stored_receipt(&mut con, ticket_id).await {
+        Ok(Some(record)) => match get_viewer(&mut con, access_token).await {
+            Ok(viewer) => check_receipt_claim(ticket_id, record, &viewer.affiliate_key),
+            Err(err) => Err(err),
+        },
+        Ok(None) => Err(receipt_missing()),
+        Err(err) => Err(err),
+    };
+    if checked.is_err() {
+        release_receipt(pool, ticket_id).await?;
+    }
+    checked
+}
+
+/// suelta el comprobante apartado por claim_receipt
+pub async fn release_receipt(pool: &RedisPool, ticket_id: &str) -> Result<(), AppError> {
+    let mut con = pool.get().await?;
+    con.del::<String, ()>(receipt_claim_key(ticket_id))
+        .await
+        .map_err(|_| AppError::storage("couldn't release receipt"))
+}
+
+fn receipt_claim_key(ticket_id: &str) -> String {
+    format!("receipt_claims:{ticket_id}")
+}
+
+/// el ticket_id de comprobante_path, validado antes de ir a buscar el comprobante
+pub(crate) fn claimed_ticket_id(comprobante_path: &str) -> Result<&str, AppError> {
+    let ticket_id = ticket_id_from_path(comprobante_path.trim());
+    validate_ticket_id(ticket_id).map_err(|_| {
+        AppError::validation(
+            "comprobante_path debe ser el ticket_id que regresó la subida",
+            &["comprobante_path"],
+        )
+    })?;
+
+    Ok(ticket_id)
+}
+
+pub(crate) fn receipt_missing() -> AppError {
+    AppError::validation(
+        "El comprobante no existe, súbelo primero",
+        &["comprobante_path"],
+    )
+}
+
+/// el comprobante lo tiene que haber subido el socio (affiliate_key) y no estar en otro pago,
+/// lo mismo en redis y en el store en memoria
+pub(crate) fn check_receipt_claim(
+    ticket_id: &str,
+    record: StoredReceipt,
+    affiliate_key: &str,
+) -> Result<ClaimedReceipt, AppError> {
+    if record.owner.as_deref() != Some(affiliate_key) {
+        return Err(AppError::unauthorized("El comprobante no es tuyo"));
+    }
+    if record.linked_at.is_some() {
+        return Err(AppError::conflict("El comprobante ya está en otro pago"));
+    }
+
+    Ok(ClaimedReceipt {
+        ticket_id: ticket_id.to_string(),
+        key: record.key,
+    })
+}
+
+/// el comprobante ya está en un pago, el GC no lo toca. solo se escribe linked_at (no el
+/// registro entero) y después se suelta el apartado, de ahí en adelante lo protege linked_at
+pub async fn mark_receipt_linked(pool: &RedisPool, ticket_id: &str) -> Result<(), AppError> {
+    let mut con = pool.get().await?;
+    if get_stored_receipt(&mut con, ticket_id).await?.is_none() {
+        return Err(AppError::not_found("Ticket not found"));
+    }
+
+    con.json_set::<String, &str, DateTime, ()>(
+        receipt_record_key(ticket_id),
+        "$.linked_at",
+        &DateTime::now(),
+    )
+    .await
+    .map_err(|_| AppError::storage("couldn't save receipt"))?;
+
+    release_receipt(pool, ticket_id).await
+}
+
+/// marca el comprobante como usado y, si el OCR lo leyó, compara la lectura con el pago que lo
+/// usa y la deja en el pago (receipt_check) para el directivo que lo revise
+pub async fn link_receipt_to_payment(
+    pool: &RedisPool,
+    ticket_id: &str,
+) -> Result<Option<ReceiptCheck>, AppError> {
+    mark_receipt_linked(pool, ticket_id).await?;
+
+    let mut con = pool.get().await?;
+    let Some(record) = get_stored_receipt(&mut con, ticket_id).await? else {
+        return Ok(None);
+    };
+    let (Some(reading), Some(owner)) = (record.reading, record.owner) else {
+        return Ok(None);
+    };
+
+    let owner_key = get_db_access_token_with_affiliate_key(owner, pool.clone()).await?;
+    let Some((payment_key, payment)) =
+        find_payment_by_receipt(&mut con, &owner_key, &record.key).await?
+    else {
+        return Ok(None);
+    };
+
+    let check = check_receipt(&reading, payment.total_amount, &payment.ticket_number);
+    con.json_set::<&str, &str, ReceiptCheck, ()>(&payment_key, "$.receipt_check", &check)
+        .await
+        .map_err(|_| AppError::storage("couldn't save receipt check"))?;
+
+    Ok(Some(check))
+}
+
+/// sube el comprobante y crea el pago que lo usa, si el pago no se crea el comprobante se borra
+pub async fn submit_payment_with_receipt(
+    form: PaymentSubmissionForm,
+    access_token: String,
+    pool: &RedisPool,
+    blob_store: &dyn BlobStore,
+    ocr: Option<&dyn ReceiptOcr>,
+) -> Result<PaymentSubmissionInfo, AppError> {
+    // el pago se revisa antes de procesar la foto
+    let payment: PaymentSubmission = serde_json::from_str(&form.payment).map_err(|err| {
+        AppError::validation(format!("El pago no es válido: {err}"), &["payment"])
+    })?;
+
+    let (ticket_id, record) =
+        store_receipt(form.file, &access_token, pool, blob_store, ocr).await?;
+
+    let created = PaymentRepo::new(pool.clone())
+        .create_payment(
+            access_token,
+            payment.name,
+            record.key.clone(),
+            payment.total_amount,
+            payment.currency.unwrap_or_else(base_currency),
+            payment.ticket_number,
+            payment.account_number,
+            payment.being_payed,
+        )
+        .await;
+
+    let message = match created {
+        Ok(message) => message,
+        Err(err) => {
+            if let Err(cleanup) = discard_receipt(pool, blob_store, &ticket_id, &record).await {
+                println!("Couldn't discard receipt {ticket_id}: {cleanup:?}");
+            }
+            return Err(err);
+        }
+    };
+
+    // el pago ya existe, si falla marcar el comprobante no se regresa error (el cliente
+    // reintentaría y quedaría el pago repetido), el GC lo marca al ver que un pago lo usa
+    let (message, receipt_check) = match link_receipt_to_payment(pool, &ticket_id).await {
+        Ok(receipt_check) => (message, receipt_check),
+        Err(err) => {
+            println!("Couldn't link receipt {ticket_id}: {err:?}");
+            (receipt_link_warning(message), None)
+        }
+    };
+
+    Ok(PaymentSubmissionInfo {
+        ticket_id,
+        message,
+        receipt_check,
+    })
+}
+
+/// el pago se creó pero el comprobante no se pudo marcar como usado
+pub fn receipt_link_warning(message: String) -> String {
+    format!(
+        "{message} (el comprobante se terminará de registrar más tarde, no vuelvas a enviar el pago)"
+    )
+}
+
+/// borra los comprobantes sin pago subidos hace más de max_age, regresa cuántos borró.
+/// si un comprobante falla se anota y se sigue con el resto
+pub async fn collect_orphan_receipts(
+    pool: &RedisPool,
+    blob_store: &dyn BlobStore,
+    max_age: Duration,
+) -> Result<usize, AppError> {
+    let mut con = pool.get().await?;
+    let cutoff = Utc::now() - max_age;
+    let record_keys = scan_keys(&mut con, "receipts:*").await?;
+
+    // los pagos creados antes de linked_at solo tienen la llave en photo_path, se cargan una
+    // sola vez y solo si hay algún candidato
+    let mut photo_paths: Option<Vec<String>> = None;
+    let mut removed = 0;
+
+    for record_key in record_keys {
+        let Some(ticket_id) = record_key.strip_prefix("receipts:") else {
+            continue;
+        };
+
+        match collect_orphan_receipt(pool, blob_store, ticket_id, cutoff, &mut photo_paths).await {
+            Ok(true) => removed += 1,
+            Ok(false) => {}
+            Err(err) => println!("Couldn't collect receipt {ticket_id}: {err:?}"),
+        }
+    }
+
+    Ok(removed)
+}
+
+/// un comprobante del GC: true si se borró
+/// el GC lo aparta igual que claim_receipt mientras lo revisa, así un pago no lo puede tomar
+/// entre la revisión y el borrado. si ya está apartado es de un pago que se está creando
+async fn collect_orphan_receipt(
+    pool: &RedisPool,
+    blob_store: &dyn BlobStore,
+    ticket_id: &str,
+    cutoff: chrono::DateTime<Utc>,
+    photo_paths: &mut Option<Vec<String>>,
+) -> Result<bool, AppError> {
+    let mut con = pool.get().await?;
+    let options = SetOptions::default()
+        .conditional_set(ExistenceCheck::NX)
+        .with_expiration(SetExpiry::EX(RECEIPT_CLAIM_TTL_SECS));
+    let claimed: Option<String> = con
+        .set_options(receipt_claim_key(ticket_id), "orphan_gc", options)
+        .await
+        .map_err(|_| AppError::storage("couldn't claim receipt"))?;
+    if claimed.is_none() {
+        return Ok(false);
+    }
+
+    let collected = collect_claimed_receipt(pool, blob_store, ticket_id, cutoff, photo_paths).await;
+    release_receipt(pool, ticket_id).await?;
+    collected
+}
+
+async fn collect_claimed_receipt(
+    pool: &RedisPool,
+    blob_store: &dyn BlobStore,
+    ticket_id: &str,
+    cutoff: chrono::DateTime<Utc>,
+    photo_paths: &mut Option<Vec<String>>,
+) -> Result<bool, AppError> {
+    let mut con = pool.get().await?;
+    let Some(record) = get_stored_receipt(&mut con, ticket_id).await? else {
+        return Ok(false);
+    };
+    if record.linked_at.is_some() || record.uploaded_at.utc() > cutoff {
+        return Ok(false);
+    }
+
+    if photo_paths.is_none() {
+        let payments = PaymentRepo::new(pool.clone()).get_all_payments().await?;
+        *photo_paths = Some(payments.into_iter().map(|p| p.photo_path).collect());
+    }
+    let referenced = photo_paths
+        .iter()
+        .flatten()
+        .any(|path| path.contains(ticket_id));
+
+    if referenced {
+        mark_receipt_linked(pool, ticket_id).await?;
+        Ok(false)
+    } else {
+        discard_receipt(pool, blob_store, ticket_id, &record).await?;
+        Ok(true)
+    }
+}
+
+/// main lo deja corriendo: collect_orphan_receipts cada hora
+pub async fn run_orphan_receipt_gc(
+    pool: RedisPool,
+    blob_store: Arc<dyn BlobStore>,
+    max_age: Duration,
+) {
+    let mut interval = actix_web::rt::time::interval(ORPHAN_GC_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        match collect_orphan_receipts(&pool, blob_store.as_ref(), max_age).await {
+            Ok(0) => {}
+            Ok(removed) => println!("Removed {removed} orphan receipts"),
+            Err(err) => println!("Couldn't collect orphan receipts: {err:?}"),
+        }
+    }
+}
+
+async fn discard_receipt(
+    pool: &RedisPool,
+    blob_store: &dyn BlobStore,
+    ticket_id: &str,
+    record: &StoredReceipt,
+) -> Result<(), AppError> {
+    blob_store.delete(&record.key).await?;
+
+    let mut con = pool.get().await?;
+    con.del::<String, ()>(receipt_record_key(ticket_id))
+        .await
+        .map_err(|_| AppError::storage("couldn't delete receipt"))
+}
@@ -0,0 +1,142 @@
+use std::io::Cursor;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits};
+
+use crate::errors::AppError;
+
+// los comprobantes son fotos del celular o PDFs del banco, nada más
+// las fotos se decodifican y se vuelven a codificar: así se van los metadatos (EXIF con GPS,
+// modelo del teléfono, ...) y una foto de 12MP queda de un tamaño razonable
+
+/// tamaño máximo de un comprobante tal como lo sube el cliente
+/// (el límite del multipart en models::file::UploadForm es el mismo)
+pub const MAX_RECEIPT_BYTES: usize = 10 * 1024 * 1024;
+
+/// lado más largo de una foto ya guardada, lo más grande se achica
+pub const MAX_RECEIPT_DIMENSION: u32 = 2000;
+
+// una foto más grande que esto no es de un celular, es un intento de reventar la memoria
+const MAX_DECODED_DIMENSION: u32 = 12_000;
+
+const JPEG_QUALITY: u8 = 85;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReceiptKind {
+    Jpeg,
+    Png,
+    Pdf,
+}
+
+impl ReceiptKind {
+    /// tipo según los primeros bytes, lo que diga el cliente (nombre, Content-Type) no cuenta
+    pub fn sniff(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(ReceiptKind::Jpeg)
+        } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(ReceiptKind::Png)
+        } else if bytes.starts_with(b"%PDF-") {
+            Some(ReceiptKind::Pdf)
+        } else {
+            None
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ReceiptKind::Jpeg => "image/jpeg",
+            ReceiptKind::Png => "image/png",
+            ReceiptKind::Pdf => "application/pdf",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ReceiptKind::Jpeg => "jpeg",
+            ReceiptKind::Png => "png",
+            ReceiptKind::Pdf => "pdf",
+        }
+    }
+}
+
+/// el comprobante listo para guardar
+#[derive(Clone, Debug)]
+pub struct NormalizedReceipt {
+    pub kind: ReceiptKind,
+    pub bytes: Vec<u8>,
+}
+
+/// valida tamaño y tipo, a las fotos les quita los metadatos y las achica
+/// los PDFs se guardan tal cual
+pub fn normalize_receipt(bytes: Vec<u8>) -> Result<NormalizedReceipt, AppError> {
+    if bytes.is_empty() {
+        return Err(AppError::validation("El comprobante está vacío", &["file"]));
+    }
+    if bytes.len() > MAX_RECEIPT_BYTES {
+        return Err(AppError::validation(
+            format!(
+                "El comprobante pesa más de {} MB",
+                MAX_RECEIPT_BYTES / (1024 * 1024)
+            ),
+            &["file"],
+        ));
+    }
+
+    let Some(kind) = ReceiptKind::sniff(&bytes) else {
+        return Err(AppError::validation(
+            "El comprobante debe ser JPEG, PNG o PDF",
+            &["file"],
+        ));
+    };
+
+    let bytes = match kind {
+        ReceiptKind::Pdf => bytes,
+        ReceiptKind::Jpeg => reencode(&bytes, ImageFormat::Jpeg)?,
+        ReceiptKind::Png => reencode(&bytes, ImageFormat::Png)?,
+    };
+
+    Ok(NormalizedReceipt { kind, bytes })
+}
+
+fn invalid_image() -> AppError {
+    AppError::validation("El comprobante no es una imagen válida", &["file"])
+}
+
+fn reencode(bytes: &[u8], format: ImageFormat) -> Result<Vec<u8>, AppError> {
+    let mut reader = ImageReader::with_format(Cursor::new(bytes), format);
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DECODED_DIMENSION);
+    limits.max_image_height = Some(MAX_DECODED_DIMENSION);
+    reader.limits(limits);
+
+    let mut decoder = reader.into_decoder().map_err(|_| invalid_image())?;
+    // la orientación vive en el EXIF, se aplica antes de perderlo o la foto queda de lado
+    let orientation = decoder.orientation().map_err(|_| invalid_image())?;
+    let mut image = DynamicImage::from_decoder(decoder).map_err(|_| invalid_image())?;
+    image.apply_orientation(orientation);
+
+    if image.width().max(image.height()) > MAX_RECEIPT_DIMENSION {
+        // resize mantiene la proporción dentro del cuadro
+        image = image.resize(
+            MAX_RECEIPT_DIMENSION,
+            MAX_RECEIPT_DIMENSION,
+            FilterType::Triangle,
+        );
+    }
+
+    let mut output = Vec::new();
+    let encoded = match format {
+        // JPEG no tiene transparencia
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut output, JPEG_QUALITY)),
+        _ => image.write_with_encoder(PngEncoder::new(&mut output)),
+    };
+    encoded.map_err(|err| {
+        println!("Couldn't encode receipt: {:?}", err);
+        AppError::storage("couldn't process receipt")
+    })?;
+
+    Ok(output)
+}
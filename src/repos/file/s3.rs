@@ -0,0 +1,197 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use reqwest::{
+    Client, Response, StatusCode, Url,
+    header::{CONTENT_LENGTH, CONTENT_TYPE, RANGE},
+};
+use rusty_s3::{Bucket, Credentials, S3Action, UrlStyle};
+
+use super::store::{BlobStore, BlobStream, ByteRange, content_type_for, validate_key};
+use crate::errors::AppError;
+
+// cada request va con una URL prefirmada (SigV4) que dura lo justo para mandarlo
+const SIGNATURE_TTL: Duration = Duration::from_secs(60);
+
+/// comprobantes en un bucket de S3 o de cualquier server compatible (MinIO)
+#[derive(Clone, Debug)]
+pub struct S3BlobStore {
+    bucket: Bucket,
+    credentials: Credentials,
+    client: Client,
+}
+
+impl S3BlobStore {
+    /// endpoint vacío: AWS (https://s3.<region>.amazonaws.com, bucket como subdominio)
+    /// con endpoint (MinIO, http://localhost:9000): el bucket va en el path
+    pub fn new(
+        endpoint: &str,
+        bucket_name: &str,
+        region: &str,
+        access_key_id: &str,
+        secret_access_key: &str,
+    ) -> Result<Self, AppError> {
+        let (endpoint, style) = match endpoint.trim() {
+            "" => (
+                format!("https://s3.{}.amazonaws.com", region),
+                UrlStyle::VirtualHost,
+            ),
+            endpoint => (endpoint.to_string(), UrlStyle::Path),
+        };
+        let endpoint: Url = endpoint
+            .parse()
+            .map_err(|_| AppError::validation("S3_ENDPOINT inválido", &["S3_ENDPOINT"]))?;
+
+        if bucket_name.trim().is_empty() {
+            return Err(AppError::validation(
+                "BLOB_STORE=s3 necesita BUCKET_NAME",
+                &["BUCKET_NAME"],
+            ));
+        }
+        let bucket = Bucket::new(
+            endpoint,
+            style,
+            bucket_name.trim().to_string(),
+            region.to_string(),
+        )
+        .map_err(|err| {
+            AppError::validation(format!("Bucket inválido: {}", err), &["BUCKET_NAME"])
+        })?;
+
+        Ok(S3BlobStore {
+            bucket,
+            credentials: Credentials::new(access_key_id, secret_access_key),
+            client: Client::new(),
+        })
+    }
+
+    async fn send(
+        &self,
+        key: &str,
+        request: reqwest::RequestBuilder,
+    ) -> Result<Response, AppError> {
+        request.send().await.map_err(|err| {
+            println!("Couldn't reach S3 for {}: {:?}", key, err);
+            AppError::storage("Couldn't reach file storage")
+        })
+    }
+}
+
+fn unexpected_status(action: &str, key: &str, status: StatusCode) -> AppError {
+    println!("S3 {} {} returned {}", action, key, status);
+    AppError::storage(format!("Couldn't {} file", action))
+}
+
+#[async_trait]
+impl BlobStore for S3BlobStore {
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<(), AppError> {
+        validate_key(key)?;
+        let url = self
+            .bucket
+            .put_object(Some(&self.credentials), key)
+            .sign(SIGNATURE_TTL);
+
+        let request = self
+            .client
+            .put(url)
+            .header(CONTENT_TYPE, content_type_for(key))
+            .body(bytes);
+        let response = self.send(key, request).await?;
+
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            status => Err(unexpected_status("write", key, status)),
+        }
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, AppError> {
+        validate_key(key)?;
+        let url = self
+            .bucket
+            .get_object(Some(&self.credentials), key)
+            .sign(SIGNATURE_TTL);
+
+        let response = self.send(key, self.client.get(url)).await?;
+
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => {
+                let bytes = response.bytes().await.map_err(|err| {
+                    println!("Couldn't read {} from S3: {:?}", key, err);
+                    AppError::storage("Couldn't read file")
+                })?;
+                Ok(Some(bytes.to_vec()))
+            }
+            status => Err(unexpected_status("read", key, status)),
+        }
+    }
+
+    async fn size(&self, key: &str) -> Result<Option<u64>, AppError> {
+        validate_key(key)?;
+        let url = self
+            .bucket
+            .head_object(Some(&self.credentials), key)
+            .sign(SIGNATURE_TTL);
+
+        let response = self.send(key, self.client.head(url)).await?;
+
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => response
+                .headers()
+                .get(CONTENT_LENGTH)
+                .and_then(|length| length.to_str().ok()?.parse().ok())
+                .map(Some)
+                .ok_or_else(|| unexpected_status("check", key, status)),
+            status => Err(unexpected_status("check", key, status)),
+        }
+    }
+
+    async fn stream(
+        &self,
+        key: &str,
+        range: Option<ByteRange>,
+    ) -> Result<Option<BlobStream>, AppError> {
+        validate_key(key)?;
+        let url = self
+            .bucket
+            .get_object(Some(&self.credentials), key)
+            .sign(SIGNATURE_TTL);
+
+        let mut request = self.client.get(url);
+        if let Some(range) = range {
+            request = request.header(RANGE, format!("bytes={}-{}", range.start, range.end));
+        }
+        let response = self.send(key, request).await?;
+
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => {
+                let key = key.to_string();
+                let chunks = response.bytes_stream().map_err(move |err| {
+                    println!("Couldn't read {} from S3: {:?}", key, err);
+                    AppError::storage("Couldn't read file")
+                });
+                Ok(Some(Box::pin(chunks)))
+            }
+            status => Err(unexpected_status("read", key, status)),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), AppError> {
+        validate_key(key)?;
+        let url = self
+            .bucket
+            .delete_object(Some(&self.credentials), key)
+            .sign(SIGNATURE_TTL);
+
+        let response = self.send(key, self.client.delete(url)).await?;
+
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(()),
+            status if status.is_success() => Ok(()),
+            status => Err(unexpected_status("delete", key, status)),
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+
+use crate::config::Env;
+use crate::errors::AppError;
+
+// URLs de descarga que duran poco: el frontend las pone directo en un <img> o las abre en otra
+// pestaña sin mandar el access_token en la URL, la firma es HMAC(ticket_id + expires)
+
+/// firma y revisa las URLs de /general/get_ticket_payment, main lo registra como Data
+#[derive(Clone)]
+pub struct ReceiptUrlSigner {
+    // ya con la llave, cada firma parte de un clon
+    keyed: Hmac<Sha256>,
+    ttl_secs: i64,
+}
+
+/// lo que regresa /general/ticket_payment_url
+#[derive(Clone, Debug, Serialize)]
+pub struct SignedTicketUrl {
+    /// relativa al server, el frontend le pone el host
+    pub url: String,
+    /// segundos desde epoch (UTC)
+    pub expires: i64,
+}
+
+impl ReceiptUrlSigner {
+    pub fn new(secret: &[u8], ttl_secs: u64) -> Self {
+        ReceiptUrlSigner {
+            // HMAC acepta llaves de cualquier tamaño, esto no falla
+            keyed: Hmac::<Sha256>::new_from_slice(secret).expect("HMAC key"),
+            ttl_secs: ttl_secs as i64,
+        }
+    }
+
+    /// sin RECEIPT_URL_SECRET se usa uno random: sirve con un solo server, pero las URLs dejan
+    /// de funcionar al reiniciar
+    pub fn from_env(config: &Env) -> Self {
+        let secret = match config.receipt_url_secret.trim() {
+            "" => {
+                println!("RECEIPT_URL_SECRET is empty, using a random one");
+                rand::random::<[u8; 32]>().to_vec()
+            }
+            secret => secret.as_bytes().to_vec(),
+        };
+
+        ReceiptUrlSigner::new(&secret, config.receipt_url_ttl_secs)
+    }
+
+    pub fn sign(&self, ticket_id: &str) -> SignedTicketUrl {
+        let expires = Utc::now().timestamp() + self.ttl_secs;
+        let signature: String = self
+            .mac(ticket_id, expires)
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+
+        SignedTicketUrl {
+            url: format!(
+                "/general/get_ticket_payment?ticket_id={ticket_id}&expires={expires}&signature={signature}"
+            ),
+            expires,
+        }
+    }
+
+    /// Unauthorized si la firma no corresponde o ya venció
+    pub fn verify(&self, ticket_id: &str, expires: i64, signature: &str) -> Result<(), AppError> {
+        let invalid = || AppError::unauthorized("La URL del comprobante no es válida");
+
+        if expires < Utc::now().timestamp() {
+            return Err(AppError::unauthorized("La URL del comprobante ya venció"));
+        }
+        let signature = decode_hex(signature).ok_or_else(invalid)?;
+
+        // verify_slice compara en tiempo constante
+        self.mac(ticket_id, expires)
+            .verify_slice(&signature)
+            .map_err(|_| invalid())
+    }
+
+    fn mac(&self, ticket_id: &str, expires: i64) -> Hmac<Sha256> {
+        let mut mac = self.keyed.clone();
+        mac.update(ticket_id.as_bytes());
+        mac.update(b":");
+        mac.update(expires.to_string().as_bytes());
+        mac
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
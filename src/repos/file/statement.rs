@@ -0,0 +1,522 @@
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+
+use crate::{
+    errors::AppError,
+    models::{
+        currency::Currency,
+        dates::{Date, today},
+        file::StatementFormat,
+        graphql::{FineStatus, LoanStatus, PaymentStatus, QuotaType, Statement},
+        money::Money,
+    },
+};
+
+// el estado de cuenta (repos::graphql::statement) en CSV o PDF para /general/statement
+// los dos salen de las mismas filas (statement_rows), el CSV es para hojas de cálculo y el PDF
+// para imprimir: A4, Helvetica y nada más, así no hay que cargar fuentes
+
+/// el archivo listo para mandar
+pub fn render_statement(
+    statement: &Statement,
+    format: StatementFormat,
+) -> Result<Vec<u8>, AppError> {
+    match format {
+        StatementFormat::Pdf => Ok(render_statement_pdf(statement)),
+        StatementFormat::Csv => render_statement_csv(statement),
+    }
+}
+
+/// estado-de-cuenta-{affiliate_key}[-{from}][-{to}].{ext}, solo con caracteres seguros
+pub fn statement_file_name(statement: &Statement, format: StatementFormat) -> String {
+    let mut name = format!("estado-de-cuenta-{}", statement.affiliate_key);
+    for date in [statement.from, statement.to].into_iter().flatten() {
+        name.push_str(&format!("-{date}"));
+    }
+    name.retain(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    format!("{name}.{}", format.extension())
+}
+
+/// una línea del estado de cuenta, los montos que no aplican van vacíos
+struct StatementRow {
+    section: Section,
+    date: Option<Date>,
+    concept: String,
+    reference: String,
+    status: &'static str,
+    amount: Option<Money>,
+    paid: Option<Money>,
+    balance: Option<Money>,
+    currency: Currency,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Summary,
+    Payment,
+    Loan,
+    Quota,
+    Fine,
+}
+
+impl Section {
+    /// como va en la columna seccion del CSV
+    fn code(&self) -> &'static str {
+        match self {
+            Section::Summary => "RESUMEN",
+            Section::Payment => "PAGO",
+            Section::Loan => "PRESTAMO",
+            Section::Quota => "CUOTA",
+            Section::Fine => "MULTA",
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            Section::Summary => "Resumen",
+            Section::Payment => "Pagos",
+            Section::Loan => "Préstamos",
+            Section::Quota => "Cuotas",
+            Section::Fine => "Multas",
+        }
+    }
+}
+
+const CSV_HEADER: [&str; 9] = [
+    "seccion",
+    "fecha",
+    "concepto",
+    "referencia",
+    "estado",
+    "monto",
+    "pagado",
+    "saldo",
+    "moneda",
+];
+
+fn render_statement_csv(statement: &Statement) -> Result<Vec<u8>, AppError> {
+    let failed = |err: &dyn std::fmt::Debug| {
+        println!("Couldn't write statement CSV: {err:?}");
+        AppError::storage("couldn't generate statement")
+    };
+    let money = |money: Option<Money>| money.map(|money| money.to_string()).unwrap_or_default();
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer
+        .write_record(CSV_HEADER)
+        .map_err(|err| failed(&err))?;
+    for row in statement_rows(statement) {
+        writer
+            .write_record([
+                row.section.code().to_string(),
+                row.date.map(|date| date.to_string()).unwrap_or_default(),
+                row.concept,
+                row.reference,
+                row.status.to_string(),
+                money(row.amount),
+                money(row.paid),
+                money(row.balance),
+                row.currency.to_string(),
+            ])
+            .map_err(|err| failed(&err))?;
+    }
+
+    writer.into_inner().map_err(|err| failed(&err))
+}
+
+fn statement_rows(statement: &Statement) -> Vec<StatementRow> {
+    let summary = &statement.summary;
+    let mut rows: Vec<StatementRow> = [
+        ("Total pagado", summary.total_paid),
+        ("Abonado a cuotas", summary.paid_to_quotas),
+        ("Abonado a préstamos", summary.paid_to_loans),
+        ("Abonado a multas", summary.paid_to_fines),
+        ("Pagos en revisión", summary.pending_review),
+        ("Cuotas pendientes", summary.quotas_pending),
+        ("Saldo de préstamos", summary.loan_debt),
+        ("Multas sin pagar", summary.unpaid_fines),
+    ]
+    .into_iter()
+    .map(|(concept, amount)| StatementRow {
+        section: Section::Summary,
+        date: None,
+        concept: concept.to_string(),
+        reference: String::new(),
+        status: "",
+        amount: Some(amount),
+        paid: None,
+        balance: None,
+        currency: statement.currency,
+    })
+    .collect();
+
+    rows.extend(statement.payments.iter().map(|payment| StatementRow {
+        section: Section::Payment,
+        date: Some(payment.payment_date.local_date()),
+        concept: payment.name.clone(),
+        reference: payment.ticket_num.clone(),
+        status: payment_status_label(&payment.state),
+        amount: Some(payment.total_amount),
+        paid: None,
+        balance: None,
+        currency: payment.currency,
+    }));
+
+    rows.extend(statement.loans.iter().map(|loan| StatementRow {
+        section: Section::Loan,
+        date: loan.created_at.map(|created_at| created_at.local_date()),
+        concept: loan.reason.clone(),
+        reference: loan.id.clone(),
+        status: loan_status_label(&loan.status),
+        amount: Some(loan.total),
+        paid: Some(loan.payed),
+        balance: Some(loan.debt),
+        currency: loan.currency,
+    }));
+
+    let loan_currency = |loan_id: Option<&String>| {
+        statement
+            .loans
+            .iter()
+            .find(|loan| Some(&loan.id) == loan_id)
+            .map_or(statement.currency, |loan| loan.currency)
+    };
+    rows.extend(statement.quotas.iter().map(|quota| {
+        let paid = quota.monto_pagado.unwrap_or(Money::ZERO);
+        let payed = quota.payed == Some(true);
+        let concept = match quota.quota_type {
+            QuotaType::Afiliado if quota.is_extraordinary == Some(true) => {
+                "Cuota extraordinaria".to_string()
+            }
+            QuotaType::Afiliado => "Cuota de afiliado".to_string(),
+            QuotaType::Prestamo => format!(
+                "Cuota {} - {}",
+                quota.quota_number.unwrap_or_default(),
+                quota.nombre_prestamo.as_deref().unwrap_or("préstamo"),
+            ),
+        };
+
+        StatementRow {
+            section: Section::Quota,
+            date: quota.exp_date,
+            concept,
+            reference: quota.loan_id.clone().unwrap_or_default(),
+            status: if payed { "PAGADA" } else { "PENDIENTE" },
+            amount: Some(quota.amount),
+            paid: Some(paid),
+            balance: Some(if payed {
+                Money::ZERO
+            } else {
+                quota.amount - paid
+            }),
+            currency: loan_currency(quota.loan_id.as_ref()),
+        }
+    }));
+
+    rows.extend(statement.fines.iter().map(|fine| StatementRow {
+        section: Section::Fine,
+        date: None,
+        concept: fine.reason.clone(),
+        reference: fine.id.clone(),
+        status: fine_status_label(&fine.status),
+        amount: Some(fine.amount),
+        paid: None,
+        balance: Some(if fine.status == FineStatus::Unpaid {
+            fine.amount
+        } else {
+            Money::ZERO
+        }),
+        currency: statement.currency,
+    }));
+
+    rows
+}
+
+// los estados como se leen en el estado de cuenta y en las exportaciones
+pub(super) fn payment_status_label(status: &PaymentStatus) -> &'static str {
+    match status {
+        PaymentStatus::OnRevision => "EN REVISIÓN",
+        PaymentStatus::Accepted => "ACEPTADO",
+        PaymentStatus::Rejected => "RECHAZADO",
+        PaymentStatus::ParsedError => "-",
+    }
+}
+
+pub(super) fn loan_status_label(status: &LoanStatus) -> &'static str {
+    match status {
+        LoanStatus::Active => "ACTIVO",
+        LoanStatus::Pending => "PENDIENTE",
+        LoanStatus::Overdue => "VENCIDO",
+        LoanStatus::Payed => "PAGADO",
+        LoanStatus::ParsedError => "-",
+    }
+}
+
+pub(super) fn fine_status_label(status: &FineStatus) -> &'static str {
+    match status {
+        FineStatus::Paid => "PAGADA",
+        FineStatus::Unpaid => "SIN PAGAR",
+        FineStatus::ParsedError => "-",
+    }
+}
+
+// A4 en puntos
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 40.0;
+const LINE_HEIGHT: f32 = 13.0;
+const FONT_SIZE: f32 = 8.5;
+
+const REGULAR: Name<'static> = Name(b"F1");
+const BOLD: Name<'static> = Name(b"F2");
+
+// columnas de las tablas: (título, x, alineada a la derecha)
+const COLUMNS: [(&str, f32, bool); 8] = [
+    ("Fecha", MARGIN, false),
+    ("Concepto", 92.0, false),
+    ("Referencia", 242.0, false),
+    ("Estado", 318.0, false),
+    ("Monto", 430.0, true),
+    ("Pagado", 485.0, true),
+    ("Saldo", 540.0, true),
+    ("", 545.0, false),
+];
+
+/// las páginas se van llenando de arriba a abajo, cuando no cabe otra línea se abre otra
+struct PdfPages {
+    pages: Vec<Content>,
+    y: f32,
+}
+
+impl PdfPages {
+    fn new() -> Self {
+        PdfPages {
+            pages: vec![Content::new()],
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    /// baja una línea (o lo que diga height), en otra página si ya no cabe
+    fn advance(&mut self, height: f32) {
+        self.y -= height;
+        if self.y < MARGIN {
+            self.pages.push(Content::new());
+            self.y = PAGE_HEIGHT - MARGIN - height;
+        }
+    }
+
+    fn text(&mut self, x: f32, font: Name, size: f32, text: &str) {
+        let y = self.y;
+        let content = self.pages.last_mut().expect("siempre hay una página");
+        content
+            .begin_text()
+            .set_font(font, size)
+            .next_line(x, y)
+            .show(Str(&win_ansi(text)))
+            .end_text();
+    }
+
+    fn text_right(&mut self, right: f32, font: Name, size: f32, text: &str) {
+        self.text(right - text_width(text, size), font, size, text);
+    }
+
+    fn rule(&mut self) {
+        let y = self.y - 3.0;
+        let content = self.pages.last_mut().expect("siempre hay una página");
+        content
+            .set_line_width(0.5)
+            .move_to(MARGIN, y)
+            .line_to(PAGE_WIDTH - MARGIN, y)
+            .stroke();
+    }
+}
+
+fn render_statement_pdf(statement: &Statement) -> Vec<u8> {
+    let mut pages = PdfPages::new();
+
+    pages.text(MARGIN, BOLD, 16.0, "Estado de cuenta");
+    pages.advance(22.0);
+    pages.text(
+        MARGIN,
+        REGULAR,
+        10.0,
+        &format!(
+            "Socio: {} ({})",
+            statement.member_name, statement.affiliate_key
+        ),
+    );
+    pages.advance(LINE_HEIGHT);
+    let from = statement
+        .from
+        .map_or("el inicio".to_string(), |from| from.to_string());
+    let to = statement.to.unwrap_or_else(today);
+    pages.text(
+        MARGIN,
+        REGULAR,
+        10.0,
+        &format!("Período: desde {from} hasta {to}"),
+    );
+    pages.advance(LINE_HEIGHT);
+    pages.text(
+        MARGIN,
+        REGULAR,
+        10.0,
+        &format!(
+            "Generado: {}",
+            statement.generated_at.local().format("%Y-%m-%d %H:%M")
+        ),
+    );
+    pages.advance(LINE_HEIGHT);
+
+    let rows = statement_rows(statement);
+    for section in [
+        Section::Summary,
+        Section::Payment,
+        Section::Loan,
+        Section::Quota,
+        Section::Fine,
+    ] {
+        pages.advance(LINE_HEIGHT * 1.5);
+        pages.text(MARGIN, BOLD, 11.0, section.title());
+        pages.advance(LINE_HEIGHT * 1.2);
+
+        let section_rows = rows.iter().filter(|row| row.section == section);
+        if section == Section::Summary {
+            for row in section_rows {
+                pages.text(MARGIN, REGULAR, FONT_SIZE, &row.concept);
+                let amount = row.amount.unwrap_or_default();
+                pages.text_right(300.0, REGULAR, FONT_SIZE, &amount.to_string());
+                pages.text(305.0, REGULAR, FONT_SIZE, row.currency.as_str());
+                pages.advance(LINE_HEIGHT);
+            }
+            continue;
+        }
+
+        for (title, x, right) in COLUMNS {
+            if right {
+                pages.text_right(x, BOLD, FONT_SIZE, title);
+            } else {
+                pages.text(x, BOLD, FONT_SIZE, title);
+            }
+        }
+        pages.rule();
+        pages.advance(LINE_HEIGHT);
+
+        let mut empty = true;
+        for row in section_rows {
+            empty = false;
+            let money = |money: Option<Money>| money.map(|money| money.to_string());
+            let date = row.date.map(|date| date.to_string()).unwrap_or_default();
+            pages.text(COLUMNS[0].1, REGULAR, FONT_SIZE, &date);
+            pages.text(
+                COLUMNS[1].1,
+                REGULAR,
+                FONT_SIZE,
+                &truncate(&row.concept, 30),
+            );
+            pages.text(
+                COLUMNS[2].1,
+                REGULAR,
+                FONT_SIZE,
+                &truncate(&row.reference, 14),
+            );
+            pages.text(COLUMNS[3].1, REGULAR, FONT_SIZE, row.status);
+            for (column, value) in [(4, row.amount), (5, row.paid), (6, row.balance)] {
+                if let Some(value) = money(value) {
+                    pages.text_right(COLUMNS[column].1, REGULAR, FONT_SIZE, &value);
+                }
+            }
+            pages.text(COLUMNS[7].1, REGULAR, FONT_SIZE, row.currency.as_str());
+            pages.advance(LINE_HEIGHT);
+        }
+        if empty {
+            pages.text(MARGIN, REGULAR, FONT_SIZE, "Sin movimientos en el período");
+            pages.advance(LINE_HEIGHT);
+        }
+    }
+
+    write_pdf(
+        pages.pages,
+        &format!("Estado de cuenta {}", statement.member_name),
+    )
+}
+
+fn write_pdf(pages: Vec<Content>, title: &str) -> Vec<u8> {
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let regular_id = Ref::new(3);
+    let bold_id = Ref::new(4);
+    let info_id = Ref::new(5);
+    // cada página lleva su objeto y su contenido
+    let page_ids: Vec<(Ref, Ref)> = (0..pages.len() as i32)
+        .map(|i| (Ref::new(6 + i * 2), Ref::new(7 + i * 2)))
+        .collect();
+
+    let mut pdf = Pdf::new();
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id)
+        .kids(page_ids.iter().map(|(page_id, _)| *page_id))
+        .count(page_ids.len() as i32);
+    pdf.type1_font(regular_id)
+        .base_font(Name(b"Helvetica"))
+        .encoding_predefined(Name(b"WinAnsiEncoding"));
+    pdf.type1_font(bold_id)
+        .base_font(Name(b"Helvetica-Bold"))
+        .encoding_predefined(Name(b"WinAnsiEncoding"));
+    pdf.document_info(info_id).title(TextStr(title));
+
+    for ((page_id, content_id), content) in page_ids.into_iter().zip(pages) {
+        let mut page = pdf.page(page_id);
+        page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT))
+            .parent(page_tree_id)
+            .contents(content_id);
+        let mut resources = page.resources();
+        let mut fonts = resources.fonts();
+        fonts.pair(REGULAR, regular_id).pair(BOLD, bold_id);
+        fonts.finish();
+        resources.finish();
+        page.finish();
+
+        pdf.stream(content_id, &content.finish());
+    }
+
+    pdf.finish()
+}
+
+/// las fuentes estándar del PDF usan WinAnsi (latin-1 más unos extras), lo demás sale como ?
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            '…' => 0x85,
+            '€' => 0x80,
+            c if (c as u32) < 0x80 || (0xA0..=0xFF).contains(&(c as u32)) => c as u8,
+            _ => b'?',
+        })
+        .collect()
+}
+
+// ancho aproximado en Helvetica, solo se usa para alinear montos a la derecha
+fn text_width(text: &str, size: f32) -> f32 {
+    let em: f32 = text
+        .chars()
+        .map(|c| match c {
+            '.' | ',' | ' ' => 0.278,
+            '-' => 0.333,
+            c if c.is_ascii_digit() => 0.556,
+            c if c.is_ascii_uppercase() => 0.667,
+            _ => 0.556,
+        })
+        .sum();
+
+    em * size
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+
+    let mut truncated: String = text.chars().take(max_chars - 1).collect();
+    truncated.push('…');
+    truncated
+}
@@ -0,0 +1,90 @@
+use std::pin::Pin;
+
+use actix_web::web::Bytes;
+use async_trait::async_trait;
+use futures::Stream;
+
+use crate::errors::AppError;
+
+// dónde se guardan los comprobantes, main decide el backend con BLOB_STORE:
+// LocalBlobStore (local.rs, una carpeta en disco) o S3BlobStore (s3.rs, AWS o MinIO)
+// las llaves son rutas relativas tipo "payment-tickets/<id>.jpeg", el content type sale de
+// la extensión así los dos backends lo sirven igual
+
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    /// guarda (o reemplaza) el archivo completo
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<(), AppError>;
+
+    /// None si la llave no existe
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, AppError>;
+
+    /// tamaño en bytes, None si la llave no existe
+    async fn size(&self, key: &str) -> Result<Option<u64>, AppError>;
+
+    /// el archivo (o solo el rango) en pedazos, sin cargarlo completo en memoria
+    /// el rango ya tiene que estar dentro del tamaño, None si la llave no existe
+    async fn stream(
+        &self,
+        key: &str,
+        range: Option<ByteRange>,
+    ) -> Result<Option<BlobStream>, AppError>;
+
+    async fn exists(&self, key: &str) -> Result<bool, AppError> {
+        Ok(self.size(key).await?.is_some())
+    }
+
+    /// borrar algo que no existe no es error
+    async fn delete(&self, key: &str) -> Result<(), AppError>;
+}
+
+pub type BlobStream = Pin<Box<dyn Stream<Item = Result<Bytes, AppError>> + Send>>;
+
+/// bytes de start a end, los dos incluidos (igual que en los headers Range y Content-Range)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+// un rango nunca está vacío (start <= end)
+#[allow(clippy::len_without_is_empty)]
+impl ByteRange {
+    pub fn len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+}
+
+/// content type según la extensión de la llave
+pub fn content_type_for(key: &str) -> &'static str {
+    let extension = key.rsplit_once('.').map(|(_, extension)| extension);
+
+    match extension.map(str::to_ascii_lowercase).as_deref() {
+        Some("jpeg" | "jpg") => "image/jpeg",
+        Some("png") => "image/png",
+        Some("pdf") => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
+/// la llave tiene que ser relativa y sin "..", así nunca se sale de la carpeta (o del bucket)
+pub(crate) fn validate_key(key: &str) -> Result<(), AppError> {
+    let valid = !key.is_empty()
+        && key.split('/').all(|segment| {
+            !segment.is_empty()
+                && segment != "."
+                && segment != ".."
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        });
+
+    if valid {
+        Ok(())
+    } else {
+        Err(AppError::validation(
+            format!("Llave de archivo inválida: {}", key),
+            &["key"],
+        ))
+    }
+}
@@ -0,0 +1,245 @@
+use redis::{from_redis_value, AsyncCommands, JsonAsyncCommands, Value as RedisValue};
+use serde_json::from_str;
+
+use crate::{
+    endpoints::handlers::configs::connection_pool::{RedisConnection, RedisPool},
+    models::{
+        dates::DateTime,
+        graphql::{Attachment, AttachmentOwnerType, AttachmentStatus},
+        redis::Attachment as RedisAttachment,
+        GraphQLMappable,
+    },
+    repos::{
+        auth::utils::hashing_composite_key,
+        graphql::{
+            store::{AttachmentStore, UserStore},
+            utils::{extract_user_hash_from_key, get_db_access_token_with_affiliate_key, scan_keys},
+        },
+    },
+};
+use crate::errors::AppError;
+use async_trait::async_trait;
+
+// documentos (pagarés, DPI, solicitudes, pruebas de disputas) pegados a un socio, préstamo o
+// multa: los bytes van al BlobStore (repos::file::attachment), acá solo el registro en
+// attachments:{owner_type}:{owner_id}:{id} y la revisión de los directivos
+
+pub struct AttachmentRepo {
+    pub pool: RedisPool,
+}
+
+#[async_trait]
+impl AttachmentStore for AttachmentRepo {
+    async fn get_attachments(
+        &self,
+        owner_type: AttachmentOwnerType,
+        owner_id: &str,
+    ) -> Result<Vec<Attachment>, AppError> {
+        let mut con = self.pool.get().await?;
+        let pattern = format!("attachments:{}:{}:*", owner_type.as_str(), owner_id);
+
+        fetch_attachments(&mut con, &pattern).await
+    }
+
+    async fn get_pending_attachments(
+        &self,
+        access_token: String,
+    ) -> Result<Vec<Attachment>, AppError> {
+        let mut con = self.pool.get().await?;
+        get_directive_identity(&mut con, &access_token).await?;
+
+        let mut attachments = fetch_attachments(&mut con, "attachments:*").await?;
+        attachments.retain(|attachment| attachment.status == AttachmentStatus::Pending);
+
+        Ok(attachments)
+    }
+
+    async fn review_attachment(
+        &self,
+        access_token: String,
+        id: String,
+        status: AttachmentStatus,
+        commentary: Option<String>,
+    ) -> Result<Attachment, AppError> {
+        let mut con = self.pool.get().await?;
+        let (reviewer_id, reviewer_name) = get_directive_identity(&mut con, &access_token).await?;
+
+        let commentary = validate_review(status, commentary)?;
+
+        let Some((key, mut attachment)) = find_attachment(&mut con, &id).await? else {
+            return Err(AppError::not_found("Documento no encontrado"));
+        };
+
+        attachment.status = status;
+        attachment.reviewed_by = Some(reviewer_id);
+        attachment.reviewed_by_name = Some(reviewer_name);
+        attachment.reviewed_at = Some(DateTime::now());
+        attachment.review_comment = commentary;
+
+        con.json_set::<&str, &str, RedisAttachment, ()>(&key, "$", &attachment)
+            .await
+            .map_err(|_| AppError::storage("Couldn't save attachment review"))?;
+
+        Ok(attachment.to_graphql_type(key))
+    }
+
+    async fn save_attachment(
+        &self,
+        id: &str,
+        attachment: &RedisAttachment,
+    ) -> Result<Attachment, AppError> {
+        let mut con = self.pool.get().await?;
+        let key = attachment_key(attachment.owner_type, &attachment.owner_id, id);
+
+        con.json_set::<&str, &str, RedisAttachment, ()>(&key, "$", attachment)
+            .await
+            .map_err(|_| AppError::storage("Couldn't save attachment"))?;
+
+        Ok(attachment.to_graphql_type(key))
+    }
+
+    async fn get_redis_attachment(&self, id: &str) -> Result<Option<RedisAttachment>, AppError> {
+        let mut con = self.pool.get().await?;
+
+        Ok(find_attachment(&mut con, id).await?.map(|(_, attachment)| attachment))
+    }
+
+    async fn get_member_key(
+        &self,
+        owner_type: AttachmentOwnerType,
+        owner_id: &str,
+    ) -> Result<String, AppError> {
+        let not_found = || AppError::not_found("No existe a quién pegarle el documento");
+
+        let model = match owner_type {
+            AttachmentOwnerType::Member => {
+                get_db_access_token_with_affiliate_key(owner_id.to_owned(), self.pool.clone())
+                    .await
+                    .map_err(|_| not_found())?;
+                return Ok(owner_id.to_owned());
+            }
+            AttachmentOwnerType::Loan => "loans",
+            AttachmentOwnerType::Fine => "fines",
+        };
+
+        let mut con = self.pool.get().await?;
+        let keys = scan_keys(&mut con, &format!("users:*:{}:{}", model, owner_id)).await?;
+        // el id es un hash, si sale más de uno algo está mal y mejor no adivinar
+        let [key] = keys.as_slice() else {
+            return Err(not_found());
+        };
+        let owner_key = extract_user_hash_from_key(key).ok_or_else(not_found)?;
+
+        con.get::<String, Option<String>>(format!("users:{}:affiliate_key", owner_key))
+            .await?
+            .ok_or_else(not_found)
+    }
+}
+
+/// la revisión de un directivo: solo APPROVED o REJECTED, y el rechazo lleva comentario
+/// regresa el comentario sin los que vienen en blanco
+pub(crate) fn validate_review(
+    status: AttachmentStatus,
+    commentary: Option<String>,
+) -> Result<Option<String>, AppError> {
+    let commentary = commentary.filter(|commentary| !commentary.trim().is_empty());
+    match status {
+        AttachmentStatus::Pending => Err(AppError::validation(
+            "Estado inválido, debe ser APPROVED o REJECTED",
+            &["status"],
+        )),
+        AttachmentStatus::Rejected if commentary.is_none() => Err(AppError::validation(
+            "Se requiere comentario al rechazar el documento",
+            &["commentary"],
+        )),
+        _ => Ok(commentary),
+    }
+}
+
+/// los documentos solo los ven el socio dueño (owner_key es su hash) o un directivo
+pub(crate) async fn check_attachment_viewer(
+    users: &dyn UserStore,
+    access_token: &str,
+    owner_key: &str,
+) -> Result<(), AppError> {
+    if hashing_composite_key(&[&access_token.to_owned()]) == owner_key
+        || users.is_directive(access_token).await
+    {
+        return Ok(());
+    }
+    Err(AppError::unauthorized(
+        "No puedes ver los documentos de otro socio",
+    ))
+}
+
+/// attachments:{owner_type}:{owner_id}:{id}
+pub fn attachment_key(owner_type: AttachmentOwnerType, owner_id: &str, id: &str) -> String {
+    format!("attachments:{}:{}:{}", owner_type.as_str(), owner_id, id)
+}
+
+async fn find_attachment(
+    con: &mut RedisConnection,
+    id: &str,
+) -> Result<Option<(String, RedisAttachment)>, AppError> {
+    let keys = scan_keys(con, &format!("attachments:*:*:{}", id)).await?;
+    let Some(key) = keys.into_iter().next() else {
+        return Ok(None);
+    };
+
+    Ok(fetch_redis_attachment(con, &key).await.map(|attachment| (key, attachment)))
+}
+
+async fn fetch_attachments(
+    con: &mut RedisConnection,
+    pattern: &str,
+) -> Result<Vec<Attachment>, AppError> {
+    let keys = scan_keys(con, pattern)
+        .await
+        .map_err(|_| AppError::storage("Couldn't scan attachments"))?;
+
+    let mut attachments = Vec::new();
+    for key in keys {
+        if let Some(attachment) = fetch_redis_attachment(con, &key).await {
+            attachments.push(attachment.to_graphql_type(key));
+        }
+    }
+    attachments.sort_by_key(|attachment| attachment.uploaded_at);
+
+    Ok(attachments)
+}
+
+async fn fetch_redis_attachment(con: &mut RedisConnection, key: &str) -> Option<RedisAttachment> {
+    let raw = con.json_get::<&str, &str, RedisValue>(key, "$").await.ok()?;
+    let nested = from_redis_value::<String>(&raw).ok()?;
+
+    from_str::<Vec<RedisAttachment>>(&nested).ok()?.pop()
+}
+
+/// affiliate_key y nombre del usuario, solo si es directivo
+async fn get_directive_identity(
+    con: &mut RedisConnection,
+    access_token: &str,
+) -> Result<(String, String), AppError> {
+    let db_access_token = hashing_composite_key(&[&access_token.to_owned()]);
+
+    let is_directive = con
+        .get::<String, bool>(format!("users:{}:is_directive", db_access_token))
+        .await
+        .unwrap_or(false);
+    if !is_directive {
+        return Err(AppError::unauthorized(
+            "Solo los directivos pueden revisar documentos",
+        ));
+    }
+
+    let name = con
+        .get::<String, String>(format!("users:{}:complete_name", db_access_token))
+        .await
+        .map_err(|_| AppError::unauthorized("Directivo no encontrado"))?;
+    let affiliate_key = con
+        .get::<String, String>(format!("users:{}:affiliate_key", db_access_token))
+        .await
+        .unwrap_or_default();
+
+    Ok((affiliate_key, name))
+}
@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use redis::{from_redis_value, AsyncCommands, JsonAsyncCommands, Value as RedisValue};
+use rust_decimal::Decimal;
+use serde_json::from_str;
+
+use crate::{
+    endpoints::handlers::configs::connection_pool::{RedisConnection, RedisPool},
+    models::{
+        currency::{base_currency, Currency, ExchangeRates},
+        dates::DateTime,
+        graphql::{BaseCurrencyReport, ExchangeRate, Loan, Payment, PaymentStatus},
+        money::Money,
+        redis::ExchangeRate as RedisExchangeRate,
+        GraphQLMappable,
+    },
+    repos::{
+        auth::utils::hashing_composite_key,
+        graphql::{
+            loan::LoanRepo,
+            payment::PaymentRepo,
+            store::{CurrencyStore, LoanStore, PaymentStore},
+            utils::scan_keys,
+        },
+    },
+};
+use crate::errors::AppError;
+use async_trait::async_trait;
+
+pub struct CurrencyRepo {
+    pub pool: RedisPool,
+}
+
+#[async_trait]
+impl CurrencyStore for CurrencyRepo {
+    async fn get_exchange_rates(&self) -> Result<Vec<ExchangeRate>, AppError> {
+        let mut con = self.pool.get().await?;
+
+        let mut rates: Vec<ExchangeRate> = fetch_redis_exchange_rates(&mut con).await?
+            .into_iter()
+            .map(|(key, rate)| rate.to_graphql_type(key))
+            .collect();
+        rates.sort_by_key(|rate| rate.currency.as_str());
+
+        Ok(rates)
+    }
+
+    async fn set_exchange_rate(
+        &self,
+        access_token: String,
+        currency: Currency,
+        rate: String,
+    ) -> Result<ExchangeRate, AppError> {
+        let mut con = self.pool.get().await?;
+
+        let (updated_by, updated_by_name) = get_directive_identity(&mut con, &access_token).await?;
+        let rate = parse_exchange_rate(currency, &rate)?;
+
+        let key = exchange_rate_key(currency);
+        let redis_rate = RedisExchangeRate {
+            rate,
+            updated_by,
+            updated_by_name,
+            updated_at: DateTime::now(),
+        };
+
+        con.json_set::<&str, &str, RedisExchangeRate, ()>(&key, "$", &redis_rate)
+            .await
+            .map_err(|_| AppError::storage("Couldn't save exchange rate"))?;
+
+        Ok(redis_rate.to_graphql_type(key))
+    }
+
+    async fn get_base_currency_report(&self) -> Result<BaseCurrencyReport, AppError> {
+        let rates = {
+            let mut con = self.pool.get().await?;
+            load_exchange_rates(&mut con).await?
+        };
+
+        let payments = PaymentRepo::new(self.pool.clone()).get_all_payments().await?;
+        let loans = LoanRepo::new(self.pool.clone()).get_all_loans().await?;
+
+        base_currency_report(&rates, &payments, &loans)
+    }
+}
+
+/// el tipo de cambio que mandó el directivo, la moneda base no lleva y tiene que ser mayor a 0
+pub(crate) fn parse_exchange_rate(currency: Currency, rate: &str) -> Result<Decimal, AppError> {
+    if currency == base_currency() {
+        return Err(AppError::validation(
+            "La moneda base siempre vale 1, no necesita tipo de cambio",
+            &["currency"],
+        ));
+    }
+
+    let rate = rate
+        .trim()
+        .parse::<Decimal>()
+        .map_err(|_| {
+            AppError::validation(format!("Tipo de cambio inválido: {}", rate), &["rate"])
+        })?;
+    if rate <= Decimal::ZERO {
+        return Err(AppError::validation(
+            "El tipo de cambio debe ser mayor a 0",
+            &["rate"],
+        ));
+    }
+
+    Ok(rate)
+}
+
+/// suma pagos y préstamos en la moneda base, los pagos rechazados no cuentan
+pub(crate) fn base_currency_report(
+    rates: &ExchangeRates,
+    payments: &[Payment],
+    loans: &[Loan],
+) -> Result<BaseCurrencyReport, AppError> {
+    let mut report = BaseCurrencyReport {
+        base_currency: base_currency(),
+        accepted_payments_total: Money::ZERO,
+        pending_payments_total: Money::ZERO,
+        loans_total: Money::ZERO,
+        loans_debt: Money::ZERO,
+    };
+
+    for payment in payments {
+        let amount = rates.to_base(payment.total_amount, payment.currency)?;
+        match payment.state {
+            PaymentStatus::Accepted => report.accepted_payments_total += amount,
+            PaymentStatus::OnRevision => report.pending_payments_total += amount,
+            _ => {}
+        }
+    }
+
+    for loan in loans {
+        report.loans_total += rates.to_base(loan.total, loan.currency)?;
+        report.loans_debt += rates.to_base(loan.debt, loan.currency)?;
+    }
+
+    Ok(report)
+}
+
+/// key del tipo de cambio de una moneda: exchange_rates:{moneda}
+pub fn exchange_rate_key(currency: Currency) -> String {
+    format!("exchange_rates:{}", currency)
+}
+
+/// arma la tabla de tipos de cambio con lo que hay guardado en redis
+pub async fn load_exchange_rates(con: &mut RedisConnection) -> Result<ExchangeRates, AppError> {
+    let rates: HashMap<Currency, Decimal> = fetch_redis_exchange_rates(con).await?
+        .into_iter()
+        .filter_map(|(key, rate)| {
+            let currency = key.rsplit(':').next()?.parse::<Currency>().ok()?;
+            Some((currency, rate.rate))
+        })
+        .collect();
+
+    Ok(ExchangeRates::new(rates))
+}
+
+async fn fetch_redis_exchange_rates(
+    con: &mut RedisConnection,
+) -> Result<Vec<(String, RedisExchangeRate)>, AppError> {
+    let keys = scan_keys(con, "exchange_rates:*")
+        .await
+        .map_err(|_| AppError::storage("Couldn't scan exchange rates"))?;
+
+    let mut rates = Vec::new();
+    for key in keys {
+        let Ok(raw) = con.json_get::<&str, &str, RedisValue>(&key, "$").await else {
+            continue;
+        };
+        let Ok(nested) = from_redis_value::<String>(&raw) else {
+            continue;
+        };
+        if let Some(rate) = from_str::<Vec<RedisExchangeRate>>(&nested)
+            .ok()
+            .and_then(|mut parsed| parsed.pop())
+        {
+            rates.push((key, rate));
+        }
+    }
+
+    Ok(rates)
+}
+
+/// affiliate_key y nombre del usuario, solo si es directivo
+async fn get_directive_identity(
+    con: &mut RedisConnection,
+    access_token: &str,
+) -> Result<(String, String), AppError> {
+    let db_access_token = hashing_composite_key(&[&access_token.to_owned()]);
+
+    let is_directive = con
+        .get::<String, bool>(format!("users:{}:is_directive", db_access_token))
+        .await
+        .unwrap_or(false);
+    if !is_directive {
+        return Err(AppError::unauthorized(
+            "Solo los directivos pueden cambiar el tipo de cambio",
+        ));
+    }
+
+    let name = con
+        .get::<String, String>(format!("users:{}:complete_name", db_access_token))
+        .await
+        .map_err(|_| AppError::unauthorized("Directivo no encontrado"))?;
+    let affiliate_key = con
+        .get::<String, String>(format!("users:{}:affiliate_key", db_access_token))
+        .await
+        .unwrap_or_default();
+
+    Ok((affiliate_key, name))
+}
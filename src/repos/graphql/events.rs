@@ -0,0 +1,53 @@
+use std::pin::Pin;
+
+use futures::{Stream, StreamExt};
+use redis::{AsyncCommands, Client};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::endpoints::handlers::configs::connection_pool::RedisConnection;
+use crate::errors::AppError;
+
+// canales de redis pub/sub que alimentan las subscriptions de graphql
+// los repos publican después de escribir y cada subscription escucha su canal
+pub const PAYMENT_STATUS_CHANGED_CHANNEL: &str = "events:payment_status_changed";
+pub const PAYMENT_SUBMITTED_CHANNEL: &str = "events:payment_submitted";
+pub const FINE_ISSUED_CHANNEL: &str = "events:fine_issued";
+
+pub type EventStream<T> = Pin<Box<dyn Stream<Item = T> + Send>>;
+
+/// publica un evento en el canal, si falla solo se loguea: el dato ya quedó guardado y no
+/// queremos tumbar la mutation porque nadie estaba escuchando
+pub async fn publish_event<T: Serialize>(con: &mut RedisConnection, channel: &str, event: &T) {
+    let payload = match serde_json::to_string(event) {
+        Ok(payload) => payload,
+        Err(err) => {
+            println!("publish_event - couldn't serialize event for {}: {}", channel, err);
+            return;
+        }
+    };
+
+    if let Err(err) = con.publish::<&str, String, i64>(channel, payload).await {
+        println!("publish_event - couldn't publish to {}: {:?}", channel, err);
+    }
+}
+
+/// abre una conexión pub/sub propia (la multiplexada no soporta SUBSCRIBE) y regresa los
+/// eventos del canal ya parseados, los mensajes que no se pueden parsear se ignoran
+pub async fn subscribe_events<T>(client: &Client, channel: &str) -> Result<EventStream<T>, AppError>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    let mut pubsub = client
+        .get_async_pubsub()
+        .await
+        .map_err(|_| AppError::storage("Couldn't open pub/sub connection"))?;
+    pubsub
+        .subscribe(channel)
+        .await
+        .map_err(|_| AppError::storage(format!("Couldn't subscribe to {}", channel)))?;
+
+    Ok(Box::pin(pubsub.into_on_message().filter_map(|message| async move {
+        let payload: String = message.get_payload().ok()?;
+        serde_json::from_str::<T>(&payload).ok()
+    })))
+}
@@ -1,215 +1,250 @@
-use actix_web::web::Data;
-use r2d2::Pool;
-use redis::{cmd, from_redis_value, Client, Commands, JsonCommands, Value as RedisValue};
+use std::collections::HashMap;
+
+use redis::JsonAsyncCommands;
 use regex::Regex;
-use serde_json::from_str;
 
 use crate::{
+    endpoints::handlers::configs::connection_pool::RedisPool,
     models::{
+        dates::DateTime,
         graphql::{Fine, FineStatus, UsersWithFines},
+        money::Money,
         redis::Fine as RedisFine,
+        GraphQLMappable,
     },
     repos::{
         auth::utils::hashing_composite_key,
-        graphql::utils::{get_db_access_token_with_affiliate_key, get_multiple_models_by_id},
+        graphql::{
+            events::{publish_event, FINE_ISSUED_CHANNEL},
+            loader::RequestLoader,
+            store::FineStore,
+            utils::{
+                enrich_with_presenter_names, get_db_access_token_with_affiliate_key,
+                is_direct_child_key, scan_keys,
+            },
+        },
     },
 };
+use crate::errors::AppError;
+use async_trait::async_trait;
+use std::sync::Arc;
 
 pub struct FineRepo {
-    pub pool: Data<Pool<Client>>,
+    pub pool: RedisPool,
+    /// cache del request, GeneralContext le pasa el suyo
+    pub loader: Arc<RequestLoader>,
 }
 
 impl FineRepo {
-    pub fn get_user_fines(&self, access_token: String) -> Result<Vec<Fine>, String> {
+    /// repo con un loader propio, para usarlo fuera de un request de graphql
+    pub fn new(pool: RedisPool) -> Self {
+        FineRepo {
+            loader: Arc::new(RequestLoader::new(pool.clone())),
+            pool,
+        }
+    }
+}
+
+#[async_trait]
+impl FineStore for FineRepo {
+    async fn get_user_fines(&self, access_token: String) -> Result<Vec<Fine>, AppError> {
         // primero obtenemos el db_access_token (user_hash) desde el affiliate_key
-        let db_access_token = get_db_access_token_with_affiliate_key(access_token, self.pool.clone())?;
-        
+        let db_access_token =
+            get_db_access_token_with_affiliate_key(access_token, self.pool.clone()).await?;
+
+        self.get_fines_by_owner(&db_access_token).await
+    }
+
+    /// multas de un socio usando directamente su hash de redis (users:{owner_key}:fines:*)
+    async fn get_fines_by_owner(&self, owner_key: &str) -> Result<Vec<Fine>, AppError> {
         // usamos la versión _with_keys para poder enriquecer con presented_by_name
         let (fines, keys) = crate::repos::graphql::utils::get_multiple_models_by_id_with_keys::<Fine, RedisFine>(
             None,
-            Some(db_access_token),
+            Some(owner_key.to_owned()),
             self.pool.clone(),
+            &self.loader,
             "fines".to_owned(),
-        )?;
+        )
+        .await?;
 
         // enriquecemos las multas con el nombre del presentador
-        let pool_ref = self.pool.get_ref();
-        let enriched_fines = crate::repos::graphql::utils::enrich_with_presenter_names(
-            fines,
-            keys,
-            pool_ref,
-        );
+        let enriched_fines = enrich_with_presenter_names(fines, keys, &self.loader).await;
 
         Ok(enriched_fines)
     }
 
-    pub fn create_fine(
+    async fn create_fine(
         &self,
         affiliate_key: String,
-        amount: f32,
+        amount: Money,
         motive: String,
-    ) -> Result<String, String> {
-        let mut con = &mut self.pool.get().expect("Couldn't connect to pool");
+    ) -> Result<String, AppError> {
+        let con = &mut self.pool.get().await?;
 
         // check if the loan exist in the first place
 
         let db_access_token =
-            get_db_access_token_with_affiliate_key(affiliate_key, self.pool.clone())?;
+            get_db_access_token_with_affiliate_key(affiliate_key, self.pool.clone()).await?;
 
-        if let Ok(keys) =
-            con.scan_match::<String, String>(format!("users:{}:fines:*", db_access_token))
+        if let Ok(keys_parsed) =
+            scan_keys(con, &format!("users:{}:fines:*", db_access_token)).await
         {
-            let keys_parsed: Vec<String> = keys.collect();
 
             // for creating the fine and not having collissions
             let fine_hash_key =
                 hashing_composite_key(&[&keys_parsed.len().to_string(), &db_access_token]);
 
-            let con = &mut self.pool.get().expect("Couldn't connect to pool");
+            let fine_key = format!("users:{}:fines:{}", db_access_token, fine_hash_key);
+            let redis_fine = RedisFine {
+                amount,
+                motive,
+                status: "UNPAID".to_owned(),
+                created_at: Some(DateTime::now()),
+            };
 
             let _: () = con
-                .json_set(
-                    format!("users:{}:fines:{}", db_access_token, fine_hash_key),
-                    "$",
-                    &RedisFine {
-                        amount,
-                        motive,
-                        status: "UNPAID".to_owned(),
-                    },
-                )
-                .expect("FINE CREATION: Couldn't Create Fine");
+                .json_set(&fine_key, "$", &redis_fine)
+                .await
+                .map_err(|_| AppError::storage("FINE CREATION: Couldn't Create Fine"))?;
+
+            // le avisamos al socio multado (fineIssued)
+            let fine = enrich_with_presenter_names(
+                vec![redis_fine.to_graphql_type(fine_key.clone())],
+                vec![fine_key],
+                &self.loader,
+            )
+            .await;
+            publish_event(con, FINE_ISSUED_CHANNEL, &fine[0]).await;
+
             return Ok("Fine Createad".to_owned());
         }
 
-        Err("FINE CREATION: Couldn't Create Fine".to_owned())
+        Err(AppError::storage("FINE CREATION: Couldn't Create Fine"))
     }
 
-    pub fn edit_fine(
+    async fn edit_fine(
         &self,
         fine_key: String,
-        new_amount: Option<f64>,
+        new_amount: Option<Money>,
         new_motive: Option<String>,
         new_status: Option<FineStatus>,
-    ) -> Result<String, String> {
-        let mut con = &mut self.pool.get().expect("Couldn't connect to pool");
+    ) -> Result<String, AppError> {
+        let con = &mut self.pool.get().await?;
 
         // we search the specific fine
 
-        match con.scan_match::<String, String>(format!("users:*:fines:{}", fine_key)) {
+        match scan_keys(con, &format!("users:*:fines:{}", fine_key)).await {
             // there should be only one key
-            Ok(mut keys) => {
+            Ok(keys) => {
                 // we grab the only needed key
-                let key = keys.next().unwrap();
-
-                // the old boilerplate for getting the json value in rust
-
-                // we get the latest fine
-                let old_fine_raw = con
-                    .json_get::<String, &str, RedisValue>(key.clone(), "$")
-                    .unwrap();
-
-                let nested_data = from_redis_value::<String>(&old_fine_raw).unwrap();
-
-                let old_fine_parsed =
-                    from_str::<Vec<RedisFine>>(nested_data.as_str()).unwrap()[0].clone();
-
-                //TODO: refactor this if statements
-
-                let new_amount = if new_amount.is_some() {
-                    new_amount
-                } else {
-                    Some(old_fine_parsed.amount as f64)
+                let Some(key) = keys.into_iter().next() else {
+                    return Err(AppError::not_found("Fine not found"));
                 };
 
-                let new_motive = if new_motive.is_some() {
-                    new_motive
-                } else {
-                    Some(old_fine_parsed.motive)
-                };
+                // we get the latest fine
+                let old_fine_parsed = self
+                    .loader
+                    .get_model::<RedisFine>(&key)
+                    .await?
+                    .ok_or_else(|| AppError::not_found("Fine not found"))?;
 
-                let new_status = if new_status.is_some() {
-                    new_status
-                } else {
-                    Some(FineStatus::from_string(old_fine_parsed.status))
-                };
+                let new_status = new_status
+                    .unwrap_or_else(|| FineStatus::from_string(old_fine_parsed.status));
 
                 let _: () = con
                     .json_set(
                         &key,
                         "$",
                         &RedisFine {
-                            amount: new_amount.unwrap() as f32,
-                            motive: new_motive.unwrap(),
-                            status: new_status.unwrap().to_string(),
+                            amount: new_amount.unwrap_or(old_fine_parsed.amount),
+                            motive: new_motive.unwrap_or(old_fine_parsed.motive),
+                            status: new_status.to_string(),
+                            created_at: old_fine_parsed.created_at,
                         },
                     )
-                    .expect("FINE CREATION: Couldn't Create Fine");
+                    .await
+                    .map_err(|_| AppError::storage("Couldn't update fine"))?;
+                // lo que quedó en la cache ya es viejo
+                self.loader.forget_model(&key);
                 return Ok("Fine updated".to_owned());
             }
             Err(_) => {
-                return Err("Couldn't update fine".to_owned());
+                return Err(AppError::storage("Couldn't update fine"));
             }
         }
     }
 
     /// get's each user affiliate id, complete name and there respective fines
-    pub fn get_users_with_there_fines(&self) -> Result<Vec<UsersWithFines>, String> {
-        let mut con_for_users_key = &mut self.pool.get().expect("Couldn't connect to pool");
+    /// antes eran 2 scans de multas + 2 conexiones por socio, ahora las multas de todos salen
+    /// de un solo scan + JSON.MGET y los nombres/affiliate_key de un MGET
+    async fn get_users_with_there_fines(&self) -> Result<Vec<UsersWithFines>, AppError> {
+        let mut con = self.pool.get().await?;
+        let loader = &self.loader;
 
         // we get first all the user db id
-        match con_for_users_key.scan_match::<&str, String>("users:*:complete_name") {
-            Ok(users_keys) => {
-                let mut users_with_fines: Vec<UsersWithFines> = Vec::new();
-                let regex = Regex::new(r"(users):(\w+):(complete_name)").unwrap();
-
-                for user_key in users_keys {
-                    let parsed_key = regex.captures(user_key.as_str()).unwrap();
-
-                    let name_con = &mut self.pool.get().expect("Couldn't connect to pool");
+        let regex = Regex::new(r"(users):(\w+):(complete_name)").unwrap();
+        let user_hashes: Vec<String> =
+            match scan_keys(&mut con, "users:*:complete_name").await {
+                Ok(users_keys) => users_keys
+                    .into_iter()
+                    .filter_map(|key| regex.captures(&key).map(|parsed| parsed[2].to_owned()))
+                    .collect(),
+                Err(_) => return Err(AppError::storage("Couldn't get users")),
+            };
+
+        // todas las multas de todos los socios, agrupadas por el hash del socio
+        let fine_keys = scan_keys(&mut con, "users:*:fines:*")
+            .await
+            .map_err(|_| AppError::storage("Couldn't scan fines"))?;
+        let redis_fines = loader.get_models::<RedisFine>(&fine_keys).await?;
+
+        let mut fines_by_user: HashMap<String, (Vec<Fine>, Vec<String>)> = HashMap::new();
+        for (key, redis_fine) in fine_keys.into_iter().zip(redis_fines) {
+            let Some(redis_fine) = redis_fine else {
+                continue;
+            };
+            let Some(user_hash) = key
+                .strip_prefix("users:")
+                .and_then(|rest| rest.split(':').next())
+                .map(str::to_owned)
+            else {
+                continue;
+            };
+            if !is_direct_child_key(&key, &format!("users:{}:fines:", user_hash)) {
+                continue;
+            }
 
-                    let affiliate_con = &mut self.pool.get().expect("Couldn't connect to pool");
+            let (fines, keys) = fines_by_user.entry(user_hash).or_default();
+            fines.push(redis_fine.to_graphql_type(key.clone()));
+            keys.push(key);
+        }
 
-                    let user_fines = get_multiple_models_by_id::<Fine, RedisFine>(
-                        None,
-                        Some(parsed_key[2].to_owned()),
-                        self.pool.clone(),
-                        "fines".to_owned(),
-                    )
-                    .unwrap_or(Vec::new());
-
-                    if !user_fines.is_empty() {
-                        users_with_fines.push(UsersWithFines {
-                            user_id: affiliate_con
-                                .get::<String, String>(format!(
-                                    "users:{}:affiliate_key",
-                                    parsed_key[2].to_owned()
-                                ))
-                                .unwrap_or("Not Name Found".to_owned()),
-                            complete_name: name_con
-                                .get::<String, String>(format!(
-                                    "users:{}:complete_name",
-                                    parsed_key[2].to_owned()
-                                ))
-                                .unwrap_or("Not Name Found".to_owned()),
-                            fines: get_multiple_models_by_id::<Fine, RedisFine>(
-                                None,
-                                Some(parsed_key[2].to_owned()),
-                                self.pool.clone(),
-                                "fines".to_owned(),
-                            )
-                            .unwrap_or(Vec::new()),
-                        });
-                    } else {
-                        // we don't put the fines for that one, cause it doesn't have
-                        continue;
-                    }
-                }
-
-                Ok(users_with_fines)
-            }
-            Err(_) => {
-                return Err("Couldn't update fine".to_owned());
-            }
+        let names = loader.complete_names(&user_hashes).await?;
+        let affiliate_keys = loader.get_user_field(&user_hashes, "affiliate_key").await?;
+
+        let mut users_with_fines: Vec<UsersWithFines> = Vec::new();
+        for user_hash in user_hashes {
+            // we don't put the fines for those who doesn't have
+            let Some((fines, keys)) = fines_by_user.remove(&user_hash) else {
+                continue;
+            };
+
+            users_with_fines.push(UsersWithFines {
+                user_id: affiliate_keys
+                    .get(&user_hash)
+                    .cloned()
+                    .flatten()
+                    .unwrap_or("Not Name Found".to_owned()),
+                complete_name: names
+                    .get(&user_hash)
+                    .cloned()
+                    .flatten()
+                    .unwrap_or("Not Name Found".to_owned()),
+                // el nombre ya está en la cache del loader, no es otro viaje a redis
+                fines: enrich_with_presenter_names(fines, keys, loader).await,
+            });
         }
+
+        Ok(users_with_fines)
     }
 }
@@ -0,0 +1,231 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+use futures::lock::Mutex as AsyncMutex;
+use serde::de::DeserializeOwned;
+use serde_json::from_str;
+
+use crate::endpoints::handlers::configs::connection_pool::RedisPool;
+use crate::errors::AppError;
+use crate::models::graphql::Member;
+
+/// loader por request (vive en GeneralContext): junta las lecturas de redis en un solo
+/// MGET / JSON.MGET y cachea lo que ya se leyó, así 20 multas del mismo socio no hacen
+/// 20 GET de complete_name
+/// solo sirve para lecturas, las mutations escriben directo con el pool
+/// juniper resuelve los campos async de una lista en paralelo: los viajes a redis se hacen
+/// de uno en uno y el que espera vuelve a revisar la cache, así no se piden keys repetidas
+pub struct RequestLoader {
+    pool: RedisPool,
+    // key de redis -> valor (None si la key no existe)
+    strings: Mutex<Cache>,
+    // key de redis -> documento JSON crudo tal como lo regresa JSON.MGET con path "$"
+    documents: Mutex<Cache>,
+    // se toma mientras se espera a redis, los hits de cache no pasan por aquí
+    fetching: AsyncMutex<()>,
+    // cuántos viajes a redis hizo el loader, para los tests
+    round_trips: AtomicUsize,
+}
+
+impl RequestLoader {
+    pub fn new(pool: RedisPool) -> Self {
+        RequestLoader {
+            pool,
+            strings: Mutex::new(HashMap::new()),
+            documents: Mutex::new(HashMap::new()),
+            fetching: AsyncMutex::new(()),
+            round_trips: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn round_trips(&self) -> usize {
+        self.round_trips.load(Ordering::Relaxed)
+    }
+
+    /// vacía la cache, las subscriptions lo llaman antes de cada evento porque la conexión
+    /// (y su contexto) vive mucho más que un request
+    pub fn clear(&self) {
+        if let Ok(mut strings) = self.strings.lock() {
+            strings.clear();
+        }
+        if let Ok(mut documents) = self.documents.lock() {
+            documents.clear();
+        }
+    }
+
+    /// saca un modelo de la cache, para las mutations que lo acaban de escribir y lo pueden
+    /// volver a leer en el mismo request
+    pub fn forget_model(&self, key: &str) {
+        if let Ok(mut documents) = self.documents.lock() {
+            documents.remove(key);
+        }
+    }
+
+    /// valores de varias keys de texto, las que no están en cache se piden en un solo MGET
+    pub async fn get_strings(
+        &self,
+        keys: &[String],
+    ) -> Result<HashMap<String, Option<String>>, AppError> {
+        if !missing_keys(&*lock_cache(&self.strings)?, keys).is_empty() {
+            let _fetching = self.fetching.lock().await;
+            // otro resolver pudo haber traído las keys mientras esperábamos
+            let missing = missing_keys(&*lock_cache(&self.strings)?, keys);
+            self.fetch_strings(missing).await?;
+        }
+
+        let cache = lock_cache(&self.strings)?;
+        Ok(keys
+            .iter()
+            .map(|key| (key.clone(), cache.get(key).cloned().flatten()))
+            .collect())
+    }
+
+    async fn fetch_strings(&self, missing: Vec<String>) -> Result<(), AppError> {
+        if !missing.is_empty() {
+            let mut con = self.pool.get().await?;
+            // MGET explícito: con una sola key el mget de redis-rs manda GET
+            let values: Vec<Option<String>> = redis::cmd("MGET")
+                .arg(&missing)
+                .query_async(&mut con)
+                .await
+                .map_err(|_| AppError::storage("Couldn't batch get keys"))?;
+            self.round_trips.fetch_add(1, Ordering::Relaxed);
+            lock_cache(&self.strings)?.extend(missing.into_iter().zip(values));
+        }
+        Ok(())
+    }
+
+    /// el mismo campo (complete_name, affiliate_key, ...) de varios socios: users:{hash}:{field}
+    /// regresa user_hash -> valor
+    pub async fn get_user_field(
+        &self,
+        user_hashes: &[String],
+        field: &str,
+    ) -> Result<HashMap<String, Option<String>>, AppError> {
+        let keys: Vec<String> = user_hashes
+            .iter()
+            .map(|hash| user_field_key(hash, field))
+            .collect();
+        let values = self.get_strings(&keys).await?;
+
+        Ok(user_hashes
+            .iter()
+            .map(|hash| {
+                let value = values.get(&user_field_key(hash, field)).cloned().flatten();
+                (hash.clone(), value)
+            })
+            .collect())
+    }
+
+    /// nombres completos de varios socios en un solo viaje
+    pub async fn complete_names(
+        &self,
+        user_hashes: &[String],
+    ) -> Result<HashMap<String, Option<String>>, AppError> {
+        self.get_user_field(user_hashes, "complete_name").await
+    }
+
+    /// socio (nombre + affiliate_key) a partir de su hash de redis
+    pub async fn member(&self, owner_key: &str) -> Result<Member, AppError> {
+        let name_key = user_field_key(owner_key, "complete_name");
+        let affiliate_key = user_field_key(owner_key, "affiliate_key");
+        let mut values = self.get_strings(&[name_key.clone(), affiliate_key.clone()]).await?;
+
+        let name = values
+            .remove(&name_key)
+            .flatten()
+            .ok_or_else(|| AppError::not_found("Socio no encontrado"))?;
+
+        Ok(Member {
+            affiliate_key: values.remove(&affiliate_key).flatten().unwrap_or_default(),
+            name,
+            owner_key: owner_key.to_owned(),
+        })
+    }
+
+    /// modelos guardados como JSON, en el mismo orden que las keys
+    /// los que no están en cache se piden en un solo JSON.MGET, None si la key no existe
+    /// o no se puede parsear como RedisType
+    pub async fn get_models<RedisType>(
+        &self,
+        keys: &[String],
+    ) -> Result<Vec<Option<RedisType>>, AppError>
+    where
+        RedisType: DeserializeOwned,
+    {
+        if !missing_keys(&*lock_cache(&self.documents)?, keys).is_empty() {
+            let _fetching = self.fetching.lock().await;
+            let missing = missing_keys(&*lock_cache(&self.documents)?, keys);
+            self.fetch_documents(missing).await?;
+        }
+
+        let cache = lock_cache(&self.documents)?;
+        Ok(keys
+            .iter()
+            .map(|key| {
+                cache
+                    .get(key)
+                    .and_then(|raw| raw.as_deref())
+                    .and_then(parse_document)
+            })
+            .collect())
+    }
+
+    /// un solo modelo JSON, pasa por la misma cache que get_models
+    pub async fn get_model<RedisType>(&self, key: &str) -> Result<Option<RedisType>, AppError>
+    where
+        RedisType: DeserializeOwned,
+    {
+        Ok(self.get_models(&[key.to_owned()]).await?.pop().flatten())
+    }
+
+    async fn fetch_documents(&self, missing: Vec<String>) -> Result<(), AppError> {
+        if !missing.is_empty() {
+            let mut con = self.pool.get().await?;
+            let values: Vec<Option<String>> = redis::cmd("JSON.MGET")
+                .arg(&missing)
+                .arg("$")
+                .query_async(&mut con)
+                .await
+                .map_err(|_| AppError::storage("Couldn't batch get models"))?;
+            self.round_trips.fetch_add(1, Ordering::Relaxed);
+            lock_cache(&self.documents)?.extend(missing.into_iter().zip(values));
+        }
+        Ok(())
+    }
+}
+
+type Cache = HashMap<String, Option<String>>;
+
+/// el guard nunca se guarda a través de un .await, solo se toma para leer o llenar la cache
+fn lock_cache(cache: &Mutex<Cache>) -> Result<MutexGuard<'_, Cache>, AppError> {
+    cache
+        .lock()
+        .map_err(|_| AppError::storage("Loader cache poisoned"))
+}
+
+fn user_field_key(user_hash: &str, field: &str) -> String {
+    format!("users:{}:{}", user_hash, field)
+}
+
+/// keys que todavía no están en cache, sin repetir
+fn missing_keys(cache: &Cache, keys: &[String]) -> Vec<String> {
+    let mut seen: HashSet<&String> = HashSet::new();
+    keys.iter()
+        .filter(|key| !cache.contains_key(*key) && seen.insert(*key))
+        .cloned()
+        .collect()
+}
+
+/// con path "$" redis regresa el documento dentro de un array, los datos viejos pueden venir
+/// como el objeto directo
+fn parse_document<RedisType>(raw: &str) -> Option<RedisType>
+where
+    RedisType: DeserializeOwned,
+{
+    match from_str::<Vec<RedisType>>(raw) {
+        Ok(mut parsed) => parsed.pop(),
+        Err(_) => from_str::<RedisType>(raw).ok(),
+    }
+}
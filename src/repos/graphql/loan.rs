@@ -1,153 +1,143 @@
-use actix_web::web::Data;
-use r2d2::Pool;
-use redis::Client;
+use redis::JsonAsyncCommands;
 
-use redis::{from_redis_value, Commands, JsonCommands, Value as RedisValue};
-use regex::Regex;
-use serde_json::from_str;
-
-use crate::repos::graphql::utils::{get_db_access_token_with_affiliate_key, get_multiple_models_by_id, get_multiple_models_by_pattern};
+use crate::endpoints::handlers::configs::connection_pool::RedisPool;
+use crate::repos::graphql::loader::RequestLoader;
+use crate::repos::graphql::utils::{extract_user_hash_from_key, get_db_access_token_with_affiliate_key, get_key, get_multiple_models_by_id, is_direct_child_key, scan_keys};
 use crate::{
-    models::{graphql::{Loan, LoanStatus}, redis::Loan as RedisLoan},
+    models::{
+        currency::Currency, dates::DateTime, graphql::{Loan, LoanStatus}, money::Money,
+        redis::Loan as RedisLoan,
+    },
     repos::auth::utils::hashing_composite_key,
 };
+use crate::errors::AppError;
+use crate::repos::graphql::store::LoanStore;
+use async_trait::async_trait;
+use std::sync::Arc;
 
 pub struct LoanRepo {
-    pub pool: Data<Pool<Client>>,
+    pub pool: RedisPool,
+    /// cache del request, GeneralContext le pasa el suyo
+    pub loader: Arc<RequestLoader>,
 }
 
-//TODO: add error managment for redis
 impl LoanRepo {
+    /// repo con un loader propio, para usarlo fuera de un request de graphql
+    pub fn new(pool: RedisPool) -> Self {
+        LoanRepo {
+            loader: Arc::new(RequestLoader::new(pool.clone())),
+            pool,
+        }
+    }
+}
+
+//TODO: add error managment for redis
+#[async_trait]
+impl LoanStore for LoanRepo {
     //TODO: refactor for generalize this kind of methods of get n thing
 
     // ! NOT FULLY TESTED, BUT IT SHOULD WORK
 
-    //TODO: implent true logic
-    pub fn get_user_loans(&self, access_token: String) -> Result<Vec<Loan>, String> {
+    /// préstamos de un socio usando directamente su hash de redis (users:{owner_key}:loans:*)
+    async fn get_loans_by_owner(&self, owner_key: &str) -> Result<Vec<Loan>, AppError> {
         get_multiple_models_by_id::<Loan, RedisLoan>(
-            Some(access_token),
             None,
+            Some(owner_key.to_owned()),
             self.pool.clone(),
+            &self.loader,
             "loans".to_owned(), // TODO: see a way to don't burn the keys
         )
+        .await
     }
 
     /// obtiene todos los préstamos de todos los socios con nombre del solicitante
-    pub fn get_all_loans(&self) -> Result<Vec<Loan>, String> {
-        let mut con = self.pool.get().map_err(|_| "Couldn't connect to pool")?;
+    /// los préstamos salen de un solo JSON.MGET y los nombres de un MGET, no de a uno por key
+    async fn get_all_loans(&self) -> Result<Vec<Loan>, AppError> {
+        let mut con = self.pool.get().await?;
 
         // escaneamos todas las keys de préstamos de todos los usuarios
-        match con.scan_match::<String, String>("users:*:loans:*".to_string()) {
-            Ok(keys) => {
-                let mut loans_list: Vec<Loan> = Vec::new();
-                let key_vec: Vec<String> = keys.collect();
-
-                // regex para extraer el hash del usuario de la key
-                let re_user_hash = Regex::new(r"users:(?<hash>\w+):loans:\w+").unwrap();
-
-                for key in key_vec {
-                    // extraer hash del usuario de la key
-                    let user_hash = match re_user_hash.captures(&key) {
-                        Some(caps) => caps["hash"].to_string(),
-                        None => {
-                            println!("get_all_loans - couldn't extract user hash from key: {}", key);
-                            continue;
-                        }
-                    };
-
-                    // obtener el loan de redis
-                    let redis_raw_res = con.json_get::<String, &str, redis::Value>(key.clone(), "$");
-                    let redis_raw = match redis_raw_res {
-                        Ok(v) => v,
-                        Err(e) => {
-                            println!("get_all_loans - json_get failed for key {}: {:?}", key, e);
-                            continue;
-                        }
-                    };
-
-                    let nested_data = match from_redis_value::<String>(&redis_raw) {
-                        Ok(s) => s,
-                        Err(e) => {
-                            println!("get_all_loans - from_redis_value failed for key {}: {:?}", key, e);
-                            continue;
-                        }
-                    };
-
-                    // parsear como array o como objeto individual
-                    let parsed_vec_res = from_str::<Vec<RedisLoan>>(nested_data.as_str());
-                    let mut parsed_objects: Vec<RedisLoan> = match parsed_vec_res {
-                        Ok(v) => v,
-                        Err(_) => match from_str::<RedisLoan>(nested_data.as_str()) {
-                            Ok(obj) => vec![obj],
-                            Err(e) => {
-                                println!("get_all_loans - JSON parse failed for key {}: {}", key, e);
-                                continue;
-                            }
-                        },
-                    };
-
-                    if parsed_objects.is_empty() {
-                        continue;
-                    }
-
-                    let redis_loan = parsed_objects.pop().unwrap();
-
-                    // obtener el nombre completo del usuario que solicitó el préstamo
-                    let presented_by_name = con
-                        .get::<String, String>(format!("users:{}:complete_name", user_hash))
-                        .unwrap_or_else(|_| "Nombre no encontrado".to_string());
-
-                    // mapear a graphql loan con los campos nuevos
-                    let loan_id = crate::repos::graphql::utils::get_key(key.clone(), "loans".to_owned());
-
-                    loans_list.push(Loan {
-                        id: loan_id,
-                        quotas: redis_loan.total_quota,
-                        payed: redis_loan.payed,
-                        debt: redis_loan.debt,
-                        total: redis_loan.total,
-                        status: LoanStatus::from_string(redis_loan.status.clone()),
-                        reason: redis_loan.reason.clone(),
-                        interest_rate: redis_loan.interest_rate.unwrap_or(0.0),
-                        presented_by_name,
-                    });
-                }
-
-                Ok(loans_list)
-            }
-            Err(_) => Err("Couldn't scan for loan keys".to_string()),
+        let key_vec = scan_keys(&mut con, "users:*:loans:*")
+            .await
+            .map_err(|_| AppError::storage("Couldn't scan for loan keys"))?;
+
+        // las cuotas del préstamo también matchean users:*:loans:*
+        let loan_keys: Vec<(String, String)> = key_vec
+            .into_iter()
+            .filter_map(|key| {
+                let user_hash = extract_user_hash_from_key(&key)?;
+                is_direct_child_key(&key, &format!("users:{}:loans:", user_hash))
+                    .then_some((key, user_hash))
+            })
+            .collect();
+
+        let keys: Vec<String> = loan_keys.iter().map(|(key, _)| key.clone()).collect();
+        let user_hashes: Vec<String> = loan_keys.iter().map(|(_, hash)| hash.clone()).collect();
+        let redis_loans = self.loader.get_models::<RedisLoan>(&keys).await?;
+        let names = self.loader.complete_names(&user_hashes).await?;
+
+        let mut loans_list: Vec<Loan> = Vec::new();
+        for ((key, user_hash), redis_loan) in loan_keys.into_iter().zip(redis_loans) {
+            let Some(redis_loan) = redis_loan else {
+                continue;
+            };
+            let Ok(loan_id) = get_key(key, "loans".to_owned()) else {
+                continue;
+            };
+
+            // nombre completo del socio que solicitó el préstamo
+            let presented_by_name = names
+                .get(&user_hash)
+                .cloned()
+                .flatten()
+                .unwrap_or_else(|| "Nombre no encontrado".to_string());
+
+            loans_list.push(Loan {
+                id: loan_id,
+                total_quotas: redis_loan.total_quota,
+                payed: redis_loan.payed,
+                debt: redis_loan.debt,
+                total: redis_loan.total,
+                currency: redis_loan.currency,
+                status: LoanStatus::from_string(redis_loan.status.clone()),
+                reason: redis_loan.reason.clone(),
+                interest_rate: redis_loan.interest_rate.unwrap_or(0.0),
+                created_at: redis_loan.created_at,
+                presented_by_name,
+                owner_key: user_hash,
+            });
         }
+
+        Ok(loans_list)
     }
 
-    pub fn create_loan(
+    async fn create_loan(
         &self,
         affiliate_key: String,
         total_quota: i32,
-        base_needed_payment: f64,
+        base_needed_payment: Money,
+        currency: Currency,
         interest_rate: f64,
         reason: String,
-    ) -> Result<String, String> {
+    ) -> Result<String, AppError> {
         // obtenemos el db_access_token usando el affiliate_key
         // este helper busca en redis la key affiliate_key_to_db_access:{affiliate_key}
         // y retorna el db_composite_key correcto del usuario
         let db_access_token = get_db_access_token_with_affiliate_key(
             affiliate_key.clone(),
             self.pool.clone(),
-        )?;
+        )
+        .await?;
 
-        let mut con = &mut self.pool.get().expect("Couldn't connect to pool");
+        let con = &mut self.pool.get().await?;
 
-        if let Ok(keys) =
-            con.scan_match::<String, String>(format!("users:{}:loans:*", db_access_token))
+        if let Ok(keys_parsed) =
+            scan_keys(con, &format!("users:{}:loans:*", db_access_token)).await
         {
-            let keys_parsed: Vec<String> = keys.collect();
 
             // para crear el loan y evitar colisiones
             let loan_hash_key =
                 hashing_composite_key(&[&keys_parsed.len().to_string(), &db_access_token]);
 
-            let con = &mut self.pool.get().expect("Couldn't connect to pool");
-
             let _: () = con
                 .json_set(
                     format!("users:{}:loans:{}", db_access_token, loan_hash_key),
@@ -155,19 +145,22 @@ impl LoanRepo {
                     &RedisLoan {
                         total_quota,
                         base_needed_payment,
-                        payed: 0.0,
+                        payed: Money::ZERO,
                         debt: base_needed_payment,
                         total: base_needed_payment,
+                        currency,
                         status: "PENDING".to_owned(),
                         reason,
                         interest_rate: Some(interest_rate),
+                        created_at: Some(DateTime::now()),
                     },
                 )
-                .expect("LOAN CREATION: Couldn't Create Loan");
+                .await
+                .map_err(|_| AppError::storage("LOAN CREATION: Couldn't Create Loan"))?;
             return Ok("Loan Created".to_owned());
         }
 
-        Err("LOAN CREATION: Couldn't Create Loan".to_owned())
+        Err(AppError::storage("LOAN CREATION: Couldn't Create Loan"))
     }
 
     //pub fn add_ill_pay(&self, loan_id: String, ill_pay: Pagare) -> () {}
@@ -0,0 +1,1044 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Mutex, MutexGuard};
+
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+
+use crate::errors::AppError;
+use crate::models::currency::{Currency, ExchangeRates, base_currency};
+use crate::models::dates::{DateTime, today};
+use crate::models::file::StoredReceipt;
+use crate::models::graphql::{
+    Affiliate, Attachment, AttachmentOwnerType, AttachmentStatus, BaseCurrencyReport,
+    DuplicatePaymentGroup, ExchangeRate, Fine, FineStatus, Loan, Member, Payment, PaymentHistory,
+    PaymentStatus, PaymentStatusChange, PaymentTarget, PaymentType, Quota, UsersWithFines,
+};
+use crate::models::money::Money;
+use crate::models::redis::{
+    Attachment as RedisAttachment, ExchangeRate as RedisExchangeRate, Fine as RedisFine,
+    Loan as RedisLoan, Payment as RedisPayment, PaymentStatusChange as RedisPaymentStatusChange,
+};
+use crate::models::{
+    DEFAULT_PRESENTER_NAME, GraphQLMappable, PayedTo, PayedToInput, WithPresenterName,
+};
+use crate::repos::auth::utils::hashing_composite_key;
+use crate::repos::file::payment_link::{
+    ClaimedReceipt, check_receipt_claim, claimed_ticket_id, receipt_missing,
+};
+use crate::repos::graphql::attachment::{attachment_key, validate_review};
+use crate::repos::graphql::currency::{
+    base_currency_report, exchange_rate_key, parse_exchange_rate,
+};
+use crate::repos::graphql::payment::{non_empty, payment_ticket_index_key, validate_new_status};
+use crate::repos::graphql::quota::{is_pending_affiliate_quota, is_pending_loan_quota, quota_key};
+use crate::repos::graphql::store::{
+    AttachmentStore, CurrencyStore, FineStore, LoanStore, PaymentStore, QuotaStore, UserStore,
+};
+use crate::repos::graphql::utils::{extract_user_hash_from_key, get_key, is_direct_child_key};
+
+/// datos de un socio, lo que en redis son las keys sueltas users:{owner_key}:*
+#[derive(Clone, Debug)]
+pub struct MemoryUser {
+    pub affiliate_key: String,
+    pub complete_name: String,
+    pub is_directive: bool,
+    pub payed_to_capital: Money,
+    pub owed_capital: Money,
+}
+
+/// backend sin redis para los tests de graphql
+/// los modelos se guardan con las mismas keys que en redis (users:{hash}:payments:{id}, etc)
+/// así los ids y owner_key salen igual que con RedisPool. no publica eventos ni guarda
+/// archivos, las subscriptions y los endpoints REST siguen necesitando redis
+#[derive(Default)]
+pub struct MemoryStore {
+    data: Mutex<MemoryData>,
+}
+
+#[derive(Default)]
+struct MemoryData {
+    users: BTreeMap<String, MemoryUser>,
+    payments: BTreeMap<String, RedisPayment>,
+    loans: BTreeMap<String, RedisLoan>,
+    fines: BTreeMap<String, RedisFine>,
+    quotas: BTreeMap<String, Quota>,
+    payment_history: HashMap<String, Vec<RedisPaymentStatusChange>>,
+    // payment_tickets:{hash} -> keys de los pagos con esa cuenta + boleta
+    payment_tickets: HashMap<String, Vec<String>>,
+    // exchange_rates:{moneda}
+    exchange_rates: BTreeMap<String, RedisExchangeRate>,
+    // attachments:{owner_type}:{owner_id}:{id}
+    attachments: BTreeMap<String, RedisAttachment>,
+    // receipts:{ticket_id} sin el prefijo, los bytes no se guardan
+    receipts: BTreeMap<String, StoredReceipt>,
+    // receipt_claims:{ticket_id}, comprobantes apartados por claim_receipt
+    receipt_claims: BTreeSet<String>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        MemoryStore::default()
+    }
+
+    /// registra un socio y devuelve su owner_key (el hash del access_token)
+    pub fn add_user(&self, access_token: &str, user: MemoryUser) -> Result<String, AppError> {
+        let owner_key = hashing_composite_key(&[&access_token.to_owned()]);
+        self.data()?.users.insert(owner_key.clone(), user);
+        Ok(owner_key)
+    }
+
+    /// guarda un pago tal cual en users:{owner_key}:payments:{id}, sin pasar por el índice
+    /// de boletas (como cargar datos directo en redis)
+    pub fn insert_payment(
+        &self,
+        owner_key: &str,
+        id: &str,
+        payment: RedisPayment,
+    ) -> Result<String, AppError> {
+        let key = format!("users:{}:payments:{}", owner_key, id);
+        self.data()?.payments.insert(key.clone(), payment);
+        Ok(key)
+    }
+
+    pub fn insert_loan(
+        &self,
+        owner_key: &str,
+        id: &str,
+        loan: RedisLoan,
+    ) -> Result<String, AppError> {
+        let key = format!("users:{}:loans:{}", owner_key, id);
+        self.data()?.loans.insert(key.clone(), loan);
+        Ok(key)
+    }
+
+    pub fn insert_fine(
+        &self,
+        owner_key: &str,
+        id: &str,
+        fine: RedisFine,
+    ) -> Result<String, AppError> {
+        let key = format!("users:{}:fines:{}", owner_key, id);
+        self.data()?.fines.insert(key.clone(), fine);
+        Ok(key)
+    }
+
+    /// registro de un comprobante como si se hubiera subido (receipts:{ticket_id})
+    pub fn insert_receipt(&self, ticket_id: &str, receipt: StoredReceipt) -> Result<(), AppError> {
+        self.data()?.receipts.insert(ticket_id.to_owned(), receipt);
+        Ok(())
+    }
+
+    /// el registro del comprobante, para revisar linked_at en los tests
+    pub fn receipt(&self, ticket_id: &str) -> Result<Option<StoredReceipt>, AppError> {
+        Ok(self.data()?.receipts.get(ticket_id).cloned())
+    }
+
+    /// los tipos de cambio guardados con set_exchange_rate
+    pub fn exchange_rates(&self) -> Result<ExchangeRates, AppError> {
+        Ok(self.data()?.rates())
+    }
+
+    fn data(&self) -> Result<MutexGuard<'_, MemoryData>, AppError> {
+        self.data
+            .lock()
+            .map_err(|_| AppError::storage("El store en memoria quedó envenenado"))
+    }
+}
+
+impl MemoryData {
+    fn rates(&self) -> ExchangeRates {
+        let rates: HashMap<Currency, Decimal> = self
+            .exchange_rates
+            .iter()
+            .filter_map(|(key, rate)| {
+                let currency = key.rsplit(':').next()?.parse::<Currency>().ok()?;
+                Some((currency, rate.rate))
+            })
+            .collect();
+        ExchangeRates::new(rates)
+    }
+
+    /// el directivo dueño del access_token, message es el error si no lo es
+    fn directive(&self, access_token: &str, message: &str) -> Result<MemoryUser, AppError> {
+        let user = self
+            .users
+            .get(&hashing_composite_key(&[&access_token.to_owned()]))
+            .filter(|user| user.is_directive)
+            .ok_or_else(|| AppError::unauthorized(message))?;
+        Ok(user.clone())
+    }
+
+    fn owner_by_affiliate_key(&self, affiliate_key: &str) -> Result<String, AppError> {
+        self.users
+            .iter()
+            .find(|(_, user)| user.affiliate_key == affiliate_key)
+            .map(|(owner_key, _)| owner_key.clone())
+            .ok_or_else(|| AppError::not_found("Couldn't Get Db Token"))
+    }
+
+    fn complete_name(&self, owner_key: &str) -> Option<String> {
+        self.users
+            .get(owner_key)
+            .map(|user| user.complete_name.clone())
+    }
+
+    /// lo mismo que enrich_with_presenter_names pero leyendo los nombres del store
+    fn with_presenter_name<T: WithPresenterName>(&self, mut object: T, key: &str) -> T {
+        let name = extract_user_hash_from_key(key)
+            .and_then(|owner_key| self.complete_name(&owner_key))
+            .unwrap_or_else(|| DEFAULT_PRESENTER_NAME.to_string());
+        object.set_presenter_name(name);
+        object
+    }
+
+    /// keys users:{owner}:{collection}:{id} del socio, sin lo que va anidado debajo
+    fn owned_keys<'a, V>(
+        models: &'a BTreeMap<String, V>,
+        owner_key: &str,
+        collection: &str,
+    ) -> impl Iterator<Item = (&'a String, &'a V)> {
+        let prefix = format!("users:{}:{}:", owner_key, collection);
+        models
+            .iter()
+            .filter(move |(key, _)| is_direct_child_key(key, &prefix))
+    }
+
+    /// la única key de la colección con ese id, None si no hay o hay varias (find_single_key)
+    fn single_key<V>(models: &BTreeMap<String, V>, collection: &str, id: &str) -> Option<String> {
+        let mut keys = models
+            .keys()
+            .filter(|key| get_key((*key).clone(), collection.to_owned()).is_ok_and(|k| k == id))
+            .filter(|key| {
+                extract_user_hash_from_key(key).is_some_and(|owner| {
+                    is_direct_child_key(key, &format!("users:{}:{}:", owner, collection))
+                })
+            });
+        let key = keys.next()?;
+        if keys.next().is_some() {
+            return None;
+        }
+        Some(key.clone())
+    }
+
+    /// la cuota (de préstamo o de afiliado) con esa fecha, None si no hay o hay varias
+    fn single_quota(&self, model_key: &str) -> Option<&Quota> {
+        let loan_suffix = format!(":quotas:{}", model_key);
+        let affiliate_suffix = format!(":quotas_afiliado:{}", model_key);
+        let mut quotas = self
+            .quotas
+            .iter()
+            .filter(|(key, _)| key.ends_with(&loan_suffix) || key.ends_with(&affiliate_suffix))
+            .map(|(_, quota)| quota);
+        let quota = quotas.next()?;
+        if quotas.next().is_some() {
+            return None;
+        }
+        Some(quota)
+    }
+
+    fn loan_currency(&self, loan_id: &str) -> Currency {
+        self.loans
+            .iter()
+            .find(|(key, _)| key.ends_with(&format!(":loans:{}", loan_id)))
+            .map(|(_, loan)| loan.currency)
+            .unwrap_or_else(base_currency)
+    }
+
+    /// igual que allocate_payed_to: los préstamos y sus cuotas van en la moneda del préstamo,
+    /// el resto en la base
+    fn allocated_currency(&self, payed_to: &PayedTo) -> Currency {
+        match PaymentType::from_string(payed_to.model_type.clone()) {
+            PaymentType::Loan => self.loan_currency(&payed_to.model_key),
+            PaymentType::Quota => self
+                .single_quota(&payed_to.model_key)
+                .and_then(|quota| quota.loan_id.as_deref())
+                .map(|loan_id| self.loan_currency(loan_id))
+                .unwrap_or_else(base_currency),
+            _ => base_currency(),
+        }
+    }
+
+    /// igual que refresh_duplicate_flags de redis: con dos o más pagos no rechazados en la
+    /// boleta todos quedan marcados, con uno solo se le quita la marca
+    fn refresh_duplicate_flags(&mut self, ticket_index_key: &str) {
+        let active: Vec<String> = self
+            .payment_tickets
+            .get(ticket_index_key)
+            .map(|keys| {
+                keys.iter()
+                    .filter(|key| self.is_active_payment(key))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let is_duplicate = active.len() > 1;
+        for key in active {
+            if let Some(payment) = self.payments.get_mut(&key) {
+                payment.possible_duplicates = is_duplicate;
+            }
+        }
+    }
+
+    fn is_active_payment(&self, key: &str) -> bool {
+        self.payments.get(key).is_some_and(|payment| {
+            PaymentStatus::from_string(payment.status.clone()) != PaymentStatus::Rejected
+        })
+    }
+}
+
+#[async_trait]
+impl PaymentStore for MemoryStore {
+    async fn get_user_history(&self, access_token: String) -> Result<PaymentHistory, AppError> {
+        let data = self.data()?;
+        let user = data
+            .users
+            .get(&hashing_composite_key(&[&access_token]))
+            .ok_or_else(|| AppError::not_found("Couldnt Get Payed To Capital"))?;
+
+        Ok(PaymentHistory {
+            payed_to_capital: user.payed_to_capital,
+            owed_capital: user.owed_capital,
+        })
+    }
+
+    async fn get_payments_by_owner(&self, owner_key: &str) -> Result<Vec<Payment>, AppError> {
+        let data = self.data()?;
+        Ok(
+            MemoryData::owned_keys(&data.payments, owner_key, "payments")
+                .map(|(key, payment)| payment.to_graphql_type(key.clone()))
+                .collect(),
+        )
+    }
+
+    async fn get_payed_to_target(
+        &self,
+        payed_to: &PayedTo,
+    ) -> Result<Option<PaymentTarget>, AppError> {
+        let data = self.data()?;
+        let model_key = &payed_to.model_key;
+
+        match PaymentType::from_string(payed_to.model_type.clone()) {
+            PaymentType::Loan => {
+                let Some(key) = MemoryData::single_key(&data.loans, "loans", model_key) else {
+                    return Ok(None);
+                };
+                let loan = data.loans[&key].to_graphql_type(key.clone());
+                Ok(Some(PaymentTarget::Loan(
+                    data.with_presenter_name(loan, &key),
+                )))
+            }
+            PaymentType::Fine => {
+                let Some(key) = MemoryData::single_key(&data.fines, "fines", model_key) else {
+                    return Ok(None);
+                };
+                let fine = data.fines[&key].to_graphql_type(key.clone());
+                Ok(Some(PaymentTarget::Fine(
+                    data.with_presenter_name(fine, &key),
+                )))
+            }
+            PaymentType::Quota => Ok(data
+                .single_quota(model_key)
+                .map(|quota| PaymentTarget::Quota(quota.clone()))),
+            PaymentType::ParsedError => Ok(None),
+        }
+    }
+
+    async fn get_all_payments(&self) -> Result<Vec<Payment>, AppError> {
+        let data = self.data()?;
+        Ok(data
+            .payments
+            .iter()
+            .map(|(key, payment)| {
+                data.with_presenter_name(payment.to_graphql_type(key.clone()), key)
+            })
+            .collect())
+    }
+
+    async fn create_payment(
+        &self,
+        access_token: String,
+        name: String,
+        comprobante_path: String,
+        total_amount: Money,
+        currency: Currency,
+        ticket_number: String,
+        account_number: String,
+        being_payed: Vec<PayedToInput>,
+    ) -> Result<String, AppError> {
+        let mut data = self.data()?;
+        let db_access_token = hashing_composite_key(&[&access_token]);
+
+        let payments_count = data
+            .payments
+            .keys()
+            .filter(|key| key.starts_with(&format!("users:{}:payments:", db_access_token)))
+            .count();
+        let payment_hash_key =
+            hashing_composite_key(&[&payments_count.to_string(), &db_access_token]);
+
+        let rates = data.rates();
+        let mut being_payed_output: Vec<PayedTo> = Vec::new();
+        for input in being_payed {
+            let mut payed_to: PayedTo = input.into();
+            let allocated_currency = data.allocated_currency(&payed_to);
+            payed_to.allocated_amount =
+                Some(rates.convert(payed_to.amount, currency, allocated_currency)?);
+            payed_to.allocated_currency = allocated_currency;
+            being_payed_output.push(payed_to);
+        }
+
+        let payment_key = format!("users:{db_access_token}:payments:{payment_hash_key}");
+
+        // mismo criterio que flag_existing_duplicates: la boleta ya usada en otro pago no rechazado
+        let ticket_index_key = payment_ticket_index_key(&account_number, &ticket_number);
+        let existing_keys = data
+            .payment_tickets
+            .get(&ticket_index_key)
+            .cloned()
+            .unwrap_or_default();
+        let mut possible_duplicates = false;
+        for key in existing_keys {
+            if !data.is_active_payment(&key) {
+                continue;
+            }
+            possible_duplicates = true;
+            if let Some(payment) = data.payments.get_mut(&key) {
+                payment.possible_duplicates = true;
+            }
+        }
+
+        data.payments.insert(
+            payment_key.clone(),
+            RedisPayment {
+                name,
+                total_amount,
+                currency,
+                ticket_number,
+                date_created: DateTime::now(),
+                comprobante_bucket: comprobante_path,
+                account_number,
+                comments: None,
+                status: "ON_REVISION".to_owned(),
+                being_payed: being_payed_output,
+                possible_duplicates,
+                receipt_check: None,
+            },
+        );
+        data.payment_tickets
+            .entry(ticket_index_key)
+            .or_default()
+            .push(payment_key);
+
+        Ok("Payment Created".to_owned())
+    }
+
+    // los comprobantes se cargan con insert_receipt, se revisan y apartan igual que en redis
+    async fn claim_receipt(
+        &self,
+        access_token: &str,
+        comprobante_path: &str,
+    ) -> Result<ClaimedReceipt, AppError> {
+        let ticket_id = claimed_ticket_id(comprobante_path)?;
+
+        let mut data = self.data()?;
+        if !data.receipt_claims.insert(ticket_id.to_owned()) {
+            return Err(AppError::conflict("El comprobante ya se está usando en otro pago"));
+        }
+        let checked = match data.receipts.get(ticket_id).cloned() {
+            Some(record) => data
+                .users
+                .get(&hashing_composite_key(&[&access_token.to_owned()]))
+                .ok_or_else(|| AppError::unauthorized("Couldn't verify user"))
+                .and_then(|viewer| check_receipt_claim(ticket_id, record, &viewer.affiliate_key)),
+            None => Err(receipt_missing()),
+        };
+        if checked.is_err() {
+            data.receipt_claims.remove(ticket_id);
+        }
+        checked
+    }
+
+    // sin OCR en memoria, solo se marca
+    async fn link_receipt(&self, ticket_id: &str) -> Result<(), AppError> {
+        let mut data = self.data()?;
+        let record = data
+            .receipts
+            .get_mut(ticket_id)
+            .ok_or_else(|| AppError::not_found("Ticket not found"))?;
+        record.linked_at = Some(DateTime::now());
+        data.receipt_claims.remove(ticket_id);
+        Ok(())
+    }
+
+    async fn release_receipt(&self, ticket_id: &str) -> Result<(), AppError> {
+        self.data()?.receipt_claims.remove(ticket_id);
+        Ok(())
+    }
+
+    async fn get_possible_duplicate_payments(
+        &self,
+    ) -> Result<Vec<DuplicatePaymentGroup>, AppError> {
+        let data = self.data()?;
+        let mut groups: Vec<DuplicatePaymentGroup> = Vec::new();
+
+        for payment_keys in data.payment_tickets.values() {
+            let payments: Vec<Payment> = payment_keys
+                .iter()
+                .filter(|key| data.is_active_payment(key))
+                .map(|key| {
+                    data.with_presenter_name(data.payments[key].to_graphql_type(key.clone()), key)
+                })
+                .collect();
+
+            if payments.len() < 2 {
+                continue;
+            }
+
+            groups.push(DuplicatePaymentGroup {
+                account_num: payments[0].account_num.clone(),
+                ticket_num: payments[0].ticket_num.clone(),
+                payments,
+            });
+        }
+
+        Ok(groups)
+    }
+
+    async fn get_payment_history(&self, id: String) -> Result<Vec<PaymentStatusChange>, AppError> {
+        let data = self.data()?;
+        Ok(data
+            .payment_history
+            .get(&id)
+            .map(|entries| {
+                entries
+                    .iter()
+                    .map(|entry| entry.to_graphql_type(id.clone()))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn approve_or_reject_payment(
+        &self,
+        reviewer_access_token: String,
+        id: String,
+        new_state: String,
+        commentary: String,
+    ) -> Result<Payment, AppError> {
+        let mut data = self.data()?;
+
+        let reviewer = data
+            .users
+            .get(&hashing_composite_key(&[&reviewer_access_token]))
+            .cloned()
+            .ok_or_else(|| AppError::unauthorized("Revisor no encontrado"))?;
+        if !reviewer.is_directive {
+            return Err(AppError::unauthorized(
+                "Solo los directivos pueden aprobar o rechazar pagos",
+            ));
+        }
+
+        let keys: Vec<String> = data
+            .payments
+            .keys()
+            .filter(|key| get_key((*key).clone(), "payments".to_owned()).is_ok_and(|k| k == id))
+            .cloned()
+            .collect();
+        if keys.is_empty() {
+            return Err(AppError::not_found("Payment not found"));
+        }
+
+        for key in &keys {
+            let current_status = PaymentStatus::from_string(data.payments[key].status.clone());
+            if current_status == PaymentStatus::Accepted
+                || current_status == PaymentStatus::Rejected
+            {
+                return Err(AppError::conflict("El pago ya está finalizado"));
+            }
+        }
+
+        let new_status = validate_new_status(&new_state, &commentary)?;
+
+        // las copias comparten id, se actualizan todas y el historial se registra una sola vez
+        let previous_status = data.payments[&keys[0]].status.clone();
+        for key in &keys {
+            if let Some(payment) = data.payments.get_mut(key) {
+                payment.status = new_status.as_str().to_owned();
+                if new_status == PaymentStatus::Rejected {
+                    payment.comments = Some(commentary.clone());
+                }
+            }
+        }
+
+        // al rechazar, los que compartían la boleta pueden dejar de ser duplicados
+        if new_status == PaymentStatus::Rejected {
+            let rejected = &data.payments[&keys[0]];
+            let ticket_index_key =
+                payment_ticket_index_key(&rejected.account_number, &rejected.ticket_number);
+            data.refresh_duplicate_flags(&ticket_index_key);
+        }
+
+        data.payment_history
+            .entry(id)
+            .or_default()
+            .push(RedisPaymentStatusChange {
+                previous_status,
+                new_status: new_status.as_str().to_owned(),
+                reviewer_id: reviewer.affiliate_key,
+                reviewer_name: reviewer.complete_name,
+                changed_at: DateTime::now(),
+                comment: non_empty(commentary),
+            });
+
+        Ok(data.payments[&keys[0]].to_graphql_type(keys[0].clone()))
+    }
+}
+
+#[async_trait]
+impl LoanStore for MemoryStore {
+    async fn get_loans_by_owner(&self, owner_key: &str) -> Result<Vec<Loan>, AppError> {
+        let data = self.data()?;
+        Ok(MemoryData::owned_keys(&data.loans, owner_key, "loans")
+            .map(|(key, loan)| loan.to_graphql_type(key.clone()))
+            .collect())
+    }
+
+    async fn get_all_loans(&self) -> Result<Vec<Loan>, AppError> {
+        let data = self.data()?;
+        Ok(data
+            .loans
+            .iter()
+            .map(|(key, loan)| {
+                let mut loan = loan.to_graphql_type(key.clone());
+                // get_all_loans en redis usa este texto cuando el socio no tiene nombre
+                loan.presented_by_name = data
+                    .complete_name(&loan.owner_key)
+                    .unwrap_or_else(|| "Nombre no encontrado".to_string());
+                loan
+            })
+            .collect())
+    }
+
+    async fn create_loan(
+        &self,
+        affiliate_key: String,
+        total_quota: i32,
+        base_needed_payment: Money,
+        currency: Currency,
+        interest_rate: f64,
+        reason: String,
+    ) -> Result<String, AppError> {
+        let mut data = self.data()?;
+        let db_access_token = data.owner_by_affiliate_key(&affiliate_key)?;
+
+        let loans_count = MemoryData::owned_keys(&data.loans, &db_access_token, "loans").count();
+        let loan_hash_key = hashing_composite_key(&[&loans_count.to_string(), &db_access_token]);
+
+        data.loans.insert(
+            format!("users:{}:loans:{}", db_access_token, loan_hash_key),
+            RedisLoan {
+                total_quota,
+                base_needed_payment,
+                payed: Money::ZERO,
+                debt: base_needed_payment,
+                total: base_needed_payment,
+                currency,
+                status: "PENDING".to_owned(),
+                reason,
+                interest_rate: Some(interest_rate),
+                created_at: Some(DateTime::now()),
+            },
+        );
+
+        Ok("Loan Created".to_owned())
+    }
+}
+
+#[async_trait]
+impl FineStore for MemoryStore {
+    async fn get_user_fines(&self, access_token: String) -> Result<Vec<Fine>, AppError> {
+        let owner_key = self.data()?.owner_by_affiliate_key(&access_token)?;
+        self.get_fines_by_owner(&owner_key).await
+    }
+
+    async fn get_fines_by_owner(&self, owner_key: &str) -> Result<Vec<Fine>, AppError> {
+        let data = self.data()?;
+        Ok(MemoryData::owned_keys(&data.fines, owner_key, "fines")
+            .map(|(key, fine)| data.with_presenter_name(fine.to_graphql_type(key.clone()), key))
+            .collect())
+    }
+
+    async fn create_fine(
+        &self,
+        affiliate_key: String,
+        amount: Money,
+        motive: String,
+    ) -> Result<String, AppError> {
+        let mut data = self.data()?;
+        let db_access_token = data.owner_by_affiliate_key(&affiliate_key)?;
+
+        let fines_count = data
+            .fines
+            .keys()
+            .filter(|key| key.starts_with(&format!("users:{}:fines:", db_access_token)))
+            .count();
+        let fine_hash_key = hashing_composite_key(&[&fines_count.to_string(), &db_access_token]);
+
+        data.fines.insert(
+            format!("users:{}:fines:{}", db_access_token, fine_hash_key),
+            RedisFine {
+                amount,
+                motive,
+                status: "UNPAID".to_owned(),
+                created_at: Some(DateTime::now()),
+            },
+        );
+
+        Ok("Fine Createad".to_owned())
+    }
+
+    async fn edit_fine(
+        &self,
+        fine_key: String,
+        new_amount: Option<Money>,
+        new_motive: Option<String>,
+        new_status: Option<FineStatus>,
+    ) -> Result<String, AppError> {
+        let mut data = self.data()?;
+        let fine = data
+            .fines
+            .iter_mut()
+            .find(|(key, _)| key.ends_with(&format!(":fines:{}", fine_key)))
+            .map(|(_, fine)| fine)
+            .ok_or_else(|| AppError::not_found("Fine not found"))?;
+
+        if let Some(amount) = new_amount {
+            fine.amount = amount;
+        }
+        if let Some(motive) = new_motive {
+            fine.motive = motive;
+        }
+        if let Some(status) = new_status {
+            fine.status = status.to_string();
+        }
+
+        Ok("Fine updated".to_owned())
+    }
+
+    async fn get_users_with_there_fines(&self) -> Result<Vec<UsersWithFines>, AppError> {
+        let data = self.data()?;
+        let mut users_with_fines: Vec<UsersWithFines> = Vec::new();
+
+        for (owner_key, user) in &data.users {
+            let fines: Vec<Fine> = MemoryData::owned_keys(&data.fines, owner_key, "fines")
+                .map(|(key, fine)| data.with_presenter_name(fine.to_graphql_type(key.clone()), key))
+                .collect();
+
+            // we don't put the fines for those who doesn't have
+            if fines.is_empty() {
+                continue;
+            }
+
+            users_with_fines.push(UsersWithFines {
+                user_id: user.affiliate_key.clone(),
+                complete_name: user.complete_name.clone(),
+                fines,
+            });
+        }
+
+        Ok(users_with_fines)
+    }
+}
+
+#[async_trait]
+impl QuotaStore for MemoryStore {
+    async fn get_quotas_afiliado_pendientes_by_owner(
+        &self,
+        db_access_token: &str,
+    ) -> Result<Vec<Quota>, AppError> {
+        let data = self.data()?;
+        let today = today();
+        let prefix = format!("users:{}:quotas_afiliado:", db_access_token);
+        Ok(data
+            .quotas
+            .iter()
+            .filter(|(key, quota)| {
+                key.starts_with(&prefix) && is_pending_affiliate_quota(quota, today)
+            })
+            .map(|(_, quota)| quota.clone())
+            .collect())
+    }
+
+    async fn save_quota(&self, access_token: String, quota: &Quota) -> Result<(), AppError> {
+        let key = quota_key(&hashing_composite_key(&[&access_token]), quota)?;
+        self.data()?.quotas.insert(key, quota.clone());
+        Ok(())
+    }
+
+    async fn get_quotas_by_owner(&self, db_access_token: &str) -> Result<Vec<Quota>, AppError> {
+        let data = self.data()?;
+        Ok(data
+            .quotas
+            .iter()
+            .filter(|(key, _)| {
+                is_loan_quota_key(key, db_access_token)
+                    || key.starts_with(&format!("users:{}:quotas_afiliado:", db_access_token))
+            })
+            .map(|(_, quota)| quota.clone())
+            .collect())
+    }
+
+    async fn get_quotas_prestamo_pendientes_by_owner(
+        &self,
+        db_access_token: &str,
+    ) -> Result<Vec<Quota>, AppError> {
+        let data = self.data()?;
+        let today = today();
+        Ok(data
+            .quotas
+            .iter()
+            .filter(|(key, quota)| {
+                is_loan_quota_key(key, db_access_token) && is_pending_loan_quota(quota, today)
+            })
+            .map(|(_, quota)| quota.clone())
+            .collect())
+    }
+
+    async fn get_loan_quotas_by_owner(
+        &self,
+        db_access_token: &str,
+        loan_id: &str,
+    ) -> Result<Vec<Quota>, AppError> {
+        let data = self.data()?;
+        let prefix = format!("users:{}:loans:{}:quotas:", db_access_token, loan_id);
+        let mut quotas: Vec<Quota> = data
+            .quotas
+            .iter()
+            .filter(|(key, _)| key.starts_with(&prefix))
+            .map(|(_, quota)| quota.clone())
+            .collect();
+        quotas.sort_by_key(|quota| quota.exp_date);
+        Ok(quotas)
+    }
+
+    async fn get_quota_by_loan_id(
+        &self,
+        access_token: String,
+        loan_id: String,
+    ) -> Result<Vec<Quota>, AppError> {
+        let data = self.data()?;
+        let db_access_token = hashing_composite_key(&[&access_token]);
+        Ok(data
+            .quotas
+            .iter()
+            .filter(|(key, quota)| {
+                is_loan_quota_key(key, &db_access_token)
+                    && quota.loan_id.as_deref() == Some(loan_id.as_str())
+            })
+            .map(|(_, quota)| quota.clone())
+            .collect())
+    }
+}
+
+#[async_trait]
+impl UserStore for MemoryStore {
+    async fn get_all_users_for_affiliates(&self) -> Result<Vec<Affiliate>, AppError> {
+        let data = self.data()?;
+        Ok(data
+            .users
+            .values()
+            .map(|user| Affiliate {
+                user_id: user.affiliate_key.clone(),
+                name: user.complete_name.clone(),
+            })
+            .collect())
+    }
+
+    async fn get_member_by_owner_key(&self, owner_key: &str) -> Result<Member, AppError> {
+        let data = self.data()?;
+        let user = data
+            .users
+            .get(owner_key)
+            .ok_or_else(|| AppError::not_found("Socio no encontrado"))?;
+
+        Ok(Member {
+            affiliate_key: user.affiliate_key.clone(),
+            name: user.complete_name.clone(),
+            owner_key: owner_key.to_owned(),
+        })
+    }
+
+    async fn get_member_by_affiliate_key(&self, affiliate_key: String) -> Result<Member, AppError> {
+        let owner_key = self.data()?.owner_by_affiliate_key(&affiliate_key)?;
+        self.get_member_by_owner_key(&owner_key).await
+    }
+
+    async fn is_directive(&self, access_token: &str) -> bool {
+        let Ok(data) = self.data() else {
+            return false;
+        };
+        data.users
+            .get(&hashing_composite_key(&[&access_token.to_owned()]))
+            .is_some_and(|user| user.is_directive)
+    }
+}
+
+#[async_trait]
+impl CurrencyStore for MemoryStore {
+    async fn get_exchange_rates(&self) -> Result<Vec<ExchangeRate>, AppError> {
+        let data = self.data()?;
+        let mut rates: Vec<ExchangeRate> = data
+            .exchange_rates
+            .iter()
+            .map(|(key, rate)| rate.to_graphql_type(key.clone()))
+            .collect();
+        rates.sort_by_key(|rate| rate.currency.as_str());
+        Ok(rates)
+    }
+
+    async fn set_exchange_rate(
+        &self,
+        access_token: String,
+        currency: Currency,
+        rate: String,
+    ) -> Result<ExchangeRate, AppError> {
+        let mut data = self.data()?;
+        let directive = data.directive(
+            &access_token,
+            "Solo los directivos pueden cambiar el tipo de cambio",
+        )?;
+        let rate = parse_exchange_rate(currency, &rate)?;
+
+        let key = exchange_rate_key(currency);
+        let rate = RedisExchangeRate {
+            rate,
+            updated_by: directive.affiliate_key,
+            updated_by_name: directive.complete_name,
+            updated_at: DateTime::now(),
+        };
+        data.exchange_rates.insert(key.clone(), rate.clone());
+
+        Ok(rate.to_graphql_type(key))
+    }
+
+    async fn get_base_currency_report(&self) -> Result<BaseCurrencyReport, AppError> {
+        let payments = PaymentStore::get_all_payments(self).await?;
+        let loans = LoanStore::get_all_loans(self).await?;
+        base_currency_report(&self.exchange_rates()?, &payments, &loans)
+    }
+}
+
+#[async_trait]
+impl AttachmentStore for MemoryStore {
+    async fn get_attachments(
+        &self,
+        owner_type: AttachmentOwnerType,
+        owner_id: &str,
+    ) -> Result<Vec<Attachment>, AppError> {
+        let data = self.data()?;
+        let prefix = format!("attachments:{}:{}:", owner_type.as_str(), owner_id);
+        let mut attachments: Vec<Attachment> = data
+            .attachments
+            .iter()
+            .filter(|(key, _)| key.starts_with(&prefix))
+            .map(|(key, attachment)| attachment.to_graphql_type(key.clone()))
+            .collect();
+        attachments.sort_by_key(|attachment| attachment.uploaded_at);
+        Ok(attachments)
+    }
+
+    async fn get_pending_attachments(
+        &self,
+        access_token: String,
+    ) -> Result<Vec<Attachment>, AppError> {
+        let data = self.data()?;
+        data.directive(&access_token, "Solo los directivos pueden revisar documentos")?;
+
+        let mut attachments: Vec<Attachment> = data
+            .attachments
+            .iter()
+            .filter(|(_, attachment)| attachment.status == AttachmentStatus::Pending)
+            .map(|(key, attachment)| attachment.to_graphql_type(key.clone()))
+            .collect();
+        attachments.sort_by_key(|attachment| attachment.uploaded_at);
+        Ok(attachments)
+    }
+
+    async fn review_attachment(
+        &self,
+        access_token: String,
+        id: String,
+        status: AttachmentStatus,
+        commentary: Option<String>,
+    ) -> Result<Attachment, AppError> {
+        let mut data = self.data()?;
+        let reviewer =
+            data.directive(&access_token, "Solo los directivos pueden revisar documentos")?;
+        let commentary = validate_review(status, commentary)?;
+
+        let (key, attachment) = data
+            .attachments
+            .iter_mut()
+            .find(|(key, _)| key.rsplit(':').next() == Some(id.as_str()))
+            .ok_or_else(|| AppError::not_found("Documento no encontrado"))?;
+
+        attachment.status = status;
+        attachment.reviewed_by = Some(reviewer.affiliate_key);
+        attachment.reviewed_by_name = Some(reviewer.complete_name);
+        attachment.reviewed_at = Some(DateTime::now());
+        attachment.review_comment = commentary;
+
+        Ok(attachment.to_graphql_type(key.clone()))
+    }
+
+    async fn save_attachment(
+        &self,
+        id: &str,
+        attachment: &RedisAttachment,
+    ) -> Result<Attachment, AppError> {
+        let key = attachment_key(attachment.owner_type, &attachment.owner_id, id);
+        self.data()?
+            .attachments
+            .insert(key.clone(), attachment.clone());
+        Ok(attachment.to_graphql_type(key))
+    }
+
+    async fn get_redis_attachment(&self, id: &str) -> Result<Option<RedisAttachment>, AppError> {
+        let data = self.data()?;
+        Ok(data
+            .attachments
+            .iter()
+            .find(|(key, _)| key.rsplit(':').next() == Some(id))
+            .map(|(_, attachment)| attachment.clone()))
+    }
+
+    async fn get_member_key(
+        &self,
+        owner_type: AttachmentOwnerType,
+        owner_id: &str,
+    ) -> Result<String, AppError> {
+        let not_found = || AppError::not_found("No existe a quién pegarle el documento");
+        let data = self.data()?;
+
+        let key = match owner_type {
+            AttachmentOwnerType::Member => {
+                data.owner_by_affiliate_key(owner_id).map_err(|_| not_found())?;
+                return Ok(owner_id.to_owned());
+            }
+            AttachmentOwnerType::Loan => MemoryData::single_key(&data.loans, "loans", owner_id),
+            AttachmentOwnerType::Fine => MemoryData::single_key(&data.fines, "fines", owner_id),
+        };
+
+        key.and_then(|key| extract_user_hash_from_key(&key))
+            .and_then(|owner_key| data.users.get(&owner_key))
+            .map(|user| user.affiliate_key.clone())
+            .ok_or_else(not_found)
+    }
+}
+
+/// users:{owner}:loans:{loan_id}:quotas:{fecha}
+fn is_loan_quota_key(key: &str, owner_key: &str) -> bool {
+    key.strip_prefix(&format!("users:{}:loans:", owner_key))
+        .is_some_and(|rest| rest.contains(":quotas:"))
+}
@@ -1,7 +1,16 @@
+pub mod attachment;
+pub mod currency;
+pub mod events;
 pub mod fine;
+pub mod loader;
 pub mod loan;
+pub mod memory;
 pub mod payment;
 pub mod quota;
+pub mod report;
+pub mod statement;
+pub mod store;
+pub mod user;
 pub mod utils;
 
 //TODO: do the generic function for the get_all methods
@@ -1,44 +1,89 @@
-use crate::models::graphql::PaymentStatus;
+use crate::models::currency::{base_currency, Currency, ExchangeRates};
+use crate::models::dates::DateTime;
+use crate::models::money::Money;
+use crate::models::graphql::{PaymentStatus, PaymentType};
 use crate::models::GraphQLMappable;
-use crate::repos::graphql::utils::get_multiple_models_by_pattern;
+use crate::repos::file::payment_link::{self, ClaimedReceipt};
+use crate::repos::graphql::currency::load_exchange_rates;
+use crate::repos::graphql::events::{
+    publish_event, PAYMENT_STATUS_CHANGED_CHANNEL, PAYMENT_SUBMITTED_CHANNEL,
+};
+use crate::repos::graphql::loader::RequestLoader;
+use crate::repos::graphql::quota::fetch_quota;
+use crate::repos::graphql::store::{PaymentStore, UserStore};
+use crate::repos::graphql::user::UserRepo;
+use crate::repos::graphql::utils::{
+    enrich_with_presenter_names, extract_user_hash_from_key, find_single_key, scan_keys,
+};
 use crate::{
+    endpoints::handlers::configs::connection_pool::{RedisConnection, RedisPool},
     models::{
-        graphql::{Affiliate, Payment, PaymentHistory},
-        redis::Payment as RedisPayment,
-        PayedTo,
+        graphql::{
+            DuplicatePaymentGroup, Payment, PaymentHistory, PaymentStatusChange,
+            PaymentStatusChangedEvent, PaymentTarget,
+        },
+        redis::{
+            Fine as RedisFine, Loan as RedisLoan, Payment as RedisPayment,
+            PaymentStatusChange as RedisPaymentStatusChange,
+        },
+        PayedTo, PayedToInput,
     },
     repos::{auth::utils::hashing_composite_key, graphql::utils::get_multiple_models_by_id},
 };
-use actix_web::web::Data;
-use chrono::Utc;
-use r2d2::Pool;
-use redis::{from_redis_value, Client, Commands, JsonCommands};
-use regex::Regex;
+use redis::{from_redis_value, AsyncCommands, JsonAsyncCommands};
 use serde_json::from_str;
+use crate::errors::AppError;
+use async_trait::async_trait;
+use std::sync::Arc;
 
 pub struct PaymentRepo {
-    pub pool: Data<Pool<Client>>,
+    pub pool: RedisPool,
+    /// cache del request, GeneralContext le pasa el suyo
+    pub loader: Arc<RequestLoader>,
 }
 
 impl PaymentRepo {
+    /// repo con un loader propio, para usarlo fuera de un request de graphql
+    pub fn new(pool: RedisPool) -> Self {
+        PaymentRepo {
+            loader: Arc::new(RequestLoader::new(pool.clone())),
+            pool,
+        }
+    }
+
+    /// al rechazar un pago, los que compartían la boleta con él pueden dejar de ser duplicados
+    async fn clear_resolved_duplicates(&self, con: &mut RedisConnection, rejected: &RedisPayment) {
+        let ticket_index_key =
+            payment_ticket_index_key(&rejected.account_number, &rejected.ticket_number);
+        for key in refresh_duplicate_flags(con, &ticket_index_key).await {
+            self.loader.forget_model(&key);
+        }
+    }
+}
+
+#[async_trait]
+impl PaymentStore for PaymentRepo {
     /// giving the acess token, this returns the an Object of PaymentHistory of that "user"
-    pub fn get_user_history(&self, access_token: String) -> Result<PaymentHistory, String> {
-        let mut con = self.pool.get().expect("Couldn't connect to pool");
+    async fn get_user_history(&self, access_token: String) -> Result<PaymentHistory, AppError> {
+        let mut con = self.pool.get().await?;
 
         let db_access_token = hashing_composite_key(&[&access_token]);
 
         let payed_to_capital = match con
             .get::<String, String>(format!("users:{}:payed_to_capital", db_access_token))
+            .await
         {
-            Ok(val) => val.parse::<f64>().unwrap_or(0.0),
-            Err(_) => return Err("Couldnt Get Payed To Capital".to_string()),
+            Ok(val) => val.parse::<Money>().unwrap_or(Money::ZERO),
+            Err(_) => return Err(AppError::not_found("Couldnt Get Payed To Capital")),
         };
 
-        let owed_capital =
-            match con.get::<String, String>(format!("users:{}:owed_capital", db_access_token)) {
-                Ok(val) => val.parse::<f64>().unwrap_or(0.0),
-                Err(_) => return Err("Couldnt Get Owed Capital".to_string()),
-            };
+        let owed_capital = match con
+            .get::<String, String>(format!("users:{}:owed_capital", db_access_token))
+            .await
+        {
+            Ok(val) => val.parse::<Money>().unwrap_or(Money::ZERO),
+            Err(_) => return Err(AppError::not_found("Couldnt Get Owed Capital")),
+        };
 
         Ok(PaymentHistory {
             payed_to_capital,
@@ -46,148 +91,310 @@ impl PaymentRepo {
         })
     }
 
-    pub fn get_user_payments(&self, access_token: String) -> Result<Vec<Payment>, String> {
+    /// pagos de un socio usando directamente su hash de redis (users:{owner_key}:payments:*)
+    async fn get_payments_by_owner(&self, owner_key: &str) -> Result<Vec<Payment>, AppError> {
         get_multiple_models_by_id::<Payment, RedisPayment>(
-            Some(access_token),
             None,
+            Some(owner_key.to_owned()),
             self.pool.clone(),
+            &self.loader,
             "payments".to_owned(),
         )
+        .await
+    }
+
+    /// préstamo, cuota o multa a la que apunta una parte del pago (PayedTo.target)
+    /// None si el destino ya no existe o si el model_key es ambiguo
+    /// el modelo y el nombre del socio se leen con el loader del request, así los abonos que
+    /// apuntan al mismo préstamo/multa no lo vuelven a pedir
+    async fn get_payed_to_target(
+        &self,
+        payed_to: &PayedTo,
+    ) -> Result<Option<PaymentTarget>, AppError> {
+        let loader = self.loader.as_ref();
+        let mut con = self.pool.get().await?;
+        let model_key = &payed_to.model_key;
+
+        match PaymentType::from_string(payed_to.model_type.clone()) {
+            PaymentType::Loan => {
+                let Some(key) = find_single_key(&mut con, &format!("users:*:loans:{}", model_key))
+                    .await?
+                else {
+                    return Ok(None);
+                };
+                let Some(loan) = loader.get_model::<RedisLoan>(&key).await? else {
+                    return Ok(None);
+                };
+                let loans = enrich_with_presenter_names(
+                    vec![loan.to_graphql_type(key.clone())],
+                    vec![key],
+                    loader,
+                )
+                .await;
+                Ok(loans.into_iter().next().map(PaymentTarget::Loan))
+            }
+            PaymentType::Fine => {
+                let Some(key) = find_single_key(&mut con, &format!("users:*:fines:{}", model_key))
+                    .await?
+                else {
+                    return Ok(None);
+                };
+                let Some(fine) = loader.get_model::<RedisFine>(&key).await? else {
+                    return Ok(None);
+                };
+                let fines = enrich_with_presenter_names(
+                    vec![fine.to_graphql_type(key.clone())],
+                    vec![key],
+                    loader,
+                )
+                .await;
+                Ok(fines.into_iter().next().map(PaymentTarget::Fine))
+            }
+            PaymentType::Quota => {
+                let Some(key) = find_quota_key(&mut con, model_key).await? else {
+                    return Ok(None);
+                };
+                Ok(fetch_quota(&mut con, &key).await?.map(PaymentTarget::Quota))
+            }
+            PaymentType::ParsedError => Ok(None),
+        }
     }
 
     /// Obtiene todos los pagos de todos los socios
-    pub fn get_all_payments(&self) -> Result<Vec<Payment>, String> {
+    async fn get_all_payments(&self) -> Result<Vec<Payment>, AppError> {
         // usamos el helper que retorna tanto objetos como keys
         let (payments, keys) =
             crate::repos::graphql::utils::get_multiple_models_by_pattern_with_keys::<
                 Payment,
                 RedisPayment,
-            >("users:*:payments:*".to_string(), self.pool.clone())?;
+            >("users:*:payments:*".to_string(), self.pool.clone(), &self.loader)
+            .await?;
 
         // enriquecemos los pagos con el presented_by_name usando el helper genérico
-        let pool_ref = self.pool.get_ref();
-        let enriched_payments =
-            crate::repos::graphql::utils::enrich_with_presenter_names(payments, keys, pool_ref);
+        let enriched_payments = crate::repos::graphql::utils::enrich_with_presenter_names(
+            payments,
+            keys,
+            &self.loader,
+        )
+        .await;
 
         Ok(enriched_payments)
     }
 
-    pub fn create_payment(
+    #[allow(clippy::too_many_arguments)]
+    async fn create_payment(
         &self,
         access_token: String,
         name: String,
         comprobante_path: String,
-        total_amount: f64,
+        total_amount: Money,
+        currency: Currency,
         ticket_number: String,
         account_number: String,
-        being_payed: Vec<crate::models::PayedToInput>,
-    ) -> Result<String, String> {
+        being_payed: Vec<PayedToInput>,
+    ) -> Result<String, AppError> {
         // for the moment I'll just implement it as for creating a payment without the relation
         // wich the other fields
 
-        let con = &mut self.pool.get().expect("Couldn't connect to pool");
+        let con = &mut self.pool.get().await?;
 
         let db_access_token = hashing_composite_key(&[&access_token]);
 
         // we check how many payments we have
 
-        if let Ok(keys) =
-            con.scan_match::<String, String>(format!("users:{}:payments:*", db_access_token))
+        if let Ok(keys_parsed) =
+            scan_keys(con, &format!("users:{}:payments:*", db_access_token)).await
         {
-            let keys_parsed: Vec<String> = keys.collect();
 
             // for creating the payment and not having collissions
             let payment_hash_key =
                 hashing_composite_key(&[&keys_parsed.len().to_string(), &db_access_token]);
 
-            let con = &mut self.pool.get().expect("Couldn't connect to pool");
+            // convertimos PayedToInput a PayedTo para guardarlo en redis, con el monto ya
+            // convertido a la moneda del préstamo/cuota/multa al que se abona
+            let rates = load_exchange_rates(con).await?;
+            let mut being_payed_output: Vec<crate::models::PayedTo> = Vec::new();
+            for input in being_payed {
+                being_payed_output
+                    .push(allocate_payed_to(con, &rates, currency, input.into()).await?);
+            }
 
-            let date = Utc::now().date_naive().to_string();
+            let payment_key = format!("users:{db_access_token}:payments:{payment_hash_key}");
+
+            // revisamos el índice de boletas antes de guardar, si la misma cuenta + ticket ya
+            // está en otro pago (no rechazado) marcamos a todos como posibles duplicados
+            let ticket_index_key = payment_ticket_index_key(&account_number, &ticket_number);
+            let possible_duplicates = flag_existing_duplicates(con, &ticket_index_key).await;
+
+            let redis_payment = RedisPayment {
+                name,
+                total_amount,
+                currency,
+                ticket_number,
+                date_created: DateTime::now(),
+                comprobante_bucket: comprobante_path, // I fucked up big time with the name of
+                // this shitty, imma kms
+                account_number,
+                comments: None,
+                status: "ON_REVISION".to_owned(),
+                being_payed: being_payed_output,
+                possible_duplicates,
+                receipt_check: None,
+            };
 
-            // convertimos PayedToInput a PayedTo para guardarlo en redis
-            let being_payed_output: Vec<crate::models::PayedTo> =
-                being_payed.into_iter().map(|input| input.into()).collect();
+            let _: () = con
+                .json_set(&payment_key, "$", &redis_payment)
+                .await
+                .map_err(|_| AppError::storage("PAYMENT CREATION: Couldn't Create Payment"))?;
 
             let _: () = con
-                .json_set(
-                    format!("users:{db_access_token}:payments:{payment_hash_key}"),
-                    "$",
-                    &RedisPayment {
-                        name,
-                        total_amount,
-                        ticket_number,
-                        date_created: date,
-                        comprobante_bucket: comprobante_path, // I fucked up big time with the name of
-                        // this shitty, imma kms
-                        account_number,
-                        comments: None,
-                        status: "ON_REVISION".to_owned(),
-                        being_payed: being_payed_output,
-                    },
-                )
-                .expect("PAYMENT CREATION: Couldn't Create Payment");
+                .sadd(&ticket_index_key, &payment_key)
+                .await
+                .map_err(|_| AppError::storage("PAYMENT CREATION: Couldn't Index Payment Ticket"))?;
+
+            // cola en vivo de pagos por revisar (newPaymentSubmitted)
+            let payment = enrich_with_presenter_names(
+                vec![redis_payment.to_graphql_type(payment_key.clone())],
+                vec![payment_key],
+                &self.loader,
+            )
+            .await;
+            publish_event(con, PAYMENT_SUBMITTED_CHANNEL, &payment[0]).await;
+
             return Ok("Payment Created".to_owned());
         }
 
-        Err("PAYMENT CREATION: Couldn't Create Payment".to_owned())
+        Err(AppError::storage("PAYMENT CREATION: Couldn't Create Payment"))
     }
 
-    // This goes in the payment repo, only cause is an utililty endpoint for the Payments
-    pub fn get_all_users_for_affiliates(&self) -> Result<Vec<Affiliate>, String> {
-        let con = &mut self.pool.get().expect("Couldn't connect to pool");
-
-        match con.scan_match::<&str, String>("users:*:affiliate_key") {
-            Ok(keys) => {
-                let mut affiliates: Vec<Affiliate> = Vec::new();
-                let regex = Regex::new(r"(users):(\w+):(affiliate_key)").unwrap();
-
-                for key in keys {
-                    let parsed_key = regex.captures(key.as_str()).unwrap();
-
-                    // Why borrow checker, WHY?!?!?
-                    // The equivalent of cloning
-                    let name_con = &mut self.pool.get().expect("Couldn't connect to pool");
-
-                    let affiliate_con = &mut self.pool.get().expect("Couldn't connect to pool");
-
-                    affiliates.push(Affiliate {
-                        // user db_id
-                        user_id: affiliate_con
-                            .get::<String, String>(format!(
-                                "users:{}:affiliate_key",
-                                parsed_key[2].to_owned()
-                            ))
-                            .unwrap_or("Not Name Found".to_owned()),
-                        name: name_con
-                            .get::<String, String>(format!(
-                                "users:{}:complete_name",
-                                parsed_key[2].to_owned()
-                            ))
-                            .unwrap_or("Not Name Found".to_owned()),
-                    })
+    async fn claim_receipt(
+        &self,
+        access_token: &str,
+        comprobante_path: &str,
+    ) -> Result<ClaimedReceipt, AppError> {
+        payment_link::claim_receipt(&self.pool, access_token, comprobante_path).await
+    }
+
+    async fn link_receipt(&self, ticket_id: &str) -> Result<(), AppError> {
+        payment_link::link_receipt_to_payment(&self.pool, ticket_id)
+            .await
+            .map(|_| ())
+    }
+
+    async fn release_receipt(&self, ticket_id: &str) -> Result<(), AppError> {
+        payment_link::release_receipt(&self.pool, ticket_id).await
+    }
+
+    /// Lista los pagos sospechosos de ser duplicados, agrupados por cuenta + número de boleta
+    /// Solo se toman en cuenta los pagos no rechazados (un rechazado se puede volver a subir)
+    async fn get_possible_duplicate_payments(
+        &self,
+    ) -> Result<Vec<DuplicatePaymentGroup>, AppError> {
+        let con = &mut self.pool.get().await?;
+
+        let index_keys = match scan_keys(con, "payment_tickets:*").await {
+            Ok(keys) => keys,
+            Err(_) => return Err(AppError::storage("Couldn't scan payment tickets")),
+        };
+
+        let mut groups: Vec<DuplicatePaymentGroup> = Vec::new();
+        // varios grupos suelen ser del mismo socio, el loader no repite el nombre
+        let loader = self.loader.as_ref();
+
+        for index_key in index_keys {
+            let payment_keys: Vec<String> = con.smembers(&index_key).await.unwrap_or_default();
+
+            if payment_keys.len() < 2 {
+                continue;
+            }
+
+            let mut payments: Vec<Payment> = Vec::new();
+            let mut keys: Vec<String> = Vec::new();
+
+            for key in payment_keys {
+                let Some(redis_payment) = fetch_redis_payment(con, &key).await else {
+                    continue; // el pago ya no existe, solo quedó en el índice
+                };
+
+                if PaymentStatus::from_string(redis_payment.status.clone())
+                    == PaymentStatus::Rejected
+                {
+                    continue;
                 }
 
-                Ok(affiliates)
+                payments.push(redis_payment.to_graphql_type(key.clone()));
+                keys.push(key);
+            }
+
+            if payments.len() < 2 {
+                continue;
             }
-            Err(_) => Err("Couldn't get users".to_string()),
+
+            let payments =
+                crate::repos::graphql::utils::enrich_with_presenter_names(payments, keys, loader)
+                    .await;
+
+            groups.push(DuplicatePaymentGroup {
+                account_num: payments[0].account_num.clone(),
+                ticket_num: payments[0].ticket_num.clone(),
+                payments,
+            });
         }
+
+        Ok(groups)
+    }
+
+    /// Historial de cambios de estado de un pago, del más viejo al más nuevo
+    async fn get_payment_history(
+        &self,
+        id: String,
+    ) -> Result<Vec<PaymentStatusChange>, AppError> {
+        let mut con = self.pool.get().await?;
+
+        let raw_entries: Vec<String> = con
+            .lrange(payment_history_key(&id), 0, -1)
+            .await
+            .map_err(|_| AppError::storage("Couldn't get payment history"))?;
+
+        // las entradas que no se pueden leer se saltan, el resto del historial sigue sirviendo
+        Ok(raw_entries
+            .iter()
+            .filter_map(|raw_entry| from_str::<RedisPaymentStatusChange>(raw_entry).ok())
+            .map(|entry| entry.to_graphql_type(id.clone()))
+            .collect())
     }
 
     /// Aprueba o rechaza un pago por id, actualizando estado y comentario (si es REJECTED)
-    pub async fn approve_or_reject_payment(
+    /// Cada cambio queda registrado en el historial del pago junto con el directivo que lo hizo
+    async fn approve_or_reject_payment(
         &self,
+        reviewer_access_token: String,
         id: String,
         new_state: String,
         commentary: String,
-    ) -> Result<Payment, String> {
-        let mut con = self.pool.get().map_err(|_| "Couldn't connect to pool")?;
+    ) -> Result<Payment, AppError> {
+        let mut con = self.pool.get().await?;
+
+        // primero identificamos al directivo, sin revisor no hay cambio de estado
+        let (reviewer_id, reviewer_name) =
+            get_reviewer_identity(&mut con, &reviewer_access_token).await?;
+
+        // solo los directivos revisan pagos, si no cualquiera aprobaría los suyos
+        let users = UserRepo {
+            pool: self.pool.clone(),
+            loader: self.loader.clone(),
+        };
+        if !users.is_directive(&reviewer_access_token).await {
+            return Err(AppError::unauthorized(
+                "Solo los directivos pueden aprobar o rechazar pagos",
+            ));
+        }
 
         // Buscamos todas las keys que correspondan al id: users:*:payments:{id}
         let pattern = format!("users:*:payments:{}", id);
 
-        match con.scan_match::<String, String>(pattern.clone()) {
-            Ok(keys) => {
-                let key_vec: Vec<String> = keys.collect();
+        match scan_keys(&mut con, &pattern).await {
+            Ok(key_vec) => {
 
                 if key_vec.is_empty() {
                     // Fallback: try the 'all' key behavior from before
@@ -197,58 +404,64 @@ impl PaymentRepo {
                     // Obtener JSON del pago
                     let raw = con
                         .json_get::<String, &str, redis::Value>(key.clone(), "$")
-                        .map_err(|_| "Error fetching payment")?;
+                        .await
+                        .map_err(|_| AppError::storage("Error fetching payment"))?;
                     let nested = from_redis_value::<String>(&raw)
-                        .map_err(|_| "Error decoding redis value")?;
-                    let mut parsed: Vec<RedisPayment> =
-                        from_str(&nested).map_err(|_| "Error deserializing payment")?;
+                        .map_err(|_| AppError::storage("Error decoding redis value"))?;
+                    let mut parsed: Vec<RedisPayment> = from_str(&nested)
+                        .map_err(|_| AppError::storage("Error deserializing payment"))?;
                     let mut redis_payment = parsed
                         .pop()
-                        .ok_or_else(|| "Payment not found".to_string())?;
+                        .ok_or_else(|| AppError::not_found("Payment not found"))?;
 
                     // Validar estado actual
                     let current_status = PaymentStatus::from_string(redis_payment.status.clone());
                     if current_status == PaymentStatus::Accepted
                         || current_status == PaymentStatus::Rejected
                     {
-                        return Err("El pago ya está finalizado".to_string());
+                        return Err(AppError::conflict("El pago ya está finalizado"));
                     }
 
                     // Validar nuevo estado
-                    let new_status = PaymentStatus::from_string(new_state.clone());
-                    match new_status {
-                        PaymentStatus::Accepted => {}
-                        PaymentStatus::Rejected => {
-                            if commentary.trim().is_empty() {
-                                return Err(
-                                    "Se requiere comentario al rechazar el pago".to_string()
-                                );
-                            }
-                        }
-                        _ => {
-                            return Err("Estado inválido, debe ser ACCEPTED o REJECTED".to_string())
-                        }
-                    }
+                    let new_status = validate_new_status(&new_state, &commentary)?;
 
                     // Actualizar y persistir
+                    let previous_status = redis_payment.status.clone();
                     redis_payment.status = new_status.as_str().to_owned();
                     if new_status == PaymentStatus::Rejected {
-                        redis_payment.comments = Some(commentary);
+                        redis_payment.comments = Some(commentary.clone());
                     }
 
                     con.json_set::<String, &str, _, ()>(key.clone(), "$", &redis_payment)
-                        .map_err(|_| "Error updating payment")?;
+                        .await
+                        .map_err(|_| AppError::storage("Error updating payment"))?;
+
+                    if new_status == PaymentStatus::Rejected {
+                        self.clear_resolved_duplicates(&mut con, &redis_payment).await;
+                    }
+
+                    let change = RedisPaymentStatusChange {
+                        previous_status,
+                        new_status: new_status.as_str().to_owned(),
+                        reviewer_id,
+                        reviewer_name,
+                        changed_at: DateTime::now(),
+                        comment: non_empty(commentary),
+                    };
+                    record_payment_status_change(&mut con, &id, &change).await?;
+                    publish_payment_status_change(&mut con, &key, &id, &change).await;
 
                     // Mapear a GraphQL
                     let payment = redis_payment.to_graphql_type(key);
                     Ok(payment)
                 } else {
                     // Tenemos una o más keys; actualizamos todas para mantenerlas sincronizadas
-                    let mut con2 = self.pool.get().map_err(|_| "Couldn't connect to pool")?;
+                    let mut con2 = self.pool.get().await?;
 
                     // Primero validamos que ninguna copia ya esté finalizada
                     for key in &key_vec {
-                        let raw_res = con2.json_get::<String, &str, redis::Value>(key.clone(), "$");
+                        let raw_res =
+                            con2.json_get::<String, &str, redis::Value>(key.clone(), "$").await;
                         let raw = match raw_res {
                             Ok(v) => v,
                             Err(_) => continue, // skip invalid
@@ -271,36 +484,28 @@ impl PaymentRepo {
                             if current_status == PaymentStatus::Accepted
                                 || current_status == PaymentStatus::Rejected
                             {
-                                return Err("El pago ya está finalizado".to_string());
+                                return Err(AppError::conflict("El pago ya está finalizado"));
                             }
                         }
                     }
 
                     // Validar nuevo estado
-                    let new_status = PaymentStatus::from_string(new_state.clone());
-                    match new_status {
-                        PaymentStatus::Accepted => {}
-                        PaymentStatus::Rejected => {
-                            if commentary.trim().is_empty() {
-                                return Err(
-                                    "Se requiere comentario al rechazar el pago".to_string()
-                                );
-                            }
-                        }
-                        _ => {
-                            return Err("Estado inválido, debe ser ACCEPTED o REJECTED".to_string())
-                        }
-                    }
+                    let new_status = validate_new_status(&new_state, &commentary)?;
 
                     // Actualizamos todas las copias y guardamos la primera mapeada para devolverla
                     let mut mapped_payment: Option<Payment> = None;
+                    let mut mapped_key: Option<String> = None;
+                    let mut previous_status: Option<String> = None;
+                    let mut rejected_payment: Option<RedisPayment> = None;
                     for key in key_vec {
                         // volver a leer y parsear (para obtener el objeto)
-                        let raw =
-                            match con2.json_get::<String, &str, redis::Value>(key.clone(), "$") {
-                                Ok(v) => v,
-                                Err(_) => continue,
-                            };
+                        let raw = match con2
+                            .json_get::<String, &str, redis::Value>(key.clone(), "$")
+                            .await
+                        {
+                            Ok(v) => v,
+                            Err(_) => continue,
+                        };
                         let nested = match from_redis_value::<String>(&raw) {
                             Ok(s) => s,
                             Err(_) => continue,
@@ -312,28 +517,315 @@ impl PaymentRepo {
                                 Err(_) => continue,
                             },
                         };
-                        if parsed.is_empty() {
+                        let Some(mut redis_payment) = parsed.pop() else {
                             continue;
+                        };
+                        if previous_status.is_none() {
+                            previous_status = Some(redis_payment.status.clone());
                         }
-
-                        let mut redis_payment = parsed.pop().unwrap();
                         redis_payment.status = new_status.as_str().to_owned();
                         if new_status == PaymentStatus::Rejected {
                             redis_payment.comments = Some(commentary.clone());
                         }
 
                         con2.json_set::<String, &str, _, ()>(key.clone(), "$", &redis_payment)
-                            .map_err(|_| "Error updating payment")?;
+                            .await
+                            .map_err(|_| AppError::storage("Error updating payment"))?;
 
                         if mapped_payment.is_none() {
                             mapped_payment = Some(redis_payment.to_graphql_type(key.clone()));
+                            mapped_key = Some(key.clone());
+                            if new_status == PaymentStatus::Rejected {
+                                rejected_payment = Some(redis_payment);
+                            }
                         }
                     }
 
-                    mapped_payment.ok_or_else(|| "Payment not found".to_string())
+                    if let Some(rejected_payment) = rejected_payment {
+                        self.clear_resolved_duplicates(&mut con2, &rejected_payment).await;
+                    }
+
+                    let mapped_payment =
+                        mapped_payment.ok_or_else(|| AppError::not_found("Payment not found"))?;
+
+                    // las copias comparten id, así que el historial se registra una sola vez
+                    let change = RedisPaymentStatusChange {
+                        previous_status: previous_status.unwrap_or_default(),
+                        new_status: new_status.as_str().to_owned(),
+                        reviewer_id,
+                        reviewer_name,
+                        changed_at: DateTime::now(),
+                        comment: non_empty(commentary),
+                    };
+                    record_payment_status_change(&mut con2, &id, &change).await?;
+                    if let Some(key) = mapped_key {
+                        publish_payment_status_change(&mut con2, &key, &id, &change).await;
+                    }
+
+                    Ok(mapped_payment)
                 }
             }
-            Err(_) => Err("Couldn't scan for payment keys".to_string()),
+            Err(_) => Err(AppError::storage("Couldn't scan for payment keys")),
+        }
+    }
+}
+
+/// key del índice de boletas: payment_tickets:{hash(cuenta, ticket)}
+/// guarda un set con las keys de los pagos que usaron esa boleta
+pub fn payment_ticket_index_key(account_number: &str, ticket_number: &str) -> String {
+    // normalizamos pa que "abc-1 " y "ABC-1" cuenten como la misma boleta
+    let account = account_number.trim().to_uppercase();
+    let ticket = ticket_number.trim().to_uppercase();
+
+    // el ":" evita que ("12", "345") y ("123", "45") den el mismo hash
+    format!(
+        "payment_tickets:{}",
+        hashing_composite_key(&[&account, &":".to_owned(), &ticket])
+    )
+}
+
+/// lee un pago de redis aceptando tanto el formato array ($) como objeto individual
+pub(crate) async fn fetch_redis_payment(
+    con: &mut RedisConnection,
+    key: &str,
+) -> Option<RedisPayment> {
+    let raw = con.json_get::<&str, &str, redis::Value>(key, "$").await.ok()?;
+    let nested = from_redis_value::<String>(&raw).ok()?;
+
+    match from_str::<Vec<RedisPayment>>(&nested) {
+        Ok(mut parsed) => parsed.pop(),
+        Err(_) => from_str::<RedisPayment>(&nested).ok(),
+    }
+}
+
+/// el pago del socio (users:{owner_key}:payments:*) que usa el comprobante guardado en
+/// comprobante_key, con su key de redis
+pub(crate) async fn find_payment_by_receipt(
+    con: &mut RedisConnection,
+    owner_key: &str,
+    comprobante_key: &str,
+) -> Result<Option<(String, RedisPayment)>, AppError> {
+    let keys = scan_keys(con, &format!("users:{}:payments:*", owner_key)).await?;
+
+    for key in keys {
+        if let Some(payment) = fetch_redis_payment(con, &key).await
+            && payment.comprobante_bucket == comprobante_key
+        {
+            return Ok(Some((key, payment)));
+        }
+    }
+
+    Ok(None)
+}
+
+/// marca como posibles duplicados los pagos (no rechazados) que ya están en el índice
+/// retorna true si encontró alguno, para marcar también el pago nuevo
+async fn flag_existing_duplicates(con: &mut RedisConnection, ticket_index_key: &str) -> bool {
+    let existing_keys: Vec<String> = con.smembers(ticket_index_key).await.unwrap_or_default();
+    let mut found = false;
+
+    for key in existing_keys {
+        let Some(redis_payment) = fetch_redis_payment(con, &key).await else {
+            continue;
+        };
+
+        if PaymentStatus::from_string(redis_payment.status) == PaymentStatus::Rejected {
+            continue;
+        }
+
+        found = true;
+        let _: redis::RedisResult<()> = con.json_set(&key, "$.possible_duplicates", &true).await;
+    }
+
+    found
+}
+
+/// deja possible_duplicates al día para los pagos de una boleta: con dos o más pagos no
+/// rechazados todos quedan marcados, si queda uno solo (ej: se rechazó el otro) se le quita la
+/// marca. retorna las keys que cambiaron
+pub(crate) async fn refresh_duplicate_flags(
+    con: &mut RedisConnection,
+    ticket_index_key: &str,
+) -> Vec<String> {
+    let payment_keys: Vec<String> = con.smembers(ticket_index_key).await.unwrap_or_default();
+
+    let mut active: Vec<(String, bool)> = Vec::new();
+    for key in payment_keys {
+        let Some(redis_payment) = fetch_redis_payment(con, &key).await else {
+            continue;
+        };
+        if PaymentStatus::from_string(redis_payment.status) != PaymentStatus::Rejected {
+            active.push((key, redis_payment.possible_duplicates));
+        }
+    }
+
+    let is_duplicate = active.len() > 1;
+    let mut changed = Vec::new();
+    for (key, flagged) in active {
+        if flagged == is_duplicate {
+            continue;
+        }
+        let updated: redis::RedisResult<()> =
+            con.json_set(&key, "$.possible_duplicates", &is_duplicate).await;
+        if updated.is_ok() {
+            changed.push(key);
+        }
+    }
+
+    changed
+}
+
+/// llena la moneda y el monto convertido de lo que se está pagando
+/// los préstamos y sus cuotas van en la moneda del préstamo, cuotas de afiliado y multas en la
+/// moneda base
+async fn allocate_payed_to(
+    con: &mut RedisConnection,
+    rates: &ExchangeRates,
+    payment_currency: Currency,
+    mut payed_to: crate::models::PayedTo,
+) -> Result<crate::models::PayedTo, AppError> {
+    let allocated_currency = match PaymentType::from_string(payed_to.model_type.clone()) {
+        PaymentType::Loan => get_loan_currency(con, &payed_to.model_key).await,
+        PaymentType::Quota => {
+            let loan_id = match find_quota_key(con, &payed_to.model_key).await? {
+                Some(key) => fetch_quota(con, &key).await?.and_then(|quota| quota.loan_id),
+                None => None,
+            };
+            match loan_id {
+                Some(loan_id) => get_loan_currency(con, &loan_id).await,
+                None => base_currency(),
+            }
         }
+        _ => base_currency(),
+    };
+
+    payed_to.allocated_amount =
+        Some(rates.convert(payed_to.amount, payment_currency, allocated_currency)?);
+    payed_to.allocated_currency = allocated_currency;
+
+    Ok(payed_to)
+}
+
+/// key de la cuota que se está pagando, pueden ser de préstamo o de afiliado y el model_key es
+/// la fecha. None si no hay ninguna o hay varias
+async fn find_quota_key(
+    con: &mut RedisConnection,
+    model_key: &str,
+) -> Result<Option<String>, AppError> {
+    let mut keys: Vec<String> = Vec::new();
+    for pattern in [
+        format!("users:*:loans:*:quotas:{}", model_key),
+        format!("users:*:quotas_afiliado:{}", model_key),
+    ] {
+        keys.extend(
+            scan_keys(con, &pattern)
+                .await
+                .map_err(|_| AppError::storage("Couldn't scan quota keys"))?,
+        );
     }
+    if keys.len() != 1 {
+        return Ok(None);
+    }
+    Ok(keys.pop())
+}
+
+/// moneda de un préstamo por su id, si no se encuentra asumimos la moneda base
+async fn get_loan_currency(con: &mut RedisConnection, loan_id: &str) -> Currency {
+    let key = scan_keys(con, &format!("users:*:loans:{}", loan_id))
+        .await
+        .ok()
+        .and_then(|keys| keys.into_iter().next());
+    let Some(key) = key else {
+        return base_currency();
+    };
+
+    con.json_get::<String, &str, String>(key, "$.currency")
+        .await
+        .ok()
+        .and_then(|raw| from_str::<Vec<Currency>>(&raw).ok())
+        .and_then(|mut currencies| currencies.pop())
+        .unwrap_or_else(base_currency)
+}
+
+/// key de la lista con el historial de estados de un pago
+/// vive fuera de users:* para que los scans de pagos (users:*:payments:*) no la agarren
+pub fn payment_history_key(payment_id: &str) -> String {
+    format!("payment_history:{}", payment_id)
+}
+
+/// agrega un cambio de estado al final del historial del pago
+async fn record_payment_status_change(
+    con: &mut RedisConnection,
+    payment_id: &str,
+    change: &RedisPaymentStatusChange,
+) -> Result<(), AppError> {
+    let entry = serde_json::to_string(change)
+        .map_err(|_| AppError::storage("Error serializing payment history"))?;
+
+    con.rpush::<String, String, ()>(payment_history_key(payment_id), entry)
+        .await
+        .map_err(|_| AppError::storage("Error saving payment history"))
+}
+
+/// avisa al dueño del pago (paymentStatusChanged) que su pago fue aceptado o rechazado
+async fn publish_payment_status_change(
+    con: &mut RedisConnection,
+    payment_key: &str,
+    payment_id: &str,
+    change: &RedisPaymentStatusChange,
+) {
+    let event = PaymentStatusChangedEvent {
+        payment_id: payment_id.to_owned(),
+        change: change.to_graphql_type(payment_key.to_owned()),
+        owner_key: extract_user_hash_from_key(payment_key).unwrap_or_default(),
+    };
+    publish_event(con, PAYMENT_STATUS_CHANGED_CHANNEL, &event).await;
+}
+
+/// busca el affiliate_key y nombre del directivo que está revisando el pago
+async fn get_reviewer_identity(
+    con: &mut RedisConnection,
+    access_token: &str,
+) -> Result<(String, String), AppError> {
+    let db_access_token = hashing_composite_key(&[&access_token.to_owned()]);
+
+    let reviewer_name = con
+        .get::<String, String>(format!("users:{}:complete_name", db_access_token))
+        .await
+        .map_err(|_| AppError::unauthorized("Revisor no encontrado"))?;
+
+    let reviewer_id = con
+        .get::<String, String>(format!("users:{}:affiliate_key", db_access_token))
+        .await
+        .unwrap_or_default();
+
+    Ok((reviewer_id, reviewer_name))
+}
+
+/// el nuevo estado de un pago revisado: solo ACCEPTED o REJECTED, y el rechazo lleva comentario
+pub(crate) fn validate_new_status(
+    new_state: &str,
+    commentary: &str,
+) -> Result<PaymentStatus, AppError> {
+    let new_status = PaymentStatus::from_string(new_state.to_owned());
+    match new_status {
+        PaymentStatus::Accepted => Ok(new_status),
+        PaymentStatus::Rejected => {
+            if commentary.trim().is_empty() {
+                return Err(AppError::validation(
+                    "Se requiere comentario al rechazar el pago",
+                    &["commentary"],
+                ));
+            }
+            Ok(new_status)
+        }
+        _ => Err(AppError::validation(
+            "Estado inválido, debe ser ACCEPTED o REJECTED",
+            &["newState"],
+        )),
+    }
+}
+
+pub(crate) fn non_empty(text: String) -> Option<String> {
+    if text.trim().is_empty() { None } else { Some(text) }
 }
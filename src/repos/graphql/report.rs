@@ -0,0 +1,214 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::Datelike;
+
+use crate::{
+    endpoints::handlers::configs::schema::GeneralContext,
+    errors::AppError,
+    models::{
+        currency::{Currency, base_currency},
+        dates::{Date, DateTime, today},
+        graphql::{
+            DelinquencyBucket, FinancialReport, FineStatus, FinesReport, LoanPortfolio,
+            MonthlyCollection, PaymentStatus, PaymentType,
+        },
+        money::Money,
+    },
+};
+
+// reportes de toda la cooperativa para los directivos, se calculan acá con los repos del
+// contexto (igual que el estado de cuenta) en vez de que el frontend sume get_all_payments
+
+// rangos de atraso de las cuotas: 1-30, 31-60, 61-90 y más de 90 días
+const DELINQUENCY_BUCKETS: [(i32, Option<i32>); 4] =
+    [(1, Some(30)), (31, Some(60)), (61, Some(90)), (91, None)];
+
+pub struct ReportRepo {
+    pub context: GeneralContext,
+}
+
+impl ReportRepo {
+    /// reporte financiero entre from y to (inclusivos, cualquiera puede ir vacío)
+    /// la cartera y la morosidad se calculan al corte: to, o hoy si no viene o es futuro
+    pub async fn get_financial_report(
+        &self,
+        access_token: String,
+        from: Option<Date>,
+        to: Option<Date>,
+    ) -> Result<FinancialReport, AppError> {
+        if !self.context.user_repo().is_directive(&access_token).await {
+            return Err(AppError::unauthorized(
+                "Solo los directivos pueden ver los reportes",
+            ));
+        }
+        if let (Some(from), Some(to)) = (from, to)
+            && from > to
+        {
+            return Err(AppError::validation(
+                "La fecha inicial no puede ser después de la final",
+                &["from", "to"],
+            ));
+        }
+
+        let rates = self.context.exchange_rates().await?;
+        let cutoff = to.map_or_else(today, |to| to.min(today()));
+        let in_period =
+            |date: Date| from.is_none_or(|from| date >= from) && to.is_none_or(|to| date <= to);
+
+        // lo cobrado por mes, BTreeMap para que salgan en orden
+        let mut months: BTreeMap<String, MonthlyCollection> = BTreeMap::new();
+        let mut fines_collected = Money::ZERO;
+        for payment in self.context.payment_repo().get_all_payments().await? {
+            let date = payment.payment_date.local_date();
+            if payment.state != PaymentStatus::Accepted || !in_period(date) {
+                continue;
+            }
+
+            let month = format!("{:04}-{:02}", date.naive().year(), date.naive().month());
+            let collection = months
+                .entry(month.clone())
+                .or_insert_with(|| MonthlyCollection {
+                    month,
+                    loans: Money::ZERO,
+                    quotas: Money::ZERO,
+                    fines: Money::ZERO,
+                    total: Money::ZERO,
+                    payments: 0,
+                });
+            collection.total += rates.to_base(payment.total_amount, payment.currency)?;
+            collection.payments += 1;
+
+            for payed_to in &payment.being_payed {
+                let amount = rates.to_base(payed_to.amount, payment.currency)?;
+                match PaymentType::from_string(payed_to.model_type.clone()) {
+                    PaymentType::Loan => collection.loans += amount,
+                    PaymentType::Quota => collection.quotas += amount,
+                    PaymentType::Fine => {
+                        collection.fines += amount;
+                        fines_collected += amount;
+                    }
+                    PaymentType::ParsedError => {}
+                }
+            }
+        }
+
+        // los préstamos viejos no tienen fecha, cuentan siempre
+        let loans: Vec<_> = self
+            .context
+            .loan_repo()
+            .get_all_loans()
+            .await?
+            .into_iter()
+            .filter(|loan| {
+                loan.created_at
+                    .is_none_or(|created_at| created_at.local_date() <= cutoff)
+            })
+            .collect();
+        let mut loan_portfolio = LoanPortfolio {
+            loans_with_debt: 0,
+            total_lent: Money::ZERO,
+            total_payed: Money::ZERO,
+            outstanding: Money::ZERO,
+        };
+        for loan in loans.iter().filter(|loan| loan.debt > Money::ZERO) {
+            loan_portfolio.loans_with_debt += 1;
+            loan_portfolio.total_lent += rates.to_base(loan.total, loan.currency)?;
+            loan_portfolio.total_payed += rates.to_base(loan.payed, loan.currency)?;
+            loan_portfolio.outstanding += rates.to_base(loan.debt, loan.currency)?;
+        }
+        let loan_currencies: HashMap<&str, Currency> = loans
+            .iter()
+            .map(|loan| (loan.id.as_str(), loan.currency))
+            .collect();
+
+        let mut delinquency: Vec<DelinquencyBucket> = DELINQUENCY_BUCKETS
+            .iter()
+            .map(|&(min_days, max_days)| DelinquencyBucket {
+                min_days,
+                max_days,
+                quotas: 0,
+                amount: Money::ZERO,
+            })
+            .collect();
+        let mut fines = FinesReport {
+            issued_count: 0,
+            issued_amount: Money::ZERO,
+            unpaid_amount: Money::ZERO,
+            collected: fines_collected,
+        };
+
+        // cuotas y multas no tienen un get_all, se van a traer socio por socio
+        let users = self.context.user_repo();
+        let affiliates = users.get_all_users_for_affiliates().await?;
+        for affiliate in &affiliates {
+            let Ok(member) = users
+                .get_member_by_affiliate_key(affiliate.user_id.clone())
+                .await
+            else {
+                continue;
+            };
+
+            let quotas = self
+                .context
+                .quota_repo()
+                .get_quotas_by_owner(&member.owner_key)
+                .await?;
+            for quota in quotas.iter().filter(|quota| quota.payed != Some(true)) {
+                let Some(exp_date) = quota.exp_date else {
+                    continue;
+                };
+                let days_late = (cutoff.naive() - exp_date.naive()).num_days();
+                let remaining = quota.amount - quota.monto_pagado.unwrap_or(Money::ZERO);
+                if days_late < 1 || remaining <= Money::ZERO {
+                    continue;
+                }
+
+                let currency = quota
+                    .loan_id
+                    .as_deref()
+                    .and_then(|loan_id| loan_currencies.get(loan_id).copied())
+                    .unwrap_or_else(base_currency);
+                let Some(bucket) = delinquency.iter_mut().find(|bucket| {
+                    days_late >= bucket.min_days as i64
+                        && bucket.max_days.is_none_or(|max| days_late <= max as i64)
+                }) else {
+                    continue;
+                };
+                bucket.quotas += 1;
+                bucket.amount += rates.to_base(remaining, currency)?;
+            }
+
+            // las multas siempre van en la moneda base, igual que los préstamos las viejas no
+            // tienen fecha y cuentan siempre
+            for fine in self
+                .context
+                .fine_repo()
+                .get_fines_by_owner(&member.owner_key)
+                .await?
+            {
+                let issued_on = fine.created_at.map(|created_at| created_at.local_date());
+                if issued_on.is_none_or(in_period) {
+                    fines.issued_count += 1;
+                    fines.issued_amount += fine.amount;
+                }
+                if fine.status == FineStatus::Unpaid
+                    && issued_on.is_none_or(|issued_on| issued_on <= cutoff)
+                {
+                    fines.unpaid_amount += fine.amount;
+                }
+            }
+        }
+
+        Ok(FinancialReport {
+            from,
+            to,
+            generated_at: DateTime::now(),
+            currency: base_currency(),
+            member_count: affiliates.len() as i32,
+            collected: months.into_values().collect(),
+            loan_portfolio,
+            delinquency,
+            fines,
+        })
+    }
+}
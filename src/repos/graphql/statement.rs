@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+
+use crate::{
+    endpoints::handlers::configs::schema::GeneralContext,
+    errors::AppError,
+    models::{
+        currency::{Currency, base_currency},
+        dates::{Date, DateTime},
+        graphql::{FineStatus, Member, PaymentStatus, PaymentType, Statement, StatementSummary},
+        money::Money,
+    },
+};
+
+// estado de cuenta de un socio: junta pagos, préstamos, cuotas y multas de los repos del
+// contexto para un período, así funciona igual con redis que con el MemoryStore
+// el PDF y el CSV se arman con lo mismo en repos::file::statement
+
+pub struct StatementRepo {
+    pub context: GeneralContext,
+}
+
+impl StatementRepo {
+    /// estado de cuenta del dueño del access_token, o del socio affiliate_key si lo pide un
+    /// directivo. from y to son inclusivos y cualquiera puede ir vacío
+    pub async fn get_statement(
+        &self,
+        access_token: String,
+        affiliate_key: Option<String>,
+        from: Option<Date>,
+        to: Option<Date>,
+    ) -> Result<Statement, AppError> {
+        if let (Some(from), Some(to)) = (from, to)
+            && from > to
+        {
+            return Err(AppError::validation(
+                "La fecha inicial no puede ser después de la final",
+                &["from", "to"],
+            ));
+        }
+
+        let member = self.resolve_member(access_token, affiliate_key).await?;
+        let rates = self.context.exchange_rates().await?;
+        let in_period =
+            |date: Date| from.is_none_or(|from| date >= from) && to.is_none_or(|to| date <= to);
+
+        let mut payments = self
+            .context
+            .payment_repo()
+            .get_payments_by_owner(&member.owner_key)
+            .await?;
+        payments.retain(|payment| in_period(payment.payment_date.local_date()));
+        payments.sort_by_key(|payment| payment.payment_date);
+
+        // los préstamos viejos no tienen fecha, mejor que salgan a que se pierda una deuda
+        let mut loans = self
+            .context
+            .loan_repo()
+            .get_loans_by_owner(&member.owner_key)
+            .await?;
+        loans.retain(|loan| {
+            loan.created_at
+                .is_none_or(|created_at| to.is_none_or(|to| created_at.local_date() <= to))
+        });
+        loans.sort_by_key(|loan| loan.created_at);
+
+        let mut quotas = self
+            .context
+            .quota_repo()
+            .get_quotas_by_owner(&member.owner_key)
+            .await?;
+        quotas.retain(|quota| quota.exp_date.is_none_or(in_period));
+        quotas.sort_by_key(|quota| quota.exp_date);
+
+        let fines = self
+            .context
+            .fine_repo()
+            .get_fines_by_owner(&member.owner_key)
+            .await?;
+
+        let mut summary = StatementSummary {
+            total_paid: Money::ZERO,
+            paid_to_quotas: Money::ZERO,
+            paid_to_loans: Money::ZERO,
+            paid_to_fines: Money::ZERO,
+            pending_review: Money::ZERO,
+            quotas_pending: Money::ZERO,
+            loan_debt: Money::ZERO,
+            unpaid_fines: Money::ZERO,
+        };
+
+        for payment in &payments {
+            let total = rates.to_base(payment.total_amount, payment.currency)?;
+            match payment.state {
+                PaymentStatus::Accepted => summary.total_paid += total,
+                PaymentStatus::OnRevision => {
+                    summary.pending_review += total;
+                    continue;
+                }
+                _ => continue,
+            }
+
+            for payed_to in &payment.being_payed {
+                let amount = rates.to_base(payed_to.amount, payment.currency)?;
+                match PaymentType::from_string(payed_to.model_type.clone()) {
+                    PaymentType::Quota => summary.paid_to_quotas += amount,
+                    PaymentType::Loan => summary.paid_to_loans += amount,
+                    PaymentType::Fine => summary.paid_to_fines += amount,
+                    PaymentType::ParsedError => {}
+                }
+            }
+        }
+
+        // las cuotas de préstamo van en la moneda del préstamo, las de afiliado en la base
+        let loan_currencies: HashMap<&str, Currency> = loans
+            .iter()
+            .map(|loan| (loan.id.as_str(), loan.currency))
+            .collect();
+        for quota in quotas.iter().filter(|quota| quota.payed != Some(true)) {
+            let remaining = quota.amount - quota.monto_pagado.unwrap_or(Money::ZERO);
+            if remaining <= Money::ZERO {
+                continue;
+            }
+            let currency = quota
+                .loan_id
+                .as_deref()
+                .and_then(|loan_id| loan_currencies.get(loan_id).copied())
+                .unwrap_or_else(base_currency);
+            summary.quotas_pending += rates.to_base(remaining, currency)?;
+        }
+
+        for loan in &loans {
+            summary.loan_debt += rates.to_base(loan.debt, loan.currency)?;
+        }
+
+        // las multas siempre van en la moneda base
+        summary.unpaid_fines = fines
+            .iter()
+            .filter(|fine| fine.status == FineStatus::Unpaid)
+            .map(|fine| fine.amount)
+            .sum();
+
+        Ok(Statement {
+            affiliate_key: member.affiliate_key,
+            member_name: member.name,
+            from,
+            to,
+            generated_at: DateTime::now(),
+            currency: base_currency(),
+            summary,
+            payments,
+            loans,
+            quotas,
+            fines,
+        })
+    }
+
+    /// cada socio ve el suyo, los directivos el de cualquiera
+    async fn resolve_member(
+        &self,
+        access_token: String,
+        affiliate_key: Option<String>,
+    ) -> Result<Member, AppError> {
+        let users = self.context.user_repo();
+        let me = users
+            .get_member_by_access_token(access_token.clone())
+            .await
+            .map_err(|_| AppError::unauthorized("Credenciales inválidas"))?;
+
+        match affiliate_key {
+            Some(affiliate_key) if affiliate_key != me.affiliate_key => {
+                if !users.is_directive(&access_token).await {
+                    return Err(AppError::unauthorized(
+                        "Solo los directivos pueden ver el estado de cuenta de otro socio",
+                    ));
+                }
+                users.get_member_by_affiliate_key(affiliate_key).await
+            }
+            _ => Ok(me),
+        }
+    }
+}
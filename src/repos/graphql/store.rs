@@ -0,0 +1,346 @@
+use async_trait::async_trait;
+use chrono::Datelike;
+
+use crate::errors::AppError;
+use crate::models::currency::Currency;
+use crate::models::dates::today;
+use crate::models::graphql::{
+    Affiliate, Attachment, AttachmentOwnerType, AttachmentStatus, BaseCurrencyReport,
+    DuplicatePaymentGroup, ExchangeRate, Fine, FineStatus, Loan, Member, Payment, PaymentHistory,
+    PaymentStatusChange, PaymentTarget, Quota, UsersWithFines,
+};
+use crate::models::money::Money;
+use crate::models::redis::Attachment as RedisAttachment;
+use crate::models::{PayedTo, PayedToInput};
+use crate::repos::auth::utils::hashing_composite_key;
+use crate::repos::file::payment_link::ClaimedReceipt;
+
+// acceso a datos por dominio, GeneralContext decide qué backend usar:
+// los *Repo de cada módulo (redis) o MemoryStore (memory.rs, para tests sin redis)
+// los métodos con implementación acá solo traducen el access_token al hash del socio o
+// arman algo encima de otro método, así cada backend implementa una sola vez la lógica
+
+#[async_trait]
+pub trait PaymentStore: Send + Sync {
+    /// capital pagado y adeudado del dueño del access_token
+    async fn get_user_history(&self, access_token: String) -> Result<PaymentHistory, AppError>;
+
+    async fn get_user_payments(&self, access_token: String) -> Result<Vec<Payment>, AppError> {
+        self.get_payments_by_owner(&hashing_composite_key(&[&access_token])).await
+    }
+
+    /// pagos de un socio usando directamente su hash (users:{owner_key}:payments:*)
+    async fn get_payments_by_owner(&self, owner_key: &str) -> Result<Vec<Payment>, AppError>;
+
+    /// préstamo, cuota o multa a la que apunta una parte del pago (PayedTo.target)
+    /// None si el destino ya no existe o si el model_key es ambiguo
+    async fn get_payed_to_target(
+        &self,
+        payed_to: &PayedTo,
+    ) -> Result<Option<PaymentTarget>, AppError>;
+
+    /// todos los pagos de todos los socios, con el nombre de quien lo presentó
+    async fn get_all_payments(&self) -> Result<Vec<Payment>, AppError>;
+
+    #[allow(clippy::too_many_arguments)]
+    async fn create_payment(
+        &self,
+        access_token: String,
+        name: String,
+        comprobante_path: String,
+        total_amount: Money,
+        currency: Currency,
+        ticket_number: String,
+        account_number: String,
+        being_payed: Vec<PayedToInput>,
+    ) -> Result<String, AppError>;
+
+    /// revisa que comprobante_path sea un comprobante que subió el dueño del access_token y que
+    /// no esté en otro pago, y lo aparta para que otro pago no lo tome mientras se crea este.
+    /// lo que regresa es lo que se guarda en el pago
+    async fn claim_receipt(
+        &self,
+        access_token: &str,
+        comprobante_path: &str,
+    ) -> Result<ClaimedReceipt, AppError>;
+
+    /// el comprobante ya quedó en un pago (después de create_payment), si tiene lectura del OCR
+    /// se compara con el pago
+    async fn link_receipt(&self, ticket_id: &str) -> Result<(), AppError>;
+
+    /// suelta el comprobante apartado por claim_receipt cuando el pago no se pudo crear
+    async fn release_receipt(&self, ticket_id: &str) -> Result<(), AppError>;
+
+    /// pagos no rechazados que comparten cuenta + número de boleta
+    async fn get_possible_duplicate_payments(
+        &self,
+    ) -> Result<Vec<DuplicatePaymentGroup>, AppError>;
+
+    /// cambios de estado de un pago, del más viejo al más nuevo
+    async fn get_payment_history(&self, id: String)
+    -> Result<Vec<PaymentStatusChange>, AppError>;
+
+    /// un directivo acepta o rechaza un pago, queda registrado en el historial
+    async fn approve_or_reject_payment(
+        &self,
+        reviewer_access_token: String,
+        id: String,
+        new_state: String,
+        commentary: String,
+    ) -> Result<Payment, AppError>;
+}
+
+#[async_trait]
+pub trait LoanStore: Send + Sync {
+    async fn get_user_loans(&self, access_token: String) -> Result<Vec<Loan>, AppError> {
+        self.get_loans_by_owner(&hashing_composite_key(&[&access_token])).await
+    }
+
+    /// préstamos de un socio usando directamente su hash (users:{owner_key}:loans:*)
+    async fn get_loans_by_owner(&self, owner_key: &str) -> Result<Vec<Loan>, AppError>;
+
+    /// todos los préstamos de todos los socios con el nombre del solicitante
+    async fn get_all_loans(&self) -> Result<Vec<Loan>, AppError>;
+
+    async fn create_loan(
+        &self,
+        affiliate_key: String,
+        total_quota: i32,
+        base_needed_payment: Money,
+        currency: Currency,
+        interest_rate: f64,
+        reason: String,
+    ) -> Result<String, AppError>;
+}
+
+#[async_trait]
+pub trait FineStore: Send + Sync {
+    /// multas del socio, ojo: recibe el affiliate_key aunque el argumento se llame access_token
+    async fn get_user_fines(&self, access_token: String) -> Result<Vec<Fine>, AppError>;
+
+    /// multas de un socio usando directamente su hash (users:{owner_key}:fines:*)
+    async fn get_fines_by_owner(&self, owner_key: &str) -> Result<Vec<Fine>, AppError>;
+
+    async fn create_fine(
+        &self,
+        affiliate_key: String,
+        amount: Money,
+        motive: String,
+    ) -> Result<String, AppError>;
+
+    /// fine_key es el id de la multa, lo que no se manda se queda igual
+    async fn edit_fine(
+        &self,
+        fine_key: String,
+        new_amount: Option<Money>,
+        new_motive: Option<String>,
+        new_status: Option<FineStatus>,
+    ) -> Result<String, AppError>;
+
+    /// socios que tienen multas, con su affiliate_key y nombre
+    async fn get_users_with_there_fines(&self) -> Result<Vec<UsersWithFines>, AppError>;
+}
+
+// el identifier de las cuotas de afiliado lleva el mes en español ("Juan - Marzo 2025"),
+// chrono solo formatea los meses en inglés
+const MESES_ES: [&str; 12] = [
+    "Enero",
+    "Febrero",
+    "Marzo",
+    "Abril",
+    "Mayo",
+    "Junio",
+    "Julio",
+    "Agosto",
+    "Septiembre",
+    "Octubre",
+    "Noviembre",
+    "Diciembre",
+];
+
+#[async_trait]
+pub trait QuotaStore: Send + Sync {
+    /// Obtiene cuotas mensuales de afiliado con identificadores formateados para frontend
+    /// Incluye campos identifier y nombre_usuario poblados automáticamente
+    async fn get_monthly_affiliate_quota(
+        &self,
+        affiliates: Vec<Affiliate>,
+        _access_token: String,
+    ) -> Result<Vec<Quota>, AppError> {
+        let hoy = today();
+        let mut resultado = Vec::new();
+        for afiliado in affiliates {
+            let quotas = self.get_quotas_afiliado_pendientes(afiliado.user_id.clone()).await?;
+            for mut quota in quotas {
+                if let Some(fecha) = quota.exp_date
+                    && fecha <= hoy
+                {
+                    let mes = MESES_ES
+                        .get((fecha.naive().month() as usize).saturating_sub(1))
+                        .unwrap_or(&"Mes");
+                    let anio = fecha.naive().year();
+                    let nombre = afiliado.name.clone();
+                    let identifier = format!("{} - {} {}", nombre, mes, anio);
+                    // Poblar campos adicionales para frontend
+                    quota.identifier = Some(identifier);
+                    quota.nombre_usuario = Some(nombre);
+                    resultado.push(quota);
+                }
+            }
+        }
+        Ok(resultado)
+    }
+
+    /// Obtiene cuotas de préstamo pendientes con campos adicionales para frontend
+    /// los campos nombre_prestamo ya vienen poblados desde dummy_data
+    async fn get_pending_loans_quotas(&self, access_token: String) -> Result<Vec<Quota>, AppError> {
+        self.get_quotas_prestamo_pendientes(access_token).await
+    }
+
+    /// cuotas de afiliado no pagadas que vencen a más tardar este mes
+    async fn get_quotas_afiliado_pendientes(
+        &self,
+        access_token: String,
+    ) -> Result<Vec<Quota>, AppError> {
+        self.get_quotas_afiliado_pendientes_by_owner(&hashing_composite_key(&[&access_token]))
+            .await
+    }
+
+    /// igual que get_quotas_afiliado_pendientes pero con el hash del socio
+    async fn get_quotas_afiliado_pendientes_by_owner(
+        &self,
+        db_access_token: &str,
+    ) -> Result<Vec<Quota>, AppError>;
+
+    /// Guarda una cuota - usado principalmente para datos dummy y testing
+    async fn save_quota(&self, access_token: String, quota: &Quota) -> Result<(), AppError>;
+
+    /// todas las cuotas (de afiliado y de préstamo) del socio, sin filtrar
+    async fn get_pending_quotas(&self, access_token: String) -> Result<Vec<Quota>, AppError> {
+        self.get_quotas_by_owner(&hashing_composite_key(&[&access_token])).await
+    }
+
+    /// igual que get_pending_quotas pero con el hash del socio
+    async fn get_quotas_by_owner(&self, db_access_token: &str) -> Result<Vec<Quota>, AppError>;
+
+    /// cuotas de préstamo no pagadas que todavía no vencen
+    async fn get_quotas_prestamo_pendientes(
+        &self,
+        access_token: String,
+    ) -> Result<Vec<Quota>, AppError> {
+        self.get_quotas_prestamo_pendientes_by_owner(&hashing_composite_key(&[&access_token]))
+            .await
+    }
+
+    /// igual que get_quotas_prestamo_pendientes pero con el hash del socio
+    async fn get_quotas_prestamo_pendientes_by_owner(
+        &self,
+        db_access_token: &str,
+    ) -> Result<Vec<Quota>, AppError>;
+
+    /// cuotas de afiliado y de préstamo que el socio todavía no ha pagado (Member.pendingQuotas)
+    async fn get_pending_quotas_by_owner(
+        &self,
+        db_access_token: &str,
+    ) -> Result<Vec<Quota>, AppError> {
+        let mut quotas = self.get_quotas_afiliado_pendientes_by_owner(db_access_token).await?;
+        quotas.extend(self.get_quotas_prestamo_pendientes_by_owner(db_access_token).await?);
+        Ok(quotas)
+    }
+
+    /// todas las cuotas de un préstamo (Loan.quotas), ordenadas por fecha de vencimiento
+    async fn get_loan_quotas_by_owner(
+        &self,
+        db_access_token: &str,
+        loan_id: &str,
+    ) -> Result<Vec<Quota>, AppError>;
+
+    /// todas las cuotas de un loan_id, sin filtrar por estado de pago ni vigencia
+    async fn get_quota_by_loan_id(
+        &self,
+        access_token: String,
+        loan_id: String,
+    ) -> Result<Vec<Quota>, AppError>;
+}
+
+#[async_trait]
+pub trait UserStore: Send + Sync {
+    /// todos los socios con su affiliate_key y nombre completo
+    async fn get_all_users_for_affiliates(&self) -> Result<Vec<Affiliate>, AppError>;
+
+    /// socio a partir de su hash (users:{owner_key}:*)
+    async fn get_member_by_owner_key(&self, owner_key: &str) -> Result<Member, AppError>;
+
+    /// socio a partir de su affiliate_key (lo que maneja el frontend)
+    async fn get_member_by_affiliate_key(&self, affiliate_key: String)
+    -> Result<Member, AppError>;
+
+    /// true si el dueño del access_token es directivo
+    async fn is_directive(&self, access_token: &str) -> bool;
+
+    /// el socio dueño del access_token
+    async fn get_member_by_access_token(&self, access_token: String) -> Result<Member, AppError> {
+        self.get_member_by_owner_key(&hashing_composite_key(&[&access_token])).await
+    }
+}
+
+#[async_trait]
+pub trait CurrencyStore: Send + Sync {
+    /// tipos de cambio vigentes de todas las monedas distintas a la base
+    async fn get_exchange_rates(&self) -> Result<Vec<ExchangeRate>, AppError>;
+
+    /// actualiza el tipo de cambio de una moneda, solo lo pueden hacer directivos
+    /// rate es cuánto vale 1 unidad de la moneda en la moneda base (ej: "7.75")
+    async fn set_exchange_rate(
+        &self,
+        access_token: String,
+        currency: Currency,
+        rate: String,
+    ) -> Result<ExchangeRate, AppError>;
+
+    /// totales de pagos y préstamos convertidos a la moneda base con el tipo de cambio vigente
+    async fn get_base_currency_report(&self) -> Result<BaseCurrencyReport, AppError>;
+}
+
+#[async_trait]
+pub trait AttachmentStore: Send + Sync {
+    /// documentos de un socio, préstamo o multa, del más viejo al más nuevo
+    async fn get_attachments(
+        &self,
+        owner_type: AttachmentOwnerType,
+        owner_id: &str,
+    ) -> Result<Vec<Attachment>, AppError>;
+
+    /// documentos que ningún directivo ha revisado, solo para directivos
+    async fn get_pending_attachments(
+        &self,
+        access_token: String,
+    ) -> Result<Vec<Attachment>, AppError>;
+
+    /// un directivo aprueba o rechaza un documento, el rechazo lleva comentario
+    async fn review_attachment(
+        &self,
+        access_token: String,
+        id: String,
+        status: AttachmentStatus,
+        commentary: Option<String>,
+    ) -> Result<Attachment, AppError>;
+
+    /// registra un documento que ya está en el BlobStore
+    async fn save_attachment(
+        &self,
+        id: &str,
+        attachment: &RedisAttachment,
+    ) -> Result<Attachment, AppError>;
+
+    /// el documento tal como está guardado, None si no existe
+    async fn get_redis_attachment(&self, id: &str) -> Result<Option<RedisAttachment>, AppError>;
+
+    /// affiliate_key del socio al que pertenece el dueño del documento: el mismo socio, el que
+    /// pidió el préstamo o el multado. NotFound si el dueño no existe
+    async fn get_member_key(
+        &self,
+        owner_type: AttachmentOwnerType,
+        owner_id: &str,
+    ) -> Result<String, AppError>;
+}
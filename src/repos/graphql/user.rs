@@ -0,0 +1,97 @@
+use redis::AsyncCommands;
+use regex::Regex;
+
+use crate::endpoints::handlers::configs::connection_pool::RedisPool;
+use crate::models::graphql::{Affiliate, Member};
+use crate::repos::auth::utils::hashing_composite_key;
+use crate::repos::graphql::loader::RequestLoader;
+use crate::repos::graphql::utils::{get_db_access_token_with_affiliate_key, scan_keys};
+use crate::errors::AppError;
+use crate::repos::graphql::store::UserStore;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// utilidades sobre los socios que usan varios dominios (pagos, cuotas, etc)
+pub struct UserRepo {
+    pub pool: RedisPool,
+    /// cache del request, GeneralContext le pasa el suyo
+    pub loader: Arc<RequestLoader>,
+}
+
+impl UserRepo {
+    /// repo con un loader propio, para usarlo fuera de un request de graphql
+    pub fn new(pool: RedisPool) -> Self {
+        UserRepo {
+            loader: Arc::new(RequestLoader::new(pool.clone())),
+            pool,
+        }
+    }
+}
+
+#[async_trait]
+impl UserStore for UserRepo {
+    /// todos los socios con su affiliate_key y nombre completo
+    /// los dos campos de todos los socios salen de un MGET con el loader
+    async fn get_all_users_for_affiliates(&self) -> Result<Vec<Affiliate>, AppError> {
+        let con = &mut self.pool.get().await?;
+
+        let keys = scan_keys(con, "users:*:affiliate_key")
+            .await
+            .map_err(|_| AppError::storage("Couldn't get users"))?;
+        let regex = Regex::new(r"(users):(\w+):(affiliate_key)").unwrap();
+        let user_hashes: Vec<String> = keys
+            .iter()
+            .filter_map(|key| regex.captures(key).map(|parsed_key| parsed_key[2].to_owned()))
+            .collect();
+
+        let affiliate_keys = self.loader.get_user_field(&user_hashes, "affiliate_key").await?;
+        let names = self.loader.complete_names(&user_hashes).await?;
+
+        Ok(user_hashes
+            .iter()
+            .map(|user_hash| Affiliate {
+                // user db_id
+                user_id: affiliate_keys
+                    .get(user_hash)
+                    .cloned()
+                    .flatten()
+                    .unwrap_or("Not Name Found".to_owned()),
+                name: names
+                    .get(user_hash)
+                    .cloned()
+                    .flatten()
+                    .unwrap_or("Not Name Found".to_owned()),
+            })
+            .collect())
+    }
+
+    /// socio a partir de su hash de redis (users:{owner_key}:*), pasa por el loader así
+    /// los resolvers que piden el mismo socio varias veces no vuelven a redis
+    async fn get_member_by_owner_key(&self, owner_key: &str) -> Result<Member, AppError> {
+        self.loader.member(owner_key).await
+    }
+
+    /// socio a partir de su affiliate_key (lo que maneja el frontend)
+    async fn get_member_by_affiliate_key(
+        &self,
+        affiliate_key: String,
+    ) -> Result<Member, AppError> {
+        let owner_key =
+            get_db_access_token_with_affiliate_key(affiliate_key, self.pool.clone()).await?;
+        self.get_member_by_owner_key(&owner_key).await
+    }
+
+    /// true si el dueño del access_token es directivo
+    async fn is_directive(&self, access_token: &str) -> bool {
+        let Ok(mut con) = self.pool.get().await else {
+            return false;
+        };
+
+        con.get::<String, bool>(format!(
+            "users:{}:is_directive",
+            hashing_composite_key(&[&access_token.to_owned()])
+        ))
+        .await
+        .unwrap_or(false)
+    }
+}
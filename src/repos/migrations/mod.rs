@@ -0,0 +1,286 @@
+use std::collections::BTreeSet;
+
+use redis::{from_redis_value, AsyncCommands, JsonAsyncCommands, Value as RedisValue};
+use serde_json::Value as JsonValue;
+
+use crate::endpoints::handlers::configs::connection_pool::{RedisConnection, RedisPool};
+use crate::models::{
+    dates::{Date, DateTime},
+    money::Money,
+};
+use crate::repos::graphql::{
+    payment::{fetch_redis_payment, payment_ticket_index_key, refresh_duplicate_flags},
+    utils,
+};
+
+// cada migración deja una marca en redis para no volver a correr en cada arranque
+const STORED_DATES_MIGRATION_KEY: &str = "migrations:stored_dates_v1";
+const STORED_AMOUNTS_MIGRATION_KEY: &str = "migrations:stored_amounts_v1";
+const PAYMENT_TICKETS_MIGRATION_KEY: &str = "migrations:payment_tickets_v1";
+
+/// corre las migraciones de datos pendientes, se llama una vez al levantar el server
+/// una migración con datos pendientes no frena a las demás
+pub async fn run_migrations(pool: &RedisPool) -> Result<(), String> {
+    let errors: Vec<String> = [
+        migrate_stored_dates(pool).await,
+        migrate_stored_amounts(pool).await,
+        migrate_payment_tickets(pool).await,
+    ]
+    .into_iter()
+    .filter_map(Result::err)
+    .collect();
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+/// pasa las fechas guardadas como texto libre a los formatos de models::dates
+/// - payments: date_created "YYYY-MM-DD" -> RFC 3339 en UTC (medianoche local de la cooperativa)
+/// - quotas: exp_date en cualquier formato aceptado -> "YYYY-MM-DD"
+///
+/// los valores que no se pueden interpretar se dejan como están y se reportan en el log, y la
+/// migración no se marca como hecha para que vuelva a correr en el próximo arranque
+pub async fn migrate_stored_dates(pool: &RedisPool) -> Result<(), String> {
+    let mut con = pool.get().await.map_err(|_| "Couldn't connect to pool")?;
+
+    if con.exists(STORED_DATES_MIGRATION_KEY).await.unwrap_or(false) {
+        return Ok(());
+    }
+
+    let payment_keys = scan_keys(&mut con, "users:*:payments:*").await?;
+    let quota_keys = [
+        scan_keys(&mut con, "users:*:quotas_afiliado:*").await?,
+        scan_keys(&mut con, "users:*:loans:*:quotas:*").await?,
+    ]
+    .concat();
+
+    let mut migrated = 0;
+    let mut failed = 0;
+
+    for key in payment_keys {
+        match migrate_json_field(&mut con, &key, "date_created", |raw| {
+            raw.parse::<DateTime>().map(|date_time| date_time.to_string())
+        })
+        .await
+        {
+            Ok(true) => migrated += 1,
+            Ok(false) => {}
+            Err(e) => {
+                println!("migrate_stored_dates - {}: {}", key, e);
+                failed += 1;
+            }
+        }
+    }
+
+    for key in quota_keys {
+        match migrate_json_field(&mut con, &key, "exp_date", |raw| {
+            raw.parse::<Date>().map(|date| date.to_string())
+        })
+        .await
+        {
+            Ok(true) => migrated += 1,
+            Ok(false) => {}
+            Err(e) => {
+                println!("migrate_stored_dates - {}: {}", key, e);
+                failed += 1;
+            }
+        }
+    }
+
+    println!(
+        "migrate_stored_dates - {} fechas migradas, {} no se pudieron interpretar",
+        migrated, failed
+    );
+
+    if failed > 0 {
+        return Err(format!(
+            "migrate_stored_dates - quedan {} fechas sin interpretar",
+            failed
+        ));
+    }
+
+    con.set::<&str, String, ()>(STORED_DATES_MIGRATION_KEY, DateTime::now().to_string())
+        .await
+        .map_err(|_| "Couldn't mark dates migration as done".to_string())
+}
+
+async fn scan_keys(con: &mut RedisConnection, pattern: &str) -> Result<Vec<String>, String> {
+    utils::scan_keys(con, pattern)
+        .await
+        .map_err(|_| format!("Couldn't scan keys for {}", pattern))
+}
+
+/// reescribe un campo string de un objeto JSON con el valor normalizado
+/// retorna Ok(false) si el campo no existe, no es string o ya estaba normalizado
+async fn migrate_json_field(
+    con: &mut RedisConnection,
+    key: &str,
+    field: &str,
+    normalize: impl Fn(&str) -> Result<String, String>,
+) -> Result<bool, String> {
+    let path = format!("$.{}", field);
+
+    // las keys que no son JSON (ej: users:{hash}:payments = false) simplemente se saltan
+    let Ok(raw) = con.json_get::<&str, &str, RedisValue>(key, &path).await else {
+        return Ok(false);
+    };
+    let Ok(nested) = from_redis_value::<String>(&raw) else {
+        return Ok(false);
+    };
+
+    let values: Vec<JsonValue> = serde_json::from_str(&nested).unwrap_or_default();
+    let Some(JsonValue::String(current)) = values.into_iter().next() else {
+        return Ok(false);
+    };
+
+    let normalized = normalize(&current)?;
+    if normalized == current {
+        return Ok(false);
+    }
+
+    con.json_set::<&str, &str, String, ()>(key, &path, &normalized)
+        .await
+        .map_err(|_| "Couldn't update field".to_string())?;
+
+    Ok(true)
+}
+
+/// pasa los montos guardados como números JSON (f64/f32) a strings de Money ("123.45")
+/// los modelos igual aceptan números al leer, esto es para que redis quede consistente
+pub async fn migrate_stored_amounts(pool: &RedisPool) -> Result<(), String> {
+    let mut con = pool.get().await.map_err(|_| "Couldn't connect to pool")?;
+
+    if con.exists(STORED_AMOUNTS_MIGRATION_KEY).await.unwrap_or(false) {
+        return Ok(());
+    }
+
+    // (patrón de keys, campos con montos)
+    let models: [(&str, &[&str]); 5] = [
+        ("users:*:payments:*", &["total_amount"]),
+        ("users:*:loans:*", &["base_needed_payment", "payed", "debt", "total"]),
+        ("users:*:fines:*", &["amount"]),
+        ("users:*:quotas_afiliado:*", &["amount", "monto_pagado", "multa"]),
+        ("users:*:loans:*:quotas:*", &["amount", "monto_pagado", "multa"]),
+    ];
+
+    let mut migrated = 0;
+
+    for (pattern, fields) in models {
+        for key in scan_keys(&mut con, pattern).await? {
+            match migrate_amount_fields(&mut con, &key, fields).await {
+                Ok(true) => migrated += 1,
+                Ok(false) => {}
+                Err(e) => println!("migrate_stored_amounts - {}: {}", key, e),
+            }
+        }
+    }
+
+    println!("migrate_stored_amounts - {} objetos migrados", migrated);
+
+    con.set::<&str, String, ()>(STORED_AMOUNTS_MIGRATION_KEY, DateTime::now().to_string())
+        .await
+        .map_err(|_| "Couldn't mark amounts migration as done".to_string())
+}
+
+/// mete los pagos que ya existían al índice payment_tickets:* y recalcula possible_duplicates,
+/// sin esto solo se detectaban duplicados de pagos creados después de agregar el índice
+pub async fn migrate_payment_tickets(pool: &RedisPool) -> Result<(), String> {
+    let mut con = pool.get().await.map_err(|_| "Couldn't connect to pool")?;
+
+    if con.exists(PAYMENT_TICKETS_MIGRATION_KEY).await.unwrap_or(false) {
+        return Ok(());
+    }
+
+    let mut index_keys = BTreeSet::new();
+
+    for key in scan_keys(&mut con, "users:*:payments:*").await? {
+        let Some(payment) = fetch_redis_payment(&mut con, &key).await else {
+            continue;
+        };
+        if payment.ticket_number.trim().is_empty() {
+            continue;
+        }
+
+        let index_key = payment_ticket_index_key(&payment.account_number, &payment.ticket_number);
+        con.sadd::<&str, &str, ()>(&index_key, &key)
+            .await
+            .map_err(|_| "Couldn't index payment ticket".to_string())?;
+        index_keys.insert(index_key);
+    }
+
+    let mut flagged = 0;
+    for index_key in &index_keys {
+        flagged += refresh_duplicate_flags(&mut con, index_key).await.len();
+    }
+
+    println!(
+        "migrate_payment_tickets - {} boletas indexadas, {} pagos actualizados",
+        index_keys.len(),
+        flagged
+    );
+
+    con.set::<&str, String, ()>(PAYMENT_TICKETS_MIGRATION_KEY, DateTime::now().to_string())
+        .await
+        .map_err(|_| "Couldn't mark payment tickets migration as done".to_string())
+}
+
+/// reescribe los campos numéricos de un objeto JSON (y los amount de being_payed en pagos)
+/// retorna Ok(false) si la key no es un objeto JSON o no tenía montos numéricos
+async fn migrate_amount_fields(
+    con: &mut RedisConnection,
+    key: &str,
+    fields: &[&str],
+) -> Result<bool, String> {
+    let Ok(raw) = con.json_get::<&str, &str, RedisValue>(key, "$").await else {
+        return Ok(false);
+    };
+    let Ok(nested) = from_redis_value::<String>(&raw) else {
+        return Ok(false);
+    };
+
+    let values: Vec<JsonValue> = serde_json::from_str(&nested).unwrap_or_default();
+    let Some(JsonValue::Object(mut object)) = values.into_iter().next() else {
+        return Ok(false);
+    };
+
+    let mut changed = false;
+
+    for field in fields {
+        if let Some(value) = object.get_mut(*field) {
+            changed |= amount_to_string(value)?;
+        }
+    }
+
+    if let Some(JsonValue::Array(being_payed)) = object.get_mut("being_payed") {
+        for item in being_payed {
+            if let Some(value) = item.get_mut("amount") {
+                changed |= amount_to_string(value)?;
+            }
+        }
+    }
+
+    if !changed {
+        return Ok(false);
+    }
+
+    con.json_set::<&str, &str, JsonValue, ()>(key, "$", &JsonValue::Object(object))
+        .await
+        .map_err(|_| "Couldn't update amounts".to_string())?;
+
+    Ok(true)
+}
+
+fn amount_to_string(value: &mut JsonValue) -> Result<bool, String> {
+    let JsonValue::Number(number) = value else {
+        return Ok(false);
+    };
+
+    let amount: Money = serde_json::from_value(JsonValue::Number(number.clone()))
+        .map_err(|e| e.to_string())?;
+    *value = JsonValue::String(amount.to_string());
+
+    Ok(true)
+}
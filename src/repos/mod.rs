@@ -1,3 +1,4 @@
 pub mod auth;
-// pub mod file; // COMENTADO POR AHORA PARA ENFOCARSE EN RECOVER-PASSWORD
+pub mod file;
 pub mod graphql;
+pub mod migrations;
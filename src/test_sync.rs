@@ -1,5 +1,13 @@
-use std::sync::{OnceLock, Mutex};
+use std::sync::OnceLock;
+
+use futures::lock::{Mutex, MutexGuard};
 
 /// Shared lock used by integration tests to serialize Redis access.
 /// Placed here so every test binary uses the same OnceLock instance.
+/// es async para poder tenerlo tomado entre .await dentro de los #[tokio::test]
 pub static REDIS_TEST_LOCK: OnceLock<Mutex<()>> = OnceLock::new();
+
+/// toma el lock compartido de redis, se libera cuando el guard sale de alcance
+pub async fn redis_test_lock() -> MutexGuard<'static, ()> {
+    REDIS_TEST_LOCK.get_or_init(|| Mutex::new(())).lock().await
+}
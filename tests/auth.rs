@@ -6,7 +6,8 @@ use rand::{
 };
 
 use general_api::{
-    endpoints::handlers::configs::connection_pool::get_pool_connection,
+    endpoints::handlers::configs::connection_pool::{get_pool_connection, RedisPool},
+    models::money::Money,
     repos::{
         auth::{
             create_user_with_access_token, get_user_access_token, utils::hashing_composite_key,
@@ -16,10 +17,17 @@ use general_api::{
     },
 };
 use redis::Commands;
+use general_api::repos::graphql::store::PaymentStore;
+
+/// cada llamada abre su propia conexión: la multiplexada queda atada al runtime de tokio que
+/// la abrió y cada #[tokio::test] tiene el suyo
+fn pool() -> RedisPool {
+    get_pool_connection().unwrap()
+}
 
 // Helper function para limpiar datos de usuario de prueba
 fn cleanup_test_user(username: &str) {
-    let mut con = get_pool_connection().into_inner().get().unwrap();
+    let mut con = pool().client().get_connection().unwrap();
 
     // Generar las claves que usa este usuario específico
     let access_token = hashing_composite_key(&[&username.to_string(), &"ElTestoPaga".to_string()]);
@@ -54,7 +62,7 @@ fn cleanup_test_user(username: &str) {
 
 /// Helper function to seed security questions for test users
 /// Creates test users with 3 security questions/answers each
-fn seed_security_questions() -> Vec<(String, String, [String; 3])> {
+async fn seed_security_questions() -> Vec<(String, String, [String; 3])> {
     let mut test_users = Vec::new();
     let test_id = format!("{:?}", std::thread::current().id());
     
@@ -75,18 +83,24 @@ fn seed_security_questions() -> Vec<(String, String, [String; 3])> {
         
         // Crear el usuario
         if let Ok(token_info) = create_user_with_access_token(
+            &pool(),
             username.clone(),
             password.clone(),
             format!("Test User {}", i),
-        ) {
+        )
+        .await
+        {
             // Pequeño delay antes de configurar respuestas
             std::thread::sleep(std::time::Duration::from_millis(50));
             
             // Configurar respuestas de seguridad
             if let Ok(_) = configure_all_security_answers(
+                &pool(),
                 token_info.access_token.clone(),
                 answers.clone(),
-            ) {
+            )
+            .await
+            {
                 test_users.push((username, token_info.access_token, answers));
             }
         }
@@ -96,8 +110,8 @@ fn seed_security_questions() -> Vec<(String, String, [String; 3])> {
 
 /// Tests for checking login function integrity
 /// For for getting acess_token with the given credentials in Redis DB (Login)
-#[test]
-fn from_credentials_to_acess_token() {
+#[tokio::test]
+async fn from_credentials_to_acess_token() {
     let _ = dotenv();
 
     let username = "El_Mago_Pero_Del_Test";
@@ -108,10 +122,12 @@ fn from_credentials_to_acess_token() {
 
     // Primero crear el usuario para el test
     let creation_result = create_user_with_access_token(
+        &pool(),
         username.to_string(),
         password.to_string(),
         "Test User Complete Name".to_string(),
-    );
+    )
+    .await;
 
     assert!(
         creation_result.is_ok(),
@@ -120,7 +136,8 @@ fn from_credentials_to_acess_token() {
     );
 
     // Ahora obtener el token de acceso
-    let access_token = get_user_access_token(username.to_string(), password.to_string());
+    let access_token = get_user_access_token(&pool(), username.to_string(), password.to_string())
+        .await;
 
     assert_eq!(
         access_token.unwrap().access_token.to_uppercase(),
@@ -134,12 +151,10 @@ fn from_credentials_to_acess_token() {
 }
 
 /// For checking if credentials cretead an user instance in Redis DB (Signup)
-#[test]
-fn from_credentials_to_data() {
+#[tokio::test]
+async fn from_credentials_to_data() {
     let _ = dotenv();
-    let repo = PaymentRepo {
-        pool: get_pool_connection(),
-    };
+    let repo = PaymentRepo::new(pool());
 
     // random string
     let mut random_string = Alphanumeric.sample_string(&mut rng(), 16);
@@ -148,12 +163,15 @@ fn from_credentials_to_data() {
     let mut access_token = String::new();
     loop {
         access_token = match create_user_with_access_token(
+            &pool(),
             random_string.clone(),
             random_string.clone(),
             random_string.clone(),
-        ) {
+        )
+        .await
+        {
             Ok(token_info) => {
-                let mut con = get_pool_connection().into_inner().get().unwrap();
+                let mut con = pool().client().get_connection().unwrap();
 
                 let db_acess_token = hashing_composite_key(&[&token_info.access_token]);
                 let _: () = con
@@ -180,15 +198,17 @@ fn from_credentials_to_data() {
 
     // Pretty similar as the Login test
     assert_eq!(
-        10101.0,
+        Money::from(10101),
         repo.get_user_history(access_token.clone())
+            .await
             .unwrap()
             .owed_capital
     );
 
     assert_eq!(
-        1010.0,
+        Money::from(1010),
         repo.get_user_history(access_token.clone())
+            .await
             .unwrap()
             .payed_to_capital
     );
@@ -196,32 +216,32 @@ fn from_credentials_to_data() {
 
 //*! 2 Tests go here
 /// For getting data from User in Redis DB (Login)
-#[test]
-fn check_if_can_acess_data() {
+#[tokio::test]
+async fn check_if_can_acess_data() {
     let _ = dotenv();
     // Username aleatorio para evitar colisiones
     let username = format!("testuser_{}", Alphanumeric.sample_string(&mut rng(), 12));
     let passcode = "ElTestoPaga".to_string();
 
-    let repo = PaymentRepo {
-        pool: get_pool_connection(),
-    };
+    let repo = PaymentRepo::new(pool());
 
     // Limpiar datos previos del usuario de prueba
     cleanup_test_user(&username);
 
     // Crear el usuario para el test
     let token = create_user_with_access_token(
+        &pool(),
         username.clone(),
         passcode.clone(),
         "EL Pedro Del Testo".to_string(),
     )
+    .await
     .expect("Should create test user");
 
     let access_token = token.access_token;
 
     // Configurar los valores de capital para el test
-    let mut con = get_pool_connection().into_inner().get().unwrap();
+    let mut con = pool().client().get_connection().unwrap();
     let db_acess_token = hashing_composite_key(&[&access_token]);
 
     let _: () = con
@@ -232,15 +252,17 @@ fn check_if_can_acess_data() {
         .expect("Should set payed_to_capital");
 
     assert_eq!(
-        10101.0,
+        Money::from(10101),
         repo.get_user_history(access_token.clone())
+            .await
             .unwrap()
             .owed_capital
     );
 
     assert_eq!(
-        1010.0,
+        Money::from(1010),
         repo.get_user_history(access_token.clone())
+            .await
             .unwrap()
             .payed_to_capital
     );
@@ -250,16 +272,16 @@ fn check_if_can_acess_data() {
 }
 
 /// Test for validating security answer - correct answer path
-#[test]
-fn test_validate_security_answer_correct() {
+#[tokio::test]
+async fn test_validate_security_answer_correct() {
     let _ = dotenv();
     
-    let test_users = seed_security_questions();
+    let test_users = seed_security_questions().await;
     assert!(!test_users.is_empty(), "Should have seeded test users");
     
     let (username, _access_token, answers) = test_users.first().unwrap().clone();
     // Validate with correct answer from index 0
-    let result = validate_security_answer(username.clone(), 0, answers[0].clone());
+    let result = validate_security_answer(&pool(), username.clone(), 0, answers[0].clone()).await;
     assert!(result.is_ok(), "Should validate correct answer: {:?}", result.err());
     
     // The result should be the db_composite_key
@@ -271,53 +293,59 @@ fn test_validate_security_answer_correct() {
 }
 
 /// Test for validating security answer - incorrect answer path
-#[test]
-fn test_validate_security_answer_incorrect() {
+#[tokio::test]
+async fn test_validate_security_answer_incorrect() {
     let _ = dotenv();
     
-    let test_users = seed_security_questions();
+    let test_users = seed_security_questions().await;
     assert!(!test_users.is_empty(), "Should have seeded test users");
     
     let (username, _access_token, _answers) = test_users.first().unwrap().clone();
     let wrong_answer = "completely_wrong_answer";
     
     // Validate with incorrect answer
-    let result = validate_security_answer(username.clone(), 0, wrong_answer.to_string());
+    let result = validate_security_answer(&pool(), username.clone(), 0, wrong_answer.to_string())
+        .await;
     assert!(result.is_err(), "Should reject incorrect answer");
     
     let error_msg = result.unwrap_err();
     assert!(
-        error_msg.message.contains("Respuesta incorrecta") || error_msg.message.contains("incorrecta"),
+        error_msg.message().contains("Respuesta incorrecta") || error_msg.message().contains("incorrecta"),
         "Should return incorrect answer message, got: {}",
-        error_msg.message
+        error_msg.message()
     );
+    assert_eq!(error_msg.code(), "UNAUTHORIZED");
     
     // Cleanup
     cleanup_test_user(&username);
 }
 
 /// Test for reset password - successful reset path
-#[test]
-fn test_reset_password_success() {
+#[tokio::test]
+async fn test_reset_password_success() {
     let _ = dotenv();
     
-    let test_users = seed_security_questions();
+    let test_users = seed_security_questions().await;
     assert!(!test_users.is_empty(), "Should have seeded test users");
     
     let (username, _access_token, answers) = test_users.first().unwrap();
     let new_password = "NewPassword123";
     // Get original token for comparison
     let original_token = get_user_access_token(
+        &pool(),
         username.clone(),
         "ElTestoPaga".to_string(),
-    ).expect("Should get original token");
+    )
+    .await.expect("Should get original token");
     // Reset password with question_index 0
     let result = reset_password(
+        &pool(),
         username.clone(),
         0,
         answers[0].clone(),
         new_password.to_string(),
-    );
+    )
+    .await;
     assert!(result.is_ok(), "Should reset password successfully: {:?}", result.err());
     let new_token_info = result.unwrap();
     assert!(!new_token_info.access_token.is_empty(), "Should return new access_token");
@@ -329,9 +357,11 @@ fn test_reset_password_success() {
     );
     // Verify user can login with new password
     let new_login_result = get_user_access_token(
+        &pool(),
         username.clone(),
         new_password.to_string(),
-    );
+    )
+    .await;
     assert!(new_login_result.is_ok(), "Should be able to login with new password");
     let new_login_token = new_login_result.unwrap();
     assert_eq!(
@@ -344,8 +374,8 @@ fn test_reset_password_success() {
 }
 
 /// Test for reset password without configured security question
-#[test]
-fn test_reset_password_without_question() {
+#[tokio::test]
+async fn test_reset_password_without_question() {
     let _ = dotenv();
     
     // Create a user without security question
@@ -355,20 +385,24 @@ fn test_reset_password_without_question() {
     cleanup_test_user(&username);
     
     let creation_result = create_user_with_access_token(
+        &pool(),
         username.clone(),
         password.clone(),
         "Test User No Question".to_string(),
-    );
+    )
+    .await;
     
     assert!(creation_result.is_ok(), "Should create test user");
     
     // Try to reset password without configuring security question
     let result = reset_password(
+        &pool(),
         username.clone(),
         0,
         "some_answer".to_string(),
         "NewPassword".to_string(),
-    );
+    )
+    .await;
     
     assert!(result.is_err(), "Should fail when security question not configured");
     
@@ -6,7 +6,8 @@ use rand::{
 use rand::rng;
 
 use general_api::{
-    endpoints::handlers::configs::connection_pool::get_pool_connection,
+    endpoints::handlers::configs::connection_pool::{get_pool_connection, RedisPool},
+    models::money::Money,
     repos::auth::{
         configure_all_security_answers, create_user_with_access_token,
         reset_password, utils::hashing_composite_key,
@@ -15,9 +16,16 @@ use general_api::{
     repos::graphql::loan::LoanRepo,
 };
 use redis::{Commands, JsonCommands};
+use general_api::repos::graphql::store::PaymentStore;
+
+/// cada llamada abre su propia conexión: la multiplexada queda atada al runtime de tokio que
+/// la abrió y cada #[tokio::test] tiene el suyo
+fn pool() -> RedisPool {
+    get_pool_connection().unwrap()
+}
 
 fn cleanup_test_user(username: &str) {
-    let mut con = get_pool_connection().into_inner().get().unwrap();
+    let mut con = pool().client().get_connection().unwrap();
 
     let access_token =
         hashing_composite_key(&[&username.to_string(), &"ElTestoPaga".to_string()]);
@@ -63,8 +71,8 @@ fn cleanup_test_user(username: &str) {
 }
 
 /// TEST: Verificar que datos financieros (owed_capital, payed_to_capital) se remapiean correctamente
-#[test]
-fn test_financial_data_remapped_after_reset() {
+#[tokio::test]
+async fn test_financial_data_remapped_after_reset() {
     let _ = dotenv();
 
     let username =
@@ -76,10 +84,12 @@ fn test_financial_data_remapped_after_reset() {
 
     // 1. Crear usuario
     let creation = create_user_with_access_token(
+        &pool(),
         username.clone(),
         original_password.to_string(),
         "Remapping Test User".to_string(),
-    );
+    )
+    .await;
     assert!(creation.is_ok(), "Should create user");
 
     let original_token_info = creation.unwrap();
@@ -87,7 +97,7 @@ fn test_financial_data_remapped_after_reset() {
     let original_db_key = hashing_composite_key(&[&original_access_token]);
 
     // 2. Setear datos financieros con el token original
-    let mut con = get_pool_connection().into_inner().get().unwrap();
+    let mut con = pool().client().get_connection().unwrap();
     let original_owed = 5000.0;
     let original_payed = 2000.0;
 
@@ -105,14 +115,13 @@ fn test_financial_data_remapped_after_reset() {
         .expect("Should set payed_to_capital");
 
     // 3. Verificar datos con token original
-    let repo = PaymentRepo {
-        pool: get_pool_connection(),
-    };
+    let repo = PaymentRepo::new(pool());
     let history_before = repo
         .get_user_history(original_access_token.clone())
+        .await
         .expect("Should get user history");
-    assert_eq!(history_before.owed_capital, original_owed, "Original owed_capital should match");
-    assert_eq!(history_before.payed_to_capital, original_payed, "Original payed_to_capital should match");
+    assert_eq!(history_before.owed_capital, Money::try_from(original_owed).unwrap(), "Original owed_capital should match");
+    assert_eq!(history_before.payed_to_capital, Money::try_from(original_payed).unwrap(), "Original payed_to_capital should match");
 
     // 4. Configurar respuestas de seguridad
     let answers = [
@@ -120,16 +129,23 @@ fn test_financial_data_remapped_after_reset() {
         "answer_1".to_string(),
         "answer_2".to_string(),
     ];
-    let config_result = configure_all_security_answers(original_access_token.clone(), answers.clone());
+    let config_result = configure_all_security_answers(
+        &pool(),
+        original_access_token.clone(),
+        answers.clone(),
+    )
+    .await;
     assert!(config_result.is_ok(), "Should configure security answers");
 
     // 5. Resetear contraseña
     let reset_result = reset_password(
+        &pool(),
         username.clone(),
         0,
         answers[0].clone(),
         new_password.to_string(),
-    );
+    )
+    .await;
     assert!(reset_result.is_ok(), "Should reset password");
     
     let new_token_info = reset_result.unwrap();
@@ -142,20 +158,21 @@ fn test_financial_data_remapped_after_reset() {
     // 6. Verificar que los datos financieros están disponibles con el nuevo token
     let history_after = repo
         .get_user_history(new_access_token.clone())
+        .await
         .expect("Should get user history with new token");
     assert_eq!(
-        history_after.owed_capital, original_owed,
+        history_after.owed_capital, Money::try_from(original_owed).unwrap(),
         "Owed capital should be preserved after reset"
     );
     assert_eq!(
-        history_after.payed_to_capital, original_payed,
+        history_after.payed_to_capital, Money::try_from(original_payed).unwrap(),
         "Payed to capital should be preserved after reset"
     );
 
     // 7. Verificar que el token original NO funciona más
-    let old_token_result = repo.get_user_history(original_access_token.clone());
+    let old_token_result = repo.get_user_history(original_access_token.clone()).await;
     assert!(
-        old_token_result.is_err() || (old_token_result.is_ok() && old_token_result.unwrap().owed_capital == 0.0),
+        old_token_result.is_err() || (old_token_result.is_ok() && old_token_result.unwrap().owed_capital == Money::ZERO),
         "Old token should not return the original data (either error or empty)"
     );
 
@@ -164,8 +181,8 @@ fn test_financial_data_remapped_after_reset() {
 }
 
 /// TEST: Verificar que el mapeo affiliate_key → db_composite_key se actualiza correctamente
-#[test]
-fn test_affiliate_key_mapping_updated_after_reset() {
+#[tokio::test]
+async fn test_affiliate_key_mapping_updated_after_reset() {
     let _ = dotenv();
 
     let username =
@@ -177,10 +194,12 @@ fn test_affiliate_key_mapping_updated_after_reset() {
 
     // 1. Crear usuario
     let creation = create_user_with_access_token(
+        &pool(),
         username.clone(),
         original_password.to_string(),
         "Affiliate Mapping Test".to_string(),
-    );
+    )
+    .await;
     assert!(creation.is_ok());
 
     let original_token_info = creation.unwrap();
@@ -189,7 +208,7 @@ fn test_affiliate_key_mapping_updated_after_reset() {
     let affiliate_key = hashing_composite_key(&[&username]);
 
     // 2. Verificar mapeo inicial
-    let mut con = get_pool_connection().into_inner().get().unwrap();
+    let mut con = pool().client().get_connection().unwrap();
     let mapped_db_key: String = con
         .get(format!("affiliate_key_to_db_access:{}", affiliate_key))
         .expect("Should get mapping");
@@ -204,13 +223,16 @@ fn test_affiliate_key_mapping_updated_after_reset() {
         "answer_1".to_string(),
         "answer_2".to_string(),
     ];
-    let _ = configure_all_security_answers(original_access_token.clone(), answers.clone());
+    let _ = configure_all_security_answers(&pool(), original_access_token.clone(), answers.clone())
+        .await;
     let reset_result = reset_password(
+        &pool(),
         username.clone(),
         0,
         answers[0].clone(),
         new_password.to_string(),
-    );
+    )
+    .await;
     assert!(reset_result.is_ok());
 
     let new_token_info = reset_result.unwrap();
@@ -250,8 +272,8 @@ fn test_affiliate_key_mapping_updated_after_reset() {
 }
 
 /// TEST: Verificar que las 3 respuestas de seguridad se remapiean y siguen siendo válidas
-#[test]
-fn test_security_answers_remapped_after_reset() {
+#[tokio::test]
+async fn test_security_answers_remapped_after_reset() {
     let _ = dotenv();
 
     let username =
@@ -263,10 +285,12 @@ fn test_security_answers_remapped_after_reset() {
 
     // 1. Crear usuario
     let creation = create_user_with_access_token(
+        &pool(),
         username.clone(),
         original_password.to_string(),
         "Security Answers Remapping Test".to_string(),
-    );
+    )
+    .await;
     assert!(creation.is_ok());
 
     let original_token_info = creation.unwrap();
@@ -278,16 +302,23 @@ fn test_security_answers_remapped_after_reset() {
         "second_answer".to_string(),
         "third_answer".to_string(),
     ];
-    let config_result = configure_all_security_answers(original_access_token.clone(), answers.clone());
+    let config_result = configure_all_security_answers(
+        &pool(),
+        original_access_token.clone(),
+        answers.clone(),
+    )
+    .await;
     assert!(config_result.is_ok());
 
     // 3. Resetear contraseña
     let reset_result = reset_password(
+        &pool(),
         username.clone(),
         0,
         answers[0].clone(),
         new_password.to_string(),
-    );
+    )
+    .await;
     assert!(reset_result.is_ok());
 
     let new_token_info = reset_result.unwrap();
@@ -298,10 +329,12 @@ fn test_security_answers_remapped_after_reset() {
     for (index, answer) in answers.iter().enumerate() {
         let validate_result =
             general_api::repos::auth::validate_security_answer(
+                &pool(),
                 username.clone(),
                 index as u8,
                 answer.clone(),
-            );
+            )
+            .await;
         assert!(
             validate_result.is_ok(),
             "Answer {} should still be valid after reset",
@@ -314,8 +347,8 @@ fn test_security_answers_remapped_after_reset() {
 }
 
 /// TEST: Verificar que los contadores de datos (payments, loans, fines flags) se remapiean
-#[test]
-fn test_data_flags_remapped_after_reset() {
+#[tokio::test]
+async fn test_data_flags_remapped_after_reset() {
     let _ = dotenv();
 
     let username =
@@ -327,10 +360,12 @@ fn test_data_flags_remapped_after_reset() {
 
     // 1. Crear usuario
     let creation = create_user_with_access_token(
+        &pool(),
         username.clone(),
         original_password.to_string(),
         "Flags Remapping Test".to_string(),
-    );
+    )
+    .await;
     assert!(creation.is_ok());
 
     let original_token_info = creation.unwrap();
@@ -343,13 +378,16 @@ fn test_data_flags_remapped_after_reset() {
         "answer_1".to_string(),
         "answer_2".to_string(),
     ];
-    let _ = configure_all_security_answers(original_access_token.clone(), answers.clone());
+    let _ = configure_all_security_answers(&pool(), original_access_token.clone(), answers.clone())
+        .await;
     let reset_result = reset_password(
+        &pool(),
         username.clone(),
         0,
         answers[0].clone(),
         new_password.to_string(),
-    );
+    )
+    .await;
     assert!(reset_result.is_ok());
 
     let new_token_info = reset_result.unwrap();
@@ -357,7 +395,7 @@ fn test_data_flags_remapped_after_reset() {
     let new_db_key = hashing_composite_key(&[&new_access_token]);
 
     // 3. Verificar que los flags existan en el nuevo db_key
-    let mut con = get_pool_connection().into_inner().get().unwrap();
+    let mut con = pool().client().get_connection().unwrap();
     
     let payments_flag: bool = con
         .get(format!("users:{}:payments", new_db_key))
@@ -395,8 +433,8 @@ fn test_data_flags_remapped_after_reset() {
 }
 
 /// TEST: Verificar que los datos reales de PAYMENTS se mantienen accesibles tras reset
-#[test]
-fn test_payments_data_accessible_after_reset() {
+#[tokio::test]
+async fn test_payments_data_accessible_after_reset() {
     let _ = dotenv();
 
     let username =
@@ -408,10 +446,12 @@ fn test_payments_data_accessible_after_reset() {
 
     // 1. Crear usuario
     let creation = create_user_with_access_token(
+        &pool(),
         username.clone(),
         original_password.to_string(),
         "Payments Test User".to_string(),
-    );
+    )
+    .await;
     assert!(creation.is_ok(), "Should create user");
 
     let original_token_info = creation.unwrap();
@@ -419,18 +459,17 @@ fn test_payments_data_accessible_after_reset() {
     let original_db_key = hashing_composite_key(&[&original_access_token]);
 
     // 2. Verificar que PaymentRepo puede recuperar historial antes (se va a obtener valores por defecto)
-    let repo = PaymentRepo {
-        pool: get_pool_connection(),
-    };
+    let repo = PaymentRepo::new(pool());
     let history_before = repo
         .get_user_history(original_access_token.clone())
+        .await
         .expect("Should get user history before reset");
     // Los valores default son 0.0
-    assert_eq!(history_before.payed_to_capital, 0.0, "Initial payed_to_capital should be 0.0");
-    assert_eq!(history_before.owed_capital, 0.0, "Initial owed_capital should be 0.0");
+    assert_eq!(history_before.payed_to_capital, Money::from(0), "Initial payed_to_capital should be 0.0");
+    assert_eq!(history_before.owed_capital, Money::from(0), "Initial owed_capital should be 0.0");
 
     // 3. Crear manualmente datos de pago en Redis (con json_set para simular crear un pago)
-    let mut con = get_pool_connection().into_inner().get().unwrap();
+    let mut con = pool().client().get_connection().unwrap();
     let payment_hash_key = hashing_composite_key(&[&"0".to_string(), &original_db_key]);
     let payment_json = serde_json::json!({
         "name": "Test Payment",
@@ -463,6 +502,7 @@ fn test_payments_data_accessible_after_reset() {
     // 4. Verificar que el pago está accesible antes del reset
     let payments_before = repo
         .get_user_payments(original_access_token.clone())
+        .await
         .expect("Should get payments before reset");
     assert_eq!(payments_before.len(), 1, "Should have 1 payment before reset");
 
@@ -472,15 +512,18 @@ fn test_payments_data_accessible_after_reset() {
         "answer_1".to_string(),
         "answer_2".to_string(),
     ];
-    let _ = configure_all_security_answers(original_access_token.clone(), answers.clone());
+    let _ = configure_all_security_answers(&pool(), original_access_token.clone(), answers.clone())
+        .await;
 
     // 6. Resetear contraseña
     let reset_result = reset_password(
+        &pool(),
         username.clone(),
         0,
         answers[0].clone(),
         new_password.to_string(),
-    );
+    )
+    .await;
     assert!(reset_result.is_ok(), "Should reset password");
     
     let new_token_info = reset_result.unwrap();
@@ -489,23 +532,25 @@ fn test_payments_data_accessible_after_reset() {
     // 7. Verificar que los datos de pagos están disponibles con el nuevo token
     let payments_after = repo
         .get_user_payments(new_access_token.clone())
+        .await
         .expect("Should get payments after reset");
     assert_eq!(payments_after.len(), 1, "Should still have 1 payment after reset");
 
     // 8. Verificar que el historial muestra los datos copiados
     let history_after = repo
         .get_user_history(new_access_token.clone())
+        .await
         .expect("Should get user history after reset with new token");
-    assert_eq!(history_after.payed_to_capital, 2000.0, "payed_to_capital should be preserved after reset");
-    assert_eq!(history_after.owed_capital, 5000.0, "owed_capital should be preserved after reset");
+    assert_eq!(history_after.payed_to_capital, Money::from(2000), "payed_to_capital should be preserved after reset");
+    assert_eq!(history_after.owed_capital, Money::from(5000), "owed_capital should be preserved after reset");
 
     // Cleanup
     cleanup_test_user(&username);
 }
 
 /// TEST: Verificar que los datos reales de LOANS se mantienen accesibles tras reset
-#[test]
-fn test_loans_data_accessible_after_reset() {
+#[tokio::test]
+async fn test_loans_data_accessible_after_reset() {
     let _ = dotenv();
 
     let username =
@@ -517,10 +562,12 @@ fn test_loans_data_accessible_after_reset() {
 
     // 1. Crear usuario
     let creation = create_user_with_access_token(
+        &pool(),
         username.clone(),
         original_password.to_string(),
         "Loans Test User".to_string(),
-    );
+    )
+    .await;
     assert!(creation.is_ok(), "Should create user");
 
     let original_token_info = creation.unwrap();
@@ -528,7 +575,7 @@ fn test_loans_data_accessible_after_reset() {
     let original_db_key = hashing_composite_key(&[&original_access_token]);
 
     // 2. Crear manualmente un préstamo en Redis
-    let mut con = get_pool_connection().into_inner().get().unwrap();
+    let mut con = pool().client().get_connection().unwrap();
     let loan_hash_key = hashing_composite_key(&[&"0".to_string(), &original_db_key]);
     let loan_json = serde_json::json!({
         "total_quota": 24,
@@ -555,13 +602,12 @@ fn test_loans_data_accessible_after_reset() {
         .expect("Should set owed_capital");
 
     // 3. Crear repo y verificar que el préstamo existe antes del reset
-    let repo = PaymentRepo {
-        pool: get_pool_connection(),
-    };
+    let repo = PaymentRepo::new(pool());
     let history_before = repo
         .get_user_history(original_access_token.clone())
+        .await
         .expect("Should get user history before reset");
-    assert_eq!(history_before.owed_capital, 4000.0, "Should have owed_capital before reset");
+    assert_eq!(history_before.owed_capital, Money::from(4000), "Should have owed_capital before reset");
 
     // 4. Configurar respuestas de seguridad
     let answers = [
@@ -569,15 +615,18 @@ fn test_loans_data_accessible_after_reset() {
         "answer_1".to_string(),
         "answer_2".to_string(),
     ];
-    let _ = configure_all_security_answers(original_access_token.clone(), answers.clone());
+    let _ = configure_all_security_answers(&pool(), original_access_token.clone(), answers.clone())
+        .await;
 
     // 5. Resetear contraseña
     let reset_result = reset_password(
+        &pool(),
         username.clone(),
         0,
         answers[0].clone(),
         new_password.to_string(),
-    );
+    )
+    .await;
     assert!(reset_result.is_ok(), "Should reset password");
     
     let new_token_info = reset_result.unwrap();
@@ -585,23 +634,22 @@ fn test_loans_data_accessible_after_reset() {
 
     // 6. Verificar que los datos de préstamos están disponibles con el nuevo token
     // Para esto usamos LoanRepo
-    let loan_repo = LoanRepo {
-        pool: get_pool_connection(),
-    };
+    let loan_repo = LoanRepo::new(pool());
     
     // Nota: LoanRepo.get_user_loans requiere affiliate_key, así que usamos PaymentRepo.get_user_history
     let history_after = repo
         .get_user_history(new_access_token.clone())
+        .await
         .expect("Should get user history after reset");
-    assert_eq!(history_after.owed_capital, 4000.0, "owed_capital should be preserved after reset");
+    assert_eq!(history_after.owed_capital, Money::from(4000), "owed_capital should be preserved after reset");
 
     // Cleanup
     cleanup_test_user(&username);
 }
 
 /// TEST: Verificar que los datos reales de FINES se mantienen accesibles tras reset
-#[test]
-fn test_fines_data_accessible_after_reset() {
+#[tokio::test]
+async fn test_fines_data_accessible_after_reset() {
     let _ = dotenv();
 
     let username =
@@ -613,10 +661,12 @@ fn test_fines_data_accessible_after_reset() {
 
     // 1. Crear usuario
     let creation = create_user_with_access_token(
+        &pool(),
         username.clone(),
         original_password.to_string(),
         "Fines Test User".to_string(),
-    );
+    )
+    .await;
     assert!(creation.is_ok(), "Should create user");
 
     let original_token_info = creation.unwrap();
@@ -624,7 +674,7 @@ fn test_fines_data_accessible_after_reset() {
     let original_db_key = hashing_composite_key(&[&original_access_token]);
 
     // 2. Crear manualmente una multa en Redis
-    let mut con = get_pool_connection().into_inner().get().unwrap();
+    let mut con = pool().client().get_connection().unwrap();
     let fine_hash_key = hashing_composite_key(&[&"0".to_string(), &original_db_key]);
     let fine_json = serde_json::json!({
         "amount": 500.0,
@@ -646,13 +696,12 @@ fn test_fines_data_accessible_after_reset() {
         .expect("Should set owed_capital");
 
     // 3. Crear repo y verificar que la multa existe antes del reset
-    let repo = PaymentRepo {
-        pool: get_pool_connection(),
-    };
+    let repo = PaymentRepo::new(pool());
     let history_before = repo
         .get_user_history(original_access_token.clone())
+        .await
         .expect("Should get user history before reset");
-    assert_eq!(history_before.owed_capital, 500.0, "Should have owed_capital from fine");
+    assert_eq!(history_before.owed_capital, Money::from(500), "Should have owed_capital from fine");
 
     // 4. Configurar respuestas de seguridad
     let answers = [
@@ -660,15 +709,18 @@ fn test_fines_data_accessible_after_reset() {
         "answer_1".to_string(),
         "answer_2".to_string(),
     ];
-    let _ = configure_all_security_answers(original_access_token.clone(), answers.clone());
+    let _ = configure_all_security_answers(&pool(), original_access_token.clone(), answers.clone())
+        .await;
 
     // 5. Resetear contraseña
     let reset_result = reset_password(
+        &pool(),
         username.clone(),
         0,
         answers[0].clone(),
         new_password.to_string(),
-    );
+    )
+    .await;
     assert!(reset_result.is_ok(), "Should reset password");
     
     let new_token_info = reset_result.unwrap();
@@ -677,8 +729,9 @@ fn test_fines_data_accessible_after_reset() {
     // 6. Verificar que los datos de multas están disponibles con el nuevo token
     let history_after = repo
         .get_user_history(new_access_token.clone())
+        .await
         .expect("Should get user history after reset");
-    assert_eq!(history_after.owed_capital, 500.0, "owed_capital should be preserved after reset");
+    assert_eq!(history_after.owed_capital, Money::from(500), "owed_capital should be preserved after reset");
 
     // Cleanup
     cleanup_test_user(&username);
@@ -6,7 +6,7 @@ use rand::{
 use rand::rng;
 
 use general_api::{
-    endpoints::handlers::configs::connection_pool::get_pool_connection,
+    endpoints::handlers::configs::connection_pool::{get_pool_connection, RedisPool},
     repos::auth::{
         configure_all_security_answers, create_user_with_access_token,
         get_user_access_token, reset_password, utils::hashing_composite_key,
@@ -15,8 +15,14 @@ use general_api::{
 };
 use redis::Commands;
 
+/// cada llamada abre su propia conexión: la multiplexada queda atada al runtime de tokio que
+/// la abrió y cada #[tokio::test] tiene el suyo
+fn pool() -> RedisPool {
+    get_pool_connection().unwrap()
+}
+
 fn cleanup_test_user(username: &str) {
-    let mut con = get_pool_connection().into_inner().get().unwrap();
+    let mut con = pool().client().get_connection().unwrap();
 
     let access_token =
         hashing_composite_key(&[&username.to_string(), &"ElTestoPaga".to_string()]);
@@ -47,8 +53,8 @@ fn cleanup_test_user(username: &str) {
 }
 
 /// TEST EDGE CASE 1: Validate with out-of-bounds question_index
-#[test]
-fn test_validate_with_out_of_bounds_index() {
+#[tokio::test]
+async fn test_validate_with_out_of_bounds_index() {
     let _ = dotenv();
 
     let username =
@@ -58,10 +64,12 @@ fn test_validate_with_out_of_bounds_index() {
     cleanup_test_user(&username);
 
     let creation_result = create_user_with_access_token(
+        &pool(),
         username.clone(),
         password.clone(),
         "Edge Case Test User".to_string(),
-    );
+    )
+    .await;
     assert!(creation_result.is_ok(), "Should create test user");
 
     let answers = [
@@ -69,12 +77,16 @@ fn test_validate_with_out_of_bounds_index() {
         "answer_1".to_string(),
         "answer_2".to_string(),
     ];
-    let access_token =
-        get_user_access_token(username.clone(), password.clone()).unwrap().access_token;
-    let config_result = configure_all_security_answers(access_token, answers.clone());
+    let access_token = get_user_access_token(&pool(), username.clone(), password.clone())
+        .await
+        .unwrap()
+        .access_token;
+    let config_result = configure_all_security_answers(&pool(), access_token, answers.clone())
+        .await;
     assert!(config_result.is_ok(), "Should configure security answers");
 
-    let result = validate_security_answer(username.clone(), 3, "answer_0".to_string());
+    let result = validate_security_answer(&pool(), username.clone(), 3, "answer_0".to_string())
+        .await;
     println!("Out-of-bounds result: {:?}", result);
     assert!(result.is_err(), "Should fail with out-of-bounds index");
 
@@ -82,8 +94,8 @@ fn test_validate_with_out_of_bounds_index() {
 }
 
 /// TEST EDGE CASE 2: Validate with question_index = 255 (max u8)
-#[test]
-fn test_validate_with_max_u8_index() {
+#[tokio::test]
+async fn test_validate_with_max_u8_index() {
     let _ = dotenv();
 
     let username =
@@ -93,18 +105,23 @@ fn test_validate_with_max_u8_index() {
     cleanup_test_user(&username);
 
     let creation_result = create_user_with_access_token(
+        &pool(),
         username.clone(),
         password.clone(),
         "Edge Case Test User".to_string(),
-    );
+    )
+    .await;
     assert!(creation_result.is_ok());
 
     let answers = ["answer_0".to_string(), "answer_1".to_string(), "answer_2".to_string()];
-    let access_token =
-        get_user_access_token(username.clone(), password.clone()).unwrap().access_token;
-    let _ = configure_all_security_answers(access_token, answers.clone());
-
-    let result = validate_security_answer(username.clone(), 255, "answer_0".to_string());
+    let access_token = get_user_access_token(&pool(), username.clone(), password.clone())
+        .await
+        .unwrap()
+        .access_token;
+    let _ = configure_all_security_answers(&pool(), access_token, answers.clone()).await;
+
+    let result = validate_security_answer(&pool(), username.clone(), 255, "answer_0".to_string())
+        .await;
     println!("Max u8 result: {:?}", result);
     assert!(result.is_err(), "Should fail with index 255");
 
@@ -112,8 +129,8 @@ fn test_validate_with_max_u8_index() {
 }
 
 /// TEST EDGE CASE 3: Multiple consecutive password resets
-#[test]
-fn test_multiple_consecutive_resets() {
+#[tokio::test]
+async fn test_multiple_consecutive_resets() {
     let _ = dotenv();
 
     let username =
@@ -125,10 +142,12 @@ fn test_multiple_consecutive_resets() {
     cleanup_test_user(&username);
 
     let creation = create_user_with_access_token(
+        &pool(),
         username.clone(),
         password_1.to_string(),
         "Edge Case User".to_string(),
-    );
+    )
+    .await;
     assert!(creation.is_ok());
 
     let answers = [
@@ -136,33 +155,47 @@ fn test_multiple_consecutive_resets() {
         "answer_1".to_string(),
         "answer_2".to_string(),
     ];
-    let access_token =
-        get_user_access_token(username.clone(), password_1.to_string()).unwrap().access_token;
-    let _ = configure_all_security_answers(access_token, answers.clone());
-
-    let reset_1 =
-        reset_password(username.clone(), 0, answers[0].clone(), password_2.to_string());
+    let access_token = get_user_access_token(&pool(), username.clone(), password_1.to_string())
+        .await
+        .unwrap()
+        .access_token;
+    let _ = configure_all_security_answers(&pool(), access_token, answers.clone()).await;
+
+    let reset_1 = reset_password(
+        &pool(),
+        username.clone(),
+        0,
+        answers[0].clone(),
+        password_2.to_string(),
+    )
+    .await;
     assert!(reset_1.is_ok(), "First reset should succeed");
 
-    let login_1 = get_user_access_token(username.clone(), password_2.to_string());
+    let login_1 = get_user_access_token(&pool(), username.clone(), password_2.to_string()).await;
     assert!(login_1.is_ok(), "Should login with password_2");
 
-    let reset_2 =
-        reset_password(username.clone(), 1, answers[1].clone(), password_3.to_string());
+    let reset_2 = reset_password(
+        &pool(),
+        username.clone(),
+        1,
+        answers[1].clone(),
+        password_3.to_string(),
+    )
+    .await;
     assert!(reset_2.is_ok(), "Second reset should succeed");
 
-    let login_2 = get_user_access_token(username.clone(), password_3.to_string());
+    let login_2 = get_user_access_token(&pool(), username.clone(), password_3.to_string()).await;
     assert!(login_2.is_ok(), "Should login with password_3");
 
-    let old_login = get_user_access_token(username.clone(), password_1.to_string());
+    let old_login = get_user_access_token(&pool(), username.clone(), password_1.to_string()).await;
     assert!(old_login.is_err(), "Should NOT login with old password_1");
 
     cleanup_test_user(&username);
 }
 
 /// TEST EDGE CASE 4: Validate with different indices for same user
-#[test]
-fn test_validate_all_three_indices() {
+#[tokio::test]
+async fn test_validate_all_three_indices() {
     let _ = dotenv();
 
     let username =
@@ -172,26 +205,30 @@ fn test_validate_all_three_indices() {
     cleanup_test_user(&username);
 
     let creation = create_user_with_access_token(
+        &pool(),
         username.clone(),
         password.clone(),
         "Edge Case User".to_string(),
-    );
+    )
+    .await;
     assert!(creation.is_ok());
 
     let answers = ["answer_0".to_string(), "answer_1".to_string(), "answer_2".to_string()];
-    let access_token =
-        get_user_access_token(username.clone(), password.clone()).unwrap().access_token;
-    let _ = configure_all_security_answers(access_token, answers.clone());
+    let access_token = get_user_access_token(&pool(), username.clone(), password.clone())
+        .await
+        .unwrap()
+        .access_token;
+    let _ = configure_all_security_answers(&pool(), access_token, answers.clone()).await;
 
-    let result_0 = validate_security_answer(username.clone(), 0, answers[0].clone());
+    let result_0 = validate_security_answer(&pool(), username.clone(), 0, answers[0].clone()).await;
     println!("Index 0 validation: {:?}", result_0);
     assert!(result_0.is_ok(), "Should validate index 0");
 
-    let result_1 = validate_security_answer(username.clone(), 1, answers[1].clone());
+    let result_1 = validate_security_answer(&pool(), username.clone(), 1, answers[1].clone()).await;
     println!("Index 1 validation: {:?}", result_1);
     assert!(result_1.is_ok(), "Should validate index 1");
 
-    let result_2 = validate_security_answer(username.clone(), 2, answers[2].clone());
+    let result_2 = validate_security_answer(&pool(), username.clone(), 2, answers[2].clone()).await;
     println!("Index 2 validation: {:?}", result_2);
     assert!(result_2.is_ok(), "Should validate index 2");
 
@@ -205,8 +242,8 @@ fn test_validate_all_three_indices() {
 }
 
 /// TEST EDGE CASE 5: Wrong answer with correct index
-#[test]
-fn test_wrong_answer_correct_index() {
+#[tokio::test]
+async fn test_wrong_answer_correct_index() {
     let _ = dotenv();
 
     let username =
@@ -216,10 +253,12 @@ fn test_wrong_answer_correct_index() {
     cleanup_test_user(&username);
 
     let creation = create_user_with_access_token(
+        &pool(),
         username.clone(),
         password.clone(),
         "Edge Case User".to_string(),
-    );
+    )
+    .await;
     assert!(creation.is_ok());
 
     let answers = [
@@ -227,11 +266,14 @@ fn test_wrong_answer_correct_index() {
         "correct_answer_1".to_string(),
         "correct_answer_2".to_string(),
     ];
-    let access_token =
-        get_user_access_token(username.clone(), password.clone()).unwrap().access_token;
-    let _ = configure_all_security_answers(access_token, answers);
-
-    let result = validate_security_answer(username.clone(), 0, "wrong_answer".to_string());
+    let access_token = get_user_access_token(&pool(), username.clone(), password.clone())
+        .await
+        .unwrap()
+        .access_token;
+    let _ = configure_all_security_answers(&pool(), access_token, answers).await;
+
+    let result = validate_security_answer(&pool(), username.clone(), 0, "wrong_answer".to_string())
+        .await;
     println!("Wrong answer with correct index: {:?}", result);
     assert!(result.is_err(), "Should fail with wrong answer");
 
@@ -239,8 +281,8 @@ fn test_wrong_answer_correct_index() {
 }
 
 /// TEST EDGE CASE 6: Correct answer with wrong index
-#[test]
-fn test_correct_answer_wrong_index() {
+#[tokio::test]
+async fn test_correct_answer_wrong_index() {
     let _ = dotenv();
 
     let username =
@@ -250,10 +292,12 @@ fn test_correct_answer_wrong_index() {
     cleanup_test_user(&username);
 
     let creation = create_user_with_access_token(
+        &pool(),
         username.clone(),
         password.clone(),
         "Edge Case User".to_string(),
-    );
+    )
+    .await;
     assert!(creation.is_ok());
 
     let answers = [
@@ -261,11 +305,13 @@ fn test_correct_answer_wrong_index() {
         "answer_1".to_string(),
         "answer_2".to_string(),
     ];
-    let access_token =
-        get_user_access_token(username.clone(), password.clone()).unwrap().access_token;
-    let _ = configure_all_security_answers(access_token, answers.clone());
+    let access_token = get_user_access_token(&pool(), username.clone(), password.clone())
+        .await
+        .unwrap()
+        .access_token;
+    let _ = configure_all_security_answers(&pool(), access_token, answers.clone()).await;
 
-    let result = validate_security_answer(username.clone(), 1, answers[0].clone());
+    let result = validate_security_answer(&pool(), username.clone(), 1, answers[0].clone()).await;
     println!("Correct answer with wrong index: {:?}", result);
     assert!(result.is_err(), "Should fail when answer is at wrong index");
 
@@ -273,8 +319,8 @@ fn test_correct_answer_wrong_index() {
 }
 
 /// TEST EDGE CASE 7: Case sensitivity and normalization
-#[test]
-fn test_case_sensitivity_normalization() {
+#[tokio::test]
+async fn test_case_sensitivity_normalization() {
     let _ = dotenv();
 
     let username =
@@ -284,10 +330,12 @@ fn test_case_sensitivity_normalization() {
     cleanup_test_user(&username);
 
     let creation = create_user_with_access_token(
+        &pool(),
         username.clone(),
         password.clone(),
         "Edge Case User".to_string(),
-    );
+    )
+    .await;
     assert!(creation.is_ok());
 
     let answers = [
@@ -295,17 +343,29 @@ fn test_case_sensitivity_normalization() {
         "another_answer".to_string(),
         "third_answer".to_string(),
     ];
-    let access_token =
-        get_user_access_token(username.clone(), password.clone()).unwrap().access_token;
-    let _ = configure_all_security_answers(access_token, answers);
-
-    let result_upper =
-        validate_security_answer(username.clone(), 0, "LOWERCASE_ANSWER".to_string());
+    let access_token = get_user_access_token(&pool(), username.clone(), password.clone())
+        .await
+        .unwrap()
+        .access_token;
+    let _ = configure_all_security_answers(&pool(), access_token, answers).await;
+
+    let result_upper = validate_security_answer(
+        &pool(),
+        username.clone(),
+        0,
+        "LOWERCASE_ANSWER".to_string(),
+    )
+    .await;
     println!("Uppercase validation: {:?}", result_upper);
     assert!(result_upper.is_ok(), "Should normalize case and match");
 
-    let result_mixed =
-        validate_security_answer(username.clone(), 0, "LowerCase_Answer".to_string());
+    let result_mixed = validate_security_answer(
+        &pool(),
+        username.clone(),
+        0,
+        "LowerCase_Answer".to_string(),
+    )
+    .await;
     println!("Mixed case validation: {:?}", result_mixed);
     assert!(result_mixed.is_ok(), "Should normalize and match");
 
@@ -313,8 +373,8 @@ fn test_case_sensitivity_normalization() {
 }
 
 /// TEST EDGE CASE 8: Reset preserves all 3 answers
-#[test]
-fn test_reset_preserves_all_answers() {
+#[tokio::test]
+async fn test_reset_preserves_all_answers() {
     let _ = dotenv();
 
     let username =
@@ -325,10 +385,12 @@ fn test_reset_preserves_all_answers() {
     cleanup_test_user(&username);
 
     let creation = create_user_with_access_token(
+        &pool(),
         username.clone(),
         original_password.to_string(),
         "Edge Case User".to_string(),
-    );
+    )
+    .await;
     assert!(creation.is_ok());
 
     let original_answers = [
@@ -337,31 +399,34 @@ fn test_reset_preserves_all_answers() {
         "original_answer_2".to_string(),
     ];
     let access_token =
-        get_user_access_token(username.clone(), original_password.to_string())
+        get_user_access_token(&pool(), username.clone(), original_password.to_string())
+            .await
             .unwrap()
             .access_token;
-    let _ = configure_all_security_answers(access_token, original_answers.clone());
+    let _ = configure_all_security_answers(&pool(), access_token, original_answers.clone()).await;
 
     let reset = reset_password(
+        &pool(),
         username.clone(),
         0,
         original_answers[0].clone(),
         new_password.to_string(),
-    );
+    )
+    .await;
     assert!(reset.is_ok(), "Reset should succeed");
 
     let validate_0 =
-        validate_security_answer(username.clone(), 0, original_answers[0].clone());
+        validate_security_answer(&pool(), username.clone(), 0, original_answers[0].clone()).await;
     println!("Post-reset validation with answer 0: {:?}", validate_0);
     assert!(validate_0.is_ok(), "Answer 0 should still work after reset");
 
     let validate_1 =
-        validate_security_answer(username.clone(), 1, original_answers[1].clone());
+        validate_security_answer(&pool(), username.clone(), 1, original_answers[1].clone()).await;
     println!("Post-reset validation with answer 1: {:?}", validate_1);
     assert!(validate_1.is_ok(), "Answer 1 should still work after reset");
 
     let validate_2 =
-        validate_security_answer(username.clone(), 2, original_answers[2].clone());
+        validate_security_answer(&pool(), username.clone(), 2, original_answers[2].clone()).await;
     println!("Post-reset validation with answer 2: {:?}", validate_2);
     assert!(validate_2.is_ok(), "Answer 2 should still work after reset");
 
@@ -369,8 +434,8 @@ fn test_reset_preserves_all_answers() {
 }
 
 /// TEST EDGE CASE 9: Answer with spaces - normalization
-#[test]
-fn test_answer_with_spaces() {
+#[tokio::test]
+async fn test_answer_with_spaces() {
     let _ = dotenv();
 
     let username =
@@ -380,10 +445,12 @@ fn test_answer_with_spaces() {
     cleanup_test_user(&username);
 
     let creation = create_user_with_access_token(
+        &pool(),
         username.clone(),
         password.clone(),
         "Edge Case User".to_string(),
-    );
+    )
+    .await;
     assert!(creation.is_ok());
 
     let answers = [
@@ -391,12 +458,19 @@ fn test_answer_with_spaces() {
         "another answer".to_string(),
         "third".to_string(),
     ];
-    let access_token =
-        get_user_access_token(username.clone(), password.clone()).unwrap().access_token;
-    let _ = configure_all_security_answers(access_token, answers);
-
-    let result =
-        validate_security_answer(username.clone(), 0, "   answer with spaces   ".to_string());
+    let access_token = get_user_access_token(&pool(), username.clone(), password.clone())
+        .await
+        .unwrap()
+        .access_token;
+    let _ = configure_all_security_answers(&pool(), access_token, answers).await;
+
+    let result = validate_security_answer(
+        &pool(),
+        username.clone(),
+        0,
+        "   answer with spaces   ".to_string(),
+    )
+    .await;
     println!("Answer with spaces validation: {:?}", result);
     assert!(result.is_ok(), "Should normalize spaces and match");
 
@@ -404,14 +478,19 @@ fn test_answer_with_spaces() {
 }
 
 /// TEST EDGE CASE 10: Non-existent user
-#[test]
-fn test_validate_nonexistent_user() {
+#[tokio::test]
+async fn test_validate_nonexistent_user() {
     let _ = dotenv();
 
     let nonexistent_user = "this_user_definitely_does_not_exist_12345";
 
-    let result =
-        validate_security_answer(nonexistent_user.to_string(), 0, "some_answer".to_string());
+    let result = validate_security_answer(
+        &pool(),
+        nonexistent_user.to_string(),
+        0,
+        "some_answer".to_string(),
+    )
+    .await;
     println!("Non-existent user validation: {:?}", result);
     assert!(result.is_err(), "Should fail for non-existent user");
 }
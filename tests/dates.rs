@@ -0,0 +1,134 @@
+// Tests de los tipos de fecha (models::dates) y de la migración de fechas guardadas como texto
+// La parte de migración asume que redis está corriendo en 127.0.0.1
+
+use std::time::Duration;
+
+use general_api::models::dates::{Date, DateTime};
+use general_api::endpoints::handlers::configs::connection_pool::RedisPool;
+use general_api::repos::migrations::migrate_stored_dates;
+use redis::{Client, Commands, JsonCommands};
+
+#[test]
+fn date_accepts_legacy_formats() {
+    let expected: Date = "2025-03-07".parse().unwrap();
+
+    assert_eq!("07/03/2025".parse::<Date>().unwrap(), expected);
+    assert_eq!("07-03-2025".parse::<Date>().unwrap(), expected);
+    assert_eq!(" 2025-03-07 ".parse::<Date>().unwrap(), expected);
+    assert_eq!(expected.to_string(), "2025-03-07");
+
+    assert!("0-00-0000".parse::<Date>().is_err());
+    assert!("fecha-invalida".parse::<Date>().is_err());
+}
+
+#[test]
+fn date_only_payment_dates_are_local_midnight() {
+    // America/Guatemala es UTC-6 todo el año
+    let date_time: DateTime = "2025-10-13".parse().unwrap();
+
+    assert_eq!(date_time.to_string(), "2025-10-13T06:00:00+00:00");
+    assert_eq!(date_time.local().to_rfc3339(), "2025-10-13T00:00:00-06:00");
+    assert_eq!(date_time.local_date(), "2025-10-13".parse().unwrap());
+}
+
+#[test]
+fn date_time_keeps_explicit_offsets() {
+    let date_time: DateTime = "2025-10-14T03:00:00+00:00".parse().unwrap();
+
+    // en UTC ya es 14, pero en la cooperativa todavía es 13
+    assert_eq!(date_time.local_date(), "2025-10-13".parse().unwrap());
+    assert!("0-00-0000".parse::<DateTime>().is_err());
+}
+
+#[test]
+fn optional_dates_with_garbage_deserialize_as_none() {
+    use general_api::models::graphql::Quota;
+
+    let quota: Quota = serde_json::from_str(
+        r#"{"user_id":"u","amount":10.0,"exp_date":"fecha-invalida","quota_type":"Afiliado",
+            "monto_pagado":null,"multa":null,"pay_by":null,"loan_id":null,"is_extraordinary":null,
+            "payed":false,"quota_number":null,"nombre_prestamo":null,"nombre_usuario":null,
+            "identifier":null}"#,
+    )
+    .unwrap();
+
+    assert_eq!(quota.exp_date, None);
+}
+
+#[test]
+fn payments_with_garbage_dates_still_deserialize() {
+    use general_api::models::redis::Payment;
+
+    let mut stored = serde_json::to_value(Payment::default()).unwrap();
+    stored["date_created"] = serde_json::json!("0-00-0000");
+
+    let payment: Payment = serde_json::from_value(stored).unwrap();
+    assert_eq!(payment.date_created, DateTime(chrono::DateTime::UNIX_EPOCH));
+}
+
+#[tokio::test]
+async fn migration_normalizes_stored_date_strings() {
+    let _lock = general_api::test_sync::redis_test_lock().await;
+    let client = Client::open("redis://127.0.0.1/").unwrap();
+    let mut con = client.get_connection().unwrap();
+    let pool = RedisPool::new(client, Duration::from_secs(5), Duration::from_secs(10));
+
+    let now = chrono::Utc::now().timestamp_nanos_opt().unwrap();
+    let payment_key = format!("users:testmigration{}:payments:p1", now);
+    let quota_key = format!("users:testmigration{}:quotas_afiliado:q1", now);
+
+    let _: () = con
+        .json_set(
+            &payment_key,
+            "$",
+            &serde_json::json!({ "date_created": "2025-10-13", "name": "viejo" }),
+        )
+        .unwrap();
+    let _: () = con
+        .json_set(&quota_key, "$", &serde_json::json!({ "exp_date": "01/02/2025" }))
+        .unwrap();
+    let _: () = con.del("migrations:stored_dates_v1").unwrap();
+
+    migrate_stored_dates(&pool).await.expect("migration failed");
+
+    let payment_date: String = con.json_get(&payment_key, "$.date_created").unwrap();
+    let quota_date: String = con.json_get(&quota_key, "$.exp_date").unwrap();
+    assert_eq!(payment_date, r#"["2025-10-13T06:00:00+00:00"]"#);
+    assert_eq!(quota_date, r#"["2025-02-01"]"#);
+    assert!(con.exists::<_, bool>("migrations:stored_dates_v1").unwrap());
+
+    let _: () = con.del(&payment_key).unwrap();
+    let _: () = con.del(&quota_key).unwrap();
+}
+
+#[tokio::test]
+async fn migration_with_unparseable_dates_is_not_marked_done() {
+    let _lock = general_api::test_sync::redis_test_lock().await;
+    let client = Client::open("redis://127.0.0.1/").unwrap();
+    let mut con = client.get_connection().unwrap();
+    let pool = RedisPool::new(client, Duration::from_secs(5), Duration::from_secs(10));
+
+    let now = chrono::Utc::now().timestamp_nanos_opt().unwrap();
+    let good_key = format!("users:testmigration{}:payments:p1", now);
+    let bad_key = format!("users:testmigration{}:payments:p2", now);
+
+    let _: () = con
+        .json_set(&good_key, "$", &serde_json::json!({ "date_created": "2025-10-13" }))
+        .unwrap();
+    let _: () = con
+        .json_set(&bad_key, "$", &serde_json::json!({ "date_created": "0-00-0000" }))
+        .unwrap();
+    let _: () = con.del("migrations:stored_dates_v1").unwrap();
+
+    assert!(migrate_stored_dates(&pool).await.is_err());
+
+    // lo que sí se pudo interpretar queda migrado, lo demás se queda para el próximo arranque
+    let good_date: String = con.json_get(&good_key, "$.date_created").unwrap();
+    let bad_date: String = con.json_get(&bad_key, "$.date_created").unwrap();
+    assert_eq!(good_date, r#"["2025-10-13T06:00:00+00:00"]"#);
+    assert_eq!(bad_date, r#"["0-00-0000"]"#);
+    assert!(!con.exists::<_, bool>("migrations:stored_dates_v1").unwrap());
+
+    let _: () = con.del(&good_key).unwrap();
+    let _: () = con.del(&bad_key).unwrap();
+}
@@ -0,0 +1,52 @@
+// Tests de AppError: el mismo error sale como extensions.code en graphql y como status en REST
+
+use actix_web::{body::to_bytes, http::StatusCode, ResponseError};
+use general_api::errors::AppError;
+use juniper::{DefaultScalarValue, IntoFieldError};
+
+#[test]
+fn field_errors_carry_code_and_fields() {
+    let error: juniper::FieldError<DefaultScalarValue> =
+        AppError::validation("Tipo de cambio inválido: abc", &["rate"]).into_field_error();
+
+    assert_eq!(error.message(), "Tipo de cambio inválido: abc");
+    let extensions = serde_json::to_value(error.extensions()).unwrap();
+    assert_eq!(extensions["code"], "VALIDATION");
+    assert_eq!(extensions["fields"], serde_json::json!(["rate"]));
+
+    // solo las validaciones traen fields
+    let error: juniper::FieldError<DefaultScalarValue> =
+        AppError::not_found("Payment not found").into_field_error();
+    let extensions = serde_json::to_value(error.extensions()).unwrap();
+    assert_eq!(extensions, serde_json::json!({ "code": "NOT_FOUND" }));
+}
+
+#[test]
+fn errors_map_to_http_status_codes() {
+    let cases = [
+        (AppError::not_found("x"), StatusCode::NOT_FOUND),
+        (AppError::unauthorized("x"), StatusCode::UNAUTHORIZED),
+        (AppError::validation("x", &[]), StatusCode::BAD_REQUEST),
+        (AppError::conflict("x"), StatusCode::CONFLICT),
+        (AppError::storage("x"), StatusCode::INTERNAL_SERVER_ERROR),
+        (AppError::query_too_complex("x"), StatusCode::BAD_REQUEST),
+        (AppError::persisted_query_not_found("x"), StatusCode::BAD_REQUEST),
+    ];
+
+    for (error, status) in cases {
+        assert_eq!(error.status_code(), status, "{:?}", error);
+        assert_eq!(error.error_response().status(), status);
+    }
+}
+
+#[actix_web::test]
+async fn rest_body_keeps_message_and_adds_code() {
+    let response = AppError::conflict("Couldn't Create User").error_response();
+    let body = to_bytes(response.into_body()).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(
+        body,
+        serde_json::json!({ "message": "Couldn't Create User", "code": "CONFLICT" })
+    );
+}
@@ -0,0 +1,1510 @@
+// Tests de los comprobantes: BlobStore en disco, S3 contra un MinIO local y las rutas
+// /general/upload_ticket_payment, /general/get_ticket_payment y /general/submit_payment, y los
+// documentos de /general/upload_attachment y /general/get_attachment, y las descargas de
+// /general/statement y /general/export
+// las rutas necesitan redis corriendo (igual que los otros tests), el de S3 solo corre si está
+// MINIO_ENDPOINT (ej. docker run -p 9000:9000 minio/minio server /data, con un bucket creado
+// y MINIO_BUCKET / MINIO_ACCESS_KEY / MINIO_SECRET_KEY)
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use actix_web::http::StatusCode;
+use actix_web::http::header::{EntityTag, IfNoneMatch};
+use actix_web::test::{TestRequest, call_service, init_service, read_body, read_body_json};
+use actix_web::{App, web::Data};
+use futures::TryStreamExt;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::{DynamicImage, GenericImageView, RgbImage};
+use rand::distr::{Alphanumeric, SampleString};
+
+use general_api::endpoints::file_endpoints::file_endpoints;
+use general_api::endpoints::handlers::configs::connection_pool::get_pool_connection;
+use general_api::endpoints::handlers::configs::schema::GeneralContext;
+use general_api::errors::AppError;
+use general_api::models::currency::Currency;
+use general_api::models::dates::today;
+use general_api::models::file::StoredReceipt;
+use general_api::models::graphql::{AttachmentOwnerType, Quota, QuotaType};
+use general_api::models::redis::Loan as RedisLoan;
+use general_api::repos::auth::create_user_with_access_token;
+use general_api::repos::auth::utils::hashing_composite_key;
+use general_api::repos::file::local::LocalBlobStore;
+use general_api::repos::file::ocr::ReceiptOcr;
+use general_api::repos::file::payment_link::collect_orphan_receipts;
+use general_api::repos::file::receipt::{
+    MAX_RECEIPT_BYTES, MAX_RECEIPT_DIMENSION, ReceiptKind, normalize_receipt,
+};
+use general_api::repos::file::s3::S3BlobStore;
+use general_api::repos::file::signed_url::ReceiptUrlSigner;
+use general_api::repos::file::store::{BlobStore, ByteRange, content_type_for};
+use general_api::repos::file::validate_ticket_id;
+use general_api::repos::graphql::attachment::AttachmentRepo;
+use general_api::repos::graphql::payment::PaymentRepo;
+use general_api::repos::graphql::store::{AttachmentStore, PaymentStore};
+
+fn random_suffix() -> String {
+    Alphanumeric.sample_string(&mut rand::rng(), 10)
+}
+
+/// carpeta nueva en el temp del sistema para cada test
+fn temp_root() -> PathBuf {
+    std::env::temp_dir().join(format!("general-api-blobs-{}", random_suffix()))
+}
+
+fn photo(width: u32, height: u32) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+        image::Rgb([(x % 256) as u8, (y % 256) as u8, 128])
+    }))
+}
+
+fn jpeg(width: u32, height: u32) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    photo(width, height)
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, 90))
+        .unwrap();
+    bytes
+}
+
+fn png(width: u32, height: u32) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    photo(width, height)
+        .write_with_encoder(PngEncoder::new(&mut bytes))
+        .unwrap();
+    bytes
+}
+
+/// el JPEG con un segmento EXIF (APP1) como los del celular: orientación 6 (girar 90°) y un
+/// texto que no debe sobrevivir
+fn jpeg_with_exif(width: u32, height: u32) -> Vec<u8> {
+    let mut exif = b"Exif\0\0MM\0\x2a\0\0\0\x08".to_vec();
+    // un solo tag: Orientation (0x0112), SHORT, 1 valor = 6
+    exif.extend_from_slice(b"\0\x01\x01\x12\0\x03\0\0\0\x01\0\x06\0\0\0\0\0\0");
+    exif.extend_from_slice(b"GPS 14.6349 -90.5069");
+
+    let plain = jpeg(width, height);
+    let mut bytes = plain[..2].to_vec();
+    bytes.extend_from_slice(&[0xFF, 0xE1]);
+    bytes.extend_from_slice(&((exif.len() + 2) as u16).to_be_bytes());
+    bytes.extend_from_slice(&exif);
+    bytes.extend_from_slice(&plain[2..]);
+    bytes
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+/// junta los pedazos del stream
+async fn read_stream(
+    store: &dyn BlobStore,
+    key: &str,
+    range: Option<ByteRange>,
+) -> Option<Vec<u8>> {
+    let chunks: Vec<_> = store
+        .stream(key, range)
+        .await
+        .unwrap()?
+        .try_collect()
+        .await
+        .unwrap();
+    Some(chunks.concat())
+}
+
+/// put, get, stream, exists y delete de cualquier backend
+async fn roundtrip(store: &dyn BlobStore) {
+    let key = format!("payment-tickets/{}.jpeg", random_suffix());
+
+    assert!(!store.exists(&key).await.unwrap());
+    assert_eq!(store.get(&key).await.unwrap(), None);
+    assert_eq!(store.size(&key).await.unwrap(), None);
+    assert!(store.stream(&key, None).await.unwrap().is_none());
+
+    store.put(&key, b"primera".to_vec()).await.unwrap();
+    store.put(&key, b"comprobante".to_vec()).await.unwrap();
+    assert!(store.exists(&key).await.unwrap());
+    assert_eq!(
+        store.get(&key).await.unwrap(),
+        Some(b"comprobante".to_vec())
+    );
+    assert_eq!(store.size(&key).await.unwrap(), Some(11));
+    assert_eq!(
+        read_stream(store, &key, None).await,
+        Some(b"comprobante".to_vec())
+    );
+    assert_eq!(
+        read_stream(store, &key, Some(ByteRange { start: 2, end: 5 })).await,
+        Some(b"mpro".to_vec())
+    );
+
+    store.delete(&key).await.unwrap();
+    assert!(!store.exists(&key).await.unwrap());
+    // borrar dos veces no es error
+    store.delete(&key).await.unwrap();
+}
+
+#[tokio::test]
+async fn local_store_roundtrip() {
+    let root = temp_root();
+    roundtrip(&LocalBlobStore::new(&root)).await;
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[tokio::test]
+async fn keys_cannot_leave_the_store() {
+    let store = LocalBlobStore::new(temp_root());
+
+    for key in [
+        "../secreto",
+        "payment-tickets/../../etc/passwd",
+        "/etc/passwd",
+        "",
+        "a//b",
+        "a b",
+    ] {
+        assert!(
+            matches!(store.get(key).await, Err(AppError::Validation { .. })),
+            "{}",
+            key
+        );
+        assert!(matches!(
+            store.put(key, vec![1]).await,
+            Err(AppError::Validation { .. })
+        ));
+    }
+}
+
+#[tokio::test]
+async fn local_store_streams_large_files_in_chunks() {
+    let root = temp_root();
+    let store = LocalBlobStore::new(&root);
+    let bytes: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+    store
+        .put("payment-tickets/grande.pdf", bytes.clone())
+        .await
+        .unwrap();
+
+    let chunks: Vec<_> = store
+        .stream("payment-tickets/grande.pdf", None)
+        .await
+        .unwrap()
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    assert!(chunks.len() > 1);
+    assert_eq!(chunks.concat(), bytes);
+
+    let range = ByteRange {
+        start: 70_000,
+        end: 150_000,
+    };
+    assert_eq!(
+        read_stream(&store, "payment-tickets/grande.pdf", Some(range)).await,
+        Some(bytes[70_000..=150_000].to_vec())
+    );
+
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn ticket_ids_must_be_hashes() {
+    assert!(validate_ticket_id(&"AB12".repeat(16)).is_ok());
+
+    for ticket_id in [
+        "no_existe".to_string(),
+        "../../etc/passwd".to_string(),
+        "ab12".repeat(16),
+        format!("{}/", "A".repeat(63)),
+        "A".repeat(65),
+    ] {
+        assert!(
+            matches!(
+                validate_ticket_id(&ticket_id),
+                Err(AppError::Validation { .. })
+            ),
+            "{}",
+            ticket_id
+        );
+    }
+}
+
+#[test]
+fn content_type_comes_from_the_extension() {
+    assert_eq!(content_type_for("payment-tickets/abc.jpeg"), "image/jpeg");
+    assert_eq!(content_type_for("payment-tickets/abc.JPG"), "image/jpeg");
+    assert_eq!(content_type_for("payment-tickets/abc.png"), "image/png");
+    assert_eq!(
+        content_type_for("payment-tickets/abc.pdf"),
+        "application/pdf"
+    );
+    assert_eq!(
+        content_type_for("payment-tickets/abc"),
+        "application/octet-stream"
+    );
+}
+
+#[test]
+fn receipts_are_sniffed_not_trusted() {
+    assert_eq!(ReceiptKind::sniff(&jpeg(4, 4)), Some(ReceiptKind::Jpeg));
+    assert_eq!(ReceiptKind::sniff(&png(4, 4)), Some(ReceiptKind::Png));
+    assert_eq!(ReceiptKind::sniff(b"%PDF-1.7\n"), Some(ReceiptKind::Pdf));
+    assert_eq!(ReceiptKind::sniff(b"<html>"), None);
+
+    for bytes in [b"<html></html>".to_vec(), b"GIF89a".to_vec(), Vec::new()] {
+        assert!(matches!(
+            normalize_receipt(bytes),
+            Err(AppError::Validation { .. })
+        ));
+    }
+    // dice ser JPEG pero no se puede decodificar
+    assert!(matches!(
+        normalize_receipt(b"\xff\xd8\xff\xe0 boleta".to_vec()),
+        Err(AppError::Validation { .. })
+    ));
+}
+
+#[test]
+fn receipts_have_a_size_cap() {
+    let mut pdf = b"%PDF-1.7\n".to_vec();
+    pdf.resize(MAX_RECEIPT_BYTES + 1, b' ');
+
+    assert!(matches!(
+        normalize_receipt(pdf),
+        Err(AppError::Validation { .. })
+    ));
+}
+
+#[test]
+fn pdfs_are_stored_as_they_are() {
+    let pdf = b"%PDF-1.7\n1 0 obj << >> endobj\n%%EOF".to_vec();
+    let receipt = normalize_receipt(pdf.clone()).unwrap();
+
+    assert_eq!(receipt.kind, ReceiptKind::Pdf);
+    assert_eq!(receipt.bytes, pdf);
+}
+
+#[test]
+fn photos_lose_exif_but_keep_orientation() {
+    let original = jpeg_with_exif(40, 20);
+    assert!(contains(&original, b"GPS 14.6349"));
+
+    let receipt = normalize_receipt(original).unwrap();
+    assert_eq!(receipt.kind, ReceiptKind::Jpeg);
+    assert!(!contains(&receipt.bytes, b"Exif"));
+    assert!(!contains(&receipt.bytes, b"GPS 14.6349"));
+
+    // orientación 6: la foto se guarda ya girada
+    let stored = image::load_from_memory(&receipt.bytes).unwrap();
+    assert_eq!(stored.dimensions(), (20, 40));
+}
+
+#[test]
+fn large_photos_are_downscaled() {
+    let receipt = normalize_receipt(jpeg(2400, 1200)).unwrap();
+    let stored = image::load_from_memory(&receipt.bytes).unwrap();
+    assert_eq!(
+        stored.dimensions(),
+        (MAX_RECEIPT_DIMENSION, MAX_RECEIPT_DIMENSION / 2)
+    );
+
+    let receipt = normalize_receipt(png(1000, 2500)).unwrap();
+    assert_eq!(receipt.kind, ReceiptKind::Png);
+    let stored = image::load_from_memory(&receipt.bytes).unwrap();
+    assert_eq!(stored.dimensions(), (800, MAX_RECEIPT_DIMENSION));
+
+    // las chicas quedan del mismo tamaño
+    let receipt = normalize_receipt(png(300, 200)).unwrap();
+    let stored = image::load_from_memory(&receipt.bytes).unwrap();
+    assert_eq!(stored.dimensions(), (300, 200));
+}
+
+#[test]
+fn s3_store_needs_a_bucket() {
+    assert!(matches!(
+        S3BlobStore::new("", " ", "us-east-1", "key", "secret"),
+        Err(AppError::Validation { .. })
+    ));
+    assert!(matches!(
+        S3BlobStore::new("no es url", "bucket", "us-east-1", "key", "secret"),
+        Err(AppError::Validation { .. })
+    ));
+    assert!(
+        S3BlobStore::new(
+            "http://localhost:9000",
+            "bucket",
+            "us-east-1",
+            "key",
+            "secret"
+        )
+        .is_ok()
+    );
+}
+
+#[tokio::test]
+async fn s3_store_roundtrip_against_minio() {
+    let Ok(endpoint) = std::env::var("MINIO_ENDPOINT") else {
+        println!("MINIO_ENDPOINT no está definido, se salta el test de S3");
+        return;
+    };
+    let env = |name: &str, default: &str| std::env::var(name).unwrap_or(default.to_string());
+
+    let store = S3BlobStore::new(
+        &endpoint,
+        &env("MINIO_BUCKET", "general-api"),
+        &env("MINIO_REGION", "us-east-1"),
+        &env("MINIO_ACCESS_KEY", "minioadmin"),
+        &env("MINIO_SECRET_KEY", "minioadmin"),
+    )
+    .unwrap();
+
+    roundtrip(&store).await;
+}
+
+#[actix_web::test]
+async fn upload_and_get_ticket_routes() {
+    dotenv::dotenv().ok();
+    let pool = get_pool_connection().unwrap();
+    let token = create_user_with_access_token(
+        &pool,
+        format!("comprobantes_{}", random_suffix()),
+        "pass".to_string(),
+        "Socio Comprobantes".to_string(),
+    )
+    .await
+    .unwrap()
+    .access_token;
+
+    let root = temp_root();
+    let blob_store: Arc<dyn BlobStore> = Arc::new(LocalBlobStore::new(&root));
+    let app = init_service(
+        App::new()
+            .app_data(Data::new(pool))
+            .app_data(Data::from(blob_store))
+            .app_data(Data::new(ReceiptUrlSigner::new(b"secreto", 300)))
+            .configure(file_endpoints),
+    )
+    .await;
+
+    // el cliente dice que es JPEG, pero es un PNG: se guarda y se sirve como PNG
+    let boundary = "comprobante-boundary";
+    let upload = |access_token: &str, file: &[u8]| {
+        let mut body = format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"boleta.jpeg\"\r\nContent-Type: image/jpeg\r\n\r\n"
+        )
+        .into_bytes();
+        body.extend_from_slice(file);
+        body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+
+        TestRequest::post()
+            .uri(&format!(
+                "/general/upload_ticket_payment?access_token={access_token}"
+            ))
+            .insert_header((
+                "content-type",
+                format!("multipart/form-data; boundary={boundary}"),
+            ))
+            .set_payload(body)
+            .to_request()
+    };
+    let boleta = png(30, 20);
+
+    let response = call_service(&app, upload("token_que_no_existe", &boleta)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = call_service(&app, upload(&token, b"<html>no soy boleta</html>")).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = call_service(&app, upload(&token, &boleta)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let info: serde_json::Value = read_body_json(response).await;
+    let ticket_id = info["ticket_id"].as_str().unwrap().to_string();
+
+    let download_uri =
+        format!("/general/get_ticket_payment?access_token={token}&ticket_id={ticket_id}");
+    let download = || TestRequest::get().uri(&download_uri);
+
+    let response = call_service(&app, download().to_request()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let headers = response.headers().clone();
+    assert_eq!(headers.get("content-type").unwrap(), "image/png");
+    assert_eq!(headers.get("accept-ranges").unwrap(), "bytes");
+    assert_eq!(
+        headers.get("etag").unwrap().to_str().unwrap(),
+        format!("\"{ticket_id}\"")
+    );
+    let body = read_body(response).await;
+    assert_eq!(
+        headers.get("content-length").unwrap().to_str().unwrap(),
+        body.len().to_string()
+    );
+    let stored = image::load_from_memory(&body).unwrap();
+    assert_eq!(stored.dimensions(), (30, 20));
+
+    // el navegador ya lo tiene
+    let response = call_service(
+        &app,
+        download()
+            .insert_header(IfNoneMatch::Items(vec![EntityTag::new_strong(
+                ticket_id.clone(),
+            )]))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    // rangos: del principio, desde un byte y los últimos bytes
+    let size = body.len();
+    for (range, start, end) in [
+        ("bytes=0-9".to_string(), 0, 9),
+        (format!("bytes={}-", size - 4), size - 4, size - 1),
+        ("bytes=-6".to_string(), size - 6, size - 1),
+    ] {
+        let response = call_service(
+            &app,
+            download()
+                .insert_header(("range", range.as_str()))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT, "{}", range);
+        assert_eq!(
+            response
+                .headers()
+                .get("content-range")
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            format!("bytes {start}-{end}/{size}")
+        );
+        assert_eq!(read_body(response).await, body[start..=end]);
+    }
+
+    let response = call_service(
+        &app,
+        download()
+            .insert_header(("range", format!("bytes={}-", size + 10)))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(
+        response
+            .headers()
+            .get("content-range")
+            .unwrap()
+            .to_str()
+            .unwrap(),
+        format!("bytes */{size}")
+    );
+
+    // If-Range con otro ETag: el archivo cambió para el cliente, va completo
+    let response = call_service(
+        &app,
+        download()
+            .insert_header(("range", "bytes=0-9"))
+            .insert_header(("if-range", "\"otro\""))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(read_body(response).await, body);
+
+    let response = call_service(
+        &app,
+        TestRequest::get()
+            .uri(&format!(
+                "/general/get_ticket_payment?access_token={token}&ticket_id=../../etc/passwd"
+            ))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = call_service(
+        &app,
+        TestRequest::get()
+            .uri(&format!(
+                "/general/get_ticket_payment?access_token={token}&ticket_id={}",
+                "0".repeat(64)
+            ))
+            .to_request(),
+    )
+    .await;
+    // un ticket que no es suyo (exista o no) no se le muestra, así no sabe si existe
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn signed_urls_expire_and_cannot_be_tampered() {
+    let signer = ReceiptUrlSigner::new(b"secreto", 300);
+    let ticket_id = "A".repeat(64);
+    let signed = signer.sign(&ticket_id);
+    let signature = signed.url.rsplit_once("signature=").unwrap().1;
+
+    assert!(signed.url.contains(&format!("ticket_id={ticket_id}")));
+    assert!(signer.verify(&ticket_id, signed.expires, signature).is_ok());
+
+    // otro ticket, otra fecha de vencimiento u otro secreto no sirven con la misma firma
+    let other_ticket = "B".repeat(64);
+    assert!(
+        signer
+            .verify(&other_ticket, signed.expires, signature)
+            .is_err()
+    );
+    assert!(
+        signer
+            .verify(&ticket_id, signed.expires + 3600, signature)
+            .is_err()
+    );
+    assert!(
+        ReceiptUrlSigner::new(b"otro", 300)
+            .verify(&ticket_id, signed.expires, signature)
+            .is_err()
+    );
+    assert!(signer.verify(&ticket_id, signed.expires, "zz").is_err());
+
+    // ya vencida
+    let expired = ReceiptUrlSigner::new(b"secreto", 0).sign(&ticket_id);
+    let signature = expired.url.rsplit_once("signature=").unwrap().1;
+    assert!(matches!(
+        signer.verify(&ticket_id, expired.expires - 1, signature),
+        Err(AppError::Unauthorized(_))
+    ));
+}
+
+#[actix_web::test]
+async fn receipts_are_served_to_owners_directives_and_signed_urls() {
+    dotenv::dotenv().ok();
+    let pool = get_pool_connection().unwrap();
+    let new_user = |name: &str| {
+        create_user_with_access_token(
+            &pool,
+            format!("{name}_{}", random_suffix()),
+            "pass".to_string(),
+            name.to_string(),
+        )
+    };
+    let owner = new_user("dueno").await.unwrap().access_token;
+    let other = new_user("otro_socio").await.unwrap().access_token;
+    let directive = new_user("directivo").await.unwrap().access_token;
+
+    let mut con = pool.get().await.unwrap();
+    let _: () = redis::AsyncCommands::set(
+        &mut con,
+        format!(
+            "users:{}:is_directive",
+            hashing_composite_key(&[&directive])
+        ),
+        true,
+    )
+    .await
+    .unwrap();
+
+    let root = temp_root();
+    let blob_store: Arc<dyn BlobStore> = Arc::new(LocalBlobStore::new(&root));
+    let app = init_service(
+        App::new()
+            .app_data(Data::new(pool))
+            .app_data(Data::from(blob_store))
+            .app_data(Data::new(ReceiptUrlSigner::new(b"secreto", 300)))
+            .configure(file_endpoints),
+    )
+    .await;
+
+    let boundary = "comprobante-boundary";
+    let mut body = format!(
+        "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"boleta.pdf\"\r\n\r\n"
+    )
+    .into_bytes();
+    body.extend_from_slice(b"%PDF-1.4 comprobante");
+    body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+    let response = call_service(
+        &app,
+        TestRequest::post()
+            .uri(&format!(
+                "/general/upload_ticket_payment?access_token={owner}"
+            ))
+            .insert_header((
+                "content-type",
+                format!("multipart/form-data; boundary={boundary}"),
+            ))
+            .set_payload(body)
+            .to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let info: serde_json::Value = read_body_json(response).await;
+    let ticket_id = info["ticket_id"].as_str().unwrap().to_string();
+
+    let status_for = |uri: String| {
+        let app = &app;
+        async move {
+            call_service(app, TestRequest::get().uri(&uri).to_request())
+                .await
+                .status()
+        }
+    };
+    let download = |access_token: &str| {
+        format!("/general/get_ticket_payment?access_token={access_token}&ticket_id={ticket_id}")
+    };
+
+    assert_eq!(status_for(download(&owner)).await, StatusCode::OK);
+    assert_eq!(status_for(download(&directive)).await, StatusCode::OK);
+    assert_eq!(status_for(download(&other)).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        status_for(format!("/general/get_ticket_payment?ticket_id={ticket_id}")).await,
+        StatusCode::UNAUTHORIZED
+    );
+
+    // la URL firmada solo se da a quien puede ver el comprobante
+    let sign_uri = |access_token: &str| {
+        format!("/general/ticket_payment_url?access_token={access_token}&ticket_id={ticket_id}")
+    };
+    assert_eq!(status_for(sign_uri(&other)).await, StatusCode::UNAUTHORIZED);
+
+    let response = call_service(&app, TestRequest::get().uri(&sign_uri(&owner)).to_request()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let signed: serde_json::Value = read_body_json(response).await;
+    let url = signed["url"].as_str().unwrap().to_string();
+
+    // sin access_token
+    let response = call_service(&app, TestRequest::get().uri(&url).to_request()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/pdf"
+    );
+    assert_eq!(read_body(response).await.as_ref(), b"%PDF-1.4 comprobante");
+
+    // firma cambiada o vencimiento extendido
+    let tampered = format!("{}0", &url[..url.len() - 1]);
+    let tampered = if tampered == url {
+        format!("{}1", &url[..url.len() - 1])
+    } else {
+        tampered
+    };
+    assert_eq!(status_for(tampered).await, StatusCode::UNAUTHORIZED);
+    let expires = signed["expires"].as_i64().unwrap();
+    assert_eq!(
+        status_for(url.replace(
+            &format!("expires={expires}"),
+            &format!("expires={}", expires + 3600)
+        ))
+        .await,
+        StatusCode::UNAUTHORIZED
+    );
+
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+/// multipart con el comprobante y, si viene, el pago en JSON
+fn receipt_form(boundary: &str, file: &[u8], payment: Option<&serde_json::Value>) -> Vec<u8> {
+    let mut body = format!(
+        "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"boleta.pdf\"\r\n\r\n"
+    )
+    .into_bytes();
+    body.extend_from_slice(file);
+    if let Some(payment) = payment {
+        body.extend_from_slice(
+            format!(
+                "\r\n--{boundary}\r\nContent-Disposition: form-data; name=\"payment\"\r\n\r\n{payment}"
+            )
+            .as_bytes(),
+        );
+    }
+    body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+    body
+}
+
+#[actix_web::test]
+async fn payments_only_use_own_unused_receipts() {
+    dotenv::dotenv().ok();
+    let pool = get_pool_connection().unwrap();
+    let new_user = |name: &str| {
+        create_user_with_access_token(
+            &pool,
+            format!("{name}_{}", random_suffix()),
+            "pass".to_string(),
+            name.to_string(),
+        )
+    };
+    let owner = new_user("pagador").await.unwrap().access_token;
+    let other = new_user("otro_pagador").await.unwrap().access_token;
+
+    let root = temp_root();
+    let blob_store: Arc<dyn BlobStore> = Arc::new(LocalBlobStore::new(&root));
+    let app = init_service(
+        App::new()
+            .app_data(Data::new(pool.clone()))
+            .app_data(Data::from(blob_store.clone()))
+            .configure(file_endpoints),
+    )
+    .await;
+    let boundary = "pago-boundary";
+    let post = |uri: String, body: Vec<u8>| {
+        TestRequest::post()
+            .uri(&uri)
+            .insert_header((
+                "content-type",
+                format!("multipart/form-data; boundary={boundary}"),
+            ))
+            .set_payload(body)
+            .to_request()
+    };
+
+    // comprobante + pago en un solo request
+    let payment = serde_json::json!({
+        "name": "Pago con comprobante",
+        "total_amount": "100",
+        "ticket_number": format!("T_{}", random_suffix()),
+        "account_number": "ACC_COMPROBANTE",
+        "being_payed": [],
+    });
+    let response = call_service(
+        &app,
+        post(
+            format!("/general/submit_payment?access_token={owner}"),
+            receipt_form(boundary, b"%PDF-1.4 pago", Some(&payment)),
+        ),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let submitted: serde_json::Value = read_body_json(response).await;
+    let submitted_ticket = submitted["ticket_id"].as_str().unwrap().to_string();
+
+    let repo = PaymentRepo::new(pool.clone());
+    let payments = repo.get_user_payments(owner.clone()).await.unwrap();
+    assert_eq!(payments.len(), 1);
+    assert_eq!(
+        payments[0].photo_path,
+        format!("payment-tickets/{submitted_ticket}.pdf")
+    );
+    // ya está en un pago, no se puede usar en otro
+    assert!(matches!(
+        repo.claim_receipt(&owner, &submitted_ticket).await,
+        Err(AppError::Conflict(_))
+    ));
+
+    // un pago mal armado no deja el comprobante subido
+    let response = call_service(
+        &app,
+        post(
+            format!("/general/submit_payment?access_token={owner}"),
+            receipt_form(
+                boundary,
+                b"%PDF-1.4 pago",
+                Some(&serde_json::json!({ "name": "sin monto" })),
+            ),
+        ),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // subida por separado: solo el dueño la puede usar
+    let response = call_service(
+        &app,
+        post(
+            format!("/general/upload_ticket_payment?access_token={owner}"),
+            receipt_form(boundary, b"%PDF-1.4 suelto", None),
+        ),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let info: serde_json::Value = read_body_json(response).await;
+    let ticket_id = info["ticket_id"].as_str().unwrap().to_string();
+
+    assert!(matches!(
+        repo.claim_receipt(&owner, &"A".repeat(64)).await,
+        Err(AppError::Validation { .. })
+    ));
+    assert!(matches!(
+        repo.claim_receipt(&owner, "../otro/archivo.pdf").await,
+        Err(AppError::Validation { .. })
+    ));
+    assert!(matches!(
+        repo.claim_receipt(&other, &ticket_id).await,
+        Err(AppError::Unauthorized(_))
+    ));
+
+    // el ticket_id solo o la llave completa
+    let claimed = repo.claim_receipt(&owner, &ticket_id).await.unwrap();
+    assert_eq!(claimed.ticket_id, ticket_id);
+    assert_eq!(claimed.key, format!("payment-tickets/{ticket_id}.pdf"));
+    // apartado: otro pago no lo puede tomar hasta que se suelte
+    assert!(matches!(
+        repo.claim_receipt(&owner, &claimed.key).await,
+        Err(AppError::Conflict(_))
+    ));
+    repo.release_receipt(&ticket_id).await.unwrap();
+    assert_eq!(
+        repo.claim_receipt(&owner, &claimed.key).await.unwrap(),
+        claimed
+    );
+    repo.release_receipt(&ticket_id).await.unwrap();
+
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+/// OCR de mentira, siempre lee la misma boleta
+struct FakeOcr(&'static str);
+
+#[async_trait::async_trait]
+impl ReceiptOcr for FakeOcr {
+    async fn read_text(
+        &self,
+        _kind: ReceiptKind,
+        _bytes: &[u8],
+    ) -> Result<Option<String>, AppError> {
+        Ok(Some(self.0.to_string()))
+    }
+}
+
+#[actix_web::test]
+async fn receipt_ocr_prefills_and_flags_mismatches() {
+    dotenv::dotenv().ok();
+    let pool = get_pool_connection().unwrap();
+    let owner = create_user_with_access_token(
+        &pool,
+        format!("ocr_{}", random_suffix()),
+        "pass".to_string(),
+        "socio_ocr".to_string(),
+    )
+    .await
+    .unwrap()
+    .access_token;
+
+    let root = temp_root();
+    let blob_store: Arc<dyn BlobStore> = Arc::new(LocalBlobStore::new(&root));
+    let ocr: Arc<dyn ReceiptOcr> = Arc::new(FakeOcr(
+        "Fecha: 25/03/2025\nNo. Boleta: 00123456\nMonto: Q 1,250.00",
+    ));
+    let app = init_service(
+        App::new()
+            .app_data(Data::new(pool.clone()))
+            .app_data(Data::from(blob_store))
+            .app_data(Data::from(ocr))
+            .configure(file_endpoints),
+    )
+    .await;
+    let boundary = "ocr-boundary";
+    let post = |uri: String, body: Vec<u8>| {
+        TestRequest::post()
+            .uri(&uri)
+            .insert_header((
+                "content-type",
+                format!("multipart/form-data; boundary={boundary}"),
+            ))
+            .set_payload(body)
+            .to_request()
+    };
+
+    // la subida regresa lo leído para prellenar el formulario
+    let response = call_service(
+        &app,
+        post(
+            format!("/general/upload_ticket_payment?access_token={owner}"),
+            receipt_form(boundary, b"%PDF-1.4 boleta", None),
+        ),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let info: serde_json::Value = read_body_json(response).await;
+    assert_eq!(info["reading"]["amount"], "1250.00");
+    assert_eq!(info["reading"]["date"], "2025-03-25");
+    assert_eq!(info["reading"]["reference"], "00123456");
+
+    // el socio escribió otro monto, el pago queda marcado para el directivo
+    let payment = serde_json::json!({
+        "name": "Pago leído",
+        "total_amount": "1200",
+        "ticket_number": "123456",
+        "account_number": "ACC_OCR",
+        "being_payed": [],
+    });
+    let response = call_service(
+        &app,
+        post(
+            format!("/general/submit_payment?access_token={owner}"),
+            receipt_form(boundary, b"%PDF-1.4 boleta", Some(&payment)),
+        ),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let submitted: serde_json::Value = read_body_json(response).await;
+    assert_eq!(submitted["receipt_check"]["amount_matches"], false);
+    assert_eq!(submitted["receipt_check"]["reference_matches"], true);
+    assert_eq!(submitted["receipt_check"]["has_mismatch"], true);
+
+    let payments = PaymentRepo::new(pool.clone())
+        .get_user_payments(owner)
+        .await
+        .unwrap();
+    let check = payments[0].receipt_check.clone().unwrap();
+    assert_eq!(check.amount, Some("1250".parse().unwrap()));
+    assert!(check.has_mismatch);
+
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[actix_web::test]
+async fn orphan_receipts_are_collected() {
+    dotenv::dotenv().ok();
+    let pool = get_pool_connection().unwrap();
+    let owner = create_user_with_access_token(
+        &pool,
+        format!("huerfanos_{}", random_suffix()),
+        "pass".to_string(),
+        "Socio Huerfanos".to_string(),
+    )
+    .await
+    .unwrap()
+    .access_token;
+
+    let root = temp_root();
+    let blob_store: Arc<dyn BlobStore> = Arc::new(LocalBlobStore::new(&root));
+    let app = init_service(
+        App::new()
+            .app_data(Data::new(pool.clone()))
+            .app_data(Data::from(blob_store.clone()))
+            .configure(file_endpoints),
+    )
+    .await;
+
+    let boundary = "huerfano-boundary";
+    let mut tickets = Vec::new();
+    for _ in 0..4 {
+        let response = call_service(
+            &app,
+            TestRequest::post()
+                .uri(&format!(
+                    "/general/upload_ticket_payment?access_token={owner}"
+                ))
+                .insert_header((
+                    "content-type",
+                    format!("multipart/form-data; boundary={boundary}"),
+                ))
+                .set_payload(receipt_form(boundary, b"%PDF-1.4 huerfano", None))
+                .to_request(),
+        )
+        .await;
+        let info: serde_json::Value = read_body_json(response).await;
+        tickets.push(info["ticket_id"].as_str().unwrap().to_string());
+    }
+    let [orphan, legacy, fresh, claimed] = tickets.as_slice() else {
+        unreachable!()
+    };
+    // un pago se está creando con el cuarto
+    PaymentRepo::new(pool.clone())
+        .claim_receipt(&owner, claimed)
+        .await
+        .unwrap();
+
+    // todos menos el tercero se subieron hace dos días
+    let mut con = pool.get().await.unwrap();
+    for ticket_id in [orphan, legacy, claimed] {
+        let record = StoredReceipt {
+            key: format!("payment-tickets/{ticket_id}.pdf"),
+            content_type: "application/pdf".to_string(),
+            size: 17,
+            uploaded_at: (chrono::Utc::now() - chrono::Duration::days(2)).into(),
+            owner: None,
+            linked_at: None,
+            reading: None,
+        };
+        let _: () = redis::JsonAsyncCommands::json_set(
+            &mut con,
+            format!("receipts:{ticket_id}"),
+            "$",
+            &record,
+        )
+        .await
+        .unwrap();
+    }
+
+    // un pago de antes de linked_at que ya apunta al comprobante
+    PaymentRepo::new(pool.clone())
+        .create_payment(
+            owner.clone(),
+            "Pago viejo".to_string(),
+            format!("payment-tickets/{legacy}.pdf"),
+            "10".parse().unwrap(),
+            Currency::Gtq,
+            format!("T_{}", random_suffix()),
+            "ACC_HUERFANOS".to_string(),
+            vec![],
+        )
+        .await
+        .unwrap();
+
+    // un registro roto no para al GC, los demás se revisan igual
+    let broken = format!("receipts:{}", "B".repeat(64));
+    let _: () = redis::AsyncCommands::set(&mut con, &broken, "no es json")
+        .await
+        .unwrap();
+
+    collect_orphan_receipts(&pool, blob_store.as_ref(), chrono::Duration::hours(24))
+        .await
+        .unwrap();
+    let _: () = redis::AsyncCommands::del(&mut con, &broken).await.unwrap();
+
+    let exists = |ticket_id: &str| {
+        let blob_store = blob_store.clone();
+        let key = format!("payment-tickets/{ticket_id}.pdf");
+        async move { blob_store.exists(&key).await.unwrap() }
+    };
+    assert!(!exists(orphan).await);
+    assert!(exists(legacy).await);
+    assert!(exists(fresh).await);
+    assert!(exists(claimed).await, "El apartado no se borra");
+
+    // el GC suelta lo que apartó para revisar, pero no el apartado del pago que se está creando
+    for ticket_id in [orphan, legacy] {
+        let key = format!("receipt_claims:{ticket_id}");
+        let held: bool = redis::AsyncCommands::exists(&mut con, &key).await.unwrap();
+        assert!(!held, "El GC no suelta {key}");
+    }
+    let claim_owner: String =
+        redis::AsyncCommands::get(&mut con, format!("receipt_claims:{claimed}"))
+            .await
+            .unwrap();
+    assert_eq!(claim_owner, owner);
+
+    // el que sí estaba en un pago queda marcado, ya no se puede usar en otro
+    let repo = PaymentRepo::new(pool.clone());
+    assert!(repo.claim_receipt(&owner, orphan).await.is_err());
+    assert!(matches!(
+        repo.claim_receipt(&owner, fresh).await,
+        Ok(claimed) if claimed.ticket_id == *fresh
+    ));
+
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[actix_web::test]
+async fn attachments_are_uploaded_and_served_to_their_member() {
+    dotenv::dotenv().ok();
+    let pool = get_pool_connection().unwrap();
+    let new_user = |name: &str| {
+        create_user_with_access_token(
+            &pool,
+            format!("{name}_{}", random_suffix()),
+            "pass".to_string(),
+            name.to_string(),
+        )
+    };
+    let owner = new_user("con_prestamo").await.unwrap().access_token;
+    let other = new_user("sin_prestamo").await.unwrap().access_token;
+    let directive = new_user("directivo_docs").await.unwrap().access_token;
+
+    let mut con = pool.get().await.unwrap();
+    let _: () = redis::AsyncCommands::set(
+        &mut con,
+        format!(
+            "users:{}:is_directive",
+            hashing_composite_key(&[&directive])
+        ),
+        true,
+    )
+    .await
+    .unwrap();
+    let loan_id = hashing_composite_key(&[&random_suffix()]);
+    let _: () = redis::JsonAsyncCommands::json_set(
+        &mut con,
+        format!("users:{}:loans:{loan_id}", hashing_composite_key(&[&owner])),
+        "$",
+        &RedisLoan::default(),
+    )
+    .await
+    .unwrap();
+
+    let root = temp_root();
+    let blob_store: Arc<dyn BlobStore> = Arc::new(LocalBlobStore::new(&root));
+    let app = init_service(
+        App::new()
+            .app_data(Data::new(pool.clone()))
+            .app_data(Data::from(blob_store))
+            .configure(file_endpoints),
+    )
+    .await;
+
+    let boundary = "documento-boundary";
+    let upload = |access_token: &str, owner_id: &str| {
+        TestRequest::post()
+            .uri(&format!(
+                "/general/upload_attachment?access_token={access_token}&owner_type=LOAN&owner_id={owner_id}&document_type=PAGARE"
+            ))
+            .insert_header((
+                "content-type",
+                format!("multipart/form-data; boundary={boundary}"),
+            ))
+            .set_payload(receipt_form(boundary, b"%PDF-1.4 pagare firmado", None))
+            .to_request()
+    };
+
+    assert_eq!(
+        call_service(&app, upload(&other, &loan_id)).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        call_service(&app, upload(&owner, &"A".repeat(64)))
+            .await
+            .status(),
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        call_service(&app, upload(&owner, "../prestamo"))
+            .await
+            .status(),
+        StatusCode::BAD_REQUEST
+    );
+
+    let response = call_service(&app, upload(&owner, &loan_id)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let attachment: serde_json::Value = read_body_json(response).await;
+    assert_eq!(attachment["owner_type"], "LOAN");
+    assert_eq!(attachment["document_type"], "PAGARE");
+    assert_eq!(attachment["status"], "PENDING");
+    assert_eq!(attachment["uploaded_by_name"], "con_prestamo");
+    let id = attachment["id"].as_str().unwrap().to_string();
+
+    let listed = AttachmentRepo { pool: pool.clone() }
+        .get_attachments(AttachmentOwnerType::Loan, &loan_id)
+        .await
+        .unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].id, id);
+
+    let download = |access_token: &str, id: &str| {
+        TestRequest::get()
+            .uri(&format!(
+                "/general/get_attachment?access_token={access_token}&id={id}"
+            ))
+            .to_request()
+    };
+    for access_token in [&owner, &directive] {
+        let response = call_service(&app, download(access_token, &id)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "application/pdf"
+        );
+        assert_eq!(
+            read_body(response).await.as_ref(),
+            b"%PDF-1.4 pagare firmado"
+        );
+    }
+    assert_eq!(
+        call_service(&app, download(&other, &id)).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+    // que no exista solo se le dice al directivo
+    let missing = "B".repeat(64);
+    assert_eq!(
+        call_service(&app, download(&other, &missing))
+            .await
+            .status(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        call_service(&app, download(&directive, &missing))
+            .await
+            .status(),
+        StatusCode::NOT_FOUND
+    );
+
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[actix_web::test]
+async fn statements_are_downloaded_as_pdf_or_csv() {
+    dotenv::dotenv().ok();
+    let pool = get_pool_connection().unwrap();
+    let new_user = |name: &str| {
+        create_user_with_access_token(
+            &pool,
+            format!("{name}_{}", random_suffix()),
+            "pass".to_string(),
+            name.to_string(),
+        )
+    };
+    let owner = new_user("con_estado").await.unwrap().access_token;
+    let other = new_user("sin_estado").await.unwrap().access_token;
+
+    let mut con = pool.get().await.unwrap();
+    let owner_key = hashing_composite_key(&[&owner]);
+    let affiliate_key: String =
+        redis::AsyncCommands::get(&mut con, format!("users:{owner_key}:affiliate_key"))
+            .await
+            .unwrap();
+    let loan_id = hashing_composite_key(&[&random_suffix()]);
+    let _: () = redis::JsonAsyncCommands::json_set(
+        &mut con,
+        format!("users:{owner_key}:loans:{loan_id}"),
+        "$",
+        &RedisLoan {
+            reason: "Préstamo de siembra".to_string(),
+            total: 500.into(),
+            debt: 200.into(),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let app = init_service(
+        App::new()
+            .app_data(Data::new(pool.clone()))
+            .configure(file_endpoints),
+    )
+    .await;
+    let download = |query: String| {
+        TestRequest::get()
+            .uri(&format!("/general/statement?{query}"))
+            .to_request()
+    };
+
+    let response = call_service(&app, download(format!("access_token={owner}&format=csv"))).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "text/csv; charset=utf-8"
+    );
+    assert_eq!(
+        response.headers().get("content-disposition").unwrap(),
+        &format!("attachment; filename=\"estado-de-cuenta-{affiliate_key}.csv\"")
+    );
+    let csv = String::from_utf8(read_body(response).await.to_vec()).unwrap();
+    assert!(
+        csv.starts_with("seccion,fecha,concepto,referencia,estado,monto,pagado,saldo,moneda\n")
+    );
+    assert!(csv.contains("RESUMEN,,Saldo de préstamos,,,200.00,,,"));
+    assert!(csv.contains(&format!("PRESTAMO,,Préstamo de siembra,{loan_id},")));
+
+    // PDF por default, con las fuentes estándar los acentos van en WinAnsi (y el texto que no
+    // es ASCII queda como string hex)
+    let response = call_service(
+        &app,
+        download(format!(
+            "access_token={owner}&from=2025-01-01&to=2025-12-31"
+        )),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/pdf"
+    );
+    assert_eq!(
+        response.headers().get("content-disposition").unwrap(),
+        &format!(
+            "attachment; filename=\"estado-de-cuenta-{affiliate_key}-2025-01-01-2025-12-31.pdf\""
+        )
+    );
+    let pdf = read_body(response).await;
+    assert!(pdf.starts_with(b"%PDF-"));
+    assert!(contains(&pdf, b"Estado de cuenta"));
+    let winansi_hex: String = b"Pr\xe9stamo de siembra"
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect();
+    assert!(contains(&pdf, format!("<{winansi_hex}>").as_bytes()));
+
+    // el de otro socio no, y el período tiene que tener sentido
+    assert_eq!(
+        call_service(
+            &app,
+            download(format!(
+                "access_token={other}&affiliate_key={affiliate_key}"
+            ))
+        )
+        .await
+        .status(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        call_service(
+            &app,
+            download(format!(
+                "access_token={owner}&from=2025-02-01&to=2025-01-01"
+            ))
+        )
+        .await
+        .status(),
+        StatusCode::BAD_REQUEST
+    );
+}
+
+#[actix_web::test]
+async fn exports_are_streamed_as_csv_or_xlsx() {
+    dotenv::dotenv().ok();
+    let pool = get_pool_connection().unwrap();
+    let new_user = |name: &str| {
+        create_user_with_access_token(
+            &pool,
+            format!("{name}_{}", random_suffix()),
+            "pass".to_string(),
+            name.to_string(),
+        )
+    };
+    let owner = new_user("exporta_socio").await.unwrap().access_token;
+    let other = new_user("exporta_otro").await.unwrap().access_token;
+    let directive = new_user("exporta_directivo").await.unwrap().access_token;
+
+    let mut con = pool.get().await.unwrap();
+    let _: () = redis::AsyncCommands::set(
+        &mut con,
+        format!(
+            "users:{}:is_directive",
+            hashing_composite_key(&[&directive])
+        ),
+        true,
+    )
+    .await
+    .unwrap();
+    let owner_key = hashing_composite_key(&[&owner]);
+    let affiliate_key: String =
+        redis::AsyncCommands::get(&mut con, format!("users:{owner_key}:affiliate_key"))
+            .await
+            .unwrap();
+    let loan_id = hashing_composite_key(&[&random_suffix()]);
+    // el segundo motivo lo abriría Excel como fórmula
+    let formula_loan_id = hashing_composite_key(&[&random_suffix()]);
+    for (id, reason) in [(&loan_id, "Préstamo de siembra"), (&formula_loan_id, "=1+1")] {
+        let _: () = redis::JsonAsyncCommands::json_set(
+            &mut con,
+            format!("users:{owner_key}:loans:{id}"),
+            "$",
+            &RedisLoan {
+                reason: reason.to_string(),
+                total: 500.into(),
+                payed: 300.into(),
+                debt: 200.into(),
+                status: "ACTIVE".to_string(),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    // una cuota pagada y una pendiente, pending=true solo manda la pendiente
+    let quotas = GeneralContext::new(pool.clone()).quota_repo();
+    for payed in [true, false] {
+        quotas
+            .save_quota(
+                owner.clone(),
+                &Quota {
+                    user_id: owner.clone(),
+                    amount: 100.into(),
+                    exp_date: Some(today()),
+                    monto_pagado: Some(if payed { 100 } else { 0 }.into()),
+                    multa: None,
+                    pay_by: None,
+                    quota_type: QuotaType::Afiliado,
+                    loan_id: None,
+                    is_extraordinary: Some(false),
+                    payed: Some(payed),
+                    quota_number: None,
+                    nombre_prestamo: None,
+                    nombre_usuario: None,
+                    identifier: None,
+                },
+            )
+            .await
+            .unwrap();
+    }
+
+    let app = init_service(
+        App::new()
+            .app_data(Data::new(pool.clone()))
+            .configure(file_endpoints),
+    )
+    .await;
+    let download = |path: &str, query: String| {
+        TestRequest::get()
+            .uri(&format!("/general/export/{path}?{query}"))
+            .to_request()
+    };
+
+    // un directivo exporta a todos, con el nombre del socio en cada fila
+    let response = call_service(&app, download("loans", format!("access_token={directive}"))).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "text/csv; charset=utf-8"
+    );
+    assert_eq!(
+        response.headers().get("content-disposition").unwrap(),
+        &format!("attachment; filename=\"prestamos-{}.csv\"", today())
+    );
+    let csv = String::from_utf8(read_body(response).await.to_vec()).unwrap();
+    assert!(csv.starts_with(
+        "socio,nombre,id,fecha,motivo,total,pagado,saldo,moneda,interes,cuotas,estado\n"
+    ));
+    assert!(csv.contains(&format!(
+        "{affiliate_key},exporta_socio,{loan_id},,Préstamo de siembra,500.00,300.00,200.00,"
+    )));
+    assert!(csv.contains(&format!("{formula_loan_id},,'=1+1,500.00,")));
+
+    // un socio solo lo suyo
+    let response = call_service(
+        &app,
+        download(
+            "quotas",
+            format!("access_token={owner}&affiliate_key={affiliate_key}&pending=true"),
+        ),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let csv = String::from_utf8(read_body(response).await.to_vec()).unwrap();
+    let rows: Vec<&str> = csv.lines().skip(1).collect();
+    assert_eq!(
+        rows,
+        [format!(
+            "{affiliate_key},exporta_socio,AFILIADO,,,{},100.00,0.00,0.00,PENDIENTE,NO,GTQ",
+            today()
+        )]
+    );
+    for query in [
+        format!("access_token={other}&affiliate_key={affiliate_key}"),
+        format!("access_token={other}"),
+    ] {
+        assert_eq!(
+            call_service(&app, download("payments", query))
+                .await
+                .status(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    // el XLSX es un zip con la hoja adentro
+    let response = call_service(
+        &app,
+        download(
+            "loans",
+            format!("access_token={directive}&affiliate_key={affiliate_key}&format=xlsx"),
+        ),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+    );
+    assert_eq!(
+        response.headers().get("content-disposition").unwrap(),
+        &format!(
+            "attachment; filename=\"prestamos-{affiliate_key}-{}.xlsx\"",
+            today()
+        )
+    );
+    let xlsx = read_body(response).await;
+    assert!(xlsx.starts_with(b"PK\x03\x04"));
+    assert!(contains(&xlsx, b"xl/worksheets/sheet1.xml"));
+}
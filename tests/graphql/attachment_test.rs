@@ -0,0 +1,310 @@
+// Tests de los documentos en graphql: Member.attachments, Loan.attachments, Fine.attachments,
+// la cola de pendientes y la revisión de directivos
+// (la subida y descarga por REST están en tests/files.rs)
+
+use super::common::{add_memory_directive, create_memory_context, memory_user};
+use general_api::endpoints::handlers::configs::schema::GeneralContext;
+use general_api::endpoints::handlers::graphql::root::{Mutation, Query};
+use general_api::models::dates::DateTime;
+use general_api::models::graphql::{AttachmentOwnerType, AttachmentStatus, DocumentType};
+use general_api::models::redis::{
+    Attachment as RedisAttachment, Fine as RedisFine, Loan as RedisLoan,
+};
+use general_api::repos::graphql::memory::MemoryStore;
+use juniper::{EmptySubscription, RootNode, Variables};
+
+struct Seeded {
+    directive_token: String,
+    member_token: String,
+    affiliate_key: String,
+    loan_id: String,
+    fine_id: String,
+    pagare_id: String,
+}
+
+fn attachment(
+    owner_type: AttachmentOwnerType,
+    owner_id: &str,
+    member_key: &str,
+    document_type: DocumentType,
+) -> RedisAttachment {
+    RedisAttachment {
+        owner_type,
+        owner_id: owner_id.to_string(),
+        member_key: member_key.to_string(),
+        document_type,
+        blob_key: format!("attachments/{}/{}/doc.pdf", owner_type.as_str(), owner_id),
+        content_type: "application/pdf".to_string(),
+        size: 1024,
+        uploaded_by: member_key.to_string(),
+        uploaded_by_name: "Directivo Test".to_string(),
+        uploaded_at: DateTime::now(),
+        status: AttachmentStatus::Pending,
+        reviewed_by: None,
+        reviewed_by_name: None,
+        reviewed_at: None,
+        review_comment: None,
+    }
+}
+
+/// socio con un préstamo y una multa, cada uno con un documento, y un directivo
+async fn seed(store: &MemoryStore, context: &GeneralContext) -> Seeded {
+    let member_token = "socio_test".to_string();
+    let affiliate_key = "AF-SOCIO-TEST".to_string();
+    let owner_key = store
+        .add_user(&member_token, memory_user(&affiliate_key, "Socio Test", false))
+        .unwrap();
+    let directive_token = add_memory_directive(store);
+
+    let loan_id = "DOCLOAN1".to_string();
+    store
+        .insert_loan(&owner_key, &loan_id, RedisLoan::default())
+        .unwrap();
+    let fine_id = "DOCFINE1".to_string();
+    store
+        .insert_fine(&owner_key, &fine_id, RedisFine::default())
+        .unwrap();
+
+    let repo = context.attachment_repo();
+    let mut ids = Vec::new();
+    for (owner_type, owner_id, document_type) in [
+        (AttachmentOwnerType::Loan, &loan_id, DocumentType::Pagare),
+        (
+            AttachmentOwnerType::Fine,
+            &fine_id,
+            DocumentType::FineDispute,
+        ),
+        (
+            AttachmentOwnerType::Member,
+            &affiliate_key,
+            DocumentType::IdDocument,
+        ),
+    ] {
+        let id = format!("DOC{}", owner_type.as_str());
+        repo.save_attachment(
+            &id,
+            &attachment(owner_type, owner_id, &affiliate_key, document_type),
+        )
+        .await
+        .expect("save_attachment failed");
+        ids.push(id);
+    }
+
+    Seeded {
+        directive_token,
+        member_token,
+        affiliate_key,
+        loan_id,
+        fine_id,
+        pagare_id: ids.remove(0),
+    }
+}
+
+async fn try_execute(context: &GeneralContext, query: &str) -> (serde_json::Value, Vec<String>) {
+    let schema = RootNode::new(Query, Mutation, EmptySubscription::new());
+    let (value, errors) = juniper::execute(query, None, &schema, &Variables::new(), context)
+        .await
+        .expect("La query debe ser válida");
+
+    let errors = errors
+        .iter()
+        .map(|error| format!("{:?}", error.error()))
+        .collect();
+    (serde_json::to_value(&value).unwrap(), errors)
+}
+
+async fn execute(context: &GeneralContext, query: &str) -> serde_json::Value {
+    let (data, errors) = try_execute(context, query).await;
+    assert!(errors.is_empty(), "Errores inesperados: {:?}", errors);
+    data
+}
+
+#[tokio::test]
+async fn test_member_loans_and_fines_list_their_attachments() {
+    let (store, context) = create_memory_context();
+    let seeded = seed(&store, &context).await;
+
+    let data = execute(
+        &context,
+        &format!(
+            r#"{{ member(accessToken: "{token}", affiliateKey: "{key}") {{
+                attachments(accessToken: "{token}") {{ documentType ownerType ownerId status }}
+                loans {{ id attachments(accessToken: "{token}") {{ id documentType size contentType }} }}
+                fines {{ id attachments(accessToken: "{token}") {{ documentType }} }}
+            }} }}"#,
+            key = seeded.affiliate_key,
+            token = seeded.member_token
+        ),
+    )
+    .await;
+    let member = &data["member"];
+
+    let own = member["attachments"].as_array().unwrap();
+    assert_eq!(own.len(), 1);
+    assert_eq!(own[0]["documentType"], "ID_DOCUMENT");
+    assert_eq!(own[0]["ownerType"], "MEMBER");
+    assert_eq!(own[0]["ownerId"], seeded.affiliate_key);
+    assert_eq!(own[0]["status"], "PENDING");
+
+    let loan = member["loans"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|loan| loan["id"] == seeded.loan_id)
+        .unwrap();
+    assert_eq!(loan["attachments"][0]["id"], seeded.pagare_id);
+    assert_eq!(loan["attachments"][0]["documentType"], "PAGARE");
+    assert_eq!(loan["attachments"][0]["size"], 1024);
+    assert_eq!(loan["attachments"][0]["contentType"], "application/pdf");
+
+    let fine = member["fines"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|fine| fine["id"] == seeded.fine_id)
+        .unwrap();
+    assert_eq!(fine["attachments"][0]["documentType"], "FINE_DISPUTE");
+
+    let data = execute(
+        &context,
+        &format!(
+            r#"{{ attachment {{ getAttachments(
+                accessToken: "{}", ownerType: LOAN, ownerId: "{}"
+            ) {{ id }} }} }}"#,
+            seeded.member_token, seeded.loan_id
+        ),
+    )
+    .await;
+    assert_eq!(
+        data["attachment"]["getAttachments"][0]["id"],
+        seeded.pagare_id
+    );
+}
+
+#[tokio::test]
+async fn test_attachments_are_only_visible_to_their_member_and_directives() {
+    let (store, context) = create_memory_context();
+    let seeded = seed(&store, &context).await;
+    let other_token = "otro_socio_test";
+    store
+        .add_user(other_token, memory_user("AF-OTRO-TEST", "Otro Socio", false))
+        .unwrap();
+
+    // el socio pide su propio member, el que cambia es el token de los documentos
+    let queries = |access_token: &str| {
+        vec![
+            format!(
+                r#"{{ member(accessToken: "{member}", affiliateKey: "{key}") {{ attachments(accessToken: "{token}") {{ id }} }} }}"#,
+                member = seeded.member_token,
+                key = seeded.affiliate_key,
+                token = access_token
+            ),
+            format!(
+                r#"{{ member(accessToken: "{member}", affiliateKey: "{key}") {{ loans {{ attachments(accessToken: "{token}") {{ id }} }} }} }}"#,
+                member = seeded.member_token,
+                key = seeded.affiliate_key,
+                token = access_token
+            ),
+            format!(
+                r#"{{ member(accessToken: "{member}", affiliateKey: "{key}") {{ fines {{ attachments(accessToken: "{token}") {{ id }} }} }} }}"#,
+                member = seeded.member_token,
+                key = seeded.affiliate_key,
+                token = access_token
+            ),
+            format!(
+                r#"{{ attachment {{ getAttachments(
+                    accessToken: "{}", ownerType: LOAN, ownerId: "{}"
+                ) {{ id }} }} }}"#,
+                access_token, seeded.loan_id
+            ),
+        ]
+    };
+
+    // sin token válido y otro socio no ven nada
+    for access_token in ["", other_token] {
+        for query in queries(access_token) {
+            let (_, errors) = try_execute(&context, &query).await;
+            assert_eq!(errors.len(), 1, "{}: {:?}", query, errors);
+            assert!(
+                errors[0].contains("No puedes ver los documentos"),
+                "{:?}",
+                errors
+            );
+        }
+    }
+
+    // el directivo sí
+    for query in queries(&seeded.directive_token) {
+        execute(&context, &query).await;
+    }
+}
+
+#[tokio::test]
+async fn test_only_directives_review_attachments() {
+    let (store, context) = create_memory_context();
+    let seeded = seed(&store, &context).await;
+
+    let review = |access_token: &str, status: &str, commentary: &str| {
+        format!(
+            r#"mutation {{ attachment {{ reviewAttachment(
+                accessToken: "{}", id: "{}", status: {}, commentary: "{}"
+            ) {{ status reviewedByName reviewComment reviewedAt }} }} }}"#,
+            access_token, seeded.pagare_id, status, commentary
+        )
+    };
+
+    let (_, errors) = try_execute(&context, &review(&seeded.member_token, "APPROVED", "")).await;
+    assert!(errors[0].contains("Solo los directivos"), "{:?}", errors);
+
+    // rechazar sin comentario no
+    let (_, errors) =
+        try_execute(&context, &review(&seeded.directive_token, "REJECTED", " ")).await;
+    assert!(errors[0].contains("comentario"), "{:?}", errors);
+
+    let (_, errors) = try_execute(&context, &review(&seeded.directive_token, "PENDING", "")).await;
+    assert!(errors[0].contains("Estado inválido"), "{:?}", errors);
+
+    let pending = format!(
+        r#"{{ attachment {{ getPendingAttachments(accessToken: "{}") {{ id }} }} }}"#,
+        seeded.directive_token
+    );
+    let data = execute(&context, &pending).await;
+    assert!(
+        data["attachment"]["getPendingAttachments"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|attachment| attachment["id"] == seeded.pagare_id)
+    );
+
+    let data = execute(
+        &context,
+        &review(&seeded.directive_token, "REJECTED", "falta la firma"),
+    )
+    .await;
+    let reviewed = &data["attachment"]["reviewAttachment"];
+    assert_eq!(reviewed["status"], "REJECTED");
+    assert_eq!(reviewed["reviewedByName"], "Directivo Test");
+    assert_eq!(reviewed["reviewComment"], "falta la firma");
+    assert!(reviewed["reviewedAt"].is_string());
+
+    // ya no está en la cola
+    let data = execute(&context, &pending).await;
+    assert!(
+        !data["attachment"]["getPendingAttachments"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|attachment| attachment["id"] == seeded.pagare_id)
+    );
+
+    let (_, errors) = try_execute(
+        &context,
+        &format!(
+            r#"{{ attachment {{ getPendingAttachments(accessToken: "{}") {{ id }} }} }}"#,
+            seeded.member_token
+        ),
+    )
+    .await;
+    assert!(!errors.is_empty());
+}
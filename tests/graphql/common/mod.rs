@@ -1,34 +1,46 @@
-use actix_web::web::Data;
-use r2d2::Pool;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::lock::MutexGuard;
 use redis::{Client, Commands, JsonCommands};
 
+use chrono::{TimeZone, Utc};
+use general_api::models::PayedTo;
+use general_api::models::currency::Currency;
+use general_api::models::money::Money;
+use general_api::endpoints::handlers::configs::connection_pool::RedisPool;
 use general_api::endpoints::handlers::configs::schema::GeneralContext;
-use general_api::models::graphql::Payment;
+use general_api::models::dates::DateTime;
+use general_api::models::file::StoredReceipt;
+use general_api::models::graphql::{Payment, Quota, QuotaType};
+use general_api::models::redis::{Fine as RedisFine, Loan as RedisLoan, Payment as RedisPayment};
+use general_api::repos::auth::utils::hashing_composite_key;
+use general_api::repos::graphql::memory::{MemoryStore, MemoryUser};
+use general_api::test_sync::redis_test_lock;
 
 pub fn create_test_context() -> GeneralContext {
     let client = Client::open("redis://127.0.0.1/").expect("No se pudo conectar a Redis");
-    let pool = Pool::builder()
-        .build(client)
-        .expect("No se pudo crear el pool de Redis");
+    // cada test abre su propia conexión, la multiplexada vive en el runtime del test
+    let pool = RedisPool::new(client, Duration::from_secs(5), Duration::from_secs(10));
     //TODO: see a way to inject the s3 client in the context
 
-    GeneralContext {
-        pool: Data::new(pool),
-    }
+    GeneralContext::new(pool)
 }
 
 /// Guarda claves creadas por tests y las borra automáticamente al hacer `drop`
 pub struct TestRedisGuard {
-    pool: Data<Pool<Client>>,
+    pool: RedisPool,
     keys: Vec<String>,
+    patterns: Vec<String>,
 }
 
 impl TestRedisGuard {
     /// Crea un nuevo guard vinculado a un pool de Redis
-    pub fn new(pool: Data<Pool<Client>>) -> Self {
+    pub fn new(pool: RedisPool) -> Self {
         TestRedisGuard {
             pool,
             keys: Vec::new(),
+            patterns: Vec::new(),
         }
     }
 
@@ -36,27 +48,82 @@ impl TestRedisGuard {
     pub fn register_key(&mut self, key: String) {
         self.keys.push(key);
     }
+
+    /// Registra un patrón (ej. `users:{hash}:*`), se borra todo lo que coincida al final
+    pub fn register_pattern(&mut self, pattern: String) {
+        self.patterns.push(pattern);
+    }
 }
 
 impl Drop for TestRedisGuard {
     /// Elimina todas las claves registradas cuando el guard sale de alcance
+    /// drop no puede ser async, así que la limpieza usa una conexión sync aparte
     fn drop(&mut self) {
-        if let Ok(mut con) = self.pool.get() {
+        if let Ok(mut con) = self.pool.client().get_connection() {
             for key in &self.keys {
                 let _: () = con.del(key).unwrap_or(());
             }
+            for pattern in &self.patterns {
+                let keys: Vec<String> = con
+                    .scan_match::<_, String>(pattern)
+                    .map(|iter| iter.collect())
+                    .unwrap_or_default();
+                for key in keys {
+                    let _: () = con.del(&key).unwrap_or(());
+                }
+            }
         }
     }
 }
 
+/// Crea un directivo de prueba ("Directivo Test") y devuelve su access_token
+/// las claves quedan registradas en el guard para limpiarlas al final
+pub fn insert_directive_helper(context: &GeneralContext, guard: &mut TestRedisGuard) -> String {
+    insert_user_helper(context, guard, "Directivo Test", true)
+}
+
+/// Crea un socio normal de prueba ("Socio Test"), no puede revisar pagos
+pub fn insert_member_helper(context: &GeneralContext, guard: &mut TestRedisGuard) -> String {
+    insert_user_helper(context, guard, "Socio Test", false)
+}
+
+fn insert_user_helper(
+    context: &GeneralContext,
+    guard: &mut TestRedisGuard,
+    complete_name: &str,
+    is_directive: bool,
+) -> String {
+    use general_api::repos::auth::utils::hashing_composite_key;
+
+    let now = chrono::Utc::now().timestamp_nanos_opt().unwrap();
+    let access_token = format!("test_user_{}", now);
+    let db_access_token = hashing_composite_key(&[&access_token]);
+
+    let mut con = context.pool().unwrap()
+        .client()
+        .get_connection()
+        .expect("No se pudo obtener conexión de Redis");
+    let name_key = format!("users:{}:complete_name", db_access_token);
+    let affiliate_key = format!("users:{}:affiliate_key", db_access_token);
+    let directive_key = format!("users:{}:is_directive", db_access_token);
+    let _: () = con.set(&name_key, complete_name).unwrap();
+    let _: () = con
+        .set(&affiliate_key, format!("affiliate_{}", now))
+        .unwrap();
+    let _: () = con.set(&directive_key, is_directive).unwrap();
+    guard.register_key(name_key);
+    guard.register_key(affiliate_key);
+    guard.register_key(directive_key);
+
+    access_token
+}
+
 /// Inserta un pago en Redis y devuelve la clave usada
 pub fn insert_payment_helper_and_return(context: &GeneralContext, payment: &Payment) -> String {
     use chrono::Utc;
-    use general_api::models::redis::Payment as RedisPayment;
-    use general_api::repos::auth::utils::hashing_composite_key;
 
-    let pool = context.pool.clone();
-    let mut con = pool.get().expect("No se pudo obtener conexión de Redis");
+    let pool = context.pool().unwrap().clone();
+    let mut con = pool.client().get_connection().expect("No se pudo obtener conexión de Redis");
 
     // use a unique composite per helper call to avoid collisions when tests run in parallel
     // avoid deprecated timestamp_nanos, build a unique string from seconds + nanos
@@ -66,38 +133,357 @@ pub fn insert_payment_helper_and_return(context: &GeneralContext, payment: &Paym
     let redis_key = format!("users:{}:payments:{}", composite_key, payment.id);
 
     let redis_payment = RedisPayment {
-        date_created: payment.payment_date.clone(),
+        date_created: payment.payment_date,
         account_number: payment.account_num.clone(),
         total_amount: payment.total_amount,
+        currency: payment.currency,
         name: payment.name.clone(),
         comments: payment.commentary.clone(),
         comprobante_bucket: payment.photo_path.clone(),
         ticket_number: payment.ticket_num.clone(),
         status: payment.state.as_str().to_string(),
         being_payed: vec![],
+        possible_duplicates: false,
+        receipt_check: None,
     };
 
     let _: redis::RedisResult<()> = con.json_set(&redis_key, "$", &redis_payment);
     redis_key
 }
 
+/// contexto sobre un MemoryStore vacío, el store se regresa para cargarle datos
+pub fn create_memory_context() -> (Arc<MemoryStore>, GeneralContext) {
+    let store = Arc::new(MemoryStore::new());
+    (store.clone(), GeneralContext::in_memory(store))
+}
+
+/// socio para MemoryStore::add_user, sin capital pagado ni adeudado
+pub fn memory_user(affiliate_key: &str, complete_name: &str, is_directive: bool) -> MemoryUser {
+    MemoryUser {
+        affiliate_key: affiliate_key.to_string(),
+        complete_name: complete_name.to_string(),
+        is_directive,
+        payed_to_capital: Money::ZERO,
+        owed_capital: Money::ZERO,
+    }
+}
+
+/// pago guardado tal cual en redis, creado el día dado a las 18:00 UTC
+/// being_payed son (model_type, monto) y el total es la suma
+pub fn memory_payment(
+    ticket_number: &str,
+    (year, month, day): (i32, u32, u32),
+    status: &str,
+    being_payed: Vec<(&str, i32)>,
+) -> RedisPayment {
+    RedisPayment {
+        name: format!("Pago {ticket_number}"),
+        date_created: DateTime::from(Utc.with_ymd_and_hms(year, month, day, 18, 0, 0).unwrap()),
+        total_amount: Money::from(being_payed.iter().map(|(_, amount)| amount).sum::<i32>()),
+        ticket_number: ticket_number.to_string(),
+        status: status.to_string(),
+        being_payed: being_payed
+            .into_iter()
+            .map(|(model_type, amount)| PayedTo {
+                model_type: model_type.to_string(),
+                amount: Money::from(amount),
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+/// cuota de afiliado de 100 para QuotaStore::save_quota
+pub fn memory_quota(access_token: &str, exp_date: &str, monto_pagado: i32, payed: bool) -> Quota {
+    Quota {
+        user_id: access_token.to_string(),
+        amount: Money::from(100),
+        exp_date: Some(exp_date.parse().unwrap()),
+        monto_pagado: Some(Money::from(monto_pagado)),
+        multa: None,
+        pay_by: None,
+        quota_type: QuotaType::Afiliado,
+        loan_id: None,
+        is_extraordinary: Some(false),
+        payed: Some(payed),
+        quota_number: None,
+        nombre_prestamo: None,
+        nombre_usuario: None,
+        identifier: None,
+    }
+}
+
+/// préstamo activo, la deuda es lo que falta del total
+pub fn memory_loan(total: i32, payed: i32) -> RedisLoan {
+    RedisLoan {
+        total: Money::from(total),
+        payed: Money::from(payed),
+        debt: Money::from(total - payed),
+        status: "ACTIVE".to_string(),
+        ..Default::default()
+    }
+}
+
+/// multa puesta el día dado a las 18:00 UTC, sin día es una multa vieja sin fecha
+pub fn memory_fine(amount: i32, status: &str, issued_on: Option<(i32, u32, u32)>) -> RedisFine {
+    RedisFine {
+        amount: Money::from(amount),
+        motive: "inasistencia".to_string(),
+        status: status.to_string(),
+        created_at: issued_on.map(|(year, month, day)| {
+            DateTime::from(Utc.with_ymd_and_hms(year, month, day, 18, 0, 0).unwrap())
+        }),
+    }
+}
+
+/// directivo "Directivo Test" en el store, devuelve su access_token
+pub fn add_memory_directive(store: &MemoryStore) -> String {
+    let access_token = "directivo_test".to_string();
+    store
+        .add_user(&access_token, memory_user("AF-DIR-TEST", "Directivo Test", true))
+        .unwrap();
+    access_token
+}
+
+/// guarda un Payment de graphql en users:{owner_key}:payments:{id} del store
+pub fn insert_memory_payment(store: &MemoryStore, owner_key: &str, payment: &Payment) -> String {
+    store
+        .insert_payment(owner_key, &payment.id, to_redis_payment(payment))
+        .unwrap()
+}
+
+/// comprobante que el socio (affiliate_key) ya subió y no está en ningún pago, devuelve el
+/// ticket_id que se manda como comprobante_path
+pub fn insert_memory_receipt(store: &MemoryStore, affiliate_key: &str, name: &str) -> String {
+    let ticket_id = hashing_composite_key(&[&affiliate_key.to_string(), &name.to_string()]);
+    store
+        .insert_receipt(
+            &ticket_id,
+            StoredReceipt {
+                key: format!("payment-tickets/{}.pdf", ticket_id),
+                content_type: "application/pdf".to_string(),
+                size: 1024,
+                uploaded_at: DateTime::now(),
+                owner: Some(affiliate_key.to_string()),
+                linked_at: None,
+                reading: None,
+            },
+        )
+        .unwrap();
+    ticket_id
+}
+
+fn to_redis_payment(payment: &Payment) -> RedisPayment {
+    RedisPayment {
+        date_created: payment.payment_date,
+        account_number: payment.account_num.clone(),
+        total_amount: payment.total_amount,
+        currency: payment.currency,
+        name: payment.name.clone(),
+        comments: payment.commentary.clone(),
+        comprobante_bucket: payment.photo_path.clone(),
+        ticket_number: payment.ticket_num.clone(),
+        status: payment.state.as_str().to_string(),
+        being_payed: payment.being_payed.clone(),
+        possible_duplicates: payment.possible_duplicates,
+        receipt_check: None,
+    }
+}
+
+/// backend de los tests de dominio, los mismos asserts corren contra redis y contra MemoryStore
+#[derive(Clone, Copy, Debug)]
+pub enum Backend {
+    Redis,
+    Memory,
+}
+
+/// para correr el cuerpo del test una vez por backend:
+/// `for backend in BACKENDS { let mut backend = backend.start().await; ... }`
+pub const BACKENDS: [Backend; 2] = [Backend::Redis, Backend::Memory];
+
+impl Backend {
+    /// contexto vacío sobre el backend, con redis toma el lock de los tests y lo que se cargue
+    /// con el TestBackend se borra al soltarlo
+    pub async fn start(self) -> TestBackend {
+        // el nombre sale en la salida del test si algún assert falla
+        println!("backend: {:?}", self);
+        match self {
+            Backend::Redis => {
+                let lock = redis_test_lock().await;
+                let context = create_test_context();
+                let guard = TestRedisGuard::new(context.pool().unwrap().clone());
+                TestBackend {
+                    context,
+                    store: TestStore::Redis {
+                        guard: Box::new(guard),
+                        _lock: lock,
+                    },
+                }
+            }
+            Backend::Memory => {
+                let (store, context) = create_memory_context();
+                TestBackend {
+                    context,
+                    store: TestStore::Memory(store),
+                }
+            }
+        }
+    }
+}
+
+pub struct TestBackend {
+    pub context: GeneralContext,
+    store: TestStore,
+}
+
+enum TestStore {
+    // el guard limpia antes de soltar el lock (orden de los campos)
+    Redis {
+        guard: Box<TestRedisGuard>,
+        _lock: MutexGuard<'static, ()>,
+    },
+    Memory(Arc<MemoryStore>),
+}
+
+impl TestBackend {
+    /// registra al socio como lo deja el registro de usuarios, devuelve su owner_key
+    /// con redis todo lo que quede bajo users:{owner_key}:* se borra al final
+    pub fn add_user(&mut self, access_token: &str, user: MemoryUser) -> String {
+        match &mut self.store {
+            TestStore::Redis { guard, .. } => {
+                let owner_key = hashing_composite_key(&[&access_token.to_string()]);
+                let mut con = self.context.pool().unwrap()
+                    .client()
+                    .get_connection()
+                    .expect("No se pudo obtener conexión de Redis");
+                let mapping_key = format!("affiliate_key_to_db_access:{}", user.affiliate_key);
+                let _: () = con.set(&mapping_key, &owner_key).unwrap();
+                let _: () = con
+                    .set(format!("users:{}:complete_name", owner_key), &user.complete_name)
+                    .unwrap();
+                let _: () = con
+                    .set(format!("users:{}:affiliate_key", owner_key), &user.affiliate_key)
+                    .unwrap();
+                let _: () = con
+                    .set(format!("users:{}:is_directive", owner_key), user.is_directive)
+                    .unwrap();
+                let _: () = con
+                    .set(
+                        format!("users:{}:payed_to_capital", owner_key),
+                        user.payed_to_capital.to_string(),
+                    )
+                    .unwrap();
+                let _: () = con
+                    .set(
+                        format!("users:{}:owed_capital", owner_key),
+                        user.owed_capital.to_string(),
+                    )
+                    .unwrap();
+                guard.register_key(mapping_key);
+                guard.register_pattern(format!("users:{}:*", owner_key));
+                owner_key
+            }
+            TestStore::Memory(store) => store.add_user(access_token, user).unwrap(),
+        }
+    }
+
+    /// dueño que no está registrado como socio pero al que el repo le va a guardar datos,
+    /// devuelve su owner_key y con redis sus claves se borran al final
+    pub fn track_owner(&mut self, access_token: &str) -> String {
+        let owner_key = hashing_composite_key(&[&access_token.to_string()]);
+        if let TestStore::Redis { guard, .. } = &mut self.store {
+            guard.register_pattern(format!("users:{}:*", owner_key));
+        }
+        owner_key
+    }
+
+    /// directivo "Directivo Test", devuelve su access_token
+    pub fn add_directive(&mut self) -> String {
+        let access_token = "directivo_test".to_string();
+        self.add_user(&access_token, memory_user("AF-DIR-TEST", "Directivo Test", true));
+        access_token
+    }
+
+    /// guarda un Payment de graphql en users:{owner_key}:payments:{id}, sin pasar por el
+    /// índice de boletas
+    pub fn insert_payment(&mut self, owner_key: &str, payment: &Payment) -> String {
+        let key = format!("users:{}:payments:{}", owner_key, payment.id);
+        match &mut self.store {
+            TestStore::Redis { guard, .. } => {
+                let mut con = self.context.pool().unwrap()
+                    .client()
+                    .get_connection()
+                    .expect("No se pudo obtener conexión de Redis");
+                let _: () = con.json_set(&key, "$", &to_redis_payment(payment)).unwrap();
+                guard.register_key(key.clone());
+                key
+            }
+            TestStore::Memory(store) => insert_memory_payment(store, owner_key, payment),
+        }
+    }
+
+    /// guarda el préstamo en users:{owner_key}:loans:{id}
+    pub fn insert_loan(&mut self, owner_key: &str, loan_id: &str, loan: RedisLoan) -> String {
+        match &mut self.store {
+            TestStore::Redis { guard, .. } => {
+                let key = format!("users:{}:loans:{}", owner_key, loan_id);
+                let mut con = self.context.pool().unwrap()
+                    .client()
+                    .get_connection()
+                    .expect("No se pudo obtener conexión de Redis");
+                let _: () = con.json_set(&key, "$", &loan).unwrap();
+                guard.register_key(key.clone());
+                key
+            }
+            TestStore::Memory(store) => store.insert_loan(owner_key, loan_id, loan).unwrap(),
+        }
+    }
+
+    /// guarda la multa en users:{owner_key}:fines:{id}
+    pub fn insert_fine(&mut self, owner_key: &str, fine_id: &str, fine: RedisFine) -> String {
+        match &mut self.store {
+            TestStore::Redis { guard, .. } => {
+                let key = format!("users:{}:fines:{}", owner_key, fine_id);
+                let mut con = self.context.pool().unwrap()
+                    .client()
+                    .get_connection()
+                    .expect("No se pudo obtener conexión de Redis");
+                let _: () = con.json_set(&key, "$", &fine).unwrap();
+                guard.register_key(key.clone());
+                key
+            }
+            TestStore::Memory(store) => store.insert_fine(owner_key, fine_id, fine).unwrap(),
+        }
+    }
+
+    /// claves que crea el repo durante el test (historial, índice de boletas...), con redis
+    /// se borran al final
+    pub fn register_key(&mut self, key: String) {
+        if let TestStore::Redis { guard, .. } = &mut self.store {
+            guard.register_key(key);
+        }
+    }
+}
+
 #[cfg(test)]
 mod integration {
     use super::*;
     use general_api::models::graphql::{Payment, PaymentStatus};
     use redis::Commands;
 
-    #[test]
-    fn test_guard_borra_solo_claves_registradas() {
+    #[tokio::test]
+    async fn test_guard_borra_solo_claves_registradas() {
         let context = create_test_context();
-        let mut guard = TestRedisGuard::new(context.pool.clone());
+        let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
 
         // Insertar dos pagos y registrar solo uno
         let payment1 = Payment {
             id: "test_guard_pago1".to_string(),
             name: "Test1".to_string(),
-            total_amount: 10.0,
-            payment_date: "2025-10-14".to_string(),
+            total_amount: Money::from(10),
+            currency: Currency::Gtq,
+            payment_date: "2025-10-14".parse().unwrap(),
             ticket_num: "T1".to_string(),
             account_num: "ACC1".to_string(),
             commentary: Some("Pago guard 1".to_string()),
@@ -105,13 +491,16 @@ mod integration {
             state: PaymentStatus::Accepted,
             being_payed: vec![],
             presented_by_name: "N/A".to_string(),
+            possible_duplicates: false,
+            receipt_check: None,
         };
 
         let payment2 = Payment {
             id: "test_guard_pago2".to_string(),
             name: "Test2".to_string(),
-            total_amount: 20.0,
-            payment_date: "2025-10-14".to_string(),
+            total_amount: Money::from(20),
+            currency: Currency::Gtq,
+            payment_date: "2025-10-14".parse().unwrap(),
             ticket_num: "T2".to_string(),
             account_num: "ACC2".to_string(),
             commentary: Some("Pago guard 2".to_string()),
@@ -119,6 +508,8 @@ mod integration {
             state: PaymentStatus::Accepted,
             being_payed: vec![],
             presented_by_name: "N/A".to_string(),
+            possible_duplicates: false,
+            receipt_check: None,
         };
 
         let key1 = insert_payment_helper_and_return(&context, &payment1);
@@ -126,7 +517,7 @@ mod integration {
         guard.register_key(key1.clone());
 
         // Verificar que ambas claves existen antes del drop
-        let mut con = context.pool.get().unwrap();
+        let mut con = context.pool().unwrap().client().get_connection().unwrap();
         assert!(
             con.exists::<_, bool>(&key1).unwrap(),
             "La clave 1 debe existir antes de drop"
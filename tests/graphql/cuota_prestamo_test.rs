@@ -22,7 +22,7 @@ fn get_test_repo() -> QuotaRepo {
     dotenv().ok();
     let redis_url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
     let pool = Data::new(Pool::new(Client::open(redis_url).unwrap()).unwrap());
-    QuotaRepo { pool }
+    QuotaRepo::new(pool)
 }
 
 // Helper para limpiar Redis antes/después de cada test
@@ -76,7 +76,7 @@ mod tests {
     // Utilidad para crear un QuotaRepo de prueba usando el helper central del proyecto
     fn get_test_repo() -> QuotaRepo {
         let context = create_test_context();
-        QuotaRepo { pool: context.pool.clone() }
+        QuotaRepo::new(context.pool.clone())
     }
 
     // Utilidad para insertar quotas de prueba
@@ -0,0 +1,279 @@
+// Tests para multi-moneda: tipos de cambio, conversión al abonar y reporte en moneda base
+
+use super::common::{add_memory_directive, create_memory_context, memory_user, BACKENDS};
+use general_api::models::PayedToInput;
+use general_api::models::currency::{Currency, ExchangeRates};
+use general_api::models::graphql::{Quota, QuotaType};
+use general_api::models::money::Money;
+use general_api::models::redis::Loan as RedisLoan;
+use general_api::repos::graphql::payment::payment_ticket_index_key;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+fn money(raw: &str) -> Money {
+    raw.parse().unwrap()
+}
+
+#[test]
+fn test_convert_goes_through_base_currency_and_rounds_once() {
+    let rates = ExchangeRates::new(HashMap::from([(Currency::Usd, Decimal::new(775, 2))]));
+
+    assert_eq!(
+        rates.convert(money("100.00"), Currency::Usd, Currency::Gtq).unwrap(),
+        money("775.00")
+    );
+    // 100 / 7.75 = 12.903225... -> 12.90
+    assert_eq!(
+        rates.convert(money("100.00"), Currency::Gtq, Currency::Usd).unwrap(),
+        money("12.90")
+    );
+    assert_eq!(
+        rates.convert(money("10.00"), Currency::Usd, Currency::Usd).unwrap(),
+        money("10.00")
+    );
+
+    let empty = ExchangeRates::default();
+    assert!(empty.to_base(money("1.00"), Currency::Usd).is_err());
+}
+
+#[tokio::test]
+async fn test_only_directives_can_set_exchange_rates() {
+    let (store, context) = create_memory_context();
+    let repo = context.currency_repo();
+
+    let member = "socio_test".to_string();
+    store
+        .add_user(&member, memory_user("AF-SOCIO-TEST", "Socio Test", false))
+        .unwrap();
+    assert!(
+        repo.set_exchange_rate(member, Currency::Usd, "7.80".to_string())
+            .await
+            .is_err(),
+        "Un socio normal no puede cambiar el tipo de cambio"
+    );
+
+    let directive = add_memory_directive(&store);
+    assert!(
+        repo.set_exchange_rate(directive.clone(), Currency::Gtq, "2".to_string())
+            .await
+            .is_err(),
+        "La moneda base no lleva tipo de cambio"
+    );
+    assert!(
+        repo.set_exchange_rate(directive.clone(), Currency::Usd, "-1".to_string())
+            .await
+            .is_err()
+    );
+
+    let saved = repo
+        .set_exchange_rate(directive, Currency::Usd, "7.80".to_string())
+        .await
+        .expect("set_exchange_rate failed");
+    assert_eq!(saved.rate, "7.8");
+    assert_eq!(saved.updated_by_name, "Directivo Test");
+
+    let rates = repo.get_exchange_rates().await.unwrap();
+    let usd = rates
+        .iter()
+        .find(|rate| rate.currency == Currency::Usd)
+        .expect("Debe existir el tipo de cambio de USD");
+    assert_eq!(usd.base_currency, Currency::Gtq);
+    assert_eq!(usd.rate, "7.8");
+}
+
+#[tokio::test]
+async fn test_usd_payment_to_usd_loan_and_local_fine_is_converted() {
+    let (store, context) = create_memory_context();
+    let directive = add_memory_directive(&store);
+    context
+        .currency_repo()
+        .set_exchange_rate(directive, Currency::Usd, "7.75".to_string())
+        .await
+        .unwrap();
+
+    let user = "test_currency".to_string();
+    let owner_key = store
+        .add_user(&user, memory_user("AF-CURRENCY", "Socio Dólares", false))
+        .unwrap();
+    let loan_id = "USDLOAN".to_string();
+    store
+        .insert_loan(
+            &owner_key,
+            &loan_id,
+            RedisLoan {
+                currency: Currency::Usd,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    context
+        .payment_repo()
+        .create_payment(
+            user.clone(),
+            "Pago en dólares".to_string(),
+            "si".to_owned(),
+            money("120.00"),
+            Currency::Usd,
+            "T1".to_string(),
+            "USD_ACC".to_string(),
+            vec![
+                PayedToInput {
+                    model_type: "LOAN".to_string(),
+                    amount: money("100.00"),
+                    model_key: loan_id,
+                },
+                PayedToInput {
+                    model_type: "FINE".to_string(),
+                    amount: money("20.00"),
+                    model_key: "multa".to_string(),
+                },
+            ],
+        )
+        .await
+        .expect("create_payment failed");
+
+    let payments = context.payment_repo().get_user_payments(user).await.unwrap();
+    assert_eq!(payments.len(), 1);
+    assert_eq!(payments[0].currency, Currency::Usd);
+
+    let loan_part = &payments[0].being_payed[0];
+    assert_eq!(loan_part.allocated_currency, Currency::Usd);
+    assert_eq!(loan_part.allocated_amount, Some(money("100.00")));
+
+    let fine_part = &payments[0].being_payed[1];
+    assert_eq!(fine_part.allocated_currency, Currency::Gtq);
+    assert_eq!(fine_part.allocated_amount, Some(money("155.00")));
+}
+
+#[tokio::test]
+async fn test_quota_of_a_usd_loan_is_allocated_in_usd() {
+    for backend in BACKENDS {
+        let mut backend = backend.start().await;
+
+        let user = "test_currency_quota".to_string();
+        let owner_key =
+            backend.add_user(&user, memory_user("AF-CURRENCY-QUOTA", "Socio Cuota Dólares", false));
+        let loan_id = "USDQUOTALOAN".to_string();
+        backend.insert_loan(
+            &owner_key,
+            &loan_id,
+            RedisLoan {
+                currency: Currency::Usd,
+                ..Default::default()
+            },
+        );
+        // la fecha es el model_key de la cuota, una que no use ningún otro test
+        let exp_date = "2041-07-13";
+        backend
+            .context
+            .quota_repo()
+            .save_quota(
+                user.clone(),
+                &Quota {
+                    user_id: user.clone(),
+                    amount: money("50.00"),
+                    exp_date: Some(exp_date.parse().unwrap()),
+                    monto_pagado: None,
+                    multa: None,
+                    pay_by: None,
+                    quota_type: QuotaType::Prestamo,
+                    loan_id: Some(loan_id),
+                    is_extraordinary: Some(false),
+                    payed: Some(false),
+                    quota_number: Some(1),
+                    nombre_prestamo: None,
+                    nombre_usuario: None,
+                    identifier: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        // sin tipo de cambio: si la cuota se asignara a la moneda base no se podría convertir
+        backend
+            .context
+            .payment_repo()
+            .create_payment(
+                user.clone(),
+                "Cuota en dólares".to_string(),
+                "si".to_owned(),
+                money("50.00"),
+                Currency::Usd,
+                "TQ1".to_string(),
+                "USD_QUOTA_ACC".to_string(),
+                vec![PayedToInput {
+                    model_type: "QUOTA".to_string(),
+                    amount: money("50.00"),
+                    model_key: exp_date.to_string(),
+                }],
+            )
+            .await
+            .expect("create_payment failed");
+        backend.register_key(payment_ticket_index_key("USD_QUOTA_ACC", "TQ1"));
+
+        let payments = backend.context.payment_repo().get_user_payments(user).await.unwrap();
+        assert_eq!(payments.len(), 1);
+        let quota_part = &payments[0].being_payed[0];
+        assert_eq!(quota_part.allocated_currency, Currency::Usd);
+        assert_eq!(quota_part.allocated_amount, Some(money("50.00")));
+    }
+}
+
+#[tokio::test]
+async fn test_foreign_payment_without_rate_is_rejected() {
+    let (_store, context) = create_memory_context();
+
+    let result = context.payment_repo().create_payment(
+        "test_currency_norate".to_string(),
+        "Sin tipo de cambio".to_string(),
+        "si".to_owned(),
+        money("10.00"),
+        Currency::Usd,
+        "NR1".to_string(),
+        "NR_ACC".to_string(),
+        vec![PayedToInput {
+            model_type: "FINE".to_string(),
+            amount: money("10.00"),
+            model_key: "multa".to_string(),
+        }],
+    )
+    .await;
+
+    assert!(result.is_err(), "Sin tipo de cambio no se puede convertir el abono");
+}
+
+#[tokio::test]
+async fn test_report_totals_payments_in_base_currency() {
+    let (store, context) = create_memory_context();
+    let directive = add_memory_directive(&store);
+    context
+        .currency_repo()
+        .set_exchange_rate(directive, Currency::Usd, "8".to_string())
+        .await
+        .unwrap();
+
+    context
+        .payment_repo()
+        .create_payment(
+            "test_currency_report".to_string(),
+            "Reporte".to_string(),
+            "si".to_owned(),
+            money("10.00"),
+            Currency::Usd,
+            "R1".to_string(),
+            "REP_ACC".to_string(),
+            vec![],
+        )
+        .await
+        .unwrap();
+
+    let report = context.currency_repo().get_base_currency_report().await.unwrap();
+    assert_eq!(report.base_currency, Currency::Gtq);
+    assert_eq!(
+        report.pending_payments_total,
+        money("80.00"),
+        "10 USD a 8 GTQ por dólar"
+    );
+    assert_eq!(report.accepted_payments_total, Money::ZERO);
+}
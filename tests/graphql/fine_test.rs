@@ -1,45 +1,45 @@
 // Pruebas unitarias para la query get_user_fines
 // Valida que el campo presented_by_name se enriquezca correctamente
 
-use general_api::models::graphql::{Fine, FineStatus};
-use general_api::repos::graphql::fine::FineRepo;
+use general_api::endpoints::handlers::configs::connection_pool::RedisPool;
+use general_api::models::money::Money;
 use super::common::{create_test_context, TestRedisGuard};
-use general_api::test_sync::REDIS_TEST_LOCK;
+use general_api::test_sync::redis_test_lock;
 use general_api::repos::auth::utils::hashing_composite_key;
 use redis::{Commands, JsonCommands};
-use actix_web::web::Data;
 
 /// Helper para insertar una multa de prueba en Redis y retornar su key
 fn insert_fine_helper_and_return(
-    pool: &r2d2::Pool<redis::Client>,
+    pool: &RedisPool,
     user_hash: &str,
     fine_id: &str,
-    amount: f32,
+    amount: Money,
     motive: &str,
 ) -> String {
     use general_api::models::redis::Fine as RedisFine;
 
-    let mut con = pool.get().expect("No se pudo obtener conexión de Redis");
+    let mut con = pool.client().get_connection().expect("No se pudo obtener conexión de Redis");
     let redis_key = format!("users:{}:fines:{}", user_hash, fine_id);
 
     let redis_fine = RedisFine {
         amount,
         motive: motive.to_string(),
         status: "UNPAID".to_string(),
+        created_at: None,
     };
 
     let _: redis::RedisResult<()> = con.json_set(&redis_key, "$", &redis_fine);
     redis_key
 }
 
-#[test]
-fn test_get_user_fines_returns_with_presented_by_name() {
+#[tokio::test]
+async fn test_get_user_fines_returns_with_presented_by_name() {
     // Serializar pruebas que tocan Redis
-    let _guard = REDIS_TEST_LOCK.get_or_init(|| std::sync::Mutex::new(())).lock().unwrap();
+    let _guard = redis_test_lock().await;
     
     // Crear contexto y guard para limpieza
     let context = create_test_context();
-    let mut guard = TestRedisGuard::new(context.pool.clone());
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
 
     // Crear un user_hash único para este test
     let now = chrono::Utc::now().timestamp_nanos_opt().unwrap();
@@ -50,7 +50,10 @@ fn test_get_user_fines_returns_with_presented_by_name() {
     let complete_name = "Juan Pérez Test";
     let user_name_key = format!("users:{}:complete_name", user_hash);
     {
-        let mut con = context.pool.get().expect("No se pudo obtener conexión de Redis");
+        let mut con = context.pool().unwrap()
+            .client()
+            .get_connection()
+            .expect("No se pudo obtener conexión de Redis");
         let _: redis::RedisResult<()> = con.set(&user_name_key, complete_name);
         guard.register_key(user_name_key.clone());
     }
@@ -60,28 +63,26 @@ fn test_get_user_fines_returns_with_presented_by_name() {
     let fine_id_2 = format!("fine_{}_2", now);
 
     let key1 = insert_fine_helper_and_return(
-        &context.pool,
+        context.pool().unwrap(),
         &user_hash,
         &fine_id_1,
-        100.0,
+        Money::from(100),
         "Multa test 1",
     );
     guard.register_key(key1);
 
     let key2 = insert_fine_helper_and_return(
-        &context.pool,
+        context.pool().unwrap(),
         &user_hash,
         &fine_id_2,
-        200.0,
+        Money::from(200),
         "Multa test 2",
     );
     guard.register_key(key2);
 
     // Crear el FineRepo y llamar a get_user_fines
     // Necesitamos simular el access_token que sería el user_hash
-    let fine_repo = FineRepo {
-        pool: context.pool.clone(),
-    };
+    let fine_repo = context.fine_repo();
 
     // get_user_fines espera un access_token, pero internamente usa get_db_access_token_with_affiliate_key
     // Para simplificar, podemos usar el user_hash directamente si modificamos temporalmente,
@@ -93,7 +94,10 @@ fn test_get_user_fines_returns_with_presented_by_name() {
     let affiliate_redis_key = format!("users:{}:affiliate_key", user_hash);
     let affiliate_to_db_key = format!("affiliate_key_to_db_access:{}", affiliate_key);
     {
-        let mut con = context.pool.get().expect("No se pudo obtener conexión de Redis");
+        let mut con = context.pool().unwrap()
+            .client()
+            .get_connection()
+            .expect("No se pudo obtener conexión de Redis");
         // Guardar users:{hash}:affiliate_key
         let _: redis::RedisResult<()> = con.set(&affiliate_redis_key, &affiliate_key);
         guard.register_key(affiliate_redis_key.clone());
@@ -103,7 +107,7 @@ fn test_get_user_fines_returns_with_presented_by_name() {
     }
 
     // Ahora get_user_fines debería poder encontrar las multas usando affiliate_key como access_token
-    let result = fine_repo.get_user_fines(affiliate_key);
+    let result = fine_repo.get_user_fines(affiliate_key).await;
 
     assert!(result.is_ok(), "get_user_fines should succeed");
     let fines = result.unwrap();
@@ -118,19 +122,19 @@ fn test_get_user_fines_returns_with_presented_by_name() {
             "presented_by_name should be enriched with the complete name: {}",
             complete_name
         );
-        assert!(fine.amount > 0.0, "Fine amount should be positive");
+        assert!(fine.amount > Money::ZERO, "Fine amount should be positive");
         assert!(!fine.reason.is_empty(), "Fine reason should not be empty");
     }
 }
 
-#[test]
-fn test_get_user_fines_defaults_to_na_when_no_user_name() {
+#[tokio::test]
+async fn test_get_user_fines_defaults_to_na_when_no_user_name() {
     // Test que valida que cuando no existe users:{hash}:complete_name, 
     // el presented_by_name sea "N/A"
-    let _guard = REDIS_TEST_LOCK.get_or_init(|| std::sync::Mutex::new(())).lock().unwrap();
+    let _guard = redis_test_lock().await;
     
     let context = create_test_context();
-    let mut guard = TestRedisGuard::new(context.pool.clone());
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
 
     // Crear un user_hash único
     let now = chrono::Utc::now().timestamp_nanos_opt().unwrap();
@@ -142,10 +146,10 @@ fn test_get_user_fines_defaults_to_na_when_no_user_name() {
     // Insertar una multa
     let fine_id = format!("fine_{}_na", now);
     let key = insert_fine_helper_and_return(
-        &context.pool,
+        context.pool().unwrap(),
         &user_hash,
         &fine_id,
-        150.0,
+        Money::from(150),
         "Multa sin nombre",
     );
     guard.register_key(key);
@@ -155,7 +159,10 @@ fn test_get_user_fines_defaults_to_na_when_no_user_name() {
     let affiliate_redis_key = format!("users:{}:affiliate_key", user_hash);
     let affiliate_to_db_key = format!("affiliate_key_to_db_access:{}", affiliate_key);
     {
-        let mut con = context.pool.get().expect("No se pudo obtener conexión de Redis");
+        let mut con = context.pool().unwrap()
+            .client()
+            .get_connection()
+            .expect("No se pudo obtener conexión de Redis");
         // Guardar users:{hash}:affiliate_key
         let _: redis::RedisResult<()> = con.set(&affiliate_redis_key, &affiliate_key);
         guard.register_key(affiliate_redis_key.clone());
@@ -164,11 +171,9 @@ fn test_get_user_fines_defaults_to_na_when_no_user_name() {
         guard.register_key(affiliate_to_db_key.clone());
     }
 
-    let fine_repo = FineRepo {
-        pool: context.pool.clone(),
-    };
+    let fine_repo = context.fine_repo();
 
-    let result = fine_repo.get_user_fines(affiliate_key);
+    let result = fine_repo.get_user_fines(affiliate_key).await;
     
     assert!(result.is_ok(), "get_user_fines should succeed even without complete_name");
     let fines = result.unwrap();
@@ -179,3 +184,78 @@ fn test_get_user_fines_defaults_to_na_when_no_user_name() {
         "presented_by_name should default to N/A when no complete_name exists"
     );
 }
+
+// los mismos casos contra redis y contra MemoryStore
+mod en_cada_backend {
+    use general_api::models::money::Money;
+    use general_api::models::redis::Fine as RedisFine;
+
+    use super::super::common::{memory_user, BACKENDS};
+
+    fn unpaid_fine(amount: Money, motive: &str) -> RedisFine {
+        RedisFine {
+            amount,
+            motive: motive.to_string(),
+            status: "UNPAID".to_string(),
+            created_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_user_fines_returns_with_presented_by_name() {
+        for backend in BACKENDS {
+            let mut backend = backend.start().await;
+
+            // socio con nombre completo para poder validar presented_by_name
+            let complete_name = "Juan Pérez Test";
+            let affiliate_key = "test_affiliate_backend".to_string();
+            let user_hash = backend.add_user(
+                "test_fine_backend",
+                memory_user(&affiliate_key, complete_name, false),
+            );
+
+            backend.insert_fine(&user_hash, "fine_1", unpaid_fine(Money::from(100), "Multa test 1"));
+            backend.insert_fine(&user_hash, "fine_2", unpaid_fine(Money::from(200), "Multa test 2"));
+
+            // get_user_fines recibe el affiliate_key y lo resuelve al hash del socio
+            let fines = backend
+                .context
+                .fine_repo()
+                .get_user_fines(affiliate_key)
+                .await
+                .expect("get_user_fines should succeed");
+
+            assert_eq!(fines.len(), 2, "Should have 2 fines");
+            for fine in fines.iter() {
+                assert_eq!(fine.presented_by_name, complete_name);
+                assert!(fine.amount > Money::ZERO, "Fine amount should be positive");
+                assert!(!fine.reason.is_empty(), "Fine reason should not be empty");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_user_fines_defaults_to_na_when_no_user_name() {
+        for backend in BACKENDS {
+            let mut backend = backend.start().await;
+
+            // el hash no está registrado como socio, no hay complete_name
+            let user_hash = backend.track_owner("test_fine_na_backend");
+            backend.insert_fine(
+                &user_hash,
+                "fine_na",
+                unpaid_fine(Money::from(150), "Multa sin nombre"),
+            );
+
+            let fines = backend
+                .context
+                .fine_repo()
+                .get_fines_by_owner(&user_hash)
+                .await
+                .expect("get_fines_by_owner should succeed even without complete_name");
+
+            assert_eq!(fines.len(), 1, "Should have 1 fine");
+            assert_eq!(fines[0].presented_by_name, "N/A");
+        }
+    }
+}
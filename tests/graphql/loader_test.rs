@@ -0,0 +1,206 @@
+// Tests del RequestLoader: lecturas agrupadas en MGET / JSON.MGET y cache por request
+
+use super::common::{TestRedisGuard, create_test_context};
+use general_api::endpoints::handlers::configs::connection_pool::RedisPool;
+use general_api::endpoints::handlers::graphql::root::{Mutation, Query};
+use general_api::models::redis::{Fine as RedisFine, Loan as RedisLoan, Payment as RedisPayment};
+use general_api::repos::auth::utils::hashing_composite_key;
+use general_api::repos::graphql::loader::RequestLoader;
+use general_api::test_sync::redis_test_lock;
+use juniper::{EmptySubscription, RootNode, Variables};
+use redis::{Commands, JsonCommands};
+
+/// socio con nombre, affiliate_key y `fines` multas; devuelve (access_token, user_hash)
+fn insert_member_with_fines(
+    pool: &RedisPool,
+    guard: &mut TestRedisGuard,
+    label: &str,
+    fines: usize,
+) -> (String, String) {
+    let now = chrono::Utc::now().timestamp_nanos_opt().unwrap();
+    let access_token = format!("test_loader_{}_{}", label, now);
+    let user_hash = hashing_composite_key(&[&access_token]);
+    let mut con = pool.client().get_connection().unwrap();
+
+    for (field, value) in [
+        ("complete_name", format!("Socio {}", label)),
+        ("affiliate_key", format!("affiliate_{}_{}", label, now)),
+    ] {
+        let key = format!("users:{}:{}", user_hash, field);
+        let _: () = con.set(&key, value).unwrap();
+        guard.register_key(key);
+    }
+
+    for index in 0..fines {
+        let key = format!("users:{}:fines:F{}", user_hash, index);
+        let _: () = con.json_set(&key, "$", &RedisFine::default()).unwrap();
+        guard.register_key(key);
+    }
+
+    (access_token, user_hash)
+}
+
+#[tokio::test]
+async fn test_loader_batches_and_caches_string_lookups() {
+    let _lock = redis_test_lock().await;
+    let context = create_test_context();
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
+    let hashes: Vec<String> = ["a", "b", "c"]
+        .iter()
+        .map(|label| insert_member_with_fines(context.pool().unwrap(), &mut guard, label, 0).1)
+        .collect();
+    let loader = RequestLoader::new(context.pool().unwrap().clone());
+
+    let mut with_unknown = hashes.clone();
+    with_unknown.push("no_existe".to_string());
+    let names = loader.complete_names(&with_unknown).await.unwrap();
+    assert_eq!(loader.round_trips(), 1, "Un solo MGET para todos los nombres");
+    assert_eq!(names[&hashes[1]].as_deref(), Some("Socio b"));
+    assert_eq!(names["no_existe"], None);
+
+    // ya están en cache, incluso el que no existe
+    loader.complete_names(&with_unknown).await.unwrap();
+    assert_eq!(loader.round_trips(), 1);
+
+    // member solo pide lo que falta (affiliate_key)
+    let member = loader.member(&hashes[0]).await.unwrap();
+    assert_eq!(member.name, "Socio a");
+    assert_eq!(loader.round_trips(), 2);
+    assert!(loader.member("no_existe").await.is_err());
+}
+
+#[tokio::test]
+async fn test_loader_fetches_models_in_one_json_mget() {
+    let _lock = redis_test_lock().await;
+    let context = create_test_context();
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
+    let (_, user_hash) = insert_member_with_fines(context.pool().unwrap(), &mut guard, "models", 3);
+    let loader = RequestLoader::new(context.pool().unwrap().clone());
+
+    let mut keys: Vec<String> = (0..3)
+        .map(|index| format!("users:{}:fines:F{}", user_hash, index))
+        .collect();
+    keys.push(format!("users:{}:fines:NOPE", user_hash));
+
+    let fines = loader.get_models::<RedisFine>(&keys).await.unwrap();
+    assert_eq!(loader.round_trips(), 1);
+    assert_eq!(fines.iter().filter(|fine| fine.is_some()).count(), 3);
+    assert!(fines[3].is_none());
+
+    assert!(loader.get_model::<RedisFine>(&keys[0]).await.unwrap().is_some());
+    assert_eq!(loader.round_trips(), 1, "El segundo acceso sale de la cache");
+}
+
+#[tokio::test]
+async fn test_fine_members_resolve_through_the_request_loader() {
+    let _lock = redis_test_lock().await;
+    let context = create_test_context();
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
+    let (access_token, _) = insert_member_with_fines(context.pool().unwrap(), &mut guard, "graph", 3);
+    let schema = RootNode::new(Query, Mutation, EmptySubscription::new());
+
+    let (value, errors) = juniper::execute(
+        &format!(
+            r#"{{ me(accessToken: "{}") {{ fines {{ presentedByName member {{ name }} }} }} }}"#,
+            access_token
+        ),
+        None,
+        &schema,
+        &Variables::new(),
+        &context,
+    )
+    .await
+    .unwrap();
+    assert!(errors.is_empty(), "Errores inesperados: {:?}", errors);
+
+    let data = serde_json::to_value(&value).unwrap();
+    let fines = data["me"]["fines"].as_array().unwrap();
+    assert_eq!(fines.len(), 3);
+    for fine in fines {
+        assert_eq!(fine["presentedByName"], "Socio graph");
+        assert_eq!(fine["member"]["name"], "Socio graph");
+    }
+    // un JSON.MGET para las multas y, como son del mismo socio, un solo MGET de nombres
+    assert_eq!(context.loader().unwrap().round_trips(), 2);
+}
+
+#[tokio::test]
+async fn test_users_with_fines_are_grouped_without_duplicates() {
+    let _lock = redis_test_lock().await;
+    let context = create_test_context();
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
+    let (_, with_fines) = insert_member_with_fines(context.pool().unwrap(), &mut guard, "fined", 2);
+    let (_, without_fines) = insert_member_with_fines(context.pool().unwrap(), &mut guard, "clean", 0);
+
+    let mut con = context.pool().unwrap().client().get_connection().unwrap();
+    let affiliate_key: String = con
+        .get(format!("users:{}:affiliate_key", with_fines))
+        .unwrap();
+    let clean_affiliate_key: String = con
+        .get(format!("users:{}:affiliate_key", without_fines))
+        .unwrap();
+
+    let users = context.fine_repo().get_users_with_there_fines().await.unwrap();
+
+    let fined = users
+        .iter()
+        .find(|user| user.user_id == affiliate_key)
+        .expect("El socio con multas debe aparecer");
+    assert_eq!(fined.complete_name, "Socio fined");
+    assert_eq!(fined.fines.len(), 2);
+    assert!(fined.fines.iter().all(|fine| fine.presented_by_name == "Socio fined"));
+    assert!(!users.iter().any(|user| user.user_id == clean_affiliate_key));
+}
+
+#[tokio::test]
+async fn test_list_queries_take_a_fixed_number_of_round_trips() {
+    let _lock = redis_test_lock().await;
+    let context = create_test_context();
+    let pool = context.pool().unwrap().clone();
+    let mut guard = TestRedisGuard::new(pool.clone());
+    let mut con = pool.client().get_connection().unwrap();
+
+    let mut hashes = Vec::new();
+    for label in ["rt_a", "rt_b", "rt_c"] {
+        let (_, user_hash) = insert_member_with_fines(&pool, &mut guard, label, 0);
+        for index in 0..2 {
+            let loan_key = format!("users:{}:loans:L{}", user_hash, index);
+            let _: () = con.json_set(&loan_key, "$", &RedisLoan::default()).unwrap();
+            guard.register_key(loan_key);
+            let payment_key = format!("users:{}:payments:P{}", user_hash, index);
+            let _: () = con.json_set(&payment_key, "$", &RedisPayment::default()).unwrap();
+            guard.register_key(payment_key);
+        }
+        hashes.push(user_hash);
+    }
+
+    // cada lista: un JSON.MGET de los modelos + un MGET de los nombres, sin importar cuántos socios haya
+    let context = create_test_context();
+    let loans = context.loan_repo().get_all_loans().await.unwrap();
+    assert_eq!(context.loader().unwrap().round_trips(), 2);
+    let ours: Vec<_> = loans.iter().filter(|loan| hashes.contains(&loan.owner_key)).collect();
+    assert_eq!(ours.len(), 6);
+    assert!(ours.iter().all(|loan| loan.presented_by_name.starts_with("Socio rt_")));
+
+    let context = create_test_context();
+    let payments = context.payment_repo().get_all_payments().await.unwrap();
+    assert_eq!(context.loader().unwrap().round_trips(), 2);
+    assert_eq!(
+        payments
+            .iter()
+            .filter(|payment| payment.presented_by_name.starts_with("Socio rt_"))
+            .count(),
+        6
+    );
+
+    let context = create_test_context();
+    let affiliates = context.user_repo().get_all_users_for_affiliates().await.unwrap();
+    assert_eq!(context.loader().unwrap().round_trips(), 2);
+    assert_eq!(
+        affiliates
+            .iter()
+            .filter(|affiliate| affiliate.name.starts_with("Socio rt_"))
+            .count(),
+        3
+    );
+}
@@ -1,32 +1,30 @@
 // tests para create_loan (repo-level)
 // usamos los mismos helpers y patrón de runtime que en los tests existentes
 
-// local test-only lock para serializar acceso a redis cuando los tests corren en paralelo
-use std::sync::{Mutex, OnceLock};
-fn redis_test_lock() -> &'static Mutex<()> {
-    static REDIS_TEST_LOCAL: OnceLock<Mutex<()>> = OnceLock::new();
-    REDIS_TEST_LOCAL.get_or_init(|| Mutex::new(()))
-}
+use general_api::test_sync::redis_test_lock;
 
-// include shared test helpers from tests/graphql/common/mod.rs
-include!("common/mod.rs");
+use super::common::{TestRedisGuard, create_test_context};
 use general_api::repos::auth::{create_user_with_access_token, utils::hashing_composite_key};
-use redis::{Value as RedisValue, from_redis_value};
+use general_api::models::currency::Currency;
+use general_api::models::money::Money;
+use redis::{Commands, JsonCommands, Value as RedisValue, from_redis_value};
 use general_api::models::redis::Loan as RedisLoan;
 use serde_json::from_str;
 
-#[test]
-fn test_repo_create_loan_happy_path() {
-    let _guard = redis_test_lock().lock().unwrap();
+#[tokio::test]
+async fn test_repo_create_loan_happy_path() {
+    let _guard = redis_test_lock().await;
     let context = create_test_context();
-    let mut guard = TestRedisGuard::new(context.pool.clone());
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
 
     // crear usuario primero para establecer el mapeo affiliate_key
     let user_name = format!("testuser_loan_{}", chrono::Utc::now().timestamp_nanos_opt().unwrap());
     let password = "testpass123".to_string();
     let real_name = "Test User Loan".to_string();
 
-    let token_info = create_user_with_access_token(user_name.clone(), password, real_name)
+    let token_info =
+        create_user_with_access_token(context.pool().unwrap(), user_name.clone(), password, real_name)
+        .await
         .expect("Failed to create user");
 
     // calcular affiliate_key de la misma forma que lo hace create_user_with_access_token
@@ -47,14 +45,19 @@ fn test_repo_create_loan_happy_path() {
     let res = repo.create_loan(
         affiliate_key.clone(),
         12,
-        5000.0,
+        Money::from(5000),
+        Currency::Gtq,
         0.15, // interest_rate 15%
         "compra de equipo".to_string(),
-    );
+    )
+    .await;
     assert!(res.is_ok(), "create_loan retornó error: {:?}", res);
 
     // verificar existencia de la key en redis
-    let mut con = context.pool.get().expect("no se pudo obtener conexión de redis");
+    let mut con = context.pool().unwrap()
+        .client()
+        .get_connection()
+        .expect("no se pudo obtener conexión de redis");
     let keys: Vec<String> = con
         .scan_match(format!("users:{}:loans:*", db_access_token))
         .unwrap()
@@ -67,18 +70,20 @@ fn test_repo_create_loan_happy_path() {
     }
 }
 
-#[test]
-fn test_repo_create_loan_persists_json_content() {
-    let _guard = redis_test_lock().lock().unwrap();
+#[tokio::test]
+async fn test_repo_create_loan_persists_json_content() {
+    let _guard = redis_test_lock().await;
     let context = create_test_context();
-    let mut guard = TestRedisGuard::new(context.pool.clone());
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
 
     // crear usuario primero para establecer el mapeo affiliate_key
     let user_name = format!("testuser_content_{}", chrono::Utc::now().timestamp_nanos_opt().unwrap());
     let password = "testpass456".to_string();
     let real_name = "Test User Content".to_string();
 
-    let token_info = create_user_with_access_token(user_name.clone(), password, real_name)
+    let token_info =
+        create_user_with_access_token(context.pool().unwrap(), user_name.clone(), password, real_name)
+        .await
         .expect("Failed to create user");
 
     let affiliate_key = hashing_composite_key(&[&user_name]);
@@ -95,20 +100,25 @@ fn test_repo_create_loan_persists_json_content() {
 
     let repo = context.loan_repo();
     let total_quota = 24;
-    let base_needed_payment = 10000.0;
+    let base_needed_payment = Money::from(10000);
     let reason = "préstamo para vivienda".to_string();
 
     let res = repo.create_loan(
         affiliate_key.clone(),
         total_quota,
         base_needed_payment,
+        Currency::Gtq,
         0.12, // interest_rate 12%
         reason.clone(),
-    );
+    )
+    .await;
     assert!(res.is_ok());
 
     // buscar la key creada y leer el json
-    let mut con = context.pool.get().expect("no se pudo obtener conexión de redis");
+    let mut con = context.pool().unwrap()
+        .client()
+        .get_connection()
+        .expect("no se pudo obtener conexión de redis");
     let keys_iter = con
         .scan_match::<String, String>(format!("users:{}:loans:*", db_access_token))
         .unwrap();
@@ -123,31 +133,33 @@ fn test_repo_create_loan_persists_json_content() {
     let redis_raw: RedisValue = con.json_get(keys[0].as_str(), "$").expect("json_get failed");
     let nested_data = from_redis_value::<String>(&redis_raw).expect("from_redis_value failed");
     let parsed: Vec<RedisLoan> = from_str(nested_data.as_str()).expect("serde_json parse failed");
-    let rl = parsed.get(0).expect("no element in parsed vector");
+    let rl = parsed.first().expect("no element in parsed vector");
 
     // verificar campos
     assert_eq!(rl.total_quota, total_quota);
-    assert!((rl.base_needed_payment - base_needed_payment).abs() < 1e-6);
-    assert!((rl.payed - 0.0).abs() < 1e-6, "payed debería ser 0.0");
-    assert!((rl.debt - base_needed_payment).abs() < 1e-6, "debt debería ser igual a base_needed_payment");
-    assert!((rl.total - base_needed_payment).abs() < 1e-6, "total debería ser igual a base_needed_payment");
+    assert_eq!(rl.base_needed_payment, base_needed_payment);
+    assert_eq!(rl.payed, Money::ZERO, "payed debería ser 0");
+    assert_eq!(rl.debt, base_needed_payment, "debt debería ser igual a base_needed_payment");
+    assert_eq!(rl.total, base_needed_payment, "total debería ser igual a base_needed_payment");
     assert_eq!(rl.status, "PENDING");
     assert_eq!(rl.reason, reason);
     assert!((rl.interest_rate.unwrap_or(0.0) - 0.12).abs() < 1e-6, "interest_rate debería ser 0.12");
 }
 
-#[test]
-fn test_repo_create_loan_twice_creates_two_keys() {
-    let _guard = redis_test_lock().lock().unwrap();
+#[tokio::test]
+async fn test_repo_create_loan_twice_creates_two_keys() {
+    let _guard = redis_test_lock().await;
     let context = create_test_context();
-    let mut guard = TestRedisGuard::new(context.pool.clone());
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
 
     // crear usuario primero para establecer el mapeo affiliate_key
     let user_name = format!("testuser_two_{}", chrono::Utc::now().timestamp_nanos_opt().unwrap());
     let password = "testpass789".to_string();
     let real_name = "Test User Two Loans".to_string();
 
-    let token_info = create_user_with_access_token(user_name.clone(), password, real_name)
+    let token_info =
+        create_user_with_access_token(context.pool().unwrap(), user_name.clone(), password, real_name)
+        .await
         .expect("Failed to create user");
 
     let affiliate_key = hashing_composite_key(&[&user_name]);
@@ -167,19 +179,26 @@ fn test_repo_create_loan_twice_creates_two_keys() {
     let _ = repo.create_loan(
         affiliate_key.clone(),
         6,
-        1000.0,
+        Money::from(1000),
+        Currency::Gtq,
         0.10, // interest_rate 10%
         "préstamo 1".to_string(),
-    );
+    )
+    .await;
     let _ = repo.create_loan(
         affiliate_key.clone(),
         12,
-        2000.0,
+        Money::from(2000),
+        Currency::Gtq,
         0.08, // interest_rate 8%
         "préstamo 2".to_string(),
-    );
+    )
+    .await;
 
-    let mut con = context.pool.get().expect("no se pudo obtener conexión de redis");
+    let mut con = context.pool().unwrap()
+        .client()
+        .get_connection()
+        .expect("no se pudo obtener conexión de redis");
     let keys: Vec<String> = con
         .scan_match(format!("users:{}:loans:*", db_access_token))
         .unwrap()
@@ -194,18 +213,20 @@ fn test_repo_create_loan_twice_creates_two_keys() {
     }
 }
 
-#[test]
-fn test_create_loan_collision_behavior() {
-    let _guard = redis_test_lock().lock().unwrap();
+#[tokio::test]
+async fn test_create_loan_collision_behavior() {
+    let _guard = redis_test_lock().await;
     let context = create_test_context();
-    let mut guard = TestRedisGuard::new(context.pool.clone());
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
 
     // crear usuario primero para establecer el mapeo affiliate_key
     let user_name = format!("testuser_collision_{}", chrono::Utc::now().timestamp_nanos_opt().unwrap());
     let password = "testpass101".to_string();
     let real_name = "Test User Collision".to_string();
 
-    let token_info = create_user_with_access_token(user_name.clone(), password, real_name)
+    let token_info =
+        create_user_with_access_token(context.pool().unwrap(), user_name.clone(), password, real_name)
+        .await
         .expect("Failed to create user");
 
     let affiliate_key = hashing_composite_key(&[&user_name]);
@@ -226,19 +247,26 @@ fn test_create_loan_collision_behavior() {
     let _ = repo.create_loan(
         affiliate_key.clone(),
         10,
-        3000.0,
+        Money::from(3000),
+        Currency::Gtq,
         0.20, // interest_rate 20%
         "mismo motivo".to_string(),
-    );
+    )
+    .await;
     let _ = repo.create_loan(
         affiliate_key.clone(),
         10,
-        3000.0,
+        Money::from(3000),
+        Currency::Gtq,
         0.20, // interest_rate 20%
         "mismo motivo".to_string(),
-    );
+    )
+    .await;
 
-    let mut con = context.pool.get().expect("no se pudo obtener conexión de redis");
+    let mut con = context.pool().unwrap()
+        .client()
+        .get_connection()
+        .expect("no se pudo obtener conexión de redis");
     let keys: Vec<String> = con
         .scan_match(format!("users:{}:loans:*", db_access_token))
         .unwrap()
@@ -256,18 +284,20 @@ fn test_create_loan_collision_behavior() {
     }
 }
 
-#[test]
-fn test_create_then_get_all_returns_created_loan() {
-    let _guard = redis_test_lock().lock().unwrap();
+#[tokio::test]
+async fn test_create_then_get_all_returns_created_loan() {
+    let _guard = redis_test_lock().await;
     let context = create_test_context();
-    let mut guard = TestRedisGuard::new(context.pool.clone());
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
 
     // crear usuario primero para establecer el mapeo affiliate_key
     let user_name = format!("testuser_getall_{}", chrono::Utc::now().timestamp_nanos_opt().unwrap());
     let password = "testpass202".to_string();
     let real_name = "Test User GetAll".to_string();
 
-    let token_info = create_user_with_access_token(user_name.clone(), password, real_name)
+    let token_info =
+        create_user_with_access_token(context.pool().unwrap(), user_name.clone(), password, real_name)
+        .await
         .expect("Failed to create user");
 
     let affiliate_key = hashing_composite_key(&[&user_name]);
@@ -288,18 +318,20 @@ fn test_create_then_get_all_returns_created_loan() {
     let res = repo.create_loan(
         affiliate_key.clone(),
         18,
-        7500.0,
+        Money::from(7500),
+        Currency::Gtq,
         0.18, // interest_rate 18%
         "préstamo para get_all test".to_string(),
-    );
+    )
+    .await;
     assert!(res.is_ok(), "create_loan failed: {:?}", res);
 
     // llamar a get_all_loans y verificar que encontramos al menos un loan con reason esperado
-    let all = repo.get_all_loans().expect("get_all_loans failed");
+    let all = repo.get_all_loans().await.expect("get_all_loans failed");
     let found = all.iter().any(|l| l.reason == "préstamo para get_all test");
 
     // registrar keys para cleanup
-    let mut con = context.pool.get().expect("no redis conn");
+    let mut con = context.pool().unwrap().client().get_connection().expect("no redis conn");
     let keys: Vec<String> = con
         .scan_match(format!("users:{}:loans:*", db_access_token))
         .unwrap()
@@ -310,3 +342,168 @@ fn test_create_then_get_all_returns_created_loan() {
 
     assert!(found, "expected created loan to appear in get_all_loans");
 }
+
+// los mismos casos contra redis y contra MemoryStore, los préstamos se leen de vuelta con el repo
+mod en_cada_backend {
+    use general_api::models::currency::Currency;
+    use general_api::models::graphql::LoanStatus;
+    use general_api::models::money::Money;
+
+    use super::super::common::{memory_user, TestBackend, BACKENDS};
+
+    /// registra al socio y devuelve (affiliate_key, access_token)
+    fn add_loan_user(backend: &mut TestBackend, user_name: &str, real_name: &str) -> (String, String) {
+        let affiliate_key = format!("AF-{}", user_name);
+        let access_token = format!("token_{}", user_name);
+        backend.add_user(&access_token, memory_user(&affiliate_key, real_name, false));
+        (affiliate_key, access_token)
+    }
+
+    #[tokio::test]
+    async fn test_repo_create_loan_happy_path() {
+        for backend in BACKENDS {
+            let mut backend = backend.start().await;
+            let (affiliate_key, access_token) =
+                add_loan_user(&mut backend, "testuser_loan_backend", "Test User Loan");
+
+            let repo = backend.context.loan_repo();
+            let res = repo
+                .create_loan(
+                    affiliate_key,
+                    12,
+                    Money::from(5000),
+                    Currency::Gtq,
+                    0.15, // interest_rate 15%
+                    "compra de equipo".to_string(),
+                )
+                .await;
+            assert!(res.is_ok(), "create_loan retornó error: {:?}", res);
+
+            let loans = repo.get_user_loans(access_token).await.unwrap();
+            assert_eq!(loans.len(), 1, "expected one loan for the user");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repo_create_loan_persists_json_content() {
+        for backend in BACKENDS {
+            let mut backend = backend.start().await;
+            let (affiliate_key, access_token) =
+                add_loan_user(&mut backend, "testuser_content_backend", "Test User Content");
+
+            let repo = backend.context.loan_repo();
+            let base_needed_payment = Money::from(10000);
+            let reason = "préstamo para vivienda".to_string();
+            let res = repo
+                .create_loan(
+                    affiliate_key,
+                    24,
+                    base_needed_payment,
+                    Currency::Gtq,
+                    0.12, // interest_rate 12%
+                    reason.clone(),
+                )
+                .await;
+            assert!(res.is_ok());
+
+            let loans = repo.get_user_loans(access_token).await.unwrap();
+            let loan = loans.first().expect("no loan for the user");
+
+            assert_eq!(loan.total_quotas, 24);
+            assert_eq!(loan.payed, Money::ZERO, "payed debería ser 0");
+            assert_eq!(loan.debt, base_needed_payment);
+            assert_eq!(loan.total, base_needed_payment);
+            assert_eq!(loan.status, LoanStatus::Pending);
+            assert_eq!(loan.reason, reason);
+            assert!((loan.interest_rate - 0.12).abs() < 1e-6, "interest_rate debería ser 0.12");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repo_create_loan_twice_creates_two_keys() {
+        for backend in BACKENDS {
+            let mut backend = backend.start().await;
+            let (affiliate_key, access_token) =
+                add_loan_user(&mut backend, "testuser_two_backend", "Test User Two Loans");
+
+            let repo = backend.context.loan_repo();
+            for (total_quotas, amount, reason) in [(6, 1000, "préstamo 1"), (12, 2000, "préstamo 2")] {
+                let res = repo
+                    .create_loan(
+                        affiliate_key.clone(),
+                        total_quotas,
+                        Money::from(amount),
+                        Currency::Gtq,
+                        0.10,
+                        reason.to_string(),
+                    )
+                    .await;
+                assert!(res.is_ok(), "create_loan retornó error: {:?}", res);
+            }
+
+            let loans = repo.get_user_loans(access_token).await.unwrap();
+            assert_eq!(loans.len(), 2, "expected two loans after two create_loan calls");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_loan_collision_behavior() {
+        for backend in BACKENDS {
+            let mut backend = backend.start().await;
+            let (affiliate_key, access_token) =
+                add_loan_user(&mut backend, "testuser_collision_backend", "Test User Collision");
+
+            let repo = backend.context.loan_repo();
+            // parámetros idénticos, la key sale del conteo así que no se pisan
+            for _ in 0..2 {
+                let _ = repo
+                    .create_loan(
+                        affiliate_key.clone(),
+                        10,
+                        Money::from(3000),
+                        Currency::Gtq,
+                        0.20, // interest_rate 20%
+                        "mismo motivo".to_string(),
+                    )
+                    .await;
+            }
+
+            let loans = repo.get_user_loans(access_token).await.unwrap();
+            assert_eq!(
+                loans.len(),
+                2,
+                "expected two loans for collision behavior, got {}",
+                loans.len()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_then_get_all_returns_created_loan() {
+        for backend in BACKENDS {
+            let mut backend = backend.start().await;
+            let (affiliate_key, _) =
+                add_loan_user(&mut backend, "testuser_getall_backend", "Test User GetAll");
+
+            let repo = backend.context.loan_repo();
+            let res = repo
+                .create_loan(
+                    affiliate_key,
+                    18,
+                    Money::from(7500),
+                    Currency::Gtq,
+                    0.18, // interest_rate 18%
+                    "préstamo para get_all backend".to_string(),
+                )
+                .await;
+            assert!(res.is_ok(), "create_loan failed: {:?}", res);
+
+            let all = repo.get_all_loans().await.expect("get_all_loans failed");
+            let loan = all
+                .iter()
+                .find(|l| l.reason == "préstamo para get_all backend")
+                .expect("expected created loan to appear in get_all_loans");
+            assert_eq!(loan.presented_by_name, "Test User GetAll");
+        }
+    }
+}
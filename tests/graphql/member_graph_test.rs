@@ -0,0 +1,264 @@
+// Tests de las relaciones entre tipos: Member -> loans/fines/payments/pendingQuotas,
+// Loan.quotas y PayedTo.target (Loan | Quota | Fine)
+
+use super::common::{add_memory_directive, create_memory_context, memory_user};
+use general_api::endpoints::handlers::configs::schema::GeneralContext;
+use general_api::endpoints::handlers::graphql::root::{Mutation, Query};
+use general_api::models::PayedToInput;
+use general_api::models::currency::Currency;
+use general_api::models::graphql::{Quota, QuotaType};
+use general_api::models::money::Money;
+use general_api::models::redis::{Fine as RedisFine, Loan as RedisLoan};
+use general_api::repos::graphql::memory::MemoryStore;
+use juniper::{EmptySubscription, RootNode, Variables};
+
+struct SeededMember {
+    access_token: String,
+    affiliate_key: String,
+    loan_id: String,
+    fine_id: String,
+}
+
+fn loan_quota(loan_id: &str, number: i32, exp_date: &str) -> Quota {
+    Quota {
+        user_id: "member_graph".to_string(),
+        amount: Money::from(100),
+        exp_date: Some(exp_date.parse().unwrap()),
+        monto_pagado: None,
+        multa: None,
+        pay_by: None,
+        quota_type: QuotaType::Prestamo,
+        loan_id: Some(loan_id.to_string()),
+        is_extraordinary: Some(false),
+        payed: Some(false),
+        quota_number: Some(number),
+        nombre_prestamo: None,
+        nombre_usuario: None,
+        identifier: None,
+    }
+}
+
+/// socio con un préstamo de 2 cuotas, una multa y un pago que abona a los dos
+async fn seed_member(store: &MemoryStore, context: &GeneralContext) -> SeededMember {
+    let access_token = "socio_test".to_string();
+    let affiliate_key = "AF-SOCIO-TEST".to_string();
+    let db_access_token = store
+        .add_user(&access_token, memory_user(&affiliate_key, "Socio Test", false))
+        .unwrap();
+
+    let loan_id = "GRAPHLOAN1".to_string();
+    store
+        .insert_loan(
+            &db_access_token,
+            &loan_id,
+            RedisLoan {
+                total_quota: 2,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    for (number, exp_date) in [(2, "2099-02-01"), (1, "2099-01-01")] {
+        context
+            .quota_repo()
+            .save_quota(access_token.clone(), &loan_quota(&loan_id, number, exp_date))
+            .await
+            .expect("save_quota failed");
+    }
+
+    let fine_id = "GRAPHFINE1".to_string();
+    store
+        .insert_fine(
+            &db_access_token,
+            &fine_id,
+            RedisFine {
+                motive: "llegó tarde".to_string(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    context
+        .payment_repo()
+        .create_payment(
+            access_token.clone(),
+            "Pago navegable".to_string(),
+            "si".to_owned(),
+            Money::from(150),
+            Currency::Gtq,
+            "G1".to_string(),
+            "GRAPH_ACC".to_string(),
+            vec![
+                PayedToInput {
+                    model_type: "LOAN".to_string(),
+                    amount: Money::from(100),
+                    model_key: loan_id.clone(),
+                },
+                PayedToInput {
+                    model_type: "FINE".to_string(),
+                    amount: Money::from(50),
+                    model_key: fine_id.clone(),
+                },
+            ],
+        )
+        .await
+        .expect("create_payment failed");
+
+    SeededMember {
+        access_token,
+        affiliate_key,
+        loan_id,
+        fine_id,
+    }
+}
+
+async fn execute(context: &GeneralContext, query: &str) -> serde_json::Value {
+    let schema = RootNode::new(Query, Mutation, EmptySubscription::new());
+    let (value, errors) = juniper::execute(
+        query,
+        None,
+        &schema,
+        &Variables::new(),
+        context,
+    )
+    .await
+    .expect("La query debe ser válida");
+
+    assert!(errors.is_empty(), "Errores inesperados: {:?}", errors);
+    serde_json::to_value(&value).unwrap()
+}
+
+#[tokio::test]
+async fn test_member_lists_loans_with_their_quotas() {
+    let (store, context) = create_memory_context();
+    let seeded = seed_member(&store, &context).await;
+
+    let data = execute(
+        &context,
+        &format!(
+            r#"{{ member(accessToken: "{}", affiliateKey: "{}") {{
+                name
+                loans {{ id totalQuotas quotas {{ quotaNumber expDate }} }}
+                fines {{ id member {{ affiliateKey }} }}
+                payments {{ id }}
+            }} }}"#,
+            seeded.access_token, seeded.affiliate_key
+        ),
+    )
+    .await;
+    let member = &data["member"];
+
+    assert_eq!(member["name"], "Socio Test");
+    // las cuotas viven en loans:{id}:quotas:* y no deben aparecer como préstamos
+    let loans = member["loans"].as_array().unwrap();
+    assert_eq!(loans.len(), 1);
+    assert_eq!(loans[0]["id"], seeded.loan_id.as_str());
+    assert_eq!(loans[0]["totalQuotas"], 2);
+    let numbers: Vec<i64> = loans[0]["quotas"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|quota| quota["quotaNumber"].as_i64().unwrap())
+        .collect();
+    assert_eq!(numbers, vec![1, 2], "Ordenadas por fecha de vencimiento");
+
+    assert_eq!(member["fines"][0]["id"], seeded.fine_id.as_str());
+    assert_eq!(
+        member["fines"][0]["member"]["affiliateKey"],
+        seeded.affiliate_key.as_str()
+    );
+    assert_eq!(member["payments"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_member_is_only_visible_to_itself_and_directives() {
+    let (store, context) = create_memory_context();
+    let seeded = seed_member(&store, &context).await;
+    let other_token = "otro_socio_test";
+    store
+        .add_user(other_token, memory_user("AF-OTRO-TEST", "Otro Socio", false))
+        .unwrap();
+    let directive = add_memory_directive(&store);
+    let schema = RootNode::new(Query, Mutation, EmptySubscription::new());
+
+    let query = |access_token: &str, affiliate_key: &str| {
+        format!(
+            r#"{{ member(accessToken: "{}", affiliateKey: "{}") {{ name }} }}"#,
+            access_token, affiliate_key
+        )
+    };
+
+    // otro socio, un token que no existe y un socio que no existe dan el mismo error
+    for query in [
+        query(other_token, &seeded.affiliate_key),
+        query("", &seeded.affiliate_key),
+        query(&seeded.access_token, "AF-NO-EXISTE"),
+    ] {
+        let (_, errors) = juniper::execute(&query, None, &schema, &Variables::new(), &context)
+            .await
+            .expect("La query debe ser válida");
+        assert_eq!(errors.len(), 1, "{}", query);
+        let error = serde_json::to_value(&errors[0]).unwrap();
+        assert_eq!(error["extensions"]["code"], "UNAUTHORIZED", "{}", query);
+    }
+
+    let data = execute(&context, &query(&directive, &seeded.affiliate_key)).await;
+    assert_eq!(data["member"]["name"], "Socio Test");
+}
+
+#[tokio::test]
+async fn test_payed_to_target_resolves_loan_and_fine() {
+    let (store, context) = create_memory_context();
+    let seeded = seed_member(&store, &context).await;
+
+    let data = execute(
+        &context,
+        &format!(
+            r#"{{ me(accessToken: "{}") {{
+                pendingQuotas {{ loanId }}
+                payments {{ beingPayed {{
+                    modelType
+                    target {{
+                        __typename
+                        ... on Loan {{ id presentedByName }}
+                        ... on Fine {{ reason }}
+                    }}
+                }} }}
+            }} }}"#,
+            seeded.access_token
+        ),
+    )
+    .await;
+    let me = &data["me"];
+
+    assert_eq!(me["pendingQuotas"].as_array().unwrap().len(), 2);
+
+    let parts = me["payments"][0]["beingPayed"].as_array().unwrap();
+    let loan_target = &parts[0]["target"];
+    assert_eq!(loan_target["__typename"], "Loan");
+    assert_eq!(loan_target["id"], seeded.loan_id.as_str());
+    assert_eq!(loan_target["presentedByName"], "Socio Test");
+
+    let fine_target = &parts[1]["target"];
+    assert_eq!(fine_target["__typename"], "Fine");
+    assert_eq!(fine_target["reason"], "llegó tarde");
+}
+
+#[tokio::test]
+async fn test_payed_to_target_is_null_when_missing() {
+    let (_store, context) = create_memory_context();
+
+    let target = context
+        .payment_repo()
+        .get_payed_to_target(
+            &general_api::models::PayedTo {
+                model_type: "FINE".to_string(),
+                model_key: "no_existe_esta_multa".to_string(),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("get_payed_to_target failed");
+
+    assert!(target.is_none());
+}
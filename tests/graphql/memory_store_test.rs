@@ -0,0 +1,452 @@
+// Tests del schema contra MemoryStore: las mismas queries y mutations que los tests de redis
+// pero sin levantar redis, el contexto se arma con GeneralContext::in_memory
+
+use std::sync::Arc;
+
+use super::common::insert_memory_receipt;
+use general_api::endpoints::handlers::configs::schema::GeneralContext;
+use general_api::endpoints::handlers::graphql::root::{Mutation, Query, Subscription};
+use general_api::models::graphql::{Quota, QuotaType};
+use general_api::models::money::Money;
+use general_api::models::redis::{Fine as RedisFine, Loan as RedisLoan};
+use general_api::repos::graphql::memory::{MemoryStore, MemoryUser};
+use juniper::{EmptySubscription, RootNode, Variables};
+
+struct Seeded {
+    store: Arc<MemoryStore>,
+    context: GeneralContext,
+    access_token: String,
+    directive_token: String,
+}
+
+fn member(affiliate_key: &str, complete_name: &str, is_directive: bool) -> MemoryUser {
+    MemoryUser {
+        affiliate_key: affiliate_key.to_string(),
+        complete_name: complete_name.to_string(),
+        is_directive,
+        payed_to_capital: Money::from(300),
+        owed_capital: Money::from(1200),
+    }
+}
+
+fn loan_quota(number: i32, exp_date: &str) -> Quota {
+    Quota {
+        user_id: "memoria".to_string(),
+        amount: Money::from(100),
+        exp_date: Some(exp_date.parse().unwrap()),
+        monto_pagado: None,
+        multa: None,
+        pay_by: None,
+        quota_type: QuotaType::Prestamo,
+        loan_id: Some("PRESTAMO1".to_string()),
+        is_extraordinary: Some(false),
+        payed: Some(false),
+        quota_number: Some(number),
+        nombre_prestamo: None,
+        nombre_usuario: None,
+        identifier: None,
+    }
+}
+
+/// un socio con un préstamo de 2 cuotas y una multa, más un directivo para revisar pagos
+async fn seed() -> Seeded {
+    let store = Arc::new(MemoryStore::new());
+    let access_token = "socio_memoria".to_string();
+    let directive_token = "directivo_memoria".to_string();
+
+    let owner_key = store
+        .add_user(&access_token, member("AF-MEM-1", "Socio Memoria", false))
+        .unwrap();
+    store
+        .add_user(
+            &directive_token,
+            member("AF-MEM-DIR", "Directivo Memoria", true),
+        )
+        .unwrap();
+    store
+        .insert_loan(
+            &owner_key,
+            "PRESTAMO1",
+            RedisLoan {
+                total_quota: 2,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    store
+        .insert_fine(
+            &owner_key,
+            "MULTA1",
+            RedisFine {
+                motive: "llegó tarde".to_string(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    let context = GeneralContext::in_memory(store.clone());
+    for (number, exp_date) in [(2, "2099-02-01"), (1, "2099-01-01")] {
+        context
+            .quota_repo()
+            .save_quota(access_token.clone(), &loan_quota(number, exp_date))
+            .await
+            .unwrap();
+    }
+
+    Seeded {
+        store,
+        context,
+        access_token,
+        directive_token,
+    }
+}
+
+async fn execute(context: &GeneralContext, query: &str) -> serde_json::Value {
+    let (data, errors) = try_execute(context, query).await;
+    assert!(errors.is_empty(), "Errores inesperados: {:?}", errors);
+    data
+}
+
+/// data y errores por separado, para los casos que deben fallar
+async fn try_execute(context: &GeneralContext, query: &str) -> (serde_json::Value, Vec<String>) {
+    let schema = RootNode::new(Query, Mutation, EmptySubscription::new());
+    let (value, errors) = juniper::execute(query, None, &schema, &Variables::new(), context)
+        .await
+        .expect("La query debe ser válida");
+
+    let errors = errors
+        .iter()
+        .map(|error| format!("{:?}", error.error()))
+        .collect();
+    (serde_json::to_value(&value).unwrap(), errors)
+}
+
+/// cada pago lleva su propio comprobante subido por el socio
+async fn create_payment(seeded: &Seeded, ticket: &str) {
+    let receipt = insert_memory_receipt(&seeded.store, "AF-MEM-1", ticket);
+    execute(
+        &seeded.context,
+        &format!(
+            r#"mutation {{ payment {{ createUserPayment(
+                accessToken: "{}",
+                comprobantePath: "{}",
+                name: "Pago en memoria",
+                totalAmount: "150",
+                ticketNumber: "{}",
+                accountNumber: "MEM_ACC",
+                beingPayed: [
+                    {{ modelType: "LOAN", amount: "100", modelKey: "PRESTAMO1" }},
+                    {{ modelType: "FINE", amount: "50", modelKey: "MULTA1" }}
+                ]
+            ) }} }}"#,
+            seeded.access_token, receipt, ticket
+        ),
+    )
+    .await;
+}
+
+async fn payment_ids(seeded: &Seeded) -> Vec<String> {
+    let data = execute(
+        &seeded.context,
+        &format!(
+            r#"{{ payment {{ getUsersPayments(accessToken: "{}") {{ id }} }} }}"#,
+            seeded.access_token
+        ),
+    )
+    .await;
+    data["payment"]["getUsersPayments"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|payment| payment["id"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_member_graph_without_redis() {
+    let seeded = seed().await;
+    create_payment(&seeded, "T-1").await;
+
+    let data = execute(
+        &seeded.context,
+        &format!(
+            r#"{{ me(accessToken: "{}") {{
+                name
+                loans {{ id quotas {{ quotaNumber }} }}
+                fines {{ id member {{ affiliateKey }} }}
+                pendingQuotas {{ loanId }}
+                payments {{ beingPayed {{
+                    target {{
+                        __typename
+                        ... on Loan {{ id presentedByName }}
+                        ... on Fine {{ reason }}
+                    }}
+                }} }}
+            }} }}"#,
+            seeded.access_token
+        ),
+    )
+    .await;
+    let me = &data["me"];
+
+    assert_eq!(me["name"], "Socio Memoria");
+    assert_eq!(me["loans"][0]["id"], "PRESTAMO1");
+    assert_eq!(me["loans"][0]["quotas"][0]["quotaNumber"], 1);
+    assert_eq!(me["loans"][0]["quotas"][1]["quotaNumber"], 2);
+    assert_eq!(me["fines"][0]["member"]["affiliateKey"], "AF-MEM-1");
+    assert_eq!(me["pendingQuotas"].as_array().unwrap().len(), 2);
+
+    let parts = me["payments"][0]["beingPayed"].as_array().unwrap();
+    assert_eq!(parts[0]["target"]["__typename"], "Loan");
+    assert_eq!(parts[0]["target"]["presentedByName"], "Socio Memoria");
+    assert_eq!(parts[1]["target"]["reason"], "llegó tarde");
+}
+
+#[tokio::test]
+async fn test_review_payment_without_redis() {
+    let seeded = seed().await;
+    create_payment(&seeded, "T-1").await;
+    let id = payment_ids(&seeded).await.remove(0);
+
+    // el dueño del pago no es directivo, no se lo puede aprobar solo
+    let (_, errors) = try_execute(
+        &seeded.context,
+        &format!(
+            r#"mutation {{ payment {{ approveOrRejectPayment(
+                accessToken: "{}", id: "{}", newState: "ACCEPTED", commentary: ""
+            ) {{ id }} }} }}"#,
+            seeded.access_token, id
+        ),
+    )
+    .await;
+    assert!(errors[0].contains("Solo los directivos"), "{:?}", errors);
+
+    let (_, errors) = try_execute(
+        &seeded.context,
+        &format!(
+            r#"mutation {{ payment {{ approveOrRejectPayment(
+                accessToken: "{}", id: "{}", newState: "REJECTED", commentary: " "
+            ) {{ id }} }} }}"#,
+            seeded.directive_token, id
+        ),
+    )
+    .await;
+    assert!(errors[0].contains("Se requiere comentario"), "{:?}", errors);
+
+    let data = execute(
+        &seeded.context,
+        &format!(
+            r#"mutation {{ payment {{ approveOrRejectPayment(
+                accessToken: "{}", id: "{}", newState: "ACCEPTED", commentary: ""
+            ) {{ state history {{ previousState newState reviewerName comment }} }} }} }}"#,
+            seeded.directive_token, id
+        ),
+    )
+    .await;
+    let payment = &data["payment"]["approveOrRejectPayment"];
+    assert_eq!(payment["state"], "ACCEPTED");
+    assert_eq!(payment["history"][0]["previousState"], "ON_REVISION");
+    assert_eq!(payment["history"][0]["reviewerName"], "Directivo Memoria");
+    assert!(payment["history"][0]["comment"].is_null());
+
+    let (_, errors) = try_execute(
+        &seeded.context,
+        &format!(
+            r#"mutation {{ payment {{ approveOrRejectPayment(
+                accessToken: "{}", id: "{}", newState: "REJECTED", commentary: "tarde"
+            ) {{ id }} }} }}"#,
+            seeded.directive_token, id
+        ),
+    )
+    .await;
+    assert!(errors[0].contains("ya está finalizado"), "{:?}", errors);
+}
+
+#[tokio::test]
+async fn test_duplicate_tickets_without_redis() {
+    let seeded = seed().await;
+    create_payment(&seeded, "T-1").await;
+    create_payment(&seeded, " t-1").await;
+    create_payment(&seeded, "T-2").await;
+
+    let duplicates_query = |access_token: &str| {
+        format!(
+            r#"{{ payment {{ getPossibleDuplicatePayments(accessToken: "{}") {{
+                ticketNum
+                payments {{ id possibleDuplicates presentedByName }}
+            }} }} }}"#,
+            access_token
+        )
+    };
+
+    // los socios no ven los pagos de los demás
+    let (_, errors) = try_execute(&seeded.context, &duplicates_query(&seeded.access_token)).await;
+    assert!(errors[0].contains("Solo los directivos"), "{:?}", errors);
+
+    let data = execute(&seeded.context, &duplicates_query(&seeded.directive_token)).await;
+    let groups = data["payment"]["getPossibleDuplicatePayments"]
+        .as_array()
+        .unwrap();
+
+    assert_eq!(groups.len(), 1, "Solo la boleta T-1 está repetida");
+    let payments = groups[0]["payments"].as_array().unwrap();
+    assert_eq!(payments.len(), 2);
+    assert!(
+        payments
+            .iter()
+            .all(|payment| payment["possibleDuplicates"] == true)
+    );
+    assert_eq!(payments[0]["presentedByName"], "Socio Memoria");
+
+    // al rechazar uno, el otro deja de ser duplicado
+    let rejected_id = payments[0]["id"].as_str().unwrap().to_string();
+    let kept_id = payments[1]["id"].as_str().unwrap().to_string();
+    execute(
+        &seeded.context,
+        &format!(
+            r#"mutation {{ payment {{ approveOrRejectPayment(
+                accessToken: "{}", id: "{}", newState: "REJECTED", commentary: "repetido"
+            ) {{ id }} }} }}"#,
+            seeded.directive_token, rejected_id
+        ),
+    )
+    .await;
+
+    let data = execute(&seeded.context, &duplicates_query(&seeded.directive_token)).await;
+    assert!(
+        data["payment"]["getPossibleDuplicatePayments"]
+            .as_array()
+            .unwrap()
+            .is_empty()
+    );
+    let kept = seeded
+        .context
+        .payment_repo()
+        .get_user_payments(seeded.access_token.clone())
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|payment| payment.id == kept_id)
+        .unwrap();
+    assert!(!kept.possible_duplicates, "El que queda ya no es duplicado");
+}
+
+#[tokio::test]
+async fn test_fines_without_redis() {
+    let seeded = seed().await;
+
+    execute(
+        &seeded.context,
+        r#"mutation { fine { createFine(affiliateKey: "AF-MEM-1", amount: "25", motive: "sin uniforme") } }"#,
+    )
+    .await;
+    execute(
+        &seeded.context,
+        r#"mutation { fine { editFine(fineKey: "MULTA1", newStatus: PAID) } }"#,
+    )
+    .await;
+
+    let data = execute(
+        &seeded.context,
+        r#"{ fine { getFines { userId completeName fines { reason status } } } }"#,
+    )
+    .await;
+    let users = data["fine"]["getFines"].as_array().unwrap();
+
+    assert_eq!(users.len(), 1, "El directivo no tiene multas");
+    assert_eq!(users[0]["userId"], "AF-MEM-1");
+    let fines = users[0]["fines"].as_array().unwrap();
+    assert_eq!(fines.len(), 2);
+    assert!(
+        fines
+            .iter()
+            .any(|fine| fine["reason"] == "llegó tarde" && fine["status"] == "PAID")
+    );
+    assert!(
+        fines
+            .iter()
+            .any(|fine| fine["reason"] == "sin uniforme" && fine["status"] == "UNPAID")
+    );
+
+    let (_, errors) = try_execute(
+        &seeded.context,
+        r#"mutation { fine { createFine(affiliateKey: "NO_EXISTE", amount: "25", motive: "x") } }"#,
+    )
+    .await;
+    assert!(errors[0].contains("Couldn't Get Db Token"), "{:?}", errors);
+}
+
+#[tokio::test]
+async fn test_receipt_claim_without_redis() {
+    let seeded = seed().await;
+    let claim = |receipt: &str| {
+        format!(
+            r#"mutation {{ payment {{ createUserPayment(
+                accessToken: "{}",
+                comprobantePath: "{}",
+                name: "Pago en memoria",
+                totalAmount: "150",
+                ticketNumber: "T-CLAIM",
+                accountNumber: "MEM_ACC",
+                beingPayed: []
+            ) }} }}"#,
+            seeded.access_token, receipt
+        )
+    };
+
+    let (_, errors) = try_execute(&seeded.context, &claim("no-es-un-ticket")).await;
+    assert!(errors[0].contains("comprobante_path debe ser el ticket_id"), "{:?}", errors);
+
+    // comprobante que subió el directivo, no el socio
+    let foreign = insert_memory_receipt(&seeded.store, "AF-MEM-DIR", "ajeno");
+    let (_, errors) = try_execute(&seeded.context, &claim(&foreign)).await;
+    assert!(errors[0].contains("no es tuyo"), "{:?}", errors);
+
+    // si el pago no se crea (USD sin tipo de cambio) el comprobante queda libre otra vez
+    let receipt = insert_memory_receipt(&seeded.store, "AF-MEM-1", "propio");
+    let (_, errors) = try_execute(
+        &seeded.context,
+        &format!(
+            r#"mutation {{ payment {{ createUserPayment(
+                accessToken: "{}",
+                comprobantePath: "{}",
+                name: "Pago en dólares",
+                totalAmount: "10",
+                currency: USD,
+                ticketNumber: "T-USD",
+                accountNumber: "MEM_ACC",
+                beingPayed: [{{ modelType: "FINE", amount: "10", modelKey: "MULTA1" }}]
+            ) }} }}"#,
+            seeded.access_token, receipt
+        ),
+    )
+    .await;
+    assert!(!errors.is_empty());
+
+    execute(&seeded.context, &claim(&receipt)).await;
+    assert!(
+        seeded.store.receipt(&receipt).unwrap().unwrap().linked_at.is_some(),
+        "El comprobante queda ligado al pago"
+    );
+
+    let (_, errors) = try_execute(&seeded.context, &claim(&receipt)).await;
+    assert!(errors[0].contains("ya está en otro pago"), "{:?}", errors);
+    assert_eq!(payment_ids(&seeded).await.len(), 1);
+}
+
+#[tokio::test]
+async fn test_subscriptions_need_redis() {
+    let seeded = seed().await;
+    let schema = RootNode::new(Query, Mutation, Subscription);
+    let query = format!(
+        r#"subscription {{ paymentStatusChanged(accessToken: "{}") {{ paymentId }} }}"#,
+        seeded.access_token
+    );
+
+    let (_, errors) =
+        juniper::resolve_into_stream(&query, None, &schema, &Variables::new(), &seeded.context)
+            .await
+            .expect("La subscription debe ser válida");
+
+    assert!(format!("{:?}", errors[0]).contains("necesita redis"));
+}
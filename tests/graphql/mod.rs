@@ -2,5 +2,17 @@ pub mod common;
 mod payment_test;
 mod payment_mutation_test;
 mod payment_create_test;
+mod payment_duplicates_test;
 mod loan_create_test;
-mod fine_test;
\ No newline at end of file
+mod fine_test;
+mod currency_test;
+mod schema_test;
+mod member_graph_test;
+mod loader_test;
+mod subscription_test;
+mod unavailable_redis_test;
+mod memory_store_test;
+mod query_limits_test;
+mod attachment_test;
+mod statement_test;
+mod report_test;
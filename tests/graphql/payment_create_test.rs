@@ -1,34 +1,30 @@
 // Tests para SCRUM-202: create_payment (repo-level)
 // Usamos los mismos helpers y patrón de runtime que en los tests existentes
 
-use general_api::models::graphql::PaymentStatus;
-
-// Local test-only lock to serialize Redis access when running tests in parallel.
-// Use std::sync::OnceLock to avoid adding dependencies.
-use std::sync::{Mutex, OnceLock};
-fn redis_test_lock() -> &'static Mutex<()> {
-    static REDIS_TEST_LOCAL: OnceLock<Mutex<()>> = OnceLock::new();
-    REDIS_TEST_LOCAL.get_or_init(|| Mutex::new(()))
-}
-// Include shared test helpers from tests/graphql/common/mod.rs
-include!("common/mod.rs");
+use general_api::models::currency::Currency;
+use general_api::models::graphql::{Payment, PaymentStatus};
+use general_api::models::money::Money;
+
+use general_api::test_sync::redis_test_lock;
+use super::common::{TestRedisGuard, create_test_context};
 use general_api::models::redis::Payment as RedisPayment;
 use general_api::repos::auth::utils::hashing_composite_key;
-use redis::{from_redis_value, Value as RedisValue};
+use redis::{from_redis_value, Commands, JsonCommands, Value as RedisValue};
 use serde_json::from_str;
 
-#[test]
-fn test_repo_create_payment_happy_path() {
-    let _guard = redis_test_lock().lock().unwrap();
+#[tokio::test]
+async fn test_repo_create_payment_happy_path() {
+    let _guard = redis_test_lock().await;
     let context = create_test_context();
-    let mut guard = TestRedisGuard::new(context.pool.clone());
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
 
     let now = chrono::Utc::now().timestamp_nanos_opt().unwrap();
     let payment = Payment {
         id: format!("test_pago_{}_create_1", now),
         name: "Repo Create Test".to_string(),
-        total_amount: 123.45,
-        payment_date: "2025-10-13".to_string(),
+        total_amount: "123.45".parse::<Money>().unwrap(),
+        currency: Currency::Gtq,
+        payment_date: "2025-10-13".parse().unwrap(),
         ticket_num: "RC1".to_string(),
         account_num: "RACC1".to_string(),
         commentary: Some("create repo test".to_string()),
@@ -36,6 +32,8 @@ fn test_repo_create_payment_happy_path() {
         state: PaymentStatus::OnRevision,
         being_payed: vec![],
         presented_by_name: "N/A".to_string(),
+        possible_duplicates: false,
+        receipt_check: None,
     };
 
     // Llamar al repo a través del contexto con la firma real
@@ -46,17 +44,21 @@ fn test_repo_create_payment_happy_path() {
         payment.name.clone(),
         "si".to_owned(),
         payment.total_amount,
+        Currency::Gtq,
         payment.ticket_num.clone(),
         payment.account_num.clone(),
         vec![],
-    );
+    )
+    .await;
     assert!(res.is_ok(), "create_payment returned error: {:?}", res);
 
     // Verificar existencia de la key en Redis
     let composite = hashing_composite_key(&[&access_token]);
     let mut con = context
-        .pool
-        .get()
+        .pool()
+        .unwrap()
+        .client()
+        .get_connection()
         .expect("No se pudo obtener conexión de Redis");
     let keys: Vec<String> = con
         .scan_match(format!("users:{}:payments:*", composite))
@@ -72,11 +74,11 @@ fn test_repo_create_payment_happy_path() {
     }
 }
 
-#[test]
-fn test_create_then_get_all_returns_created_payment() {
-    let _guard = redis_test_lock().lock().unwrap();
+#[tokio::test]
+async fn test_create_then_get_all_returns_created_payment() {
+    let _guard = redis_test_lock().await;
     let context = create_test_context();
-    let mut guard = TestRedisGuard::new(context.pool.clone());
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
 
     let repo = context.payment_repo();
     let access_token = format!(
@@ -89,19 +91,21 @@ fn test_create_then_get_all_returns_created_payment() {
         access_token.clone(),
         "AllTest".to_string(),
         "si".to_owned(),
-        42.0,
+        Money::from(42),
+        Currency::Gtq,
         "T_ALL".to_string(),
         "A_ALL".to_string(),
         vec![],
-    );
+    )
+    .await;
     assert!(res.is_ok(), "create_payment failed: {:?}", res);
 
     // Now call get_all_payments and assert we find at least one payment with the expected account_number
-    let all = repo.get_all_payments().expect("get_all_payments failed");
-    let found = all.iter().any(|p| p.account_num == "A_ALL".to_string());
+    let all = repo.get_all_payments().await.expect("get_all_payments failed");
+    let found = all.iter().any(|p| p.account_num == "A_ALL");
     // register keys for cleanup: scan user's payments and register
     let composite = hashing_composite_key(&[&access_token]);
-    let mut con = context.pool.get().expect("No redis conn");
+    let mut con = context.pool().unwrap().client().get_connection().expect("No redis conn");
     let keys: Vec<String> = con
         .scan_match(format!("users:{}:payments:*", composite))
         .unwrap()
@@ -116,15 +120,15 @@ fn test_create_then_get_all_returns_created_payment() {
     );
 }
 
-#[test]
-fn test_repo_create_payment_persists_json_content() {
-    let _guard = redis_test_lock().lock().unwrap();
+#[tokio::test]
+async fn test_repo_create_payment_persists_json_content() {
+    let _guard = redis_test_lock().await;
     let context = create_test_context();
-    let mut guard = TestRedisGuard::new(context.pool.clone());
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
 
     let access_token = "testuser_create_repo_content".to_string();
     let payment_name = "Repo Create Content Test".to_string();
-    let total_amount = 777.77_f64;
+    let total_amount: Money = "777.77".parse().unwrap();
 
     let repo = context.payment_repo();
     let res = repo.create_payment(
@@ -132,17 +136,21 @@ fn test_repo_create_payment_persists_json_content() {
         payment_name.clone(),
         "si".to_owned(),
         total_amount,
+        Currency::Gtq,
         "RC_CONTENT".to_string(),
         "RACC_CONTENT".to_string(),
         vec![],
-    );
+    )
+    .await;
     assert!(res.is_ok());
 
     // Buscar la key creada y leer el JSON
     let composite = hashing_composite_key(&[&access_token]);
     let mut con = context
-        .pool
-        .get()
+        .pool()
+        .unwrap()
+        .client()
+        .get_connection()
         .expect("No se pudo obtener conexión de Redis");
     let keys_iter = con
         .scan_match::<String, String>(format!("users:{}:payments:*", composite))
@@ -163,20 +171,20 @@ fn test_repo_create_payment_persists_json_content() {
     let nested_data = from_redis_value::<String>(&redis_raw).expect("from_redis_value failed");
     let parsed: Vec<RedisPayment> =
         from_str(nested_data.as_str()).expect("serde_json parse failed");
-    let rp = parsed.get(0).expect("No element in parsed vector");
+    let rp = parsed.first().expect("No element in parsed vector");
 
     assert_eq!(rp.name, payment_name);
-    assert!((rp.total_amount - total_amount).abs() < 1e-6);
+    assert_eq!(rp.total_amount, total_amount);
     assert_eq!(rp.account_number, "RACC_CONTENT");
     assert_eq!(rp.ticket_number, "RC_CONTENT");
     assert_eq!(rp.status, "ON_REVISION");
 }
 
-#[test]
-fn test_repo_create_payment_twice_creates_two_keys() {
-    let _guard = redis_test_lock().lock().unwrap();
+#[tokio::test]
+async fn test_repo_create_payment_twice_creates_two_keys() {
+    let _guard = redis_test_lock().await;
     let context = create_test_context();
-    let mut guard = TestRedisGuard::new(context.pool.clone());
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
 
     let access_token = "testuser_create_repo_two".to_string();
     let repo = context.payment_repo();
@@ -185,25 +193,31 @@ fn test_repo_create_payment_twice_creates_two_keys() {
         access_token.clone(),
         "N1".to_string(),
         "si".to_owned(),
-        1.0,
+        Money::from(1),
+        Currency::Gtq,
         "T1".to_string(),
         "A1".to_string(),
         vec![],
-    );
+    )
+    .await;
     let _ = repo.create_payment(
         access_token.clone(),
         "N2".to_string(),
         "si".to_owned(),
-        2.0,
+        Money::from(2),
+        Currency::Gtq,
         "T2".to_string(),
         "A2".to_string(),
         vec![],
-    );
+    )
+    .await;
 
     let composite = hashing_composite_key(&[&access_token]);
     let mut con = context
-        .pool
-        .get()
+        .pool()
+        .unwrap()
+        .client()
+        .get_connection()
         .expect("No se pudo obtener conexión de Redis");
     let keys: Vec<String> = con
         .scan_match(format!("users:{}:payments:*", composite))
@@ -218,11 +232,11 @@ fn test_repo_create_payment_twice_creates_two_keys() {
     }
 }
 
-#[test]
-fn test_create_payment_collision_behavior() {
-    let _guard = redis_test_lock().lock().unwrap();
+#[tokio::test]
+async fn test_create_payment_collision_behavior() {
+    let _guard = redis_test_lock().await;
     let context = create_test_context();
-    let mut guard = TestRedisGuard::new(context.pool.clone());
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
 
     let access_token = "test_collision".to_string();
     let repo = context.payment_repo();
@@ -232,25 +246,31 @@ fn test_create_payment_collision_behavior() {
         access_token.clone(),
         "SameName".to_string(),
         "si".to_owned(),
-        10.0,
+        Money::from(10),
+        Currency::Gtq,
         "T1".to_string(),
         "A1".to_string(),
         vec![],
-    );
+    )
+    .await;
     let _ = repo.create_payment(
         access_token.clone(),
         "SameName".to_string(),
         "si".to_owned(),
-        10.0,
+        Money::from(10),
+        Currency::Gtq,
         "T1".to_string(),
         "A1".to_string(),
         vec![],
-    );
+    )
+    .await;
 
     let composite = hashing_composite_key(&[&access_token]);
     let mut con = context
-        .pool
-        .get()
+        .pool()
+        .unwrap()
+        .client()
+        .get_connection()
         .expect("No se pudo obtener conexión de Redis");
     let keys: Vec<String> = con
         .scan_match(format!("users:{}:payments:*", composite))
@@ -267,16 +287,18 @@ fn test_create_payment_collision_behavior() {
     }
 }
 
-#[test]
-fn test_guard_does_not_remove_unrelated_keys() {
-    let _guard = redis_test_lock().lock().unwrap();
+#[tokio::test]
+async fn test_guard_does_not_remove_unrelated_keys() {
+    let _guard = redis_test_lock().await;
     let context = create_test_context();
-    let guard = TestRedisGuard::new(context.pool.clone());
+    let guard = TestRedisGuard::new(context.pool().unwrap().clone());
 
     // Create an unrelated key that should NOT be removed by the guard
     let mut con = context
-        .pool
-        .get()
+        .pool()
+        .unwrap()
+        .client()
+        .get_connection()
         .expect("No se pudo obtener conexión de Redis");
     let unrelated_key = "unrelated:test:key".to_string();
     let _: () = con
@@ -292,3 +314,180 @@ fn test_guard_does_not_remove_unrelated_keys() {
         unrelated_key
     );
 }
+
+// los mismos casos contra redis y contra MemoryStore, los pagos se leen de vuelta con el repo
+mod en_cada_backend {
+    use general_api::models::currency::Currency;
+    use general_api::models::money::Money;
+    use general_api::models::graphql::PaymentStatus;
+    use general_api::repos::graphql::payment::payment_ticket_index_key;
+
+    use super::super::common::{TestBackend, BACKENDS};
+
+    /// crea el pago con el repo y registra su índice de boletas para limpiarlo
+    async fn create_payment(
+        backend: &mut TestBackend,
+        access_token: &str,
+        name: &str,
+        total_amount: Money,
+        ticket_number: &str,
+        account_number: &str,
+    ) {
+        backend.track_owner(access_token);
+        backend.register_key(payment_ticket_index_key(account_number, ticket_number));
+        let res = backend
+            .context
+            .payment_repo()
+            .create_payment(
+                access_token.to_string(),
+                name.to_string(),
+                "si".to_owned(),
+                total_amount,
+                Currency::Gtq,
+                ticket_number.to_string(),
+                account_number.to_string(),
+                vec![],
+            )
+            .await;
+        assert!(res.is_ok(), "create_payment returned error: {:?}", res);
+    }
+
+    #[tokio::test]
+    async fn test_repo_create_payment_happy_path() {
+        for backend in BACKENDS {
+            let mut backend = backend.start().await;
+            let access_token = "testuser_create_repo_backend";
+
+            create_payment(
+                &mut backend,
+                access_token,
+                "Repo Create Test",
+                "123.45".parse().unwrap(),
+                "RC1",
+                "RACC1",
+            )
+            .await;
+
+            let payments = backend
+                .context
+                .payment_repo()
+                .get_user_payments(access_token.to_string())
+                .await
+                .unwrap();
+            assert_eq!(payments.len(), 1, "Expected one payment for the user");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_then_get_all_returns_created_payment() {
+        for backend in BACKENDS {
+            let mut backend = backend.start().await;
+
+            create_payment(
+                &mut backend,
+                "testuser_all_backend",
+                "AllTest",
+                Money::from(42),
+                "T_ALL",
+                "A_ALL_BACKEND",
+            )
+            .await;
+
+            // en redis puede haber pagos de otros tests, solo buscamos el nuestro
+            let all = backend
+                .context
+                .payment_repo()
+                .get_all_payments()
+                .await
+                .expect("get_all_payments failed");
+            assert!(
+                all.iter().any(|p| p.account_num == "A_ALL_BACKEND"),
+                "Expected created payment to appear in get_all_payments"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repo_create_payment_persists_json_content() {
+        for backend in BACKENDS {
+            let mut backend = backend.start().await;
+            let access_token = "testuser_create_repo_content_backend";
+            let total_amount: Money = "777.77".parse().unwrap();
+
+            create_payment(
+                &mut backend,
+                access_token,
+                "Repo Create Content Test",
+                total_amount,
+                "RC_CONTENT",
+                "RACC_CONTENT",
+            )
+            .await;
+
+            let payments = backend
+                .context
+                .payment_repo()
+                .get_user_payments(access_token.to_string())
+                .await
+                .unwrap();
+            let rp = payments.first().expect("No payment for the user");
+
+            assert_eq!(rp.name, "Repo Create Content Test");
+            assert_eq!(rp.total_amount, total_amount);
+            assert_eq!(rp.currency, Currency::Gtq);
+            assert_eq!(rp.account_num, "RACC_CONTENT");
+            assert_eq!(rp.ticket_num, "RC_CONTENT");
+            assert_eq!(rp.state, PaymentStatus::OnRevision);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repo_create_payment_twice_creates_two_keys() {
+        for backend in BACKENDS {
+            let mut backend = backend.start().await;
+            let access_token = "testuser_create_repo_two_backend";
+
+            create_payment(&mut backend, access_token, "N1", Money::from(1), "T1", "A1").await;
+            create_payment(&mut backend, access_token, "N2", Money::from(2), "T2", "A2").await;
+
+            let payments = backend
+                .context
+                .payment_repo()
+                .get_user_payments(access_token.to_string())
+                .await
+                .unwrap();
+            assert_eq!(
+                payments.len(),
+                2,
+                "Expected two payments after two create_payment calls"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_payment_collision_behavior() {
+        for backend in BACKENDS {
+            let mut backend = backend.start().await;
+            let access_token = "test_collision_backend";
+
+            // mismos parámetros visibles, la key sale del conteo así que no se pisan
+            for _ in 0..2 {
+                create_payment(&mut backend, access_token, "SameName", Money::from(10), "T1", "A1")
+                    .await;
+            }
+
+            let payments = backend
+                .context
+                .payment_repo()
+                .get_user_payments(access_token.to_string())
+                .await
+                .unwrap();
+            assert_eq!(
+                payments.len(),
+                2,
+                "Expected two payments for collision behavior, got {}",
+                payments.len()
+            );
+        }
+    }
+}
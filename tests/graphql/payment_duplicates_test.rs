@@ -0,0 +1,216 @@
+// Tests para la detección de pagos duplicados (misma cuenta + número de boleta)
+
+use std::time::Duration;
+
+use super::common::{add_memory_directive, create_memory_context};
+use general_api::endpoints::handlers::configs::connection_pool::RedisPool;
+use general_api::models::currency::Currency;
+use general_api::models::money::Money;
+use general_api::models::graphql::PaymentStatus;
+use general_api::models::redis::Payment as RedisPayment;
+use general_api::repos::graphql::payment::payment_ticket_index_key;
+use general_api::repos::migrations::migrate_payment_tickets;
+use redis::{Client, Commands, JsonCommands};
+
+#[tokio::test]
+async fn test_same_ticket_from_two_members_flags_both_payments() {
+    let (_store, context) = create_memory_context();
+    let repo = context.payment_repo();
+
+    let ticket = "DUP_T".to_string();
+    let account = "DUP_A".to_string();
+    let first_user = "test_dup_first".to_string();
+    let second_user = "test_dup_second".to_string();
+
+    repo.create_payment(
+        first_user.clone(),
+        "Primero".to_string(),
+        "si".to_owned(),
+        Money::from(50),
+        Currency::Gtq,
+        ticket.clone(),
+        account.clone(),
+        vec![],
+    )
+    .await
+    .expect("create_payment failed");
+
+    let first_payments = repo.get_user_payments(first_user.clone()).await.unwrap();
+    assert!(
+        !first_payments[0].possible_duplicates,
+        "El primer pago no debe estar marcado todavía"
+    );
+
+    // el segundo socio sube la misma boleta (con espacios y minúsculas de más)
+    repo.create_payment(
+        second_user.clone(),
+        "Segundo".to_string(),
+        "si".to_owned(),
+        Money::from(50),
+        Currency::Gtq,
+        format!(" {} ", ticket.to_lowercase()),
+        account.clone(),
+        vec![],
+    )
+    .await
+    .expect("create_payment failed");
+
+    let first_payments = repo.get_user_payments(first_user).await.unwrap();
+    let second_payments = repo.get_user_payments(second_user).await.unwrap();
+    assert!(
+        first_payments[0].possible_duplicates,
+        "El pago original debe quedar marcado"
+    );
+    assert!(
+        second_payments[0].possible_duplicates,
+        "El pago nuevo debe quedar marcado"
+    );
+
+    let groups = repo
+        .get_possible_duplicate_payments()
+        .await
+        .expect("get_possible_duplicate_payments failed");
+    let group = groups
+        .iter()
+        .find(|g| g.account_num == account)
+        .expect("Debe existir un grupo para la boleta duplicada");
+    assert_eq!(group.payments.len(), 2);
+}
+
+#[tokio::test]
+async fn test_different_tickets_are_not_flagged() {
+    let (_store, context) = create_memory_context();
+    let repo = context.payment_repo();
+
+    let account = "NODUP_A".to_string();
+    let user = "test_nodup".to_string();
+
+    repo.create_payment(
+        user.clone(),
+        "Uno".to_string(),
+        "si".to_owned(),
+        Money::from(10),
+        Currency::Gtq,
+        "T1".to_string(),
+        account.clone(),
+        vec![],
+    )
+    .await
+    .expect("create_payment failed");
+    repo.create_payment(
+        user.clone(),
+        "Dos".to_string(),
+        "si".to_owned(),
+        Money::from(10),
+        Currency::Gtq,
+        "T2".to_string(),
+        account.clone(),
+        vec![],
+    )
+    .await
+    .expect("create_payment failed");
+
+    let payments = repo.get_user_payments(user).await.unwrap();
+    assert_eq!(payments.len(), 2);
+    assert!(payments.iter().all(|p| !p.possible_duplicates));
+
+    let groups = repo.get_possible_duplicate_payments().await.unwrap();
+    assert!(!groups.iter().any(|g| g.account_num == account));
+}
+
+#[tokio::test]
+async fn test_resubmitting_a_rejected_ticket_is_not_flagged() {
+    let (store, context) = create_memory_context();
+    let repo = context.payment_repo();
+
+    let ticket = "REJ_T".to_string();
+    let account = "REJ_A".to_string();
+    let user = "test_dup_rejected".to_string();
+
+    repo.create_payment(
+        user.clone(),
+        "Rechazado".to_string(),
+        "si".to_owned(),
+        Money::from(20),
+        Currency::Gtq,
+        ticket.clone(),
+        account.clone(),
+        vec![],
+    )
+    .await
+    .expect("create_payment failed");
+
+    let rejected_id = repo.get_user_payments(user.clone()).await.unwrap()[0].id.clone();
+    let reviewer = add_memory_directive(&store);
+    repo.approve_or_reject_payment(
+        reviewer,
+        rejected_id.clone(),
+        "REJECTED".to_string(),
+        "Boleta ilegible".to_string(),
+    )
+    .await
+    .expect("reject failed");
+
+    repo.create_payment(
+        user.clone(),
+        "Reenvío".to_string(),
+        "si".to_owned(),
+        Money::from(20),
+        Currency::Gtq,
+        ticket,
+        account,
+        vec![],
+    )
+    .await
+    .expect("create_payment failed");
+
+    let payments = repo.get_user_payments(user).await.unwrap();
+    let resubmitted = payments
+        .iter()
+        .find(|p| p.id != rejected_id)
+        .expect("Debe existir el pago reenviado");
+    assert_eq!(resubmitted.state, PaymentStatus::OnRevision);
+    assert!(
+        !resubmitted.possible_duplicates,
+        "Reenviar una boleta rechazada no es duplicado"
+    );
+}
+
+#[tokio::test]
+async fn test_migration_indexes_old_payments_and_flags_duplicates() {
+    let _lock = general_api::test_sync::redis_test_lock().await;
+    let client = Client::open("redis://127.0.0.1/").unwrap();
+    let mut con = client.get_connection().unwrap();
+    let pool = RedisPool::new(client, Duration::from_secs(5), Duration::from_secs(10));
+
+    // pagos de antes del índice: misma boleta, sin payment_tickets:* ni marca de duplicado
+    let now = chrono::Utc::now().timestamp_nanos_opt().unwrap();
+    let account = format!("MIGDUP_A{}", now);
+    let ticket = "MIGDUP_T".to_string();
+    let first_key = format!("users:testmigdup{}:payments:p1", now);
+    let second_key = format!("users:testmigdup{}b:payments:p1", now);
+    let index_key = payment_ticket_index_key(&account, &ticket);
+
+    for key in [&first_key, &second_key] {
+        let payment = RedisPayment {
+            account_number: account.clone(),
+            ticket_number: ticket.clone(),
+            ..Default::default()
+        };
+        let _: () = con.json_set(key, "$", &payment).unwrap();
+    }
+    let _: () = con.del("migrations:payment_tickets_v1").unwrap();
+
+    migrate_payment_tickets(&pool).await.expect("migration failed");
+
+    let mut indexed: Vec<String> = con.smembers(&index_key).unwrap();
+    indexed.sort();
+    assert_eq!(indexed, vec![first_key.clone(), second_key.clone()]);
+    for key in [&first_key, &second_key] {
+        let flag: String = con.json_get(key, "$.possible_duplicates").unwrap();
+        assert_eq!(flag, "[true]", "{} debe quedar marcado", key);
+    }
+    assert!(con.exists::<_, bool>("migrations:payment_tickets_v1").unwrap());
+
+    let _: () = con.del(&[&first_key, &second_key, &index_key]).unwrap();
+}
@@ -1,29 +1,34 @@
 // Pruebas unitarias para la mutation approve_or_reject_payment
 // Estructura y helpers igual a payment_test.rs
 
-use super::common::{create_test_context, insert_payment_helper_and_return, TestRedisGuard};
+use super::common::{
+    create_test_context, insert_directive_helper, insert_member_helper,
+    insert_payment_helper_and_return, TestRedisGuard,
+};
+use general_api::errors::AppError;
+use general_api::models::currency::Currency;
+use general_api::models::money::Money;
 use general_api::endpoints::handlers::graphql::payment::PaymentMutation;
 use general_api::models::graphql::{Payment, PaymentStatus};
 use general_api::models::redis::Payment as RedisPayment;
-use general_api::models::PayedToInput;
 use general_api::repos::auth::utils::hashing_composite_key;
-use general_api::test_sync::REDIS_TEST_LOCK;
+use general_api::repos::graphql::payment::payment_history_key;
+use general_api::test_sync::redis_test_lock;
 use redis::JsonCommands;
 
-#[test]
-fn test_aprobar_pago_pendiente() {
-    let _guard = REDIS_TEST_LOCK
-        .get_or_init(|| std::sync::Mutex::new(()))
-        .lock()
-        .unwrap();
+#[tokio::test]
+async fn test_aprobar_pago_pendiente() {
+    let _guard = redis_test_lock().await;
     let context = create_test_context();
-    let mut guard = TestRedisGuard::new(context.pool.clone());
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
+    let reviewer = insert_directive_helper(&context, &mut guard);
     let now = chrono::Utc::now().timestamp_nanos_opt().unwrap();
     let payment = Payment {
         id: format!("test_pago_{}_1", now),
         name: "Test".to_string(),
-        total_amount: 100.0,
-        payment_date: "2025-10-09".to_string(),
+        total_amount: Money::from(100),
+        currency: Currency::Gtq,
+        payment_date: "2025-10-09".parse().unwrap(),
         ticket_num: "A123".to_string(),
         account_num: "ACC1".to_string(),
         commentary: Some("Pago test 1".to_string()),
@@ -31,15 +36,18 @@ fn test_aprobar_pago_pendiente() {
         state: PaymentStatus::OnRevision,
         being_payed: vec![],
         presented_by_name: "N/A".to_string(),
+        possible_duplicates: false,
+        receipt_check: None,
     };
     // Insertar bajo la clave global 'all' para que la mutación lo encuentre
-    let all_vec = vec![String::from("all")];
+    let all_vec = [String::from("all")];
     let all_key = hashing_composite_key(&[&all_vec[0]]);
-    let redis = &mut context.pool.get().expect("Couldn't connect to pool");
+    let redis = &mut context.pool().unwrap().client().get_connection().expect("Couldn't connect to pool");
     let redis_payment = RedisPayment {
-        date_created: payment.payment_date.clone(),
+        date_created: payment.payment_date,
         account_number: payment.account_num.clone(),
         total_amount: payment.total_amount,
+        currency: payment.currency,
         name: payment.name.clone(),
         comments: payment.commentary.clone(),
         comprobante_bucket: payment.photo_path.clone(),
@@ -47,9 +55,13 @@ fn test_aprobar_pago_pendiente() {
         status: payment.state.as_str().to_owned(),
         being_payed: vec![general_api::models::PayedTo {
             model_type: "LOAN".to_string(),
-            amount: 0.0,
+            amount: Money::ZERO,
             model_key: "000000000000".to_string(),
+            allocated_currency: Currency::Gtq,
+            allocated_amount: None,
         }],
+        possible_duplicates: false,
+        receipt_check: None,
     };
     let _: () = redis
         .json_set(
@@ -58,34 +70,32 @@ fn test_aprobar_pago_pendiente() {
             &redis_payment,
         )
         .expect("No se pudo insertar el pago en la clave global 'all'");
-    let result = futures::executor::block_on(async {
-        PaymentMutation::approve_or_reject_payment(
-            &context,
-            payment.id.clone(),
-            "ACCEPTED".to_string(),
-            "".to_string(),
-        )
-        .await
-    })
+    let result = PaymentMutation::approve_or_reject_payment(
+        &context,
+        reviewer.clone(),
+        payment.id.clone(),
+        "ACCEPTED".to_string(),
+        "".to_string(),
+    )
+    .await
     .unwrap();
     assert_eq!(result.state, PaymentStatus::Accepted);
     assert_eq!(result.commentary, payment.commentary);
 }
 
-#[test]
-fn test_rechazar_pago_pendiente_con_comentario() {
-    let _guard = REDIS_TEST_LOCK
-        .get_or_init(|| std::sync::Mutex::new(()))
-        .lock()
-        .unwrap();
+#[tokio::test]
+async fn test_rechazar_pago_pendiente_con_comentario() {
+    let _guard = redis_test_lock().await;
     let context = create_test_context();
-    let mut guard = TestRedisGuard::new(context.pool.clone());
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
+    let reviewer = insert_directive_helper(&context, &mut guard);
     let now = chrono::Utc::now().timestamp_nanos_opt().unwrap();
     let payment = Payment {
         id: format!("test_pago_{}_2", now),
         name: "Test".to_string(),
-        total_amount: 200.0,
-        payment_date: "2025-10-10".to_string(),
+        total_amount: Money::from(200),
+        currency: Currency::Gtq,
+        payment_date: "2025-10-10".parse().unwrap(),
         ticket_num: "B456".to_string(),
         account_num: "ACC2".to_string(),
         commentary: Some("Pago test 2".to_string()),
@@ -93,38 +103,38 @@ fn test_rechazar_pago_pendiente_con_comentario() {
         state: PaymentStatus::OnRevision,
         being_payed: vec![],
         presented_by_name: "N/A".to_string(),
+        possible_duplicates: false,
+        receipt_check: None,
     };
     let k = insert_payment_helper_and_return(&context, &payment);
     guard.register_key(k);
     let comentario = "Pago rechazado por pruebas".to_string();
-    let result = futures::executor::block_on(async {
-        PaymentMutation::approve_or_reject_payment(
-            &context,
-            payment.id.clone(),
-            "REJECTED".to_string(),
-            comentario.clone(),
-        )
-        .await
-    })
+    let result = PaymentMutation::approve_or_reject_payment(
+        &context,
+        reviewer.clone(),
+        payment.id.clone(),
+        "REJECTED".to_string(),
+        comentario.clone(),
+    )
+    .await
     .unwrap();
     assert_eq!(result.state, PaymentStatus::Rejected);
     assert_eq!(result.commentary, Some(comentario));
 }
 
-#[test]
-fn test_rechazar_pago_pendiente_sin_comentario() {
-    let _guard = REDIS_TEST_LOCK
-        .get_or_init(|| std::sync::Mutex::new(()))
-        .lock()
-        .unwrap();
+#[tokio::test]
+async fn test_rechazar_pago_pendiente_sin_comentario() {
+    let _guard = redis_test_lock().await;
     let context = create_test_context();
-    let mut guard = TestRedisGuard::new(context.pool.clone());
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
+    let reviewer = insert_directive_helper(&context, &mut guard);
     let now = chrono::Utc::now().timestamp_nanos_opt().unwrap();
     let payment = Payment {
         id: format!("test_pago_{}_3", now),
         name: "Test".to_string(),
-        total_amount: 300.0,
-        payment_date: "2025-10-11".to_string(),
+        total_amount: Money::from(300),
+        currency: Currency::Gtq,
+        payment_date: "2025-10-11".parse().unwrap(),
         ticket_num: "C789".to_string(),
         account_num: "ACC3".to_string(),
         commentary: Some("Pago test 3".to_string()),
@@ -132,39 +142,39 @@ fn test_rechazar_pago_pendiente_sin_comentario() {
         state: PaymentStatus::OnRevision,
         being_payed: vec![],
         presented_by_name: "N/A".to_string(),
+        possible_duplicates: false,
+        receipt_check: None,
     };
     let k = insert_payment_helper_and_return(&context, &payment);
     guard.register_key(k);
-    let result = futures::executor::block_on(async {
-        PaymentMutation::approve_or_reject_payment(
-            &context,
-            payment.id.clone(),
-            "REJECTED".to_string(),
-            "".to_string(),
-        )
-        .await
-    });
+    let result = PaymentMutation::approve_or_reject_payment(
+        &context,
+        reviewer.clone(),
+        payment.id.clone(),
+        "REJECTED".to_string(),
+        "".to_string(),
+    )
+    .await;
     assert!(result.is_err());
     assert_eq!(
         result.unwrap_err(),
-        "Se requiere comentario al rechazar el pago"
+        AppError::validation("Se requiere comentario al rechazar el pago", &["commentary"])
     );
 }
 
-#[test]
-fn test_mutar_pago_ya_finalizado() {
-    let _guard = REDIS_TEST_LOCK
-        .get_or_init(|| std::sync::Mutex::new(()))
-        .lock()
-        .unwrap();
+#[tokio::test]
+async fn test_mutar_pago_ya_finalizado() {
+    let _guard = redis_test_lock().await;
     let context = create_test_context();
-    let mut guard = TestRedisGuard::new(context.pool.clone());
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
+    let reviewer = insert_directive_helper(&context, &mut guard);
     let now = chrono::Utc::now().timestamp_nanos_opt().unwrap();
     let payment = Payment {
         id: format!("test_pago_{}_4", now),
         name: "Test".to_string(),
-        total_amount: 400.0,
-        payment_date: "2025-10-12".to_string(),
+        total_amount: Money::from(400),
+        currency: Currency::Gtq,
+        payment_date: "2025-10-12".parse().unwrap(),
         ticket_num: "D012".to_string(),
         account_num: "ACC4".to_string(),
         commentary: Some("Pago test 4".to_string()),
@@ -172,36 +182,39 @@ fn test_mutar_pago_ya_finalizado() {
         state: PaymentStatus::Accepted,
         being_payed: vec![],
         presented_by_name: "N/A".to_string(),
+        possible_duplicates: false,
+        receipt_check: None,
     };
     let k = insert_payment_helper_and_return(&context, &payment);
     guard.register_key(k);
-    let result = futures::executor::block_on(async {
-        PaymentMutation::approve_or_reject_payment(
-            &context,
-            payment.id.clone(),
-            "REJECTED".to_string(),
-            "Intento mutar pago finalizado".to_string(),
-        )
-        .await
-    });
+    let result = PaymentMutation::approve_or_reject_payment(
+        &context,
+        reviewer.clone(),
+        payment.id.clone(),
+        "REJECTED".to_string(),
+        "Intento mutar pago finalizado".to_string(),
+    )
+    .await;
     assert!(result.is_err());
-    assert_eq!(result.unwrap_err(), "El pago ya está finalizado");
+    assert_eq!(
+        result.unwrap_err(),
+        AppError::conflict("El pago ya está finalizado")
+    );
 }
 
-#[test]
-fn test_mutar_con_estado_invalido() {
-    let _guard = REDIS_TEST_LOCK
-        .get_or_init(|| std::sync::Mutex::new(()))
-        .lock()
-        .unwrap();
+#[tokio::test]
+async fn test_mutar_con_estado_invalido() {
+    let _guard = redis_test_lock().await;
     let context = create_test_context();
-    let mut guard = TestRedisGuard::new(context.pool.clone());
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
+    let reviewer = insert_directive_helper(&context, &mut guard);
     let now = chrono::Utc::now().timestamp_nanos_opt().unwrap();
     let payment = Payment {
         id: format!("test_pago_{}_5", now),
         name: "Test".to_string(),
-        total_amount: 500.0,
-        payment_date: "2025-10-13".to_string(),
+        total_amount: Money::from(500),
+        currency: Currency::Gtq,
+        payment_date: "2025-10-13".parse().unwrap(),
         ticket_num: "E345".to_string(),
         account_num: "ACC5".to_string(),
         commentary: Some("Pago test 5".to_string()),
@@ -209,21 +222,422 @@ fn test_mutar_con_estado_invalido() {
         state: PaymentStatus::OnRevision,
         being_payed: vec![],
         presented_by_name: "N/A".to_string(),
+        possible_duplicates: false,
+        receipt_check: None,
     };
     let k = insert_payment_helper_and_return(&context, &payment);
     guard.register_key(k);
-    let result = futures::executor::block_on(async {
-        PaymentMutation::approve_or_reject_payment(
-            &context,
-            payment.id.clone(),
-            "INVALIDO".to_string(),
-            "".to_string(),
-        )
-        .await
-    });
+    let result = PaymentMutation::approve_or_reject_payment(
+        &context,
+        reviewer.clone(),
+        payment.id.clone(),
+        "INVALIDO".to_string(),
+        "".to_string(),
+    )
+    .await;
     assert!(result.is_err());
     assert_eq!(
         result.unwrap_err(),
-        "Estado inválido, debe ser ACCEPTED o REJECTED"
+        AppError::validation("Estado inválido, debe ser ACCEPTED o REJECTED", &["newState"])
     );
 }
+
+#[tokio::test]
+async fn test_cambio_de_estado_queda_en_historial() {
+    let _guard = redis_test_lock().await;
+    let context = create_test_context();
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
+    let reviewer = insert_directive_helper(&context, &mut guard);
+    let now = chrono::Utc::now().timestamp_nanos_opt().unwrap();
+    let payment = Payment {
+        id: format!("test_pago_{}_6", now),
+        name: "Test".to_string(),
+        total_amount: Money::from(600),
+        currency: Currency::Gtq,
+        payment_date: "2025-10-14".parse().unwrap(),
+        ticket_num: "F678".to_string(),
+        account_num: "ACC6".to_string(),
+        commentary: None,
+        photo_path: "url6".to_string(),
+        state: PaymentStatus::OnRevision,
+        being_payed: vec![],
+        presented_by_name: "N/A".to_string(),
+        possible_duplicates: false,
+        receipt_check: None,
+    };
+    let k = insert_payment_helper_and_return(&context, &payment);
+    guard.register_key(k);
+    guard.register_key(payment_history_key(&payment.id));
+
+    // sin cambios todavía el historial está vacío
+    let repo = context.payment_repo();
+    assert!(repo.get_payment_history(payment.id.clone()).await.unwrap().is_empty());
+
+    PaymentMutation::approve_or_reject_payment(
+        &context,
+        reviewer,
+        payment.id.clone(),
+        "REJECTED".to_string(),
+        "Monto no coincide".to_string(),
+    )
+    .await
+    .unwrap();
+
+    let history = repo.get_payment_history(payment.id.clone()).await.unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].previous_state, PaymentStatus::OnRevision);
+    assert_eq!(history[0].new_state, PaymentStatus::Rejected);
+    assert_eq!(history[0].reviewer_name, "Directivo Test");
+    assert_eq!(history[0].comment, Some("Monto no coincide".to_string()));
+    let elapsed = chrono::Utc::now() - history[0].changed_at.utc();
+    assert!(
+        elapsed.num_seconds() < 60,
+        "changed_at debe ser la hora del cambio"
+    );
+}
+
+#[tokio::test]
+async fn test_revisor_inexistente_no_cambia_el_pago() {
+    let _guard = redis_test_lock().await;
+    let context = create_test_context();
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
+    let now = chrono::Utc::now().timestamp_nanos_opt().unwrap();
+    let payment = Payment {
+        id: format!("test_pago_{}_7", now),
+        name: "Test".to_string(),
+        total_amount: Money::from(700),
+        currency: Currency::Gtq,
+        payment_date: "2025-10-14".parse().unwrap(),
+        ticket_num: "G901".to_string(),
+        account_num: "ACC7".to_string(),
+        commentary: None,
+        photo_path: "url7".to_string(),
+        state: PaymentStatus::OnRevision,
+        being_payed: vec![],
+        presented_by_name: "N/A".to_string(),
+        possible_duplicates: false,
+        receipt_check: None,
+    };
+    let k = insert_payment_helper_and_return(&context, &payment);
+    guard.register_key(k);
+
+    let result = PaymentMutation::approve_or_reject_payment(
+        &context,
+        format!("token_que_no_existe_{}", now),
+        payment.id.clone(),
+        "ACCEPTED".to_string(),
+        "".to_string(),
+    )
+    .await;
+    assert_eq!(
+        result.unwrap_err(),
+        AppError::unauthorized("Revisor no encontrado")
+    );
+
+    let history = context
+        .payment_repo()
+        .get_payment_history(payment.id.clone())
+        .await
+        .unwrap();
+    assert!(history.is_empty());
+}
+
+#[tokio::test]
+async fn test_socio_no_puede_revisar_pagos() {
+    let _guard = redis_test_lock().await;
+    let context = create_test_context();
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
+    let member = insert_member_helper(&context, &mut guard);
+    let now = chrono::Utc::now().timestamp_nanos_opt().unwrap();
+    let payment = Payment {
+        id: format!("test_pago_{}_8", now),
+        name: "Test".to_string(),
+        total_amount: Money::from(800),
+        currency: Currency::Gtq,
+        payment_date: "2025-10-16".parse().unwrap(),
+        ticket_num: "H999".to_string(),
+        account_num: "ACC8".to_string(),
+        commentary: None,
+        photo_path: "url8".to_string(),
+        state: PaymentStatus::OnRevision,
+        being_payed: vec![],
+        presented_by_name: "N/A".to_string(),
+        possible_duplicates: false,
+        receipt_check: None,
+    };
+    let k = insert_payment_helper_and_return(&context, &payment);
+    guard.register_key(k);
+
+    // un socio normal no puede aprobar (ni el suyo ni el de nadie)
+    let result = PaymentMutation::approve_or_reject_payment(
+        &context,
+        member,
+        payment.id.clone(),
+        "ACCEPTED".to_string(),
+        "".to_string(),
+    )
+    .await;
+    assert_eq!(
+        result.unwrap_err(),
+        AppError::unauthorized("Solo los directivos pueden aprobar o rechazar pagos")
+    );
+
+    let history = context
+        .payment_repo()
+        .get_payment_history(payment.id.clone())
+        .await
+        .unwrap();
+    assert!(history.is_empty());
+}
+
+// los mismos casos contra redis y contra MemoryStore
+mod en_cada_backend {
+    use general_api::endpoints::handlers::graphql::payment::PaymentMutation;
+    use general_api::errors::AppError;
+    use general_api::models::currency::Currency;
+    use general_api::models::graphql::{Payment, PaymentStatus};
+    use general_api::models::money::Money;
+    use general_api::repos::graphql::payment::payment_history_key;
+
+    use super::super::common::{memory_user, TestBackend, BACKENDS};
+
+    /// guarda un pago del socio de prueba y registra su historial para limpiarlo, la mutation
+    /// lo busca solo por id
+    fn insert_payment(backend: &mut TestBackend, id: &str, state: PaymentStatus) -> Payment {
+        let payment = Payment {
+            id: id.to_string(),
+            name: "Test".to_string(),
+            total_amount: Money::from(100),
+            currency: Currency::Gtq,
+            payment_date: "2025-10-09".parse().unwrap(),
+            ticket_num: format!("T_{}", id),
+            account_num: format!("ACC_{}", id),
+            commentary: Some(format!("Pago {}", id)),
+            photo_path: format!("url_{}", id),
+            state,
+            being_payed: vec![],
+            presented_by_name: "N/A".to_string(),
+            possible_duplicates: false,
+            receipt_check: None,
+        };
+        let owner_key = backend.track_owner("socio_pagos_backend");
+        backend.insert_payment(&owner_key, &payment);
+        backend.register_key(payment_history_key(id));
+        payment
+    }
+
+    #[tokio::test]
+    async fn test_aprobar_pago_pendiente() {
+        for backend in BACKENDS {
+            let mut backend = backend.start().await;
+            let reviewer = backend.add_directive();
+            let payment =
+                insert_payment(&mut backend, "pago_backend_aprobar", PaymentStatus::OnRevision);
+
+            let result = PaymentMutation::approve_or_reject_payment(
+                &backend.context,
+                reviewer,
+                payment.id.clone(),
+                "ACCEPTED".to_string(),
+                "".to_string(),
+            )
+            .await
+            .unwrap();
+            assert_eq!(result.state, PaymentStatus::Accepted);
+            assert_eq!(result.commentary, payment.commentary);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rechazar_pago_pendiente_con_comentario() {
+        for backend in BACKENDS {
+            let mut backend = backend.start().await;
+            let reviewer = backend.add_directive();
+            let payment =
+                insert_payment(&mut backend, "pago_backend_rechazar", PaymentStatus::OnRevision);
+
+            let result = PaymentMutation::approve_or_reject_payment(
+                &backend.context,
+                reviewer,
+                payment.id.clone(),
+                "REJECTED".to_string(),
+                "Monto incorrecto".to_string(),
+            )
+            .await
+            .unwrap();
+            assert_eq!(result.state, PaymentStatus::Rejected);
+            assert_eq!(result.commentary, Some("Monto incorrecto".to_string()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rechazar_pago_pendiente_sin_comentario() {
+        for backend in BACKENDS {
+            let mut backend = backend.start().await;
+            let reviewer = backend.add_directive();
+            let payment = insert_payment(
+                &mut backend,
+                "pago_backend_sin_comentario",
+                PaymentStatus::OnRevision,
+            );
+
+            let result = PaymentMutation::approve_or_reject_payment(
+                &backend.context,
+                reviewer,
+                payment.id.clone(),
+                "REJECTED".to_string(),
+                "".to_string(),
+            )
+            .await;
+            assert_eq!(
+                result.unwrap_err(),
+                AppError::validation("Se requiere comentario al rechazar el pago", &["commentary"])
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mutar_pago_ya_finalizado() {
+        for backend in BACKENDS {
+            let mut backend = backend.start().await;
+            let reviewer = backend.add_directive();
+            let payment =
+                insert_payment(&mut backend, "pago_backend_finalizado", PaymentStatus::Accepted);
+
+            let result = PaymentMutation::approve_or_reject_payment(
+                &backend.context,
+                reviewer,
+                payment.id.clone(),
+                "REJECTED".to_string(),
+                "Intento mutar pago finalizado".to_string(),
+            )
+            .await;
+            assert_eq!(
+                result.unwrap_err(),
+                AppError::conflict("El pago ya está finalizado")
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mutar_con_estado_invalido() {
+        for backend in BACKENDS {
+            let mut backend = backend.start().await;
+            let reviewer = backend.add_directive();
+            let payment =
+                insert_payment(&mut backend, "pago_backend_invalido", PaymentStatus::OnRevision);
+
+            let result = PaymentMutation::approve_or_reject_payment(
+                &backend.context,
+                reviewer,
+                payment.id.clone(),
+                "INVALIDO".to_string(),
+                "".to_string(),
+            )
+            .await;
+            assert_eq!(
+                result.unwrap_err(),
+                AppError::validation("Estado inválido, debe ser ACCEPTED o REJECTED", &["newState"])
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cambio_de_estado_queda_en_historial() {
+        for backend in BACKENDS {
+            let mut backend = backend.start().await;
+            let reviewer = backend.add_directive();
+            let payment =
+                insert_payment(&mut backend, "pago_backend_historial", PaymentStatus::OnRevision);
+
+            // sin cambios todavía el historial está vacío
+            let repo = backend.context.payment_repo();
+            assert!(repo.get_payment_history(payment.id.clone()).await.unwrap().is_empty());
+
+            PaymentMutation::approve_or_reject_payment(
+                &backend.context,
+                reviewer,
+                payment.id.clone(),
+                "REJECTED".to_string(),
+                "Monto no coincide".to_string(),
+            )
+            .await
+            .unwrap();
+
+            let history = repo.get_payment_history(payment.id.clone()).await.unwrap();
+            assert_eq!(history.len(), 1);
+            assert_eq!(history[0].previous_state, PaymentStatus::OnRevision);
+            assert_eq!(history[0].new_state, PaymentStatus::Rejected);
+            assert_eq!(history[0].reviewer_name, "Directivo Test");
+            assert_eq!(history[0].comment, Some("Monto no coincide".to_string()));
+            let elapsed = chrono::Utc::now() - history[0].changed_at.utc();
+            assert!(elapsed.num_seconds() < 60, "changed_at debe ser la hora del cambio");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_revisor_inexistente_no_cambia_el_pago() {
+        for backend in BACKENDS {
+            let mut backend = backend.start().await;
+            let payment = insert_payment(
+                &mut backend,
+                "pago_backend_sin_revisor",
+                PaymentStatus::OnRevision,
+            );
+
+            let result = PaymentMutation::approve_or_reject_payment(
+                &backend.context,
+                "token_que_no_existe".to_string(),
+                payment.id.clone(),
+                "ACCEPTED".to_string(),
+                "".to_string(),
+            )
+            .await;
+            assert_eq!(
+                result.unwrap_err(),
+                AppError::unauthorized("Revisor no encontrado")
+            );
+
+            let history = backend
+                .context
+                .payment_repo()
+                .get_payment_history(payment.id.clone())
+                .await
+                .unwrap();
+            assert!(history.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_socio_no_puede_revisar_pagos() {
+        for backend in BACKENDS {
+            let mut backend = backend.start().await;
+            let member = "socio_test_backend".to_string();
+            backend.add_user(&member, memory_user("AF-SOCIO-B", "Socio Test", false));
+            let payment =
+                insert_payment(&mut backend, "pago_backend_socio", PaymentStatus::OnRevision);
+
+            // un socio normal no puede aprobar (ni el suyo ni el de nadie)
+            let result = PaymentMutation::approve_or_reject_payment(
+                &backend.context,
+                member,
+                payment.id.clone(),
+                "ACCEPTED".to_string(),
+                "".to_string(),
+            )
+            .await;
+            assert_eq!(
+                result.unwrap_err(),
+                AppError::unauthorized("Solo los directivos pueden aprobar o rechazar pagos")
+            );
+
+            let history = backend
+                .context
+                .payment_repo()
+                .get_payment_history(payment.id.clone())
+                .await
+                .unwrap();
+            assert!(history.is_empty());
+        }
+    }
+}
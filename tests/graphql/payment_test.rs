@@ -2,35 +2,36 @@
 // No se usa dotenv, las variables se cargan directamente
 
 use super::common::{create_test_context, insert_payment_helper_and_return, TestRedisGuard};
+use general_api::errors::AppError;
+use general_api::models::currency::Currency;
+use general_api::models::money::Money;
 use general_api::endpoints::handlers::graphql::payment::PaymentQuery;
-use general_api::models::graphql::{Payment, PaymentHistory};
+use general_api::models::graphql::Payment;
 use general_api::repos::auth::utils::hashing_composite_key;
 use general_api::repos::graphql::payment::PaymentRepo;
-use general_api::test_sync::REDIS_TEST_LOCK;
+use general_api::test_sync::redis_test_lock;
 use redis::Commands;
+use general_api::repos::graphql::store::PaymentStore;
 
-#[test]
-fn test_get_all_payments_returns_all_inserted_payments() {
+#[tokio::test]
+async fn test_get_all_payments_returns_all_inserted_payments() {
     // Serializar pruebas que tocan Redis sin dependencias externas
     // Acquire a blocking mutex guard to serialize access across tests
-    let _guard = REDIS_TEST_LOCK
-        .get_or_init(|| std::sync::Mutex::new(()))
-        .lock()
-        .unwrap();
+    let _guard = redis_test_lock().await;
     // Crear contexto y guard para limpieza de claves de test
     let context = create_test_context();
-    let mut guard = TestRedisGuard::new(context.pool.clone());
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
 
     // Insertar pagos de prueba
     use general_api::models::graphql::PaymentStatus;
-    use general_api::models::PayedTo;
     let now = chrono::Utc::now().timestamp_nanos_opt().unwrap();
     let payments = vec![
         Payment {
             id: format!("test_pago_{}_1", now),
             name: "Test".to_string(),
-            total_amount: 100.0,
-            payment_date: "2025-10-09".to_string(),
+            total_amount: Money::from(100),
+            currency: Currency::Gtq,
+            payment_date: "2025-10-09".parse().unwrap(),
             ticket_num: "A123".to_string(),
             account_num: "ACC1".to_string(),
             commentary: Some("Pago test 1".to_string()),
@@ -38,12 +39,15 @@ fn test_get_all_payments_returns_all_inserted_payments() {
             state: PaymentStatus::from_string("ACCEPTED".to_string()),
             being_payed: vec![],
             presented_by_name: "N/A".to_string(),
+            possible_duplicates: false,
+            receipt_check: None,
         },
         Payment {
             id: format!("test_pago_{}_2", now),
             name: "Test".to_string(),
-            total_amount: 200.0,
-            payment_date: "2025-10-10".to_string(),
+            total_amount: Money::from(200),
+            currency: Currency::Gtq,
+            payment_date: "2025-10-10".parse().unwrap(),
             ticket_num: "B456".to_string(),
             account_num: "ACC2".to_string(),
             commentary: Some("Pago test 2".to_string()),
@@ -51,6 +55,8 @@ fn test_get_all_payments_returns_all_inserted_payments() {
             state: PaymentStatus::from_string("ON_REVISION".to_string()),
             being_payed: vec![],
             presented_by_name: "N/A".to_string(),
+            possible_duplicates: false,
+            receipt_check: None,
         },
     ];
 
@@ -63,8 +69,11 @@ fn test_get_all_payments_returns_all_inserted_payments() {
 
     // Debug: verificar que las claves insertadas existen en Redis
     {
-        let pool = context.pool.clone();
-        let mut con = pool.get().expect("No se pudo obtener conexión de Redis");
+        let pool = context.pool().unwrap().clone();
+        let mut con = pool
+            .client()
+            .get_connection()
+            .expect("No se pudo obtener conexión de Redis");
 
         assert!(
             con.exists::<_, bool>(&inserted_keys[0]).unwrap(),
@@ -77,9 +86,7 @@ fn test_get_all_payments_returns_all_inserted_payments() {
     }
 
     // Ejecutar la query
-    let mut result = futures::executor::block_on(async {
-        PaymentQuery::get_all_payments(&context).await.unwrap()
-    });
+    let mut result = PaymentQuery::get_all_payments(&context).await.unwrap();
 
     // Ordenar ambos vectores por id para evitar dependencia del orden de Redis
     let mut expected_sorted = payments.clone();
@@ -116,17 +123,14 @@ fn test_get_all_payments_returns_all_inserted_payments() {
     }
 }
 
-#[test]
-fn test_get_user_payments_returns_only_user_payments() {
+#[tokio::test]
+async fn test_get_user_payments_returns_only_user_payments() {
     // Serializar pruebas que tocan Redis
-    let _guard = REDIS_TEST_LOCK
-        .get_or_init(|| std::sync::Mutex::new(()))
-        .lock()
-        .unwrap();
+    let _guard = redis_test_lock().await;
 
     // Crear contexto y guard para limpieza
     let context = create_test_context();
-    let mut guard = TestRedisGuard::new(context.pool.clone());
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
 
     // Crear un usuario específico con access_token
     let test_access_token = "test_user_payments_001";
@@ -134,7 +138,7 @@ fn test_get_user_payments_returns_only_user_payments() {
 
     // Insertar nombre del usuario en Redis
     {
-        let mut con = context.pool.get().expect("Couldn't connect to pool");
+        let mut con = context.pool().unwrap().client().get_connection().expect("Couldn't connect to pool");
         let name_key = format!("users:{}:complete_name", db_access_token);
         let _: () = con
             .set(&name_key, "Test User Payments")
@@ -151,32 +155,38 @@ fn test_get_user_payments_returns_only_user_payments() {
     let now = chrono::Utc::now().timestamp_nanos_opt().unwrap();
 
     let redis_payment1 = RedisPayment {
-        date_created: "2025-10-15".to_string(),
+        date_created: "2025-10-15".parse().unwrap(),
         account_number: "ACC001".to_string(),
-        total_amount: 150.0,
+        total_amount: Money::from(150),
+        currency: Currency::Gtq,
         name: "Pago usuario 1".to_string(),
         comments: Some("Comentario 1".to_string()),
         comprobante_bucket: "url1".to_string(),
         ticket_number: format!("TICKET_{}_1", now),
         status: "ACCEPTED".to_string(),
         being_payed: vec![PayedTo::default()],
+        possible_duplicates: false,
+        receipt_check: None,
     };
 
     let redis_payment2 = RedisPayment {
-        date_created: "2025-10-16".to_string(),
+        date_created: "2025-10-16".parse().unwrap(),
         account_number: "ACC002".to_string(),
-        total_amount: 250.0,
+        total_amount: Money::from(250),
+        currency: Currency::Gtq,
         name: "Pago usuario 2".to_string(),
         comments: Some("Comentario 2".to_string()),
         comprobante_bucket: "url2".to_string(),
         ticket_number: format!("TICKET_{}_2", now),
         status: "ON_REVISION".to_string(),
         being_payed: vec![PayedTo::default()],
+        possible_duplicates: false,
+        receipt_check: None,
     };
 
     // Insertar pagos en Redis
     {
-        let mut con = context.pool.get().expect("Couldn't connect to pool");
+        let mut con = context.pool().unwrap().client().get_connection().expect("Couldn't connect to pool");
 
         let key1 = format!(
             "users:{}:payments:{}",
@@ -199,12 +209,11 @@ fn test_get_user_payments_returns_only_user_payments() {
     }
 
     // Ejecutar get_user_payments
-    let repo = PaymentRepo {
-        pool: context.pool.clone(),
-    };
+    let repo = PaymentRepo::new(context.pool().unwrap().clone());
 
     let result = repo
         .get_user_payments(test_access_token.to_string())
+        .await
         .expect("get_user_payments failed");
 
     // Validaciones
@@ -222,36 +231,33 @@ fn test_get_user_payments_returns_only_user_payments() {
     assert!(payment2.is_some(), "Pago 2 no encontrado");
 
     let p1 = payment1.unwrap();
-    assert_eq!(p1.total_amount, 150.0);
+    assert_eq!(p1.total_amount, Money::from(150));
     assert_eq!(p1.state, PaymentStatus::Accepted);
 
     let p2 = payment2.unwrap();
-    assert_eq!(p2.total_amount, 250.0);
+    assert_eq!(p2.total_amount, Money::from(250));
     assert_eq!(p2.state, PaymentStatus::OnRevision);
 }
 
-#[test]
-fn test_get_user_history_returns_correct_values() {
+#[tokio::test]
+async fn test_get_user_history_returns_correct_values() {
     // Serializar pruebas que tocan Redis
-    let _guard = REDIS_TEST_LOCK
-        .get_or_init(|| std::sync::Mutex::new(()))
-        .lock()
-        .unwrap();
+    let _guard = redis_test_lock().await;
 
     // Crear contexto y guard para limpieza
     let context = create_test_context();
-    let mut guard = TestRedisGuard::new(context.pool.clone());
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
 
     // Crear un usuario con historial
     let test_access_token = "test_user_history_001";
     let db_access_token = hashing_composite_key(&[&test_access_token.to_string()]);
 
-    let payed_to_capital_value = 1500.50;
-    let owed_capital_value = 3200.75;
+    let payed_to_capital_value : Money = "1500.50".parse().unwrap();
+    let owed_capital_value : Money = "3200.75".parse().unwrap();
 
     // Insertar valores de historial en Redis
     {
-        let mut con = context.pool.get().expect("Couldn't connect to pool");
+        let mut con = context.pool().unwrap().client().get_connection().expect("Couldn't connect to pool");
 
         let payed_key = format!("users:{}:payed_to_capital", db_access_token);
         let owed_key = format!("users:{}:owed_capital", db_access_token);
@@ -268,12 +274,11 @@ fn test_get_user_history_returns_correct_values() {
     }
 
     // Ejecutar get_user_history
-    let repo = PaymentRepo {
-        pool: context.pool.clone(),
-    };
+    let repo = PaymentRepo::new(context.pool().unwrap().clone());
 
     let result = repo
         .get_user_history(test_access_token.to_string())
+        .await
         .expect("get_user_history failed");
 
     // Validaciones
@@ -289,13 +294,10 @@ fn test_get_user_history_returns_correct_values() {
     );
 }
 
-#[test]
-fn test_get_user_history_with_no_data_returns_error() {
+#[tokio::test]
+async fn test_get_user_history_with_no_data_returns_error() {
     // Serializar pruebas que tocan Redis
-    let _guard = REDIS_TEST_LOCK
-        .get_or_init(|| std::sync::Mutex::new(()))
-        .lock()
-        .unwrap();
+    let _guard = redis_test_lock().await;
 
     // Crear contexto
     let context = create_test_context();
@@ -304,19 +306,191 @@ fn test_get_user_history_with_no_data_returns_error() {
     let test_access_token = "test_user_no_history";
 
     // Ejecutar get_user_history
-    let repo = PaymentRepo {
-        pool: context.pool.clone(),
-    };
+    let repo = PaymentRepo::new(context.pool().unwrap().clone());
 
-    let result = repo.get_user_history(test_access_token.to_string());
+    let result = repo.get_user_history(test_access_token.to_string()).await;
 
     // Validación: debería fallar porque no hay datos
     assert!(
         result.is_err(),
         "Debería retornar error cuando no hay datos de historial"
     );
-    assert_eq!(result.unwrap_err(), "Couldnt Get Payed To Capital");
+    assert_eq!(
+        result.unwrap_err(),
+        AppError::not_found("Couldnt Get Payed To Capital")
+    );
 }
 
 // Helpers ahora importados desde tests/utils/redis_helpers.rs
 
+
+// los mismos casos contra redis y contra MemoryStore
+mod en_cada_backend {
+    use general_api::endpoints::handlers::graphql::payment::PaymentQuery;
+    use general_api::errors::AppError;
+    use general_api::models::currency::Currency;
+    use general_api::models::graphql::{Payment, PaymentStatus};
+    use general_api::models::money::Money;
+    use general_api::models::PayedTo;
+    use general_api::repos::graphql::memory::MemoryUser;
+
+    use super::super::common::{memory_user, BACKENDS};
+
+    fn payment(id: &str, amount: i32, ticket_num: &str, state: PaymentStatus) -> Payment {
+        Payment {
+            id: id.to_string(),
+            name: "Test".to_string(),
+            total_amount: Money::from(amount),
+            currency: Currency::Gtq,
+            payment_date: "2025-10-09".parse().unwrap(),
+            ticket_num: ticket_num.to_string(),
+            account_num: format!("ACC_{}", ticket_num),
+            commentary: Some(format!("Pago {}", id)),
+            photo_path: format!("url_{}", id),
+            state,
+            being_payed: vec![PayedTo::default()],
+            presented_by_name: "N/A".to_string(),
+            possible_duplicates: false,
+            receipt_check: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_all_payments_returns_all_inserted_payments() {
+        for backend in BACKENDS {
+            let mut backend = backend.start().await;
+
+            // el dueño no está registrado como socio
+            let owner_key = backend.track_owner("test_all_payments_backend");
+            let payments = vec![
+                payment("test_pago_backend_1", 100, "A123", PaymentStatus::Accepted),
+                payment("test_pago_backend_2", 200, "B456", PaymentStatus::OnRevision),
+            ];
+            for payment in &payments {
+                backend.insert_payment(&owner_key, payment);
+            }
+
+            // en redis puede haber pagos de otros tests, nos quedamos con los insertados
+            let mut result = PaymentQuery::get_all_payments(&backend.context).await.unwrap();
+            result.retain(|p| payments.iter().any(|expected| expected.id == p.id));
+            result.sort_by(|a, b| a.id.cmp(&b.id));
+
+            assert_eq!(result.len(), payments.len());
+            for (expected, actual) in payments.iter().zip(&result) {
+                assert_eq!(expected.id, actual.id);
+                assert_eq!(expected.total_amount, actual.total_amount);
+                assert_eq!(expected.payment_date, actual.payment_date);
+                assert_eq!(expected.ticket_num, actual.ticket_num);
+                assert_eq!(expected.account_num, actual.account_num);
+                assert_eq!(expected.commentary, actual.commentary);
+                assert_eq!(expected.photo_path, actual.photo_path);
+                assert_eq!(expected.state, actual.state);
+                assert_eq!(expected.being_payed, actual.being_payed);
+                // el dueño no tiene complete_name
+                assert_eq!(actual.presented_by_name, "N/A");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_user_payments_returns_only_user_payments() {
+        for backend in BACKENDS {
+            let mut backend = backend.start().await;
+
+            let access_token = "test_user_payments_backend_001";
+            let owner_key = backend.add_user(
+                access_token,
+                memory_user("AF-PAY-B1", "Test User Payments", false),
+            );
+            let other_key = backend.add_user(
+                "test_user_payments_backend_002",
+                memory_user("AF-PAY-B2", "Otro", false),
+            );
+
+            backend.insert_payment(
+                &owner_key,
+                &payment("pago_usuario_1", 150, "TICKET_1", PaymentStatus::Accepted),
+            );
+            backend.insert_payment(
+                &owner_key,
+                &payment("pago_usuario_2", 250, "TICKET_2", PaymentStatus::OnRevision),
+            );
+            // el de otro socio no debe salir
+            backend.insert_payment(
+                &other_key,
+                &payment("pago_otro", 150, "TICKET_OTRO", PaymentStatus::Accepted),
+            );
+
+            let result = backend
+                .context
+                .payment_repo()
+                .get_user_payments(access_token.to_string())
+                .await
+                .expect("get_user_payments failed");
+
+            assert_eq!(result.len(), 2, "Deberían haber 2 pagos del usuario");
+
+            let p1 = result
+                .iter()
+                .find(|p| p.ticket_num == "TICKET_1")
+                .expect("Pago 1 no encontrado");
+            assert_eq!(p1.total_amount, Money::from(150));
+            assert_eq!(p1.state, PaymentStatus::Accepted);
+
+            let p2 = result
+                .iter()
+                .find(|p| p.ticket_num == "TICKET_2")
+                .expect("Pago 2 no encontrado");
+            assert_eq!(p2.total_amount, Money::from(250));
+            assert_eq!(p2.state, PaymentStatus::OnRevision);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_user_history_returns_correct_values() {
+        for backend in BACKENDS {
+            let mut backend = backend.start().await;
+
+            let access_token = "test_user_history_backend_001";
+            let payed_to_capital: Money = "1500.50".parse().unwrap();
+            let owed_capital: Money = "3200.75".parse().unwrap();
+            backend.add_user(
+                access_token,
+                MemoryUser {
+                    payed_to_capital,
+                    owed_capital,
+                    ..memory_user("AF-HIST-B1", "Historial", false)
+                },
+            );
+
+            let result = backend
+                .context
+                .payment_repo()
+                .get_user_history(access_token.to_string())
+                .await
+                .expect("get_user_history failed");
+
+            assert_eq!(result.payed_to_capital, payed_to_capital);
+            assert_eq!(result.owed_capital, owed_capital);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_user_history_with_no_data_returns_error() {
+        for backend in BACKENDS {
+            let backend = backend.start().await;
+
+            // usuario sin datos de historial
+            let result = backend
+                .context
+                .payment_repo()
+                .get_user_history("test_user_no_history_backend".to_string())
+                .await;
+
+            assert_eq!(
+                result.unwrap_err(),
+                AppError::not_found("Couldnt Get Payed To Capital")
+            );
+        }
+    }
+}
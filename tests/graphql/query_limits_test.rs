@@ -0,0 +1,264 @@
+// Tests de QueryLimits: profundidad, complejidad y persisted queries se revisan antes de
+// ejecutar, ninguno necesita redis
+
+use std::time::Duration;
+
+use actix_web::test::{TestRequest, call_service, init_service, read_body_json};
+use actix_web::{App, http::StatusCode, web::Data};
+use general_api::endpoints::graphql_endpoints::graphql_config;
+use general_api::endpoints::handlers::configs::connection_pool::RedisPool;
+use general_api::endpoints::handlers::graphql::limits::{
+    PersistedQueryRequest, QueryLimits, query_hash,
+};
+use general_api::endpoints::handlers::graphql::root::{Mutation, Query, Subscription};
+use general_api::errors::AppError;
+use juniper::RootNode;
+use redis::Client;
+
+const MEMBER_QUERY: &str =
+    r#"{ me(accessToken: "x") { name loans { id quotas { quotaNumber } } } }"#;
+
+fn request(body: serde_json::Value) -> PersistedQueryRequest {
+    serde_json::from_value(body).unwrap()
+}
+
+fn prepare(limits: &QueryLimits, body: serde_json::Value) -> Result<String, AppError> {
+    let schema = RootNode::new(Query, Mutation, Subscription);
+    limits
+        .prepare(request(body), &schema.schema)
+        .map(|request| serde_json::to_value(&request).unwrap()["query"].to_string())
+}
+
+#[test]
+fn test_rejects_deep_queries() {
+    let limits = QueryLimits::new(3, 100);
+
+    let error = prepare(&limits, serde_json::json!({ "query": MEMBER_QUERY })).unwrap_err();
+    assert!(matches!(error, AppError::QueryTooComplex(_)), "{:?}", error);
+    assert_eq!(error.code(), "QUERY_TOO_COMPLEX");
+
+    // me -> loans -> quotas -> quotaNumber son 4 niveles
+    assert!(
+        prepare(
+            &QueryLimits::new(4, 100),
+            serde_json::json!({ "query": MEMBER_QUERY })
+        )
+        .is_ok()
+    );
+}
+
+#[test]
+fn test_rejects_queries_with_too_many_fields() {
+    let limits = QueryLimits::new(10, 4);
+
+    // me, name, loans, id, quotas, quotaNumber
+    let error = prepare(&limits, serde_json::json!({ "query": MEMBER_QUERY })).unwrap_err();
+    assert!(matches!(error, AppError::QueryTooComplex(_)), "{:?}", error);
+    assert!(
+        prepare(
+            &QueryLimits::new(10, 6),
+            serde_json::json!({ "query": MEMBER_QUERY })
+        )
+        .is_ok()
+    );
+}
+
+#[test]
+fn test_fragments_count_where_they_are_spread() {
+    let query = r#"
+        query { me(accessToken: "x") { ...Member } other: me(accessToken: "y") { ...Member } }
+        fragment Member on Member { name loans { id } }
+    "#;
+
+    // cada spread suma los 3 campos del fragment
+    assert!(
+        prepare(
+            &QueryLimits::new(10, 8),
+            serde_json::json!({ "query": query })
+        )
+        .is_ok()
+    );
+    let error = prepare(
+        &QueryLimits::new(10, 7),
+        serde_json::json!({ "query": query }),
+    )
+    .unwrap_err();
+    assert!(matches!(error, AppError::QueryTooComplex(_)), "{:?}", error);
+}
+
+#[test]
+fn test_introspection_is_not_counted() {
+    let query = r#"{ __schema { types { name fields { name type { name } } } } }"#;
+
+    assert!(
+        prepare(
+            &QueryLimits::new(1, 1),
+            serde_json::json!({ "query": query })
+        )
+        .is_ok()
+    );
+}
+
+#[test]
+fn test_persisted_hash_resolves_the_stored_query() {
+    let limits = QueryLimits::new(10, 100).with_persisted_queries([MEMBER_QUERY.to_string()], true);
+
+    let query = prepare(
+        &limits,
+        serde_json::json!({
+            "extensions": { "persistedQuery": { "version": 1, "sha256Hash": query_hash(MEMBER_QUERY) } }
+        }),
+    )
+    .unwrap();
+    assert_eq!(query, serde_json::to_string(MEMBER_QUERY).unwrap());
+}
+
+#[test]
+fn test_unknown_hash_is_not_found() {
+    let limits = QueryLimits::new(10, 100);
+
+    let error = prepare(
+        &limits,
+        serde_json::json!({
+            "extensions": { "persistedQuery": { "sha256Hash": query_hash("{ otra }") } }
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(error.code(), "PERSISTED_QUERY_NOT_FOUND");
+}
+
+#[test]
+fn test_required_persisted_queries_reject_arbitrary_queries() {
+    let limits = QueryLimits::new(10, 100).with_persisted_queries([MEMBER_QUERY.to_string()], true);
+
+    let error = prepare(
+        &limits,
+        serde_json::json!({ "query": "{ me(accessToken: \"x\") { name } }" }),
+    )
+    .unwrap_err();
+    assert_eq!(error.code(), "PERSISTED_QUERY_NOT_FOUND");
+
+    // el texto exacto de una query del allow-list sí pasa
+    assert!(prepare(&limits, serde_json::json!({ "query": MEMBER_QUERY })).is_ok());
+}
+
+#[test]
+fn test_hash_must_match_the_query() {
+    let limits = QueryLimits::new(10, 100);
+
+    let error = prepare(
+        &limits,
+        serde_json::json!({
+            "query": MEMBER_QUERY,
+            "extensions": { "persistedQuery": { "sha256Hash": query_hash("{ otra }") } }
+        }),
+    )
+    .unwrap_err();
+    assert!(matches!(error, AppError::Validation { .. }), "{:?}", error);
+}
+
+#[actix_web::test]
+async fn test_handler_rejects_before_touching_redis() {
+    // redis caído: si el handler ejecutara la query el error sería STORAGE
+    let client = Client::open("redis://127.0.0.1:1/").unwrap();
+    let pool = RedisPool::new(
+        client,
+        Duration::from_millis(200),
+        Duration::from_millis(200),
+    );
+    let app = init_service(
+        App::new()
+            .app_data(Data::new(pool))
+            .app_data(Data::new(QueryLimits::new(3, 100)))
+            .configure(graphql_config),
+    )
+    .await;
+
+    let response = call_service(
+        &app,
+        TestRequest::post()
+            .uri("/graphql")
+            .set_json(serde_json::json!({ "query": MEMBER_QUERY }))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body: serde_json::Value = read_body_json(response).await;
+    assert_eq!(body["errors"][0]["extensions"]["code"], "QUERY_TOO_COMPLEX");
+}
+
+fn prepare_subscription(limits: &QueryLimits, body: serde_json::Value) -> Result<String, AppError> {
+    let schema = RootNode::new(Query, Mutation, Subscription);
+    limits
+        .prepare_subscription(request(body), &schema.schema)
+        .map(|request| request.query)
+}
+
+#[test]
+fn test_websocket_only_accepts_subscriptions() {
+    let limits = QueryLimits::new(10, 100);
+    let subscription = r#"subscription { fineIssued(accessToken: "x") { id } }"#;
+
+    assert!(prepare_subscription(&limits, serde_json::json!({ "query": subscription })).is_ok());
+
+    for query in [
+        r#"{ me(accessToken: "x") { name } }"#,
+        r#"mutation { attachment { reviewAttachment(accessToken: "x", id: "x", status: APPROVED) { id } } }"#,
+    ] {
+        let error =
+            prepare_subscription(&limits, serde_json::json!({ "query": query })).unwrap_err();
+        assert!(matches!(error, AppError::Validation { .. }), "{:?}", error);
+    }
+
+    // con operationName solo cuenta la operación que se ejecuta
+    let document = r#"
+        query Me { me(accessToken: "x") { name } }
+        subscription Fines { fineIssued(accessToken: "x") { id } }
+    "#;
+    assert!(
+        prepare_subscription(
+            &limits,
+            serde_json::json!({ "query": document, "operationName": "Fines" })
+        )
+        .is_ok()
+    );
+    assert!(
+        prepare_subscription(
+            &limits,
+            serde_json::json!({ "query": document, "operationName": "Me" })
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn test_websocket_subscriptions_go_through_the_limits() {
+    let deep = r#"subscription {
+        fineIssued(accessToken: "x") { member { loans { quotas { quotaNumber } } } }
+    }"#;
+    let error =
+        prepare_subscription(&QueryLimits::new(3, 100), serde_json::json!({ "query": deep }))
+            .unwrap_err();
+    assert!(matches!(error, AppError::QueryTooComplex(_)), "{:?}", error);
+
+    let known = r#"subscription { fineIssued(accessToken: "x") { id } }"#;
+    let limits =
+        QueryLimits::new(10, 100).with_persisted_queries(vec![known.to_string()], true);
+    let error = prepare_subscription(
+        &limits,
+        serde_json::json!({ "query": r#"subscription { fineIssued(accessToken: "x") { reason } }"# }),
+    )
+    .unwrap_err();
+    assert_eq!(error.code(), "PERSISTED_QUERY_NOT_FOUND");
+
+    // por hash se resuelve igual que por POST
+    let query = prepare_subscription(
+        &limits,
+        serde_json::json!({
+            "extensions": { "persistedQuery": { "version": 1, "sha256Hash": query_hash(known) } }
+        }),
+    )
+    .unwrap();
+    assert_eq!(query, known);
+}
@@ -0,0 +1,225 @@
+// Tests de Query.financialReport contra MemoryStore: cobros por mes, cartera, morosidad,
+// multas y que solo lo vean los directivos
+
+use std::sync::Arc;
+
+use super::common::{memory_fine, memory_loan, memory_payment, memory_quota, memory_user};
+use general_api::endpoints::handlers::configs::schema::GeneralContext;
+use general_api::endpoints::handlers::graphql::root::{Mutation, Query};
+use general_api::models::redis::Payment as RedisPayment;
+use general_api::repos::graphql::memory::MemoryStore;
+use juniper::{EmptySubscription, RootNode, Variables};
+
+const MEMBER_TOKEN: &str = "socio_reporte";
+const OTHER_TOKEN: &str = "otro_socio_reporte";
+const DIRECTIVE_TOKEN: &str = "directivo_reporte";
+
+// pagos del día 10 de cada mes de 2025
+fn payment(month: u32, status: &str, being_payed: Vec<(&str, i32)>) -> RedisPayment {
+    memory_payment(&format!("BOLETA-{month}"), (2025, month, 10), status, being_payed)
+}
+
+/// dos socios y un directivo con pagos de enero a abril, préstamos, multas y cuotas atrasadas
+async fn seed() -> GeneralContext {
+    let store = Arc::new(MemoryStore::new());
+    let member = store
+        .add_user(MEMBER_TOKEN, memory_user("AF-REP-1", "Socio Reporte", false))
+        .unwrap();
+    let other = store
+        .add_user(OTHER_TOKEN, memory_user("AF-REP-2", "Otro Socio", false))
+        .unwrap();
+    store
+        .add_user(DIRECTIVE_TOKEN, memory_user("AF-REP-DIR", "Directivo", true))
+        .unwrap();
+
+    let payments = [
+        (
+            &member,
+            payment(1, "ACCEPTED", vec![("QUOTA", 100), ("FINE", 20)]),
+        ),
+        (&other, payment(1, "ACCEPTED", vec![("LOAN", 300)])),
+        (&member, payment(2, "ACCEPTED", vec![("LOAN", 200)])),
+        (&other, payment(2, "ON_REVISION", vec![("LOAN", 999)])),
+        (&member, payment(3, "REJECTED", vec![("FINE", 999)])),
+        (&other, payment(4, "ACCEPTED", vec![("QUOTA", 100)])),
+    ];
+    for (i, (owner_key, payment)) in payments.into_iter().enumerate() {
+        store
+            .insert_payment(owner_key, &format!("PAGO{i}"), payment)
+            .unwrap();
+    }
+
+    store
+        .insert_loan(&member, "PRESTAMO1", memory_loan(1000, 300))
+        .unwrap();
+    store
+        .insert_loan(&other, "PRESTAMO2", memory_loan(500, 100))
+        .unwrap();
+    // ya pagado, no es cartera
+    store
+        .insert_loan(&other, "PRESTAMO3", memory_loan(400, 400))
+        .unwrap();
+
+    // la segunda es vieja, sin fecha
+    store
+        .insert_fine(&member, "MULTA1", memory_fine(20, "PAID", Some((2025, 1, 5))))
+        .unwrap();
+    store
+        .insert_fine(&member, "MULTA2", memory_fine(30, "UNPAID", None))
+        .unwrap();
+    store
+        .insert_fine(&other, "MULTA3", memory_fine(50, "UNPAID", Some((2025, 4, 20))))
+        .unwrap();
+
+    let context = GeneralContext::in_memory(store);
+    // al corte del 2025-03-31
+    for (access_token, exp_date, monto_pagado, payed) in [
+        (MEMBER_TOKEN, "2025-03-20", 40, false), // 11 días
+        (MEMBER_TOKEN, "2025-01-15", 0, false),  // 75 días
+        (OTHER_TOKEN, "2024-12-01", 0, false),   // 120 días
+        (OTHER_TOKEN, "2025-02-28", 100, true),  // pagada
+        (OTHER_TOKEN, "2025-04-30", 0, false),   // todavía no vence
+    ] {
+        context
+            .quota_repo()
+            .save_quota(
+                access_token.to_string(),
+                &memory_quota(access_token, exp_date, monto_pagado, payed),
+            )
+            .await
+            .unwrap();
+    }
+
+    context
+}
+
+async fn try_execute(context: &GeneralContext, query: &str) -> (serde_json::Value, Vec<String>) {
+    let schema = RootNode::new(Query, Mutation, EmptySubscription::new());
+    let (value, errors) = juniper::execute(query, None, &schema, &Variables::new(), context)
+        .await
+        .expect("La query debe ser válida");
+
+    let errors = errors
+        .iter()
+        .map(|error| format!("{:?}", error.error()))
+        .collect();
+    (serde_json::to_value(&value).unwrap(), errors)
+}
+
+fn report_query(access_token: &str, extra: &str) -> String {
+    format!(
+        r#"{{ financialReport(accessToken: "{access_token}"{extra}) {{
+            from to currency memberCount
+            collected {{ month loans quotas fines total payments }}
+            loanPortfolio {{ loansWithDebt totalLent totalPayed outstanding }}
+            delinquency {{ minDays maxDays quotas amount }}
+            fines {{ issuedCount issuedAmount unpaidAmount collected }}
+        }} }}"#
+    )
+}
+
+#[tokio::test]
+async fn financial_report_totals() {
+    let context = seed().await;
+
+    let (data, errors) = try_execute(
+        &context,
+        &report_query(DIRECTIVE_TOKEN, r#", from: "2025-01-01", to: "2025-03-31""#),
+    )
+    .await;
+    assert!(errors.is_empty(), "Errores inesperados: {:?}", errors);
+
+    let report = &data["financialReport"];
+    assert_eq!(report["memberCount"], 3);
+
+    // solo aceptados y dentro del período, abril queda fuera
+    assert_eq!(
+        report["collected"],
+        serde_json::json!([
+            {
+                "month": "2025-01", "loans": "300.00", "quotas": "100.00", "fines": "20.00",
+                "total": "420.00", "payments": 2,
+            },
+            {
+                "month": "2025-02", "loans": "200.00", "quotas": "0.00", "fines": "0.00",
+                "total": "200.00", "payments": 1,
+            },
+        ])
+    );
+
+    assert_eq!(
+        report["loanPortfolio"],
+        serde_json::json!({
+            "loansWithDebt": 2,
+            "totalLent": "1500.00",
+            "totalPayed": "400.00",
+            "outstanding": "1100.00",
+        })
+    );
+
+    assert_eq!(
+        report["delinquency"],
+        serde_json::json!([
+            { "minDays": 1, "maxDays": 30, "quotas": 1, "amount": "60.00" },
+            { "minDays": 31, "maxDays": 60, "quotas": 0, "amount": "0.00" },
+            { "minDays": 61, "maxDays": 90, "quotas": 1, "amount": "100.00" },
+            { "minDays": 91, "maxDays": null, "quotas": 1, "amount": "100.00" },
+        ])
+    );
+
+    // la de abril no se puso en el período ni se debía al corte, la vieja cuenta siempre
+    assert_eq!(
+        report["fines"],
+        serde_json::json!({
+            "issuedCount": 2,
+            "issuedAmount": "50.00",
+            "unpaidAmount": "30.00",
+            "collected": "20.00",
+        })
+    );
+
+    // sin fechas entra abril y la morosidad es a hoy, así que ya cuenta la de abril
+    let (data, errors) = try_execute(&context, &report_query(DIRECTIVE_TOKEN, "")).await;
+    assert!(errors.is_empty(), "Errores inesperados: {:?}", errors);
+    let report = &data["financialReport"];
+    assert_eq!(report["collected"].as_array().unwrap().len(), 3);
+    let overdue: i64 = report["delinquency"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|bucket| bucket["quotas"].as_i64().unwrap())
+        .sum();
+    assert_eq!(overdue, 4);
+    assert_eq!(
+        report["fines"],
+        serde_json::json!({
+            "issuedCount": 3,
+            "issuedAmount": "100.00",
+            "unpaidAmount": "80.00",
+            "collected": "20.00",
+        })
+    );
+}
+
+#[tokio::test]
+async fn financial_report_is_for_directives() {
+    let context = seed().await;
+
+    let (_, errors) = try_execute(&context, &report_query(MEMBER_TOKEN, "")).await;
+    assert!(
+        errors.iter().any(|error| error.contains("directivos")),
+        "{:?}",
+        errors
+    );
+
+    let (_, errors) = try_execute(
+        &context,
+        &report_query(DIRECTIVE_TOKEN, r#", from: "2025-04-01", to: "2025-03-01""#),
+    )
+    .await;
+    assert!(
+        errors.iter().any(|error| error.contains("fecha inicial")),
+        "{:?}",
+        errors
+    );
+}
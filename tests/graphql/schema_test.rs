@@ -0,0 +1,109 @@
+// Tests del schema unificado (/graphql) que junta los dominios en un solo Query/Mutation
+
+use super::common::{add_memory_directive, create_memory_context, memory_user};
+use general_api::endpoints::handlers::graphql::{
+    loan::{LoanMutation, LoanQuery},
+    root::{Mutation, Query},
+};
+use juniper::{EmptySubscription, RootNode, Variables};
+
+#[tokio::test]
+async fn test_unified_schema_fetches_every_domain_in_one_request() {
+    let (store, context) = create_memory_context();
+    let schema = RootNode::new(Query, Mutation, EmptySubscription::new());
+
+    // las multas necesitan un usuario real (se buscan por affiliate_key)
+    let token = "test_schema".to_string();
+    let affiliate_key = "AF-TEST-SCHEMA".to_string();
+    store
+        .add_user(&token, memory_user(&affiliate_key, "Test Schema", false))
+        .unwrap();
+
+    let query = format!(
+        r#"{{
+            loan {{ getUserLoans(accessToken: "{token}") {{ id currency }} }}
+            fine {{ getFinesById(accessToken: "{affiliate_key}") {{ id amount }} }}
+            quota {{ getPendingQuotas(accessToken: "{token}") {{ amount }} }}
+            payment {{ getUsersPayments(accessToken: "{token}") {{ id }} }}
+        }}"#
+    );
+
+    let (value, errors) = juniper::execute(
+        &query,
+        None,
+        &schema,
+        &Variables::new(),
+        &context,
+    )
+    .await
+    .expect("La query debe ser válida para el schema unificado");
+
+    assert!(errors.is_empty(), "Errores inesperados: {:?}", errors);
+    let object = value.as_object_value().expect("Debe devolver un objeto");
+    for domain in ["loan", "fine", "quota", "payment"] {
+        assert!(
+            object.get_field_value(domain).is_some(),
+            "Falta el dominio {}",
+            domain
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_domain_schemas_still_answer_as_aliases() {
+    let (_store, context) = create_memory_context();
+    let schema = RootNode::new(LoanQuery {}, LoanMutation, EmptySubscription::new());
+
+    let (_, errors) = juniper::execute(
+        r#"{ getUserLoans(accessToken: "test_schema_alias") { id } }"#,
+        None,
+        &schema,
+        &Variables::new(),
+        &context,
+    )
+    .await
+    .expect("La query vieja debe seguir siendo válida");
+
+    assert!(errors.is_empty(), "Errores inesperados: {:?}", errors);
+}
+
+#[tokio::test]
+async fn test_members_list_comes_from_user_repo() {
+    let (store, context) = create_memory_context();
+    add_memory_directive(&store);
+
+    let members = context
+        .user_repo()
+        .get_all_users_for_affiliates()
+        .await
+        .expect("get_all_users_for_affiliates failed");
+
+    assert!(
+        members.iter().any(|member| member.name == "Directivo Test"),
+        "El socio recién creado debe aparecer en la lista"
+    );
+}
+
+#[tokio::test]
+async fn test_resolver_errors_expose_a_code() {
+    let (store, context) = create_memory_context();
+    let schema = RootNode::new(Query, Mutation, EmptySubscription::new());
+    let directive = add_memory_directive(&store);
+
+    let (_, errors) = juniper::execute(
+        &format!(
+            r#"{{ member(accessToken: "{}", affiliateKey: "test_schema_no_existe") {{ name }} }}"#,
+            directive
+        ),
+        None,
+        &schema,
+        &Variables::new(),
+        &context,
+    )
+    .await
+    .expect("La query debe ser válida");
+
+    assert_eq!(errors.len(), 1);
+    let error = serde_json::to_value(&errors[0]).unwrap();
+    assert_eq!(error["extensions"]["code"], "NOT_FOUND");
+}
@@ -0,0 +1,200 @@
+// Tests de Query.statement contra MemoryStore: qué entra en el período, los totales del
+// resumen y quién puede ver el estado de cuenta de quién
+// (la descarga en PDF/CSV está en tests/files.rs)
+
+use std::sync::Arc;
+
+use super::common::{memory_fine, memory_loan, memory_payment, memory_quota, memory_user};
+use general_api::endpoints::handlers::configs::schema::GeneralContext;
+use general_api::endpoints::handlers::graphql::root::{Mutation, Query};
+use general_api::models::redis::Payment as RedisPayment;
+use general_api::repos::graphql::memory::MemoryStore;
+use juniper::{EmptySubscription, RootNode, Variables};
+
+const MEMBER_TOKEN: &str = "socio_estado";
+const OTHER_TOKEN: &str = "otro_socio_estado";
+const DIRECTIVE_TOKEN: &str = "directivo_estado";
+
+// pagos de marzo 2025
+fn payment(day: u32, status: &str, being_payed: Vec<(&str, i32)>) -> RedisPayment {
+    memory_payment(&format!("BOLETA-{day}"), (2025, 3, day), status, being_payed)
+}
+
+/// un socio con pagos en marzo y abril, un préstamo, una multa y dos cuotas de afiliado
+async fn seed() -> GeneralContext {
+    let store = Arc::new(MemoryStore::new());
+    let owner_key = store
+        .add_user(MEMBER_TOKEN, memory_user("AF-EST-1", "Socio Estado", false))
+        .unwrap();
+    store
+        .add_user(OTHER_TOKEN, memory_user("AF-EST-2", "Otro Socio", false))
+        .unwrap();
+    store
+        .add_user(DIRECTIVE_TOKEN, memory_user("AF-EST-DIR", "Directivo", true))
+        .unwrap();
+
+    let payments = [
+        payment(5, "ACCEPTED", vec![("QUOTA", 100), ("FINE", 50)]),
+        payment(20, "ON_REVISION", vec![("LOAN", 80)]),
+        payment(25, "REJECTED", vec![("LOAN", 999)]),
+    ];
+    for (i, payment) in payments.into_iter().enumerate() {
+        store
+            .insert_payment(&owner_key, &format!("PAGO{i}"), payment)
+            .unwrap();
+    }
+    // fuera del período
+    let april = memory_payment("BOLETA-ABRIL", (2025, 4, 10), "ACCEPTED", vec![("LOAN", 500)]);
+    store
+        .insert_payment(&owner_key, "PAGO_ABRIL", april)
+        .unwrap();
+
+    store
+        .insert_loan(&owner_key, "PRESTAMO1", memory_loan(1000, 300))
+        .unwrap();
+    store
+        .insert_fine(&owner_key, "MULTA1", memory_fine(25, "UNPAID", Some((2025, 3, 2))))
+        .unwrap();
+
+    let context = GeneralContext::in_memory(store);
+    for (exp_date, monto_pagado, payed) in [
+        ("2025-03-31", 40, false),
+        ("2025-03-01", 100, true),
+        ("2025-04-30", 40, false),
+    ] {
+        let quota = memory_quota(MEMBER_TOKEN, exp_date, monto_pagado, payed);
+        context
+            .quota_repo()
+            .save_quota(MEMBER_TOKEN.to_string(), &quota)
+            .await
+            .unwrap();
+    }
+
+    context
+}
+
+async fn try_execute(context: &GeneralContext, query: &str) -> (serde_json::Value, Vec<String>) {
+    let schema = RootNode::new(Query, Mutation, EmptySubscription::new());
+    let (value, errors) = juniper::execute(query, None, &schema, &Variables::new(), context)
+        .await
+        .expect("La query debe ser válida");
+
+    let errors = errors
+        .iter()
+        .map(|error| format!("{:?}", error.error()))
+        .collect();
+    (serde_json::to_value(&value).unwrap(), errors)
+}
+
+fn statement_query(access_token: &str, extra: &str) -> String {
+    format!(
+        r#"{{ statement(accessToken: "{access_token}"{extra}) {{
+            affiliateKey memberName from to currency
+            summary {{
+                totalPaid paidToQuotas paidToLoans paidToFines pendingReview
+                quotasPending loanDebt unpaidFines
+            }}
+            payments {{ ticketNum state }}
+            loans {{ id debt }}
+            quotas {{ expDate payed }}
+            fines {{ id }}
+        }} }}"#
+    )
+}
+
+#[tokio::test]
+async fn statement_covers_the_period() {
+    let context = seed().await;
+
+    let (data, errors) = try_execute(
+        &context,
+        &statement_query(MEMBER_TOKEN, r#", from: "2025-03-01", to: "2025-03-31""#),
+    )
+    .await;
+    assert!(errors.is_empty(), "Errores inesperados: {:?}", errors);
+
+    let statement = &data["statement"];
+    assert_eq!(statement["affiliateKey"], "AF-EST-1");
+    assert_eq!(statement["memberName"], "Socio Estado");
+    assert_eq!(statement["from"], "2025-03-01");
+
+    // abril queda fuera, los de marzo van en orden
+    let tickets: Vec<&str> = statement["payments"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|payment| payment["ticketNum"].as_str().unwrap())
+        .collect();
+    assert_eq!(tickets, ["BOLETA-5", "BOLETA-20", "BOLETA-25"]);
+
+    let quota_dates: Vec<&str> = statement["quotas"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|quota| quota["expDate"].as_str().unwrap())
+        .collect();
+    assert_eq!(quota_dates, ["2025-03-01", "2025-03-31"]);
+
+    // solo lo aceptado cuenta como pagado, lo rechazado no aparece en ningún total
+    assert_eq!(
+        statement["summary"],
+        serde_json::json!({
+            "totalPaid": "150.00",
+            "paidToQuotas": "100.00",
+            "paidToLoans": "0.00",
+            "paidToFines": "50.00",
+            "pendingReview": "80.00",
+            "quotasPending": "60.00",
+            "loanDebt": "700.00",
+            "unpaidFines": "25.00",
+        })
+    );
+    assert_eq!(statement["loans"].as_array().unwrap().len(), 1);
+    assert_eq!(statement["fines"][0]["id"], "MULTA1");
+
+    // sin fechas es todo el historial
+    let (data, errors) = try_execute(&context, &statement_query(MEMBER_TOKEN, "")).await;
+    assert!(errors.is_empty(), "Errores inesperados: {:?}", errors);
+    assert_eq!(data["statement"]["payments"].as_array().unwrap().len(), 4);
+    assert_eq!(data["statement"]["summary"]["totalPaid"], "650.00");
+}
+
+#[tokio::test]
+async fn statements_are_private_to_members_and_directives() {
+    let context = seed().await;
+
+    // un socio no puede ver el de otro
+    let (_, errors) = try_execute(
+        &context,
+        &statement_query(OTHER_TOKEN, r#", affiliateKey: "AF-EST-1""#),
+    )
+    .await;
+    assert!(
+        errors.iter().any(|error| error.contains("directivos")),
+        "{:?}",
+        errors
+    );
+
+    // un directivo sí
+    let (data, errors) = try_execute(
+        &context,
+        &statement_query(DIRECTIVE_TOKEN, r#", affiliateKey: "AF-EST-1""#),
+    )
+    .await;
+    assert!(errors.is_empty(), "Errores inesperados: {:?}", errors);
+    assert_eq!(data["statement"]["memberName"], "Socio Estado");
+
+    // ni tokens inválidos ni períodos al revés
+    let (_, errors) = try_execute(&context, &statement_query("no_existe", "")).await;
+    assert!(!errors.is_empty());
+    let (_, errors) = try_execute(
+        &context,
+        &statement_query(MEMBER_TOKEN, r#", from: "2025-04-01", to: "2025-03-01""#),
+    )
+    .await;
+    assert!(
+        errors.iter().any(|error| error.contains("fecha inicial")),
+        "{:?}",
+        errors
+    );
+}
@@ -0,0 +1,203 @@
+// Tests de las subscriptions: los repos publican en redis pub/sub y cada subscription
+// filtra los eventos que le tocan al socio del access_token
+
+use std::time::Duration;
+
+use super::common::{TestRedisGuard, create_test_context, insert_directive_helper};
+use futures::StreamExt;
+use general_api::endpoints::handlers::configs::schema::GeneralContext;
+use general_api::endpoints::handlers::graphql::root::{Mutation, Query, Subscription};
+use general_api::models::PayedToInput;
+use general_api::models::currency::Currency;
+use general_api::models::money::Money;
+use general_api::repos::auth::utils::hashing_composite_key;
+use general_api::repos::graphql::payment::{payment_history_key, payment_ticket_index_key};
+use general_api::test_sync::redis_test_lock;
+use juniper::{ExecutionError, RootNode, Value, ValuesStream, Variables};
+use redis::Commands;
+
+type Schema = RootNode<'static, Query, Mutation, Subscription>;
+
+/// abre la subscription y regresa el stream del único campo pedido
+async fn subscribe<'a>(
+    schema: &'a Schema,
+    context: &'a GeneralContext,
+    query: &'a str,
+) -> Result<ValuesStream<'a>, Vec<ExecutionError<juniper::DefaultScalarValue>>> {
+    let (value, errors) =
+        juniper::resolve_into_stream(query, None, schema, &Variables::new(), context)
+            .await
+            .expect("La subscription debe ser válida");
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let (_, field) = value
+        .into_object()
+        .expect("Debe devolver un objeto")
+        .into_iter()
+        .next()
+        .expect("Debe traer el campo pedido");
+    match field {
+        Value::Scalar(stream) => Ok(stream),
+        _ => panic!("El campo de una subscription debe ser un stream"),
+    }
+}
+
+/// siguiente evento del stream, sin colgar el test si nunca llega
+async fn next_event(stream: &mut ValuesStream<'_>) -> serde_json::Value {
+    let event = tokio::time::timeout(Duration::from_secs(5), stream.next())
+        .await
+        .expect("No llegó ningún evento")
+        .expect("El stream se cerró")
+        .expect("El evento trae errores");
+    serde_json::to_value(&event).unwrap()
+}
+
+/// socio con affiliate_key indexado (para create_fine) y un pago en revisión
+async fn seed_member_with_payment(
+    context: &GeneralContext,
+    guard: &mut TestRedisGuard,
+) -> (String, String, String) {
+    let access_token = insert_directive_helper(context, guard);
+    let db_access_token = hashing_composite_key(&[&access_token]);
+    let mut con = context.pool().unwrap().client().get_connection().unwrap();
+
+    let affiliate_key: String = con
+        .get(format!("users:{}:affiliate_key", db_access_token))
+        .unwrap();
+    let index_key = format!("affiliate_key_to_db_access:{}", affiliate_key);
+    let _: () = con.set(&index_key, &db_access_token).unwrap();
+    guard.register_key(index_key);
+
+    let ticket = format!("S{}", chrono::Utc::now().timestamp_nanos_opt().unwrap());
+    guard.register_key(payment_ticket_index_key("SUBS_ACC", &ticket));
+    context
+        .payment_repo()
+        .create_payment(
+            access_token.clone(),
+            "Pago en vivo".to_string(),
+            "si".to_owned(),
+            Money::from(80),
+            Currency::Gtq,
+            ticket,
+            "SUBS_ACC".to_string(),
+            vec![PayedToInput {
+                model_type: "FINE".to_string(),
+                amount: Money::from(80),
+                model_key: "multa_subscription".to_string(),
+            }],
+        )
+        .await
+        .expect("create_payment failed");
+
+    let payment_keys: Vec<String> = con
+        .scan_match(format!("users:{}:*", db_access_token))
+        .unwrap()
+        .collect();
+    let payment_id = payment_keys
+        .iter()
+        .find_map(|key| key.split(":payments:").nth(1))
+        .expect("El pago debe existir")
+        .to_string();
+    for key in payment_keys {
+        guard.register_key(key);
+    }
+
+    (access_token, affiliate_key, payment_id)
+}
+
+#[tokio::test]
+async fn test_payment_status_changed_reaches_the_owner() {
+    let _lock = redis_test_lock().await;
+    let context = create_test_context();
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
+    let (access_token, _, payment_id) = seed_member_with_payment(&context, &mut guard).await;
+    let schema = Schema::new(Query, Mutation, Subscription);
+    let query = format!(
+        r#"subscription {{ paymentStatusChanged(accessToken: "{}") {{
+            paymentId change {{ newState reviewerName }}
+        }} }}"#,
+        access_token
+    );
+
+    let mut stream = subscribe(&schema, &context, &query).await.unwrap();
+
+    context
+        .payment_repo()
+        .approve_or_reject_payment(
+            access_token.clone(),
+            payment_id.clone(),
+            "ACCEPTED".to_string(),
+            String::new(),
+        )
+        .await
+        .expect("approve_or_reject_payment failed");
+
+    let event = next_event(&mut stream).await;
+    assert_eq!(event["paymentId"], payment_id.as_str());
+    assert_eq!(event["change"]["newState"], "ACCEPTED");
+    assert_eq!(event["change"]["reviewerName"], "Directivo Test");
+
+    guard.register_key(payment_history_key(&payment_id));
+}
+
+#[tokio::test]
+async fn test_fine_issued_reaches_the_fined_member() {
+    let _lock = redis_test_lock().await;
+    let context = create_test_context();
+    let mut guard = TestRedisGuard::new(context.pool().unwrap().clone());
+    let (access_token, affiliate_key, _) = seed_member_with_payment(&context, &mut guard).await;
+    let schema = Schema::new(Query, Mutation, Subscription);
+    let query = format!(
+        r#"subscription {{ fineIssued(accessToken: "{}") {{ reason amount member {{ name }} }} }}"#,
+        access_token
+    );
+
+    let mut stream = subscribe(&schema, &context, &query).await.unwrap();
+
+    context
+        .fine_repo()
+        .create_fine(
+            affiliate_key.clone(),
+            Money::from(25),
+            "no vino a la asamblea".to_string(),
+        )
+        .await
+        .expect("create_fine failed");
+
+    let event = next_event(&mut stream).await;
+    assert_eq!(event["reason"], "no vino a la asamblea");
+    assert_eq!(event["member"]["name"], "Directivo Test");
+
+    let mut con = context.pool().unwrap().client().get_connection().unwrap();
+    let fine_keys: Vec<String> = con
+        .scan_match(format!(
+            "users:{}:fines:*",
+            hashing_composite_key(&[&access_token])
+        ))
+        .unwrap()
+        .collect();
+    for key in fine_keys {
+        guard.register_key(key);
+    }
+}
+
+#[tokio::test]
+async fn test_new_payment_submitted_is_only_for_directives() {
+    let _lock = redis_test_lock().await;
+    let context = create_test_context();
+    let schema = Schema::new(Query, Mutation, Subscription);
+
+    let result = subscribe(
+        &schema,
+        &context,
+        r#"subscription { newPaymentSubmitted(accessToken: "no_es_directivo") { id } }"#,
+    )
+    .await;
+
+    let errors = result
+        .err()
+        .expect("Un socio normal no debe poder suscribirse");
+    assert!(format!("{:?}", errors[0]).contains("Solo los directivos"));
+}
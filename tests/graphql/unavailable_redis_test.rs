@@ -0,0 +1,89 @@
+// Tests con redis caído: los repos y resolvers deben regresar error STORAGE, no hacer panic
+
+use std::time::Duration;
+
+use general_api::endpoints::handlers::configs::connection_pool::RedisPool;
+use general_api::endpoints::handlers::configs::schema::GeneralContext;
+use general_api::endpoints::handlers::graphql::root::{Mutation, Query};
+use general_api::errors::AppError;
+use general_api::models::money::Money;
+use juniper::{EmptySubscription, RootNode, Variables};
+use redis::Client;
+use rust_decimal::Decimal;
+
+/// contexto apuntando a un puerto donde no hay nadie escuchando
+fn unavailable_context() -> GeneralContext {
+    let client = Client::open("redis://127.0.0.1:1/").unwrap();
+    let pool = RedisPool::new(client, Duration::from_millis(200), Duration::from_millis(200));
+    GeneralContext::new(pool)
+}
+
+#[tokio::test]
+async fn test_repos_return_storage_error_when_redis_is_down() {
+    let context = unavailable_context();
+
+    assert!(matches!(
+        context.loan_repo().get_user_loans("token".to_string()).await,
+        Err(AppError::Storage(_))
+    ));
+    assert!(matches!(context.payment_repo().get_all_payments().await, Err(AppError::Storage(_))));
+    assert!(matches!(
+        context.fine_repo().edit_fine("fine".to_string(), None, None, None).await,
+        Err(AppError::Storage(_))
+    ));
+    assert!(matches!(
+        context.fine_repo().create_fine(
+            "affiliate".to_string(),
+            Money::new(Decimal::new(10, 0)),
+            "motivo".to_string(),
+        )
+        .await,
+        Err(AppError::Storage(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_queries_expose_storage_code_when_redis_is_down() {
+    let context = unavailable_context();
+    let schema = RootNode::new(Query, Mutation, EmptySubscription::new());
+
+    let (_, errors) = juniper::execute(
+        r#"{
+            loan { getUserLoans(accessToken: "token") { id } }
+            payment { getAllPayments { id } }
+        }"#,
+        None,
+        &schema,
+        &Variables::new(),
+        &context,
+    )
+    .await
+    .expect("La query debe ser válida");
+
+    // los campos son non-null, así que el primer error ya anula toda la respuesta
+    assert!(!errors.is_empty());
+    for error in errors {
+        let error = serde_json::to_value(&error).unwrap();
+        assert_eq!(error["extensions"]["code"], "STORAGE");
+    }
+}
+
+#[tokio::test]
+async fn test_create_quota_returns_error_instead_of_panicking() {
+    let context = unavailable_context();
+    let schema = RootNode::new(Query, Mutation, EmptySubscription::new());
+
+    let (_, errors) = juniper::execute(
+        "mutation { quota { createQuota } }",
+        None,
+        &schema,
+        &Variables::new(),
+        &context,
+    )
+    .await
+    .expect("La mutation debe ser válida");
+
+    assert_eq!(errors.len(), 1);
+    let error = serde_json::to_value(&errors[0]).unwrap();
+    assert_eq!(error["extensions"]["code"], "CONFLICT");
+}
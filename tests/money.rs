@@ -0,0 +1,108 @@
+// Tests del tipo Money (models::money) y de la migración de montos guardados como números
+// La parte de migración asume que redis está corriendo en 127.0.0.1
+
+use std::time::Duration;
+
+use general_api::endpoints::handlers::configs::connection_pool::RedisPool;
+use general_api::models::money::Money;
+use general_api::models::redis::{Fine as RedisFine, Loan as RedisLoan};
+use general_api::repos::migrations::migrate_stored_amounts;
+use redis::{Client, Commands, JsonCommands};
+use rust_decimal::Decimal;
+
+fn money(raw: &str) -> Money {
+    raw.parse().unwrap()
+}
+
+#[test]
+fn amounts_round_half_up_to_cents() {
+    assert_eq!(money("10.005").to_string(), "10.01");
+    assert_eq!(money("10.004").to_string(), "10.00");
+    assert_eq!(money("-10.005").to_string(), "-10.01");
+    assert_eq!(money("5").to_string(), "5.00");
+    assert!("diez".parse::<Money>().is_err());
+}
+
+#[test]
+fn sums_do_not_drift() {
+    // con f64 esto da 0.30000000000000004
+    let total: Money = [money("0.1"), money("0.2")].iter().sum();
+    assert_eq!(total, money("0.30"));
+
+    let many: Money = std::iter::repeat_n(money("0.10"), 1000).sum();
+    assert_eq!(many, Money::from(100));
+}
+
+#[test]
+fn interest_is_rounded_once_at_the_end() {
+    // 1234.56 * 2.5% = 30.864 -> 30.86
+    assert_eq!(money("1234.56").percentage(Decimal::new(25, 1)), money("30.86"));
+    // 0.50 * 1% = 0.005 -> 0.01 (mitad hacia arriba)
+    assert_eq!(money("0.50").percentage(Decimal::ONE), money("0.01"));
+}
+
+#[test]
+fn split_evenly_keeps_the_total() {
+    let parts = money("100.00").split_evenly(3);
+    assert_eq!(parts, vec![money("33.34"), money("33.33"), money("33.33")]);
+    assert_eq!(parts.iter().sum::<Money>(), money("100.00"));
+
+    assert!(money("1.00").split_evenly(0).is_empty());
+}
+
+#[test]
+fn redis_models_read_legacy_numbers_and_write_strings() {
+    let fine: RedisFine =
+        serde_json::from_str(r#"{"amount":150.1,"motive":"tarde","status":"UNPAID"}"#).unwrap();
+    assert_eq!(fine.amount, money("150.10"));
+
+    let loan: RedisLoan = serde_json::from_str(
+        r#"{"total_quota":12,"base_needed_payment":"5000.00","payed":0,"debt":5000,
+            "total":5000.0,"status":"PENDING","reason":"casa","interest_rate":0.12}"#,
+    )
+    .unwrap();
+    assert_eq!(loan.debt, Money::from(5000));
+
+    let written = serde_json::to_value(&fine).unwrap();
+    assert_eq!(written["amount"], "150.10");
+}
+
+#[tokio::test]
+async fn migration_turns_stored_numbers_into_strings() {
+    let _lock = general_api::test_sync::redis_test_lock().await;
+    let client = Client::open("redis://127.0.0.1/").unwrap();
+    let mut con = client.get_connection().unwrap();
+    let pool = RedisPool::new(client, Duration::from_secs(5), Duration::from_secs(10));
+
+    let now = chrono::Utc::now().timestamp_nanos_opt().unwrap();
+    let payment_key = format!("users:testmoney{}:payments:p1", now);
+    let fine_key = format!("users:testmoney{}:fines:f1", now);
+
+    let _: () = con
+        .json_set(
+            &payment_key,
+            "$",
+            &serde_json::json!({
+                "total_amount": 100.1,
+                "being_payed": [{ "model_type": "FINE", "amount": 100.1, "model_key": "f1" }]
+            }),
+        )
+        .unwrap();
+    let _: () = con
+        .json_set(&fine_key, "$", &serde_json::json!({ "amount": "20.00" }))
+        .unwrap();
+    let _: () = con.del("migrations:stored_amounts_v1").unwrap();
+
+    migrate_stored_amounts(&pool).await.expect("migration failed");
+
+    let total_amount: String = con.json_get(&payment_key, "$.total_amount").unwrap();
+    let being_payed: String = con.json_get(&payment_key, "$.being_payed").unwrap();
+    let fine_amount: String = con.json_get(&fine_key, "$.amount").unwrap();
+    assert_eq!(total_amount, r#"["100.10"]"#);
+    assert!(being_payed.contains(r#""amount":"100.10""#));
+    assert_eq!(fine_amount, r#"["20.00"]"#);
+    assert!(con.exists::<_, bool>("migrations:stored_amounts_v1").unwrap());
+
+    let _: () = con.del(&payment_key).unwrap();
+    let _: () = con.del(&fine_key).unwrap();
+}
@@ -6,7 +6,7 @@ use rand::{
 use rand::rng;
 
 use general_api::{
-    endpoints::handlers::configs::connection_pool::get_pool_connection,
+    endpoints::handlers::configs::connection_pool::{get_pool_connection, RedisPool},
     repos::auth::{
         configure_all_security_answers, create_user_with_access_token,
         get_user_access_token, reset_password, utils::hashing_composite_key,
@@ -15,8 +15,14 @@ use general_api::{
 };
 use redis::Commands;
 
+/// cada llamada abre su propia conexión: la multiplexada queda atada al runtime de tokio que
+/// la abrió y cada #[tokio::test] tiene el suyo
+fn pool() -> RedisPool {
+    get_pool_connection().unwrap()
+}
+
 fn cleanup_test_user(username: &str) {
-    let mut con = get_pool_connection().into_inner().get().unwrap();
+    let mut con = pool().client().get_connection().unwrap();
 
     let access_token =
         hashing_composite_key(&[&username.to_string(), &"InitialPassword123".to_string()]);
@@ -55,8 +61,8 @@ fn cleanup_test_user(username: &str) {
 /// 4. Reset password using security answer
 /// 5. Verify login with new password works
 /// 6. Verify login with old password fails
-#[test]
-fn test_complete_password_recovery_flow() {
+#[tokio::test]
+async fn test_complete_password_recovery_flow() {
     let _ = dotenv();
 
     let username = format!("recovery_test_{}", rng().sample_iter(&Alphanumeric).take(8).map(char::from).collect::<String>());
@@ -73,10 +79,12 @@ fn test_complete_password_recovery_flow() {
     // STEP 1: Create user
     println!("\n[STEP 1] Creating user...");
     let creation = create_user_with_access_token(
+        &pool(),
         username.clone(),
         initial_password.clone(),
         full_name.clone(),
-    );
+    )
+    .await;
     assert!(creation.is_ok(), "User creation should succeed");
     println!("✓ User created successfully");
     if let Ok(ref token_info) = creation {
@@ -85,7 +93,8 @@ fn test_complete_password_recovery_flow() {
 
     // STEP 2: Get access token for configuration
     println!("\n[STEP 2] Getting access token for security configuration...");
-    let token_result = get_user_access_token(username.clone(), initial_password.clone());
+    let token_result = get_user_access_token(&pool(), username.clone(), initial_password.clone())
+        .await;
     assert!(token_result.is_ok(), "Should get access token with initial password");
     let initial_token = token_result.unwrap().access_token;
     println!("✓ Access token obtained: {}", &initial_token[..32]);
@@ -97,7 +106,12 @@ fn test_complete_password_recovery_flow() {
         "I was born in the city".to_string(),
         "My first pet was a dog".to_string(),
     ];
-    let config_result = configure_all_security_answers(initial_token.clone(), answers.clone());
+    let config_result = configure_all_security_answers(
+        &pool(),
+        initial_token.clone(),
+        answers.clone(),
+    )
+    .await;
     assert!(config_result.is_ok(), "Security answers configuration should succeed");
     println!("✓ Security answers configured successfully");
 
@@ -106,7 +120,7 @@ fn test_complete_password_recovery_flow() {
     for (index, answer) in answers.iter().enumerate() {
         println!("  Validating answer at index {}...", index);
         let validate_result =
-            validate_security_answer(username.clone(), index as u8, answer.clone());
+            validate_security_answer(&pool(), username.clone(), index as u8, answer.clone()).await;
         assert!(validate_result.is_ok(), "Validation of answer {} should succeed", index);
         println!("  ✓ Answer {} validated successfully", index);
     }
@@ -115,11 +129,13 @@ fn test_complete_password_recovery_flow() {
     // STEP 5: Reset password using security answer
     println!("\n[STEP 5] Resetting password using security answer...");
     let reset_result = reset_password(
+        &pool(),
         username.clone(),
         0, // Using first security question
         answers[0].clone(),
         new_password.clone(),
-    );
+    )
+    .await;
     assert!(reset_result.is_ok(), "Password reset should succeed");
     let token_after_reset = reset_result.unwrap().access_token;
     println!("✓ Password reset successfully");
@@ -127,25 +143,26 @@ fn test_complete_password_recovery_flow() {
 
     // STEP 6: Verify new password works for login
     println!("\n[STEP 6] Verifying login with new password...");
-    let new_login = get_user_access_token(username.clone(), new_password.clone());
+    let new_login = get_user_access_token(&pool(), username.clone(), new_password.clone()).await;
     assert!(new_login.is_ok(), "Login with new password should succeed");
     println!("✓ Login with new password succeeded");
     println!("  New access token obtained: {}", &new_login.unwrap().access_token[..32]);
 
     // STEP 7: Verify old password no longer works
     println!("\n[STEP 7] Verifying old password no longer works...");
-    let old_login = get_user_access_token(username.clone(), initial_password.clone());
+    let old_login = get_user_access_token(&pool(), username.clone(), initial_password.clone())
+        .await;
     assert!(old_login.is_err(), "Login with old password should fail");
     println!("✓ Old password correctly rejected");
     if let Err(err) = old_login {
-        println!("  Error: {}", err.message);
+        println!("  Error: {}", err.message());
     }
 
     // STEP 8: Verify security answers still work after reset
     println!("\n[STEP 8] Verifying security answers still work after reset...");
     for (index, answer) in answers.iter().enumerate() {
         let validate_result =
-            validate_security_answer(username.clone(), index as u8, answer.clone());
+            validate_security_answer(&pool(), username.clone(), index as u8, answer.clone()).await;
         assert!(validate_result.is_ok(), "Validation of answer {} should still work", index);
         println!("  ✓ Answer {} still valid after password reset", index);
     }
@@ -160,8 +177,8 @@ fn test_complete_password_recovery_flow() {
 /// INTEGRATION TEST: Multiple password resets in sequence
 /// 
 /// Tests that a user can perform multiple password resets using different security questions
-#[test]
-fn test_multiple_sequential_password_resets() {
+#[tokio::test]
+async fn test_multiple_sequential_password_resets() {
     let _ = dotenv();
 
     let username = format!("multi_reset_{}", rng().sample_iter(&Alphanumeric).take(8).map(char::from).collect::<String>());
@@ -177,10 +194,12 @@ fn test_multiple_sequential_password_resets() {
     // Create user
     println!("\n[1] Creating user with password: {}", password_1);
     let creation = create_user_with_access_token(
+        &pool(),
         username.clone(),
         password_1.clone(),
         "Multi Reset User".to_string(),
-    );
+    )
+    .await;
     assert!(creation.is_ok());
     println!("✓ User created");
 
@@ -190,51 +209,56 @@ fn test_multiple_sequential_password_resets() {
         "Answer to question 2".to_string(),
         "Answer to question 3".to_string(),
     ];
-    let token_1 = get_user_access_token(username.clone(), password_1.clone())
+    let token_1 = get_user_access_token(&pool(), username.clone(), password_1.clone())
+        .await
         .unwrap()
         .access_token;
-    let _ = configure_all_security_answers(token_1, answers.clone());
+    let _ = configure_all_security_answers(&pool(), token_1, answers.clone()).await;
     println!("✓ Security answers configured");
 
     // First reset using question 0
     println!("\n[2] First password reset: {} -> {}", password_1, password_2);
     let reset_1 = reset_password(
+        &pool(),
         username.clone(),
         0,
         answers[0].clone(),
         password_2.clone(),
-    );
+    )
+    .await;
     assert!(reset_1.is_ok());
     println!("✓ First reset successful");
 
     // Verify password_1 doesn't work
-    let old_login = get_user_access_token(username.clone(), password_1.clone());
+    let old_login = get_user_access_token(&pool(), username.clone(), password_1.clone()).await;
     assert!(old_login.is_err());
     println!("✓ Old password (1) correctly rejected");
 
     // Verify password_2 works
-    let new_login = get_user_access_token(username.clone(), password_2.clone());
+    let new_login = get_user_access_token(&pool(), username.clone(), password_2.clone()).await;
     assert!(new_login.is_ok());
     println!("✓ New password (2) works");
 
     // Second reset using question 1
     println!("\n[3] Second password reset: {} -> {}", password_2, password_3);
     let reset_2 = reset_password(
+        &pool(),
         username.clone(),
         1,
         answers[1].clone(),
         password_3.clone(),
-    );
+    )
+    .await;
     assert!(reset_2.is_ok());
     println!("✓ Second reset successful");
 
     // Verify password_2 doesn't work
-    let old_login_2 = get_user_access_token(username.clone(), password_2.clone());
+    let old_login_2 = get_user_access_token(&pool(), username.clone(), password_2.clone()).await;
     assert!(old_login_2.is_err());
     println!("✓ Previous password (2) correctly rejected");
 
     // Verify password_3 works
-    let new_login_2 = get_user_access_token(username.clone(), password_3.clone());
+    let new_login_2 = get_user_access_token(&pool(), username.clone(), password_3.clone()).await;
     assert!(new_login_2.is_ok());
     println!("✓ Latest password (3) works");
 
@@ -246,8 +270,8 @@ fn test_multiple_sequential_password_resets() {
 /// INTEGRATION TEST: Error handling in password recovery
 /// 
 /// Tests that the system properly rejects invalid attempts
-#[test]
-fn test_password_recovery_error_handling() {
+#[tokio::test]
+async fn test_password_recovery_error_handling() {
     let _ = dotenv();
 
     let username = format!("error_test_{}", rng().sample_iter(&Alphanumeric).take(8).map(char::from).collect::<String>());
@@ -260,10 +284,12 @@ fn test_password_recovery_error_handling() {
 
     // Create user
     let creation = create_user_with_access_token(
+        &pool(),
         username.clone(),
         password.clone(),
         "Error Test User".to_string(),
-    );
+    )
+    .await;
     assert!(creation.is_ok());
     println!("✓ User created");
 
@@ -273,43 +299,54 @@ fn test_password_recovery_error_handling() {
         "Correct answer 2".to_string(),
         "Correct answer 3".to_string(),
     ];
-    let token = get_user_access_token(username.clone(), password.clone())
+    let token = get_user_access_token(&pool(), username.clone(), password.clone())
+        .await
         .unwrap()
         .access_token;
-    let _ = configure_all_security_answers(token, answers.clone());
+    let _ = configure_all_security_answers(&pool(), token, answers.clone()).await;
     println!("✓ Security answers configured");
 
     // TEST 1: Wrong answer should fail
     println!("\n[TEST 1] Attempting reset with wrong answer...");
     let wrong_answer_reset = reset_password(
+        &pool(),
         username.clone(),
         0,
         "Wrong answer".to_string(),
         "NewPassword".to_string(),
-    );
+    )
+    .await;
     assert!(wrong_answer_reset.is_err(), "Reset with wrong answer should fail");
     println!("✓ Wrong answer correctly rejected");
 
     // TEST 2: Valid answer should still work after failed attempt
     println!("\n[TEST 2] Verifying valid answer still works after failed attempt...");
     let valid_reset = reset_password(
+        &pool(),
         username.clone(),
         0,
         answers[0].clone(),
         "ValidNewPassword".to_string(),
-    );
+    )
+    .await;
     assert!(valid_reset.is_ok(), "Valid answer should work after failed attempt");
     println!("✓ Valid answer still works");
 
     // TEST 3: Old password doesn't work anymore
     println!("\n[TEST 3] Verifying old password no longer works...");
-    let old_password_login = get_user_access_token(username.clone(), password.clone());
+    let old_password_login = get_user_access_token(&pool(), username.clone(), password.clone())
+        .await;
     assert!(old_password_login.is_err());
     println!("✓ Old password correctly rejected");
 
     // TEST 4: New password works
     println!("\n[TEST 4] Verifying new password works...");
-    let new_password_login = get_user_access_token(username.clone(), "ValidNewPassword".to_string());
+    let new_password_login = get_user_access_token(
+        &pool(),
+        username.clone(),
+        "ValidNewPassword".to_string(),
+    )
+    .await;
     assert!(new_password_login.is_ok());
     println!("✓ New password works");
 
@@ -0,0 +1,94 @@
+// Tests de la lectura del texto de los comprobantes (repos::file::ocr), no necesitan tesseract
+
+use chrono::NaiveDate;
+use general_api::models::dates::Date;
+use general_api::models::file::ReceiptReading;
+use general_api::models::money::Money;
+use general_api::repos::file::ocr::{check_receipt, parse_receipt_text};
+
+fn money(raw: &str) -> Money {
+    raw.parse().unwrap()
+}
+
+fn date(year: i32, month: u32, day: u32) -> Date {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap().into()
+}
+
+#[test]
+fn bank_deposit_is_read() {
+    let text = "BANCO INDUSTRIAL\n\
+                Depósito monetario\n\
+                Cuenta: 1234567890\n\
+                Fecha: 25/03/2025 10:42\n\
+                No. Boleta: 00123456\n\
+                Monto: Q 1,250.00\n";
+
+    assert_eq!(
+        parse_receipt_text(text),
+        ReceiptReading {
+            amount: Some(money("1250.00")),
+            date: Some(date(2025, 3, 25)),
+            reference: Some("00123456".to_string()),
+        }
+    );
+}
+
+#[test]
+fn amounts_prefer_labeled_lines() {
+    // el primer monto con moneda es un cargo, el que vale es el total
+    let text = "Comisión Q5.00\nTOTAL GTQ 305.50";
+    assert_eq!(parse_receipt_text(text).amount, Some(money("305.50")));
+
+    // sin etiqueta, el primero que trae moneda
+    assert_eq!(
+        parse_receipt_text("Cuenta 998877\nQ 75.25").amount,
+        Some(money("75.25"))
+    );
+
+    // números de cuenta sueltos no son montos
+    assert_eq!(parse_receipt_text("Cuenta 998877").amount, None);
+}
+
+#[test]
+fn dates_and_references_are_validated() {
+    assert_eq!(
+        parse_receipt_text("2025-01-31").date,
+        Some(date(2025, 1, 31))
+    );
+    assert_eq!(parse_receipt_text("05-02-25").date, Some(date(2025, 2, 5)));
+    // 31 de febrero no existe
+    assert_eq!(parse_receipt_text("31/02/2025").date, None);
+
+    assert_eq!(
+        parse_receipt_text("Autorización: AB-9876").reference,
+        Some("AB-9876".to_string())
+    );
+    // una referencia sin números es texto del banco
+    assert_eq!(parse_receipt_text("Referencia: PENDIENTE").reference, None);
+    assert_eq!(parse_receipt_text(""), ReceiptReading::default());
+}
+
+#[test]
+fn readings_are_checked_against_the_payment() {
+    let reading = ReceiptReading {
+        amount: Some(money("100")),
+        date: Some(date(2025, 3, 25)),
+        reference: Some("00123456".to_string()),
+    };
+
+    // ceros a la izquierda, guiones y espacios no cuentan
+    let check = check_receipt(&reading, money("100.00"), "123-456");
+    assert_eq!(check.amount_matches, Some(true));
+    assert_eq!(check.reference_matches, Some(true));
+    assert!(!check.has_mismatch);
+
+    let check = check_receipt(&reading, money("150"), "123456");
+    assert_eq!(check.amount_matches, Some(false));
+    assert!(check.has_mismatch);
+
+    // lo que no se leyó no se marca como diferente
+    let check = check_receipt(&ReceiptReading::default(), money("150"), "999");
+    assert_eq!(check.amount_matches, None);
+    assert_eq!(check.reference_matches, None);
+    assert!(!check.has_mismatch);
+}